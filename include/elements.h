@@ -0,0 +1,51 @@
+#ifndef ELEMENTS_H
+#define ELEMENTS_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Options for rendering, matching the command line flags of the same names, where a negative padding or
+ * precision leaves it unset and the seed is only used if has_seed is set */
+typedef struct ElementsOptions {
+    bool label;
+    bool flip_y;
+    bool grid;
+    bool legend;
+    double padding;
+    int32_t precision;
+    bool has_seed;
+    uint64_t seed;
+} ElementsOptions;
+
+/* The outcome of a call, telling the caller whether to read its result or elements_last_error */
+typedef enum ElementsStatus {
+    ELEMENTS_OK = 0,
+    /* A pointer was NULL or the source was not valid UTF-8 */
+    ELEMENTS_INVALID_ARGUMENT = 1,
+    /* The source could not be evaluated or rendered */
+    ELEMENTS_ERROR = 2,
+    /* The renderer panicked, which is a bug in the library rather than in the source */
+    ELEMENTS_PANIC = 3,
+} ElementsStatus;
+
+/* Render the source to an SVG string written to svg, or return the status of the failure, leave svg NULL,
+ * and keep the error for elements_last_error. The options may be NULL for the defaults, and the string
+ * must be released with elements_free. */
+ElementsStatus elements_render(const char *source, const ElementsOptions *options, char **svg);
+
+/* Return the error from the last failed call on this thread, including the message of a caught panic, or
+ * NULL if there was none. The string is owned by the library and stays valid until the next failed call. */
+const char *elements_last_error(void);
+
+/* Release a string returned by elements_render. */
+void elements_free(char *svg);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
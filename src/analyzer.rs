@@ -0,0 +1,548 @@
+use crate::diagnostic::{closest, Diagnostic};
+use crate::error::ElementsError;
+use crate::interpreter::{get_section, is_valid_variable};
+use crate::lang::types::{Param, Signature, Value};
+use crate::lexer::{is_function, Function, Span, Token, FUNCTION_NAMES};
+use crate::utils::limits::limits;
+
+/// The types of the values that can be told from the source alone
+const INT: Param = Param::OneOf(&["Int"]);
+const FLOAT: Param = Param::OneOf(&["Float"]);
+const STRING: Param = Param::OneOf(&["String"]);
+const POINT: Param = Param::OneOf(&["Point"]);
+const STYLE: Param = Param::OneOf(&["Style"]);
+const LAMBDA: Param = Param::OneOf(&["Lambda"]);
+
+/// The types of the values that draw nothing when left at the top level
+const DISCARDED: [&str; 4] = ["Int", "Float", "Bool", "Lambda"];
+
+/// A variable set in the source, with the types its value may have and where it was set
+struct Binding {
+    name: String,
+    param: Param,
+    span: Option<Span>,
+    is_read: bool,
+}
+
+/// The state of a pass over the source, holding the variables of each scope from the outermost in
+/// the order they were set, the problems found so far, how deeply the current call is nested, and
+/// whether the top-level variables are exported with `export-coords`
+struct Analyzer {
+    scopes: Vec<Vec<Binding>>,
+    diagnostics: Vec<Diagnostic>,
+    span: Option<Span>,
+    depth: usize,
+    is_exported: bool,
+}
+
+/// Given a slice of tokens and the span of each top-level expression, check them without evaluating anything,
+/// returning an error for every unknown function, every call whose arguments do not fit the function's signature,
+/// and every undefined variable, and a warning for every variable that is never used or hides another, and every top-level value
+/// that draws nothing
+pub fn analyze(tokens: &[Token], spans: &[Span]) -> Vec<Diagnostic> {
+    let mut analyzer = Analyzer {
+        scopes: vec![Vec::new()],
+        diagnostics: Vec::new(),
+        span: None,
+        depth: 0,
+        is_exported: false,
+    };
+    let mut i = 0;
+    let mut index = 0;
+    while i < tokens.len() {
+        // an expression is a single token unless it starts with a parenthesis
+        analyzer.span = spans.get(index).copied();
+        let length = match &tokens[i] {
+            Token::LeftParen => match get_section(tokens[i..].to_vec()) {
+                Ok(section) => section.len(),
+                Err(e) => {
+                    analyzer.error(e);
+                    break;
+                }
+            },
+            _ => 1,
+        };
+        let param = analyzer.expression(&tokens[i..i + length]);
+        analyzer.discarded(&tokens[i..i + length], param);
+        i += length;
+        index += 1;
+    }
+    analyzer.end_scope();
+
+    // unused variables are only found at the end, so put every problem back in the order of the source
+    let mut diagnostics = analyzer.diagnostics;
+    diagnostics.sort_by_key(|d| d.span.map(|span| (span.start.line, span.start.column)));
+    diagnostics
+}
+
+impl Analyzer {
+    /// Report an error in the current expression
+    fn error(&mut self, error: ElementsError) {
+        self.diagnostics.push(Diagnostic {
+            span: self.span,
+            ..Diagnostic::error(error.to_string())
+        });
+    }
+
+    /// Return the binding of a variable from the innermost scope that has it
+    fn lookup(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.iter_mut().rev().find(|b| b.name == name))
+    }
+
+    /// Report an undefined variable, suggesting a visible one it may be a misspelling of
+    fn undefined(&mut self, name: &str) {
+        let names = self.scopes.iter().flatten().map(|b| b.name.as_str());
+        let suggestion = closest(name, names).map(|name| format!("did you mean `{}`?", name));
+        self.diagnostics.push(Diagnostic {
+            span: self.span,
+            suggestion,
+            ..Diagnostic::error(format!("Undefined variable: {}", name))
+        });
+    }
+
+    /// Close the innermost scope, warning about each of its variables that was never read, except for
+    /// top-level points, which are drawn with a label, and top-level variables written out by `export-coords`
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        let is_top_level = self.scopes.is_empty();
+        if is_top_level && self.is_exported {
+            return;
+        }
+        for binding in scope {
+            if binding.is_read || (is_top_level && binding.param.overlaps(&POINT)) {
+                continue;
+            }
+            self.diagnostics.push(Diagnostic {
+                span: binding.span,
+                ..Diagnostic::warning(
+                    "unused-variable",
+                    format!("unused variable: {}", binding.name),
+                )
+            });
+        }
+    }
+
+    /// Warn about a top-level expression whose value draws nothing, so it is computed only to be thrown away
+    fn discarded(&mut self, tokens: &[Token], param: Param) {
+        let Param::OneOf(types) = param else {
+            return;
+        };
+        if !types.iter().all(|t| DISCARDED.contains(t)) {
+            return;
+        }
+        let message = match tokens.get(1) {
+            Some(Token::Function(f)) => format!(
+                "result of {} is discarded, since {} values draw nothing",
+                f.name, param
+            ),
+            _ => format!("value is discarded, since {} values draw nothing", param),
+        };
+        self.diagnostics.push(Diagnostic {
+            span: self.span,
+            suggestion: Some("set it to a variable with `setq` to use it later".to_string()),
+            ..Diagnostic::warning("discarded-result", message)
+        });
+    }
+
+    /// Return the types the value of an expression may have, reporting the problems found in it
+    fn expression(&mut self, tokens: &[Token]) -> Param {
+        match tokens {
+            [Token::Literal(l)] => literal(&l.value),
+            [Token::Variable(v)] => match self.variable(&v.name) {
+                Some(param) => param,
+                None => {
+                    self.undefined(&v.name);
+                    Param::Any
+                }
+            },
+            // calls nested deeper than evaluation allows are left for it to report
+            [Token::LeftParen, Token::Function(_), ..] if self.depth >= limits().max_depth => {
+                Param::Any
+            }
+            [Token::LeftParen, Token::Function(f), args @ .., Token::RightParen] => {
+                self.depth += 1;
+                let param = self.call(f, args);
+                self.depth -= 1;
+                param
+            }
+            _ => {
+                self.error(ElementsError::Parse(
+                    "Expected a single expression".to_string(),
+                ));
+                Param::Any
+            }
+        }
+    }
+
+    /// Return the types of a variable, marking it as read, or None if it is undefined, where quoted
+    /// strings and :keywords are strings
+    fn variable(&mut self, name: &str) -> Option<Param> {
+        if name.starts_with('"') || name.starts_with(':') {
+            return Some(STRING);
+        }
+        let binding = self.lookup(name)?;
+        binding.is_read = true;
+        Some(binding.param)
+    }
+
+    /// Split the tokens of the arguments of a call into the tokens of each argument
+    fn arguments<'a>(&mut self, tokens: &'a [Token]) -> Option<Vec<&'a [Token]>> {
+        let mut args: Vec<&[Token]> = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let length = match &tokens[i] {
+                Token::LeftParen => match get_section(tokens[i..].to_vec()) {
+                    Ok(section) => section.len(),
+                    Err(e) => {
+                        self.error(e);
+                        return None;
+                    }
+                },
+                Token::Literal(_) | Token::Variable(_) => 1,
+                token => {
+                    self.error(ElementsError::Parse(format!(
+                        "Unexpected token: {:?}",
+                        token
+                    )));
+                    return None;
+                }
+            };
+            args.push(&tokens[i..i + length]);
+            i += length;
+        }
+        Some(args)
+    }
+
+    /// Return the types of the value of a call, checking its arguments against the signature of the function
+    fn call(&mut self, function: &Function, tokens: &[Token]) -> Param {
+        // the arguments of an unknown function are still checked, but its value could be anything
+        if !is_function(&function.name) {
+            let suggestion = closest(&function.name, FUNCTION_NAMES)
+                .map(|name| format!("did you mean `{}`?", name));
+            self.diagnostics.push(Diagnostic {
+                span: self.span,
+                suggestion,
+                ..Diagnostic::error(format!("Unknown function: {}", function.name))
+            });
+        }
+        if function.name == "lambda" {
+            return self.lambda(tokens);
+        }
+        if function.name == "solve" {
+            return self.solve(tokens);
+        }
+        let Some(args) = self.arguments(tokens) else {
+            return Param::Any;
+        };
+        if matches!(function.name.as_str(), "setq" | "param" | "define-style") {
+            return self.bind(function, &args);
+        }
+        if function.name == "export-coords" {
+            self.is_exported = true;
+        }
+
+        // find the types of the positional arguments, remembering which are names not set as variables,
+        // where the value of a keyword may be any name, such as :color red
+        let mut types: Vec<Param> = Vec::new();
+        let mut names: Vec<Option<&str>> = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                [Token::Variable(v)] if v.name.starts_with(':') => {
+                    if let Some(value) = args.get(i + 1) {
+                        self.keyword_value(value);
+                    }
+                    i += 2;
+                    continue;
+                }
+                [Token::Variable(v)] => match self.variable(&v.name) {
+                    Some(param) => {
+                        types.push(param);
+                        names.push(None);
+                    }
+                    None => {
+                        types.push(STRING);
+                        names.push(Some(&v.name));
+                    }
+                },
+                arg => {
+                    types.push(self.expression(arg));
+                    names.push(None);
+                }
+            }
+            i += 1;
+        }
+
+        // a name is taken as a string where a string is expected, and is otherwise an undefined variable
+        let signature = function.function.signature();
+        let overloads = function.function.overloads();
+        let signatures: Vec<Signature> = signature.into_iter().chain(overloads.clone()).collect();
+        for (i, name) in names.iter().enumerate() {
+            let Some(name) = name else {
+                continue;
+            };
+            let takes_name = signatures.iter().any(
+                |s| matches!(s.param(i), Some(Param::OneOf(types)) if types.contains(&"String")),
+            );
+            if !takes_name {
+                self.undefined(name);
+                types[i] = Param::Any;
+            }
+        }
+
+        // check the arguments, returning the types of the value of the cases they fit
+        let result = match signature {
+            Some(signature) => signature.check(&types).map(|_| signature.returns),
+            None if !overloads.is_empty() => Signature::resolve(&overloads, &types).map(|_| {
+                let mut returns = overloads.iter().filter(|s| s.check(&types).is_ok());
+                let first = returns.next().map_or(Param::Any, |s| s.returns);
+                match returns.all(|s| s.returns == first) {
+                    true => first,
+                    false => Param::Any,
+                }
+            }),
+            None => Ok(Param::Any),
+        };
+        result.unwrap_or_else(|e| {
+            self.error(e);
+            Param::Any
+        })
+    }
+
+    /// Read the value of a keyword argument, which may be a name that is not a variable
+    fn keyword_value(&mut self, tokens: &[Token]) {
+        match tokens {
+            [Token::Variable(v)] => {
+                self.variable(&v.name);
+            }
+            tokens => {
+                self.expression(tokens);
+            }
+        }
+    }
+
+    /// Set the variable named by the first argument of setq, param, or define-style in the innermost scope
+    fn bind(&mut self, function: &Function, args: &[&[Token]]) -> Param {
+        let param = match function.name.as_str() {
+            "define-style" => {
+                for arg in args.iter().skip(1) {
+                    self.keyword_value(arg);
+                }
+                STYLE
+            }
+            _ => {
+                // the name is not a value, so only the number of arguments can be checked against the signature
+                let types: Vec<Param> = vec![Param::Any; args.len()];
+                let overloads = function.function.overloads();
+                let returns = match function.function.signature() {
+                    Some(signature) => signature.check(&types).map(|_| signature.returns),
+                    None => Signature::resolve(&overloads, &types).map(|i| overloads[i].returns),
+                };
+                let returns = match returns {
+                    Ok(returns) => returns,
+                    Err(e) => {
+                        self.error(e);
+                        return Param::Any;
+                    }
+                };
+                let value = self.expression(args[1]);
+                for arg in args.iter().skip(2) {
+                    self.expression(arg);
+                }
+                match returns {
+                    Param::Any => value,
+                    returns => returns,
+                }
+            }
+        };
+
+        // a name that is already set is read as its value, so it cannot be set again
+        let invalid = match function.name.as_str() {
+            "setq" => "Invalid variable name",
+            "param" => "Invalid parameter name",
+            _ => "Invalid style name",
+        };
+        let name = match args.first() {
+            Some([Token::Variable(v)]) if is_valid_variable(&v.name) => v.name.clone(),
+            _ => {
+                self.error(ElementsError::Type(invalid.to_string()));
+                return Param::Any;
+            }
+        };
+        if self.lookup(&name).is_some() {
+            self.error(ElementsError::Type(format!(
+                "{}: {} is already set",
+                invalid, name
+            )));
+            return Param::Any;
+        }
+        let binding = Binding {
+            name,
+            param,
+            span: self.span,
+            is_read: false,
+        };
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(binding);
+        }
+        Param::Any
+    }
+
+    /// Check the body of a lambda in a scope holding its parameters
+    fn lambda(&mut self, tokens: &[Token]) -> Param {
+        // collect the parameter names until the closing paren
+        let mut params: Vec<&str> = Vec::new();
+        let mut i = 1;
+        if tokens.first() != Some(&Token::LeftParen) {
+            self.error(ElementsError::Parse(
+                "Lambda requires a parameter list".to_string(),
+            ));
+            return LAMBDA;
+        }
+        loop {
+            match tokens.get(i) {
+                Some(Token::Function(f)) => params.push(&f.name),
+                Some(Token::Variable(v)) => params.push(&v.name),
+                Some(Token::RightParen) => break,
+                _ => {
+                    self.error(ElementsError::Parse(
+                        "Invalid lambda parameter list".to_string(),
+                    ));
+                    return LAMBDA;
+                }
+            }
+            i += 1;
+        }
+        if let Some(param) = params.iter().find(|param| !is_valid_variable(param)) {
+            self.error(ElementsError::Parse(format!(
+                "Invalid lambda parameter: {}",
+                param
+            )));
+            return LAMBDA;
+        }
+        let body = &tokens[i + 1..];
+        if body.is_empty() {
+            self.error(ElementsError::Parse("Lambda requires a body".to_string()));
+            return LAMBDA;
+        }
+
+        // a parameter named like a variable around the lambda hides it in the body
+        for param in &params {
+            if self.lookup(param).is_some() {
+                self.diagnostics.push(Diagnostic {
+                    span: self.span,
+                    ..Diagnostic::warning(
+                        "shadowed-variable",
+                        format!("lambda parameter {} shadows an earlier variable", param),
+                    )
+                });
+            }
+        }
+
+        // the parameters are only visible in the body
+        let scope = params
+            .into_iter()
+            .map(|name| Binding {
+                name: name.to_string(),
+                param: Param::Any,
+                span: self.span,
+                is_read: false,
+            })
+            .collect();
+        self.scopes.push(scope);
+        self.expression(body);
+        self.end_scope();
+        LAMBDA
+    }
+
+    /// Check the constraints of a solve in a scope holding its unknown points, then set them as variables
+    fn solve(&mut self, tokens: &[Token]) -> Param {
+        // collect the names of the unknown points until the closing paren
+        let mut unknowns: Vec<&str> = Vec::new();
+        let mut i = 1;
+        if tokens.first() != Some(&Token::LeftParen) {
+            self.error(ElementsError::Parse(
+                "Solve requires a list of unknown points".to_string(),
+            ));
+            return Param::Any;
+        }
+        loop {
+            match tokens.get(i) {
+                Some(Token::Function(f)) => unknowns.push(&f.name),
+                Some(Token::Variable(v)) => unknowns.push(&v.name),
+                Some(Token::RightParen) => break,
+                _ => {
+                    self.error(ElementsError::Parse(
+                        "Invalid list of unknown points".to_string(),
+                    ));
+                    return Param::Any;
+                }
+            }
+            i += 1;
+        }
+        if let Some(name) = unknowns.iter().find(|name| !is_valid_variable(name)) {
+            self.error(ElementsError::Parse(format!(
+                "Invalid unknown point: {}",
+                name
+            )));
+            return Param::Any;
+        }
+        if let Some(name) = unknowns
+            .iter()
+            .find(|name| self.scopes.iter().flatten().any(|b| b.name == **name))
+        {
+            self.error(ElementsError::Type(format!(
+                "Unknown point {} is already set",
+                name
+            )));
+            return Param::Any;
+        }
+
+        // the unknown points are visible in the constraints, and are then set like variables
+        let constraints = &tokens[i + 1..];
+        match constraints {
+            [Token::LeftParen, Token::Function(f), .., Token::RightParen]
+                if f.name == "constraints" => {}
+            _ => {
+                self.error(ElementsError::Parse(
+                    "Solve requires a list of constraints, such as (constraints (on? P c))"
+                        .to_string(),
+                ));
+                return Param::Any;
+            }
+        }
+        let scope: Vec<Binding> = unknowns
+            .iter()
+            .map(|name| Binding {
+                name: name.to_string(),
+                param: POINT,
+                span: self.span,
+                is_read: false,
+            })
+            .collect();
+        self.scopes.push(scope);
+        self.expression(constraints);
+        let scope = self.scopes.pop().unwrap_or_default();
+        if let Some(current) = self.scopes.last_mut() {
+            current.extend(scope.into_iter().map(|b| Binding {
+                is_read: false,
+                ..b
+            }));
+        }
+        Param::Any
+    }
+}
+
+/// Return the type of a literal
+fn literal(value: &Value) -> Param {
+    match value {
+        Value::Int(_) => INT,
+        Value::Float(_) => FLOAT,
+        _ => Param::Any,
+    }
+}
@@ -0,0 +1,380 @@
+use elements_lang::renderer::{parse_color, Layout, Length};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+/// A geometry markup language and diagram renderer
+#[derive(Debug, Parser)]
+#[command(
+    name = "elements",
+    version,
+    override_usage = "elements [OPTIONS] <INPUT>\n       elements <COMMAND> [OPTIONS]",
+    subcommand_negates_reqs = true,
+    args_override_self = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Options for rendering a file when no command is given
+    #[command(flatten)]
+    pub render: Option<RenderArgs>,
+
+    /// Show more of what is done, up to -vvv for every detail
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Show only errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print errors as text, or as one JSON object per line for editors and CI
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Human)]
+    pub error_format: ErrorFormat,
+}
+
+/// How errors are printed
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Render a file to a figure, which is also done when no command is given
+    Render(Box<RenderArgs>),
+    /// Render a file again whenever it changes, for a live preview
+    Watch(Box<RenderArgs>),
+    /// Render numbered variants of a file, with their own seeds and parameters, and a manifest of them
+    Generate(Box<GenerateArgs>),
+    /// Evaluate a single expression and print its value and properties
+    Explain(ExplainArgs),
+    /// Evaluate files without rendering them, printing every error found
+    Check(CheckArgs),
+    /// Check the claims and assertions of a file for many seeds, printing which hold
+    Verify(VerifyArgs),
+    /// Format files with canonical indentation, keeping comments
+    Fmt(FmtArgs),
+    /// Evaluate expressions line by line, keeping variables between them
+    Repl(ReplArgs),
+    /// Render figures sent over HTTP, for web apps and bots
+    Serve(ServeArgs),
+    /// Print a script completing commands and options in a shell
+    Completions(CompletionsArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct RenderArgs {
+    /// File to render, or - to read from stdin
+    pub input: String,
+
+    /// Write the figure to this path instead of one named after the input
+    #[arg(short, long, value_parser = parse_path)]
+    pub output: Option<String>,
+
+    /// Replace files that already exist
+    #[arg(long)]
+    pub force: bool,
+
+    /// Write only the figure to stdout instead of a file
+    #[arg(long, conflicts_with = "output")]
+    pub stdout: bool,
+
+    /// Output format, taken from the extension of the output if not given
+    #[arg(long, value_parser = ["svg", "png", "pdf", "html"])]
+    pub format: Option<String>,
+
+    /// Arrange every figure in one output: row, column, or grid
+    #[arg(long, value_parser = parse_layout)]
+    pub layout: Option<Layout>,
+
+    /// Label points automatically
+    #[arg(long, overrides_with = "no_label")]
+    pub label: bool,
+
+    /// Do not label points, even if elements.toml does
+    #[arg(long, overrides_with = "label")]
+    pub no_label: bool,
+
+    /// Same as -vvv
+    #[arg(long, hide = true)]
+    pub debug: bool,
+
+    /// Make the y-axis point up
+    #[arg(long, overrides_with = "no_flip_y")]
+    pub flip_y: bool,
+
+    /// Keep the y-axis pointing down, even if elements.toml flips it
+    #[arg(long, overrides_with = "flip_y")]
+    pub no_flip_y: bool,
+
+    /// Draw a grid behind the figure
+    #[arg(long, overrides_with = "no_grid")]
+    pub grid: bool,
+
+    /// Do not draw a grid, even if elements.toml does
+    #[arg(long, overrides_with = "grid")]
+    pub no_grid: bool,
+
+    /// Add a legend of the named styled values
+    #[arg(long, overrides_with = "no_legend")]
+    pub legend: bool,
+
+    /// Do not add a legend, even if elements.toml does
+    #[arg(long, overrides_with = "legend")]
+    pub no_legend: bool,
+
+    /// Also write the values of the named variables to this path, as JSON if it ends in .json or else as CSV
+    #[arg(long, value_name = "PATH", value_parser = parse_path)]
+    pub emit_coords: Option<String>,
+
+    /// Leave out the title, description, and source
+    #[arg(long)]
+    pub no_metadata: bool,
+
+    /// Animate the figure, drawing one value after another
+    #[arg(long)]
+    pub animate: bool,
+
+    /// Seconds between animated values, which implies --animate
+    #[arg(long, value_name = "SECONDS", value_parser = parse_non_negative)]
+    pub delay: Option<f64>,
+
+    /// Compute with the high precision kernel
+    #[arg(long)]
+    pub high_precision: bool,
+
+    /// Seed for random constructions
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Value of a parameter declared with param, in place of its default, such as r=2.5
+    #[arg(long = "param", value_name = "NAME=VALUE", value_parser = parse_param)]
+    pub params: Vec<(String, String)>,
+
+    /// Largest distance between values still taken as equal, such as a point and a circle it lies on
+    #[arg(long, value_name = "EPSILON", value_parser = parse_non_negative)]
+    pub tolerance: Option<f64>,
+
+    /// Most calls nested inside one another before evaluation stops
+    #[arg(long, value_name = "DEPTH")]
+    pub max_depth: Option<usize>,
+
+    /// Most calls and random samples before evaluation stops
+    #[arg(long, value_name = "STEPS")]
+    pub max_steps: Option<u64>,
+
+    /// Seconds evaluation may take before it stops
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive)]
+    pub timeout: Option<f64>,
+
+    /// Margin around the contents of the figure
+    #[arg(long, value_parser = parse_non_negative)]
+    pub padding: Option<f64>,
+
+    /// Width of the output, such as 400 or 10cm
+    #[arg(long, value_parser = parse_length)]
+    pub width: Option<Length>,
+
+    /// Height of the output, such as 400 or 10cm
+    #[arg(long, value_parser = parse_length)]
+    pub height: Option<Length>,
+
+    /// Dots per inch of PNG output
+    #[arg(long, default_value_t = 96.0, value_parser = parse_positive)]
+    pub dpi: f64,
+
+    /// Decimal places of numbers in the output
+    #[arg(long, value_name = "DIGITS")]
+    pub precision: Option<usize>,
+
+    /// Font family of labels
+    #[arg(long, value_parser = parse_font_family)]
+    pub font_family: Option<String>,
+
+    /// Font size of labels
+    #[arg(long, value_parser = parse_positive)]
+    pub font_size: Option<f64>,
+
+    /// Color of labels, as a name or hex code
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    pub font_color: Option<String>,
+
+    /// Color filling the whole figure, as a name or hex code
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    pub background: Option<String>,
+
+    /// Color of a frame around the figure, as a name or hex code
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    pub border: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct GenerateArgs {
+    #[command(flatten)]
+    pub render: RenderArgs,
+
+    /// Number of variants to render
+    #[arg(short = 'n', long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    pub count: u32,
+
+    /// Write the values of the variants to this path, as CSV if it ends in .csv or else as JSON
+    #[arg(long, value_parser = parse_path)]
+    pub manifest: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExplainArgs {
+    /// Expression to evaluate, such as '(midpoint (point 0 0) (point 2 2))'
+    pub expression: String,
+
+    /// Also render the expression to out.svg
+    #[arg(long)]
+    pub render: bool,
+
+    /// Write the rendered expression to this path instead of out.svg
+    #[arg(short, long, value_parser = parse_path, requires = "render")]
+    pub output: Option<String>,
+
+    /// Replace the rendered file if it already exists
+    #[arg(long, requires = "render")]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    /// Files to check, or - to read from stdin
+    #[arg(required = true)]
+    pub files: Vec<String>,
+
+    /// Check calls and variables without evaluating, reporting every problem at once
+    #[arg(long)]
+    pub analyze: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    /// File to verify, or - to read from stdin
+    pub input: String,
+
+    /// Number of seeds to evaluate the file with
+    #[arg(short = 'n', long, default_value_t = 20, value_parser = clap::value_parser!(u32).range(1..))]
+    pub seeds: u32,
+
+    /// First seed to evaluate the file with, where each one after it takes the next seed
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Value of a parameter declared with param, in place of its default, such as r=2.5
+    #[arg(long = "param", value_name = "NAME=VALUE", value_parser = parse_param)]
+    pub params: Vec<(String, String)>,
+
+    /// Largest distance between values still taken as equal, such as a point and a circle it lies on
+    #[arg(long, value_name = "EPSILON", value_parser = parse_non_negative)]
+    pub tolerance: Option<f64>,
+}
+
+#[derive(Debug, Args)]
+pub struct FmtArgs {
+    /// Files to format in place, or - to print stdin formatted
+    #[arg(required = true)]
+    pub files: Vec<String>,
+
+    /// Only report files that are not formatted, without changing them
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplArgs {
+    /// Label points automatically in saved figures
+    #[arg(long)]
+    pub label: bool,
+
+    /// Make the y-axis point up in saved figures
+    #[arg(long)]
+    pub flip_y: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// Port to listen on
+    #[arg(long, default_value_t = 8080)]
+    pub port: u16,
+
+    /// Address to listen on, such as 0.0.0.0 to accept figures from other machines
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: String,
+
+    /// Largest source accepted, in bytes
+    #[arg(long, value_name = "BYTES", default_value_t = 65536)]
+    pub max_size: usize,
+
+    /// Seconds a figure may take to evaluate and render before it is abandoned
+    #[arg(long, value_name = "SECONDS", default_value_t = 5.0, value_parser = parse_positive)]
+    pub timeout: f64,
+}
+
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    /// Shell to complete in
+    pub shell: Shell,
+}
+
+/// Parse the value of a parameter such as r=2.5 into its name and value
+fn parse_param(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if value.parse::<f64>().is_ok_and(|n| n.is_finite()) => {
+            Ok((name.to_string(), value.to_string()))
+        }
+        _ => Err("expected a name and a number, such as r=2.5".to_string()),
+    }
+}
+
+/// Parse a length such as 400 or 10cm
+fn parse_length(s: &str) -> Result<Length, String> {
+    Length::parse(s).map_err(|_| "expected a positive length, such as 400 or 10cm".to_string())
+}
+
+/// Parse the arrangement of figures in a layout
+fn parse_layout(s: &str) -> Result<Layout, String> {
+    match s {
+        "row" => Ok(Layout::Row),
+        "column" => Ok(Layout::Column),
+        "grid" => Ok(Layout::Grid),
+        _ => Err("expected row, column, or grid".to_string()),
+    }
+}
+
+/// Parse a finite number that is at least zero
+fn parse_non_negative(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(n) if n >= 0.0 && n.is_finite() => Ok(n),
+        _ => Err("expected a non-negative number".to_string()),
+    }
+}
+
+/// Parse a finite number that is greater than zero
+fn parse_positive(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(n) if n > 0.0 && n.is_finite() => Ok(n),
+        _ => Err("expected a positive number".to_string()),
+    }
+}
+
+/// Parse a non-empty file path
+fn parse_path(s: &str) -> Result<String, String> {
+    match s.is_empty() {
+        true => Err("expected a file path".to_string()),
+        false => Ok(s.to_string()),
+    }
+}
+
+/// Parse a font family name that can be written into an attribute
+fn parse_font_family(s: &str) -> Result<String, String> {
+    match s.is_empty() || s.contains(['"', '<', '&']) {
+        true => Err("expected a font family name".to_string()),
+        false => Ok(s.to_string()),
+    }
+}
@@ -0,0 +1,173 @@
+use crate::cli::RenderArgs;
+use elements_lang::renderer::parse_color;
+
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the file holding the default options of a project
+const CONFIG_NAME: &str = "elements.toml";
+
+/// Default options of a project, read from elements.toml, which flags given on the command line override
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct Config {
+    seed: Option<u64>,
+    padding: Option<f64>,
+    format: Option<String>,
+    precision: Option<usize>,
+    label: Option<bool>,
+    flip_y: Option<bool>,
+    grid: Option<bool>,
+    legend: Option<bool>,
+    tolerance: Option<f64>,
+    max_depth: Option<usize>,
+    max_steps: Option<u64>,
+    timeout: Option<f64>,
+    theme: Theme,
+}
+
+/// Appearance of the figures of a project
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct Theme {
+    font_family: Option<String>,
+    font_size: Option<f64>,
+    font_color: Option<String>,
+    background: Option<String>,
+    border: Option<String>,
+}
+
+/// Return the path of the configuration in the directory of the input or the closest directory above it
+fn find(input: &str) -> Option<PathBuf> {
+    let directory = match Path::new(input).parent() {
+        Some(parent) if input != "-" && !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    directory
+        .canonicalize()
+        .ok()?
+        .ancestors()
+        .map(|directory| directory.join(CONFIG_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Return the value if it is valid, or an error naming the key and what was expected
+fn check<T>(
+    value: Option<T>,
+    is_valid: impl Fn(&T) -> bool,
+    key: &str,
+    expected: &str,
+) -> Result<Option<T>, String> {
+    match value {
+        Some(value) if !is_valid(&value) => Err(format!("{} requires {}", key, expected)),
+        value => Ok(value),
+    }
+}
+
+/// Return the value of a switch given on the command line as either its flag or its --no- form, or None if
+/// neither was given
+fn switch(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Fill in the options not given on the command line from the configuration of the input's project, if
+/// there is one
+pub fn apply(args: &mut RenderArgs) -> Result<(), String> {
+    let Some(path) = find(&args.input) else {
+        return Ok(());
+    };
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let config: Config = toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    merge(args, config).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Fill in the options not given on the command line from the configuration
+fn merge(args: &mut RenderArgs, config: Config) -> Result<(), String> {
+    let theme = config.theme;
+    let color = |color: Option<String>| color.map(|color| parse_color(&color)).transpose();
+
+    // check the values as the flags of the same names would
+    let padding = check(
+        config.padding,
+        |n| *n >= 0.0 && n.is_finite(),
+        "padding",
+        "a non-negative number",
+    )?;
+    let font_size = check(
+        theme.font_size,
+        |n| *n > 0.0 && n.is_finite(),
+        "font-size",
+        "a positive number",
+    )?;
+    let tolerance = check(
+        config.tolerance,
+        |n| *n >= 0.0 && n.is_finite(),
+        "tolerance",
+        "a non-negative number",
+    )?;
+    let timeout = check(
+        config.timeout,
+        |n| *n > 0.0 && n.is_finite(),
+        "timeout",
+        "a positive number",
+    )?;
+    let format = check(
+        config.format,
+        |format| ["svg", "png", "pdf", "html"].contains(&format.as_str()),
+        "format",
+        "svg, png, pdf, or html",
+    )?;
+    let font_family = check(
+        theme.font_family,
+        |family| !family.is_empty() && !family.contains(['"', '<', '&']),
+        "font-family",
+        "a font family name",
+    )?;
+
+    // flags given on the command line take precedence over the configuration
+    args.seed = args.seed.or(config.seed);
+    args.padding = args.padding.or(padding);
+    args.precision = args.precision.or(config.precision);
+    args.label = switch(args.label, args.no_label)
+        .or(config.label)
+        .unwrap_or(false);
+    args.flip_y = switch(args.flip_y, args.no_flip_y)
+        .or(config.flip_y)
+        .unwrap_or(false);
+    args.grid = switch(args.grid, args.no_grid)
+        .or(config.grid)
+        .unwrap_or(false);
+    args.legend = switch(args.legend, args.no_legend)
+        .or(config.legend)
+        .unwrap_or(false);
+    args.tolerance = args.tolerance.or(tolerance);
+    args.max_depth = args.max_depth.or(config.max_depth);
+    args.max_steps = args.max_steps.or(config.max_steps);
+    args.timeout = args.timeout.or(timeout);
+    args.font_family = args.font_family.take().or(font_family);
+    args.font_size = args.font_size.or(font_size);
+    args.font_color = args.font_color.take().or(color(theme.font_color)?);
+    args.background = args.background.take().or(color(theme.background)?);
+    args.border = args.border.take().or(color(theme.border)?);
+
+    // the extension of an output path given on the command line also takes precedence over the format
+    let is_named = args.output.as_deref().is_some_and(|output| {
+        Path::new(output)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                ["svg", "png", "pdf", "html"]
+                    .iter()
+                    .any(|format| extension.eq_ignore_ascii_case(format))
+            })
+    });
+    if args.format.is_none() && !is_named {
+        args.format = format;
+    }
+    Ok(())
+}
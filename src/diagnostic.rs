@@ -0,0 +1,180 @@
+use crate::error::ElementsError;
+use crate::export::string_json;
+use crate::lexer::Span;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// How serious a diagnostic is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    /// Return the name of the severity
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A problem found in source code, with where it was found and how it might be fixed when known
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub file: Option<String>,
+    pub span: Option<Span>,
+    pub suggestion: Option<String>,
+    /// The name of the lint a warning comes from, by which it can be allowed
+    pub lint: Option<&'static str>,
+}
+
+impl Diagnostic {
+    /// Create an error with only a message
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            file: None,
+            span: None,
+            suggestion: None,
+            lint: None,
+        }
+    }
+
+    /// Create a warning from a lint with only a message
+    pub fn warning(lint: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            lint: Some(lint),
+            ..Self::error(message)
+        }
+    }
+
+    /// Return whether the diagnostic is a warning allowed by a comment on the line its expression starts on
+    pub fn is_allowed(&self, allowed: &HashMap<usize, Vec<String>>) -> bool {
+        let (Some(lint), Some(span)) = (self.lint, self.span) else {
+            return false;
+        };
+        allowed
+            .get(&span.start.line)
+            .is_some_and(|lints| lints.is_empty() || lints.iter().any(|l| l == lint))
+    }
+
+    /// Return the diagnostic as a JSON object on one line, with null for the fields that are not known
+    pub fn to_json(&self) -> String {
+        let span = match self.span {
+            Some(span) => format!(
+                "{{\"start\":{{\"line\":{},\"column\":{}}},\"end\":{{\"line\":{},\"column\":{}}}}}",
+                span.start.line, span.start.column, span.end.line, span.end.column
+            ),
+            None => "null".to_string(),
+        };
+        let optional = |s: &Option<String>| s.as_deref().map_or("null".to_string(), string_json);
+        format!(
+            "{{\"severity\":{},\"message\":{},\"file\":{},\"span\":{},\"suggestion\":{},\"lint\":{}}}",
+            string_json(self.severity.name()),
+            string_json(&self.message),
+            optional(&self.file),
+            span,
+            optional(&self.suggestion),
+            self.lint.map_or("null".to_string(), string_json)
+        )
+    }
+}
+
+impl From<String> for Diagnostic {
+    fn from(message: String) -> Self {
+        Self::error(message)
+    }
+}
+
+impl From<ElementsError> for Diagnostic {
+    fn from(error: ElementsError) -> Self {
+        Self::error(error.to_string())
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// Write the diagnostic after its file and position, or after its severity if it has no file, where
+    /// warnings are marked as such after their file
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.span) {
+            (Some(file), Some(span)) => {
+                write!(f, "{}:{}:{}: ", file, span.start.line, span.start.column)?
+            }
+            (Some(file), None) => write!(f, "{}: ", file)?,
+            (None, _) => match self.severity {
+                Severity::Error => write!(f, "Error: ")?,
+                Severity::Warning => write!(f, "Warning: ")?,
+            },
+        }
+        if self.file.is_some() && self.severity == Severity::Warning {
+            write!(f, "warning: ")?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(lint) = self.lint {
+            write!(f, " [{}]", lint)?;
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\n  help: {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Given source code, return the lints allowed on each line by a comment such as `; allow unused-variable`,
+/// where a comment of only `; allow` allows every lint on its line
+pub fn allowed_lints(s: &str) -> HashMap<usize, Vec<String>> {
+    let mut allowed: HashMap<usize, Vec<String>> = HashMap::new();
+    for (index, line) in s.lines().enumerate() {
+        // a semicolon in a string does not start a comment, and a comment ends at a parenthesis
+        let mut is_string = false;
+        let comment = line.split(|c: char| {
+            is_string ^= c == '"';
+            c == ';' && !is_string
+        });
+        for comment in comment.skip(1) {
+            let comment = comment.split(['(', ')']).next().unwrap_or_default();
+            let mut words = comment
+                .split([' ', '\t', ',', ':'])
+                .filter(|w| !w.is_empty());
+            if words.next() == Some("allow") {
+                let lints = allowed.entry(index + 1).or_default();
+                lints.extend(words.map(String::from));
+            }
+        }
+    }
+    allowed
+}
+
+/// Given a name and the names it may have been meant as, return the closest one within two edits
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Given two strings, return the number of characters inserted, removed, or replaced to turn one into the
+/// other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a != *b);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
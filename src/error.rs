@@ -0,0 +1,73 @@
+use std::fmt;
+
+/// Why a figure could not be read, evaluated, or rendered, holding the message shown to the user
+#[derive(Clone, Debug, PartialEq)]
+pub enum ElementsError {
+    /// Source that cannot be evaluated as written, such as mismatched parentheses or an undefined variable
+    Parse(String),
+    /// An argument of the wrong type, or outside the values it can take
+    Type(String),
+    /// A function given the wrong number of arguments
+    Arity(String),
+    /// A construction that does not exist for the given values, such as the intersection of parallel lines
+    Geometry(String),
+    /// A figure that cannot be rendered or converted to the requested format
+    Render(String),
+    /// An evaluation stopped for nesting calls too deeply, taking too many steps, or running too long
+    Limit(String),
+}
+
+impl ElementsError {
+    /// Return the name of the kind of error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ElementsError::Parse(_) => "parse",
+            ElementsError::Type(_) => "type",
+            ElementsError::Arity(_) => "arity",
+            ElementsError::Geometry(_) => "geometry",
+            ElementsError::Render(_) => "render",
+            ElementsError::Limit(_) => "limit",
+        }
+    }
+
+    /// Return the message of the error
+    pub fn message(&self) -> &str {
+        match self {
+            ElementsError::Parse(message)
+            | ElementsError::Type(message)
+            | ElementsError::Arity(message)
+            | ElementsError::Geometry(message)
+            | ElementsError::Render(message)
+            | ElementsError::Limit(message) => message,
+        }
+    }
+
+    /// Return the error of the same kind with what was being done written before its message
+    pub fn context(self, context: &str) -> Self {
+        let message = format!("{}: {}", context, self.message());
+        match self {
+            ElementsError::Parse(_) => ElementsError::Parse(message),
+            ElementsError::Type(_) => ElementsError::Type(message),
+            ElementsError::Arity(_) => ElementsError::Arity(message),
+            ElementsError::Geometry(_) => ElementsError::Geometry(message),
+            ElementsError::Render(_) => ElementsError::Render(message),
+            ElementsError::Limit(_) => ElementsError::Limit(message),
+        }
+    }
+}
+
+impl fmt::Display for ElementsError {
+    /// Write the message of the error, which reads the same whatever its kind
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for ElementsError {}
+
+impl From<ElementsError> for String {
+    /// Keep only the message, for callers that report every error as text
+    fn from(error: ElementsError) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,280 @@
+#[cfg(any(feature = "png", feature = "pdf"))]
+use crate::error::ElementsError;
+use crate::lang::environment::Environment;
+use crate::lang::types::{Point, Value};
+use crate::lexer::Token;
+use crate::renderer::Num;
+use crate::utils::geometry::distance;
+#[cfg(feature = "png")]
+use resvg::tiny_skia;
+
+/// Default width of figures exported to other formats when no size was given
+pub const DEFAULT_EXPORT_WIDTH: &str = "4in";
+
+/// Runtime embedded in interactive HTML output, which recomputes the figure as free points are dragged
+const INTERACTIVE_RUNTIME: &str = include_str!("interactive.js");
+
+/// Function to wrap a rendered SVG into an HTML page with the interactive runtime and its construction
+pub fn to_html(svg: &str, construction: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Elements figure</title>\n<style>\nsvg {{ display: block; width: 100%; max-height: 95vh; touch-action: none; }}\n.handle {{ cursor: move; }}\n</style>\n</head>\n<body>\n{}\n<script type=\"application/json\" id=\"elements-data\">{}</script>\n<script>\n{}</script>\n</body>\n</html>\n",
+        svg,
+        construction.replace("</", "<\\/"),
+        INTERACTIVE_RUNTIME
+    )
+}
+
+/// Function to describe a construction as JSON for the interactive runtime, with the source of each top-level form
+/// and where the labels added for point variables start
+pub fn construction_json(tokens: &[Token], values: &[Value], flip_y: bool) -> String {
+    let mut forms: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        forms.push(form_json(tokens, &mut i));
+    }
+
+    // labels for point variables are evaluated after the forms, as their name followed by their position
+    let labels: Vec<String> = values
+        .iter()
+        .enumerate()
+        .skip(forms.len())
+        .filter_map(|(index, value)| match value {
+            Value::String(s) => {
+                let mut parts = s.rsplitn(3, ' ');
+                let y = parts.next()?.parse::<f64>().ok()?;
+                let x = parts.next()?.parse::<f64>().ok()?;
+                Some(format!("[{},{},{}]", index, number_json(x), number_json(y)))
+            }
+            _ => None,
+        })
+        .collect();
+
+    format!(
+        "{{\"flip\":{},\"forms\":[{}],\"labels\":[{}]}}",
+        flip_y,
+        forms.join(","),
+        labels.join(",")
+    )
+}
+
+/// Function to write one form starting at the given token as JSON, with calls as arrays headed by the function name
+/// and variables as objects
+fn form_json(tokens: &[Token], i: &mut usize) -> String {
+    let token = &tokens[*i];
+    *i += 1;
+    match token {
+        Token::LeftParen => {
+            let mut items: Vec<String> = Vec::new();
+            while *i < tokens.len() && tokens[*i] != Token::RightParen {
+                match &tokens[*i] {
+                    Token::Function(f) => {
+                        items.push(string_json(&f.name));
+                        *i += 1;
+                    }
+                    _ => items.push(form_json(tokens, i)),
+                }
+            }
+            *i += 1;
+            format!("[{}]", items.join(","))
+        }
+        Token::Literal(l) => match l.value {
+            Value::Int(n) => n.to_string(),
+            Value::Float(f) => number_json(f),
+            _ => "null".to_string(),
+        },
+        Token::Variable(v) => format!("{{\"v\":{}}}", string_json(&v.name)),
+        Token::Function(f) => string_json(&f.name),
+        Token::RightParen => "null".to_string(),
+    }
+}
+
+/// Function to describe the top-level variables as CSV for answer keys, with a row for each of the points, numbers,
+/// angles, segments, and circles in order of name, giving its coordinates and its measure where it has them
+pub fn coords_csv(variables: &Environment) -> String {
+    let cell = |n: Option<f64>| match n {
+        Some(n) if n.is_finite() => Num(n).to_string(),
+        _ => String::new(),
+    };
+    let mut lines = vec!["name,type,x,y,value".to_string()];
+    for (name, kind, point, value) in coords(variables) {
+        lines.push(format!(
+            "{},{},{},{},{}",
+            name,
+            kind,
+            cell(point.map(|p| p.x)),
+            cell(point.map(|p| p.y)),
+            cell(value)
+        ));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Function to describe the top-level variables as JSON for answer keys, as an object from the name of each of the
+/// points, numbers, angles, segments, and circles to its type, coordinates, and measure
+pub fn coords_json(variables: &Environment) -> String {
+    let number = |n: f64| match n.is_finite() {
+        true => Num(n).to_string(),
+        false => "null".to_string(),
+    };
+    let entries: Vec<String> = coords(variables)
+        .into_iter()
+        .map(|(name, kind, point, value)| {
+            let mut fields = vec![format!("\"type\":{}", string_json(kind))];
+            if let Some(point) = point {
+                fields.push(format!("\"x\":{}", number(point.x)));
+                fields.push(format!("\"y\":{}", number(point.y)));
+            }
+            if let Some(value) = value {
+                fields.push(format!("\"value\":{}", number(value)));
+            }
+            format!("  {}:{{{}}}", string_json(name), fields.join(","))
+        })
+        .collect();
+    format!("{{\n{}\n}}\n", entries.join(",\n"))
+}
+
+/// Function to list the top-level variables that have coordinates or a measure, in order of name, with the type of
+/// each, its position, and its measure, which is the degrees of an angle, the length of a segment, and the radius of
+/// a circle
+fn coords(variables: &Environment) -> Vec<(&str, &'static str, Option<Point>, Option<f64>)> {
+    let mut variables: Vec<(&String, &Value)> = variables.locals().collect();
+    variables.sort_by_key(|(name, _)| *name);
+    variables
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let (kind, point, value) = match value.unstyled() {
+                Value::Point(p) => ("point", Some(*p), None),
+                Value::Int(n) => ("number", None, Some(*n as f64)),
+                Value::Float(f) => ("number", None, Some(*f)),
+                Value::Angle(a) => ("angle", Some(a.center), Some(a.degrees())),
+                Value::Lineseg(l) => ("segment", None, Some(distance(l.start, l.end))),
+                Value::Circle(c) => ("circle", Some(c.center), Some(c.radius)),
+                _ => return None,
+            };
+            Some((name.as_str(), kind, point, value))
+        })
+        .collect()
+}
+
+/// Function to write a number as JSON, where numbers that JSON cannot hold are null
+fn number_json(n: f64) -> String {
+    if n.is_finite() {
+        n.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Function to write a string as JSON, escaping quotes, backslashes, and control characters
+pub fn string_json(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Function to rasterize a rendered SVG into PNG data at the given dots per inch
+#[cfg(feature = "png")]
+pub fn to_png(svg: &str, dpi: f64) -> Result<Vec<u8>, ElementsError> {
+    let tree = parse_tree(svg)?;
+
+    // SVG pixels are 1/96 of an inch, so scale them up to the requested resolution
+    let scale = (dpi / 96.0) as f32;
+    let size = tree
+        .size
+        .to_int_size()
+        .scale_by(scale)
+        .ok_or(ElementsError::Render(
+            "Figure is too small to rasterize".to_string(),
+        ))?;
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or(
+        ElementsError::Render("Figure is too large to rasterize".to_string()),
+    )?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    pixmap
+        .encode_png()
+        .map_err(|e| ElementsError::Render(e.to_string()))
+}
+
+/// Function to convert a rendered SVG into a single page vector PDF the size of the figure
+#[cfg(feature = "pdf")]
+pub fn to_pdf(svg: &str) -> Result<Vec<u8>, ElementsError> {
+    let tree = parse_tree(svg)?;
+
+    // SVG pixels are 1/96 of an inch, so the page keeps the figure's physical size
+    let options = svg2pdf::Options {
+        dpi: 96.0,
+        ..svg2pdf::Options::default()
+    };
+    Ok(svg2pdf::convert_tree(&tree, options))
+}
+
+/// Function to parse a rendered SVG, turning its labels into paths with the system fonts
+#[cfg(any(feature = "png", feature = "pdf"))]
+fn parse_tree(svg: &str) -> Result<usvg::Tree, ElementsError> {
+    use usvg::{TreeParsing, TreePostProc};
+
+    let mut tree = usvg::Tree::from_str(svg, &usvg::Options::default())
+        .map_err(|e| ElementsError::Render(e.to_string()))?;
+    let mut fontdb = usvg::fontdb::Database::new();
+    fontdb.load_system_fonts();
+    set_generic_families(&mut fontdb);
+    tree.postprocess(usvg::PostProcessingSteps::default(), &fontdb);
+    Ok(tree)
+}
+
+/// Point the generic font families at installed fonts, since the defaults are only found on some systems
+#[cfg(any(feature = "png", feature = "pdf"))]
+fn set_generic_families(fontdb: &mut usvg::fontdb::Database) {
+    let installed = |candidates: &[&str]| {
+        candidates
+            .iter()
+            .find(|name| {
+                fontdb
+                    .faces()
+                    .any(|face| face.families.iter().any(|(family, _)| family == *name))
+            })
+            .map(|name| name.to_string())
+    };
+    let serif = installed(&[
+        "Times New Roman",
+        "Liberation Serif",
+        "DejaVu Serif",
+        "Noto Serif",
+    ]);
+    let sans_serif = installed(&[
+        "Arial",
+        "Helvetica",
+        "Liberation Sans",
+        "DejaVu Sans",
+        "Noto Sans",
+    ]);
+    let monospace = installed(&[
+        "Courier New",
+        "Liberation Mono",
+        "DejaVu Sans Mono",
+        "Noto Sans Mono",
+    ]);
+
+    if let Some(family) = serif {
+        fontdb.set_serif_family(family);
+    }
+    if let Some(family) = sans_serif {
+        fontdb.set_sans_serif_family(family);
+    }
+    if let Some(family) = monospace {
+        fontdb.set_monospace_family(family);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::render_source;
+use crate::renderer::{set_precision, RenderOptions};
+use crate::utils::limits::{set_limits, Limits};
+use crate::utils::params;
+use crate::utils::random::set_seed;
+use crate::utils::tolerance::set_default_tolerance;
+use crate::TOLERANCE;
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+thread_local! {
+    /// The error from the last failed call on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Options for rendering from C, matching the command line flags of the same names, where a negative
+/// padding or precision leaves it unset and the seed is only used if has_seed is set
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ElementsOptions {
+    pub label: bool,
+    pub flip_y: bool,
+    pub grid: bool,
+    pub legend: bool,
+    pub padding: f64,
+    pub precision: i32,
+    pub has_seed: bool,
+    pub seed: u64,
+}
+
+/// The outcome of a call, telling the caller whether to read its result or elements_last_error
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementsStatus {
+    /// The call succeeded
+    Ok = 0,
+    /// A pointer was null or the source was not valid UTF-8
+    InvalidArgument = 1,
+    /// The source could not be evaluated or rendered
+    Error = 2,
+    /// The renderer panicked, which is a bug in the library rather than in the source
+    Panic = 3,
+}
+
+/// Remember the error for elements_last_error, returning its status to the caller
+fn fail(status: ElementsStatus, error: String) -> ElementsStatus {
+    let error = CString::new(error.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(error));
+    status
+}
+
+/// Return the message a panic was raised with, which is a string for panics raised with one
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str));
+    match message {
+        Some(message) => format!("the renderer panicked: {}", message),
+        None => "the renderer panicked".to_string(),
+    }
+}
+
+/// Render the source to an SVG string written to `svg`, or return the status of the failure and keep the
+/// error for elements_last_error, where a panic in the renderer is caught rather than unwinding into the
+/// caller. The string must be released with elements_free.
+///
+/// # Safety
+///
+/// `source` must be a valid null-terminated string, `options` either null for the defaults or a valid
+/// pointer to options, and `svg` a valid pointer to write the string to.
+#[no_mangle]
+pub unsafe extern "C" fn elements_render(
+    source: *const c_char,
+    options: *const ElementsOptions,
+    svg: *mut *mut c_char,
+) -> ElementsStatus {
+    if svg.is_null() {
+        return fail(ElementsStatus::InvalidArgument, "svg is null".to_string());
+    }
+    *svg = ptr::null_mut();
+    if source.is_null() {
+        return fail(
+            ElementsStatus::InvalidArgument,
+            "source is null".to_string(),
+        );
+    }
+    let source = match CStr::from_ptr(source).to_str() {
+        Ok(source) => source,
+        Err(_) => {
+            return fail(
+                ElementsStatus::InvalidArgument,
+                "source is not valid UTF-8".to_string(),
+            )
+        }
+    };
+
+    // start every call from the defaults, as an earlier call on this thread may have changed the state of the
+    // thread, or left it half set if it panicked
+    set_limits(Limits::default());
+    set_default_tolerance(TOLERANCE);
+    params::set_overrides(Vec::new());
+    set_precision(None);
+    set_seed(rand::random());
+
+    // set the options, leaving the defaults for those not given
+    let mut render_options = RenderOptions::default();
+    if let Some(options) = options.as_ref() {
+        render_options.is_label = options.label;
+        render_options.flip_y = options.flip_y;
+        render_options.grid = options.grid.then_some(1.0);
+        render_options.legend = options.legend;
+        render_options.padding = (options.padding >= 0.0).then_some(options.padding);
+        set_precision(usize::try_from(options.precision).ok());
+        if options.has_seed {
+            set_seed(options.seed);
+        }
+    }
+
+    // a panic leaves the state of the thread half set, which the next call resets before rendering
+    let result = panic::catch_unwind(AssertUnwindSafe(|| render_source(source, &render_options)));
+    match result {
+        Ok(Ok(rendered)) => match CString::new(rendered) {
+            Ok(rendered) => {
+                *svg = rendered.into_raw();
+                ElementsStatus::Ok
+            }
+            Err(e) => fail(ElementsStatus::Error, e.to_string()),
+        },
+        Ok(Err(e)) => fail(ElementsStatus::Error, e.to_string()),
+        Err(payload) => fail(ElementsStatus::Panic, panic_message(&*payload)),
+    }
+}
+
+/// Return the error from the last failed call on this thread, including the message of a caught panic,
+/// or null if there was none. The string is owned by the library and stays valid until the next failed
+/// call.
+#[no_mangle]
+pub extern "C" fn elements_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Release a string returned by elements_render.
+///
+/// # Safety
+///
+/// `svg` must be null or a string returned by elements_render that has not been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn elements_free(svg: *mut c_char) {
+    if !svg.is_null() {
+        drop(CString::from_raw(svg));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::error::ElementsError;
+
+/// The column that expressions are kept within when they fit on one line
+const WIDTH: usize = 100;
+
+/// Part of the source kept by the formatter, which unlike tokens includes comments and blank lines
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Atom(String),
+    List(Vec<Node>),
+    /// A comment, starting at its semicolon, and whether it follows code on the same line
+    Comment(String, bool),
+    Blank,
+}
+
+/// A line of formatted output, with the comment at its end kept apart so closing parentheses can go
+/// before it
+#[derive(Debug, Default)]
+struct Line {
+    code: String,
+    comment: Option<String>,
+}
+
+impl Line {
+    /// Add a comment to the end of the line, after any comment already there
+    fn add_comment(&mut self, comment: &str) {
+        self.comment = Some(match self.comment.take() {
+            Some(existing) => format!("{} {}", existing, comment),
+            None => comment.to_string(),
+        });
+    }
+}
+
+/// Given source code, return it formatted with one top-level expression per line, arguments that do not
+/// fit on one line aligned under the first, and comments and single blank lines kept in place
+pub fn format(source: &str) -> Result<String, ElementsError> {
+    let nodes = parse(source)?;
+
+    // top-level expressions start at the first column, with blank lines between them kept
+    let mut lines: Vec<Line> = Vec::new();
+    let mut is_blank = false;
+    for node in nodes {
+        match node {
+            Node::Blank => is_blank = !lines.is_empty(),
+            Node::Comment(comment, true) if !lines.is_empty() => {
+                lines.last_mut().unwrap().add_comment(&comment)
+            }
+            node => {
+                if is_blank {
+                    lines.push(Line::default());
+                    is_blank = false;
+                }
+                match node {
+                    Node::Comment(comment, _) => lines.push(Line {
+                        code: String::new(),
+                        comment: Some(comment),
+                    }),
+                    node => lines.extend(layout(&node, 0)),
+                }
+            }
+        }
+    }
+
+    // write each line with its comment after the code
+    let mut formatted = String::new();
+    for line in lines {
+        formatted += &line.code;
+        if let Some(comment) = line.comment {
+            if !line.code.trim().is_empty() {
+                formatted.push(' ');
+            }
+            formatted += &comment;
+        }
+        formatted.push('\n');
+    }
+    Ok(formatted)
+}
+
+/// Given source code, return its atoms, lists, comments, and blank lines
+fn parse(source: &str) -> Result<Vec<Node>, ElementsError> {
+    let mut stack: Vec<Vec<Node>> = vec![Vec::new()];
+    let mut newlines = 0;
+    let mut is_line_start = true;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        // count the newlines before each node, where more than one leaves a blank line
+        if c == '\n' {
+            newlines += 1;
+            is_line_start = true;
+            continue;
+        }
+        if c.is_whitespace() {
+            continue;
+        }
+        let nodes = stack.last_mut().unwrap();
+        if newlines > 1 {
+            nodes.push(Node::Blank);
+        }
+        newlines = 0;
+
+        match c {
+            '(' => stack.push(Vec::new()),
+            ')' => {
+                let list = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some(nodes) => nodes.push(Node::List(list)),
+                    None => return Err(ElementsError::Parse("Mismatched parentheses".to_string())),
+                }
+            }
+            ';' => {
+                let mut comment = String::from(';');
+                while let Some(c) = chars.next_if(|c| *c != '\n') {
+                    comment.push(c);
+                }
+                nodes.push(Node::Comment(
+                    comment.trim_end().to_string(),
+                    !is_line_start,
+                ));
+            }
+            _ => {
+                // strings are read whole, so spaces inside them are kept
+                let mut atom = String::from(c);
+                let mut is_string = c == '"';
+                while let Some(c) = chars.next_if(|c| {
+                    is_string && *c != '\n' || !(c.is_whitespace() || matches!(c, '(' | ')' | ';'))
+                }) {
+                    atom.push(c);
+                    if c == '"' {
+                        is_string = !is_string;
+                    }
+                }
+                nodes.push(Node::Atom(atom));
+            }
+        }
+        is_line_start = false;
+    }
+
+    // every list must be closed
+    match (stack.pop(), stack.is_empty()) {
+        (Some(nodes), true) => Ok(nodes),
+        _ => Err(ElementsError::Parse("Mismatched parentheses".to_string())),
+    }
+}
+
+/// Given a node, return it on one line if no code follows a comment inside it
+fn flat(node: &Node) -> Option<Line> {
+    let nodes = match node {
+        Node::Atom(atom) => {
+            return Some(Line {
+                code: atom.clone(),
+                comment: None,
+            })
+        }
+        Node::List(nodes) => nodes,
+        Node::Comment(..) | Node::Blank => return None,
+    };
+
+    // comments at the end of a list are moved after its closing parenthesis
+    let mut line = Line {
+        code: "(".to_string(),
+        comment: None,
+    };
+    for node in nodes {
+        match node {
+            Node::Blank => {}
+            Node::Comment(comment, true) => line.add_comment(comment),
+            Node::Comment(_, false) => return None,
+            node => {
+                if line.comment.is_some() {
+                    return None;
+                }
+                let inner = flat(node)?;
+                if line.code.len() > 1 {
+                    line.code.push(' ');
+                }
+                line.code += &inner.code;
+                line.comment = inner.comment;
+            }
+        }
+    }
+    line.code.push(')');
+    Some(line)
+}
+
+/// Given a node starting at a column, return its lines, where the first line continues the line it starts
+/// on and later lines are indented from the first column
+fn layout(node: &Node, column: usize) -> Vec<Line> {
+    // keep the node on one line if it fits
+    let nodes = match (flat(node), node) {
+        (Some(line), _) if column + line.code.len() <= WIDTH => return vec![line],
+        (_, Node::List(nodes)) => nodes,
+        (_, Node::Atom(atom)) => {
+            return vec![Line {
+                code: atom.clone(),
+                comment: None,
+            }]
+        }
+        _ => return Vec::new(),
+    };
+
+    // a function name is followed by its first argument, and the others are aligned under it
+    let (align, inline) = match nodes.first() {
+        Some(Node::Atom(name)) => (column + name.len() + 2, 2),
+        _ => (column + 1, 1),
+    };
+    let mut lines = vec![Line {
+        code: "(".to_string(),
+        comment: None,
+    }];
+    let mut placed = 0;
+    for node in nodes {
+        match node {
+            Node::Blank => {}
+            Node::Comment(comment, true) => lines.last_mut().unwrap().add_comment(comment),
+            Node::Comment(comment, false) => lines.push(Line {
+                code: " ".repeat(align),
+                comment: Some(comment.clone()),
+            }),
+            node => {
+                let is_first_line = lines.len() == 1 && lines[0].comment.is_none();
+                if placed < inline && is_first_line {
+                    let first = &mut lines[0];
+                    if placed > 0 {
+                        first.code.push(' ');
+                    }
+                    let mut sublines = layout(node, column + first.code.len()).into_iter();
+                    let subline = sublines.next().unwrap_or_default();
+                    first.code += &subline.code;
+                    first.comment = subline.comment;
+                    lines.extend(sublines);
+                } else {
+                    let mut sublines = layout(node, align).into_iter();
+                    let subline = sublines.next().unwrap_or_default();
+                    lines.push(Line {
+                        code: " ".repeat(align) + &subline.code,
+                        comment: subline.comment,
+                    });
+                    lines.extend(sublines);
+                }
+                placed += 1;
+            }
+        }
+    }
+    lines.last_mut().unwrap().code.push(')');
+    lines
+}
@@ -0,0 +1,201 @@
+use crate::cli::RenderArgs;
+use crate::{output_format, output_path, run};
+use elements_lang::diagnostic::Diagnostic;
+use elements_lang::export::string_json;
+use elements_lang::interpreter::evaluate;
+use elements_lang::lang::types::{Directive, Value};
+use elements_lang::lexer::tokenize;
+use elements_lang::utils::params::{self, Parameter};
+use elements_lang::utils::random;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A variant written by generate, with the seed and parameter values it was drawn with
+struct Variant {
+    path: PathBuf,
+    seed: u64,
+    values: Vec<(String, String)>,
+}
+
+/// Render numbered variants of the input, each with the next seed and its own random value for every
+/// parameter declared with a range, and write a manifest of the values of each variant
+pub fn generate(args: &RenderArgs, count: u32, manifest: Option<&str>) -> Result<(), Diagnostic> {
+    // the input is read once for each variant, and each variant is written to its own file
+    if args.input == "-" || args.stdout {
+        return Err(
+            "generate requires an input file and writes each variant to a file"
+                .to_string()
+                .into(),
+        );
+    }
+    let source = fs::read_to_string(&args.input)
+        .map_err(|e| format!("failed to read {}: {}", args.input, e))?;
+
+    // evaluate the input once to find its parameters, where figures would be written over by each variant
+    let values = evaluate(tokenize(source))?;
+    let is_split = values
+        .iter()
+        .any(|v| matches!(v, Value::Directive(Directive::Figure { .. })));
+    if is_split && args.layout.is_none() {
+        return Err(
+            "generate writes one file per variant, so figures must be combined with --layout"
+                .to_string()
+                .into(),
+        );
+    }
+    let parameters = params::declared();
+
+    // variants are numbered beside the output, with the manifest next to them
+    let format = output_format(args);
+    let main = output_path(&args.input, args.output.as_deref(), None, format);
+    let stem = main
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let manifest = match manifest {
+        Some(manifest) => PathBuf::from(manifest),
+        None => main.with_file_name(format!("{}-manifest.json", stem)),
+    };
+    if !args.force && manifest.exists() {
+        return Err(format!(
+            "{} already exists, pass --force to overwrite it",
+            manifest.display()
+        )
+        .into());
+    }
+
+    // the first variant takes the seed of the run, and each one after it the next seed
+    let first = random::seed();
+    let mut variants: Vec<Variant> = Vec::new();
+    for i in 0..count {
+        let seed = first.wrapping_add(i as u64);
+        random::set_seed(seed);
+        let values: Vec<(String, String)> = parameters
+            .iter()
+            .map(|parameter| (parameter.name.clone(), choose(parameter, args)))
+            .collect();
+
+        // the figure is drawn from the start of the seed, so the manifest alone is enough to draw it again,
+        // and names given with --param that the figure never declares are still reported by run
+        random::set_seed(seed);
+        params::set_overrides([args.params.clone(), values.clone()].concat());
+        let path = numbered(&main, i + 1);
+        let variant = RenderArgs {
+            output: Some(path.to_string_lossy().into_owned()),
+            format: Some(format.to_string()),
+            emit_coords: args.emit_coords.as_ref().map(|coords| {
+                numbered(Path::new(coords), i + 1)
+                    .to_string_lossy()
+                    .into_owned()
+            }),
+            ..args.clone()
+        };
+        run(&variant, args.force)?;
+        log::info!("rendered variant {} with seed {}", i + 1, seed);
+        variants.push(Variant { path, seed, values });
+    }
+
+    // write the manifest as CSV if its extension asks for it, or else as JSON
+    let is_csv = manifest
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+    let text = match is_csv {
+        true => manifest_csv(&variants, &parameters),
+        false => manifest_json(&variants),
+    };
+    fs::write(&manifest, text)
+        .map_err(|e| format!("failed to write {}: {}", manifest.display(), e))?;
+    log::info!("wrote {}", manifest.display());
+    Ok(())
+}
+
+/// Return the path of a numbered variant of a file, with its number after the name, such as figure-3.svg
+fn numbered(path: &Path, n: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(extension) => {
+            path.with_file_name(format!("{}-{}.{}", stem, n, extension.to_string_lossy()))
+        }
+        None => path.with_file_name(format!("{}-{}", stem, n)),
+    }
+}
+
+/// Return the value of a parameter in a variant, which is the one given with --param if there is one, a
+/// random one in its range if it has one, and otherwise its default
+fn choose(parameter: &Parameter, args: &RenderArgs) -> String {
+    let given = args
+        .params
+        .iter()
+        .rev()
+        .find(|(name, _)| *name == parameter.name);
+    if let Some((_, value)) = given {
+        return value.clone();
+    }
+    match parameter.range {
+        // whole numbers stay whole, and other numbers are rounded to hundredths to read well on a worksheet
+        Some((min, max)) if parameter.is_integer => {
+            let n = min + (random::random() * (max - min + 1.0)).floor();
+            n.min(max).to_string()
+        }
+        Some((min, max)) => {
+            let n = ((min + random::random() * (max - min)) * 100.0).round() / 100.0;
+            n.clamp(min, max).to_string()
+        }
+        None => parameter.default.to_string(),
+    }
+}
+
+/// Write the variants as a JSON array of objects holding the file, seed, and parameter values of each
+fn manifest_json(variants: &[Variant]) -> String {
+    let rows: Vec<String> = variants
+        .iter()
+        .map(|variant| {
+            let values: Vec<String> = variant
+                .values
+                .iter()
+                .map(|(name, value)| format!("{}:{}", string_json(name), value_json(value)))
+                .collect();
+
+            // seeds are written as strings, as readers of JSON may hold numbers as doubles, which lose
+            // the last digits of seeds above 2^53
+            format!(
+                "  {{\"file\":{},\"seed\":{},\"params\":{{{}}}}}",
+                string_json(&variant.path.to_string_lossy()),
+                string_json(&variant.seed.to_string()),
+                values.join(",")
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+
+/// Write the value of a parameter as a JSON number if it is one, such as 0.5 for .5, or else as a string
+fn value_json(value: &str) -> String {
+    match value.trim().parse::<f64>() {
+        Ok(n) if n.is_finite() => n.to_string(),
+        _ => string_json(value),
+    }
+}
+
+/// Write the variants as CSV, with a column for the file, the seed, and each parameter
+fn manifest_csv(variants: &[Variant], parameters: &[Parameter]) -> String {
+    let mut header = vec!["file".to_string(), "seed".to_string()];
+    header.extend(parameters.iter().map(|parameter| parameter.name.clone()));
+    let mut lines = vec![header.join(",")];
+    for variant in variants {
+        let mut row = vec![csv_field(&variant.path), variant.seed.to_string()];
+        row.extend(variant.values.iter().map(|(_, value)| value.clone()));
+        lines.push(row.join(","));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Write a path as a CSV field, quoted if it holds a comma, quote, or newline
+fn csv_field(path: &Path) -> String {
+    let field = path.to_string_lossy();
+    match field.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.into_owned(),
+    }
+}
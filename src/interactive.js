@@ -0,0 +1,325 @@
+// Runtime for figures exported with --format html, which lets free points be dragged around.
+// It re-evaluates the construction with a small subset of the language, and moves the drawn
+// elements of each value to match. Values it cannot recompute are hidden while out of date.
+(function () {
+  "use strict";
+
+  const data = JSON.parse(document.getElementById("elements-data").textContent);
+  const svg = document.querySelector("svg");
+  const root = svg.querySelector(":scope > g[transform]") || svg;
+
+  // the viewbox in figure coordinates, which lines and rays are clipped to
+  const box = svg.viewBox.baseVal;
+  const view = data.flip
+    ? { minX: box.x, maxX: box.x + box.width, minY: -(box.y + box.height), maxY: -box.y }
+    : { minX: box.x, maxX: box.x + box.width, minY: box.y, maxY: box.y + box.height };
+
+  class Unsupported extends Error {}
+
+  /* Values */
+
+  const point = (x, y) => ({ t: "point", x, y });
+  const isPoint = (v) => v && v.t === "point";
+  const isLinear = (v) => v && (v.t === "lineseg" || v.t === "line" || v.t === "ray");
+  const need = (condition) => {
+    if (!condition) throw new Unsupported("invalid arguments");
+  };
+  const dist = (p, q) => Math.hypot(p.x - q.x, p.y - q.y);
+  const lerp = (p, q, t) => point(p.x + t * (q.x - p.x), p.y + t * (q.y - p.y));
+  const vertices = (v) => (v.t === "triangle" ? [v.a, v.b, v.c] : v.points);
+
+  const circumcenter = (a, b, c) => {
+    const d = 2 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+    if (d === 0) throw new Error("collinear points");
+    const [a2, b2, c2] = [a, b, c].map((p) => p.x * p.x + p.y * p.y);
+    return point(
+      (a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y)) / d,
+      (a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x)) / d
+    );
+  };
+  const incircle = (t) => {
+    const [a, b, c] = [dist(t.b, t.c), dist(t.a, t.c), dist(t.a, t.b)];
+    const s = (a + b + c) / 2;
+    return {
+      t: "circle",
+      c: point((a * t.a.x + b * t.b.x + c * t.c.x) / (2 * s), (a * t.a.y + b * t.b.y + c * t.c.y) / (2 * s)),
+      r: Math.sqrt(s * (s - a) * (s - b) * (s - c)) / s,
+    };
+  };
+  const centroid = (v) => {
+    const pts = vertices(v);
+    let area = 0, x = 0, y = 0;
+    pts.forEach((p, i) => {
+      const q = pts[(i + 1) % pts.length];
+      const cross = p.x * q.y - q.x * p.y;
+      area += cross / 2;
+      x += (p.x + q.x) * cross;
+      y += (p.y + q.y) * cross;
+    });
+    if (v.t === "triangle" || Math.abs(area) < 1e-10) {
+      return point(pts.reduce((s, p) => s + p.x, 0) / pts.length, pts.reduce((s, p) => s + p.y, 0) / pts.length);
+    }
+    return point(x / (6 * area), y / (6 * area));
+  };
+
+  // every line is treated as infinite when intersecting
+  const intersectLines = (l, m) => {
+    const r = { x: l.b.x - l.a.x, y: l.b.y - l.a.y };
+    const s = { x: m.b.x - m.a.x, y: m.b.y - m.a.y };
+    const denom = r.x * s.y - r.y * s.x;
+    if (Math.abs(denom) < 1e-10) throw new Error("parallel lines");
+    const t = ((m.a.x - l.a.x) * s.y - (m.a.y - l.a.y) * s.x) / denom;
+    return lerp(l.a, l.b, t);
+  };
+  const intersectLineCircle = (l, c, index) => {
+    const d = { x: l.b.x - l.a.x, y: l.b.y - l.a.y };
+    const f = { x: l.a.x - c.c.x, y: l.a.y - c.c.y };
+    const a = d.x * d.x + d.y * d.y;
+    const b = 2 * (f.x * d.x + f.y * d.y);
+    const disc = b * b - 4 * a * (f.x * f.x + f.y * f.y - c.r * c.r);
+    if (disc < 0) throw new Error("no intersection");
+    const pts = [-1, 1].map((sign) => lerp(l.a, l.b, (-b + sign * Math.sqrt(disc)) / (2 * a)));
+    // index 0 is the point with the larger x coordinate, with ties broken by the larger y coordinate
+    pts.sort((p, q) => (Math.abs(p.x - q.x) > 1e-10 ? q.x - p.x : q.y - p.y));
+    return pts[index];
+  };
+
+  // functions that only change how a value is drawn return the value itself
+  const styled = (v) => v;
+
+  const functions = {
+    "+": (...xs) => xs.reduce((a, b) => a + b),
+    "-": (a, b) => (b === undefined ? -a : a - b),
+    "*": (...xs) => xs.reduce((a, b) => a * b),
+    "/": (a, b) => a / b,
+    point: (x, y) => (need(typeof x === "number" && typeof y === "number"), point(x, y)),
+    midpoint: (p, q) => (need(isPoint(p) && isPoint(q)), lerp(p, q, 0.5)),
+    lerp: (p, q, t) => (need(isPoint(p) && isPoint(q)), lerp(p, q, t)),
+    distance: (p, q) => (need(isPoint(p) && isPoint(q)), dist(p, q)),
+    lineseg: (p, q) => (need(isPoint(p) && isPoint(q)), { t: "lineseg", a: p, b: q }),
+    line: (p, q) =>
+      p && p.t === "lineseg" ? { t: "line", a: p.a, b: p.b } : (need(isPoint(p) && isPoint(q)), { t: "line", a: p, b: q }),
+    ray: (p, q) => (need(isPoint(p) && isPoint(q)), { t: "ray", a: p, b: q }),
+    circle: (c, r) => (need(isPoint(c) && typeof r === "number"), { t: "circle", c, r }),
+    triangle: (a, b, c) => (need([a, b, c].every(isPoint)), { t: "triangle", a, b, c }),
+    polygon: (...points) => (need(points.length >= 3 && points.every(isPoint)), { t: "polygon", points }),
+    centroid: (v) => (need(v && (v.t === "triangle" || v.t === "polygon")), centroid(v)),
+    circumcenter: (v) => (need(v && v.t === "triangle"), circumcenter(v.a, v.b, v.c)),
+    incenter: (v) => (need(v && v.t === "triangle"), incircle(v).c),
+    orthocenter: (v) => {
+      need(v && v.t === "triangle");
+      const o = circumcenter(v.a, v.b, v.c);
+      return point(v.a.x + v.b.x + v.c.x - 2 * o.x, v.a.y + v.b.y + v.c.y - 2 * o.y);
+    },
+    circumcircle: (v) => {
+      need(v && v.t === "triangle");
+      const c = circumcenter(v.a, v.b, v.c);
+      return { t: "circle", c, r: dist(c, v.a) };
+    },
+    incircle: (v) => (need(v && v.t === "triangle"), incircle(v)),
+    intersect: (l, m, index) => {
+      need(isLinear(l));
+      if (isLinear(m)) return intersectLines(l, m);
+      need(m && m.t === "circle" && (index === 0 || index === 1));
+      return intersectLineCircle(l, m, index);
+    },
+    "point-x": (p) => (need(isPoint(p)), p.x),
+    "point-y": (p) => (need(isPoint(p)), p.y),
+    "circle-center": (c) => (need(c && c.t === "circle"), c.c),
+    "circle-radius": (c) => (need(c && c.t === "circle"), c.r),
+    "seg-start": (s) => (need(s && s.t === "lineseg"), s.a),
+    "seg-end": (s) => (need(s && s.t === "lineseg"), s.b),
+    color: styled,
+    fill: styled,
+    hatch: styled,
+    opacity: styled,
+    blend: styled,
+    style: styled,
+    label: styled,
+    layer: styled,
+  };
+
+  /* Evaluation */
+
+  // free points are set directly from two numbers, and can be moved by dragging
+  const free = new Map();
+  data.forms.forEach((form) => {
+    if (Array.isArray(form) && form[0] === "setq" && form[1] && Array.isArray(form[2])) {
+      const [name, x, y] = form[2];
+      if (name === "point" && typeof x === "number" && typeof y === "number") free.set(form[1].v, point(x, y));
+    }
+  });
+
+  function evaluate(form, env) {
+    if (typeof form === "number") return form;
+    if (form === null) throw new Unsupported("unknown literal");
+    if (!Array.isArray(form)) return env.has(form.v) ? env.get(form.v) : form.v;
+
+    const [name, ...args] = form;
+    if (name === "setq" || name === "define-style") {
+      env.delete(args[0].v);
+      const value = free.has(args[0].v) ? free.get(args[0].v) : evaluate(args[1], env);
+      env.set(args[0].v, value);
+      return undefined;
+    }
+    if (!(name in functions)) throw new Unsupported(name);
+    return functions[name](...args.map((arg) => evaluate(arg, env)));
+  }
+
+  // run the whole construction, keeping going past values that fail
+  function run() {
+    const env = new Map();
+    const values = data.forms.map((form) => {
+      try {
+        return evaluate(form, env);
+      } catch (e) {
+        return null;
+      }
+    });
+    return { env, values };
+  }
+
+  // find the free points each value depends on
+  const dependencies = new Map();
+  function depends(form) {
+    if (Array.isArray(form)) {
+      if (form[0] === "setq" || form[0] === "define-style") {
+        const deps = free.has(form[1].v) ? new Set([form[1].v]) : depends(form[2]);
+        dependencies.set(form[1].v, deps);
+        return new Set();
+      }
+      return new Set(form.slice(1).flatMap((arg) => [...depends(arg)]));
+    }
+    if (form && form.v !== undefined) return dependencies.get(form.v) || new Set();
+    return new Set();
+  }
+  const formDeps = data.forms.map(depends);
+
+  /* Drawing */
+
+  const anchor = (v) => {
+    if (!v) return null;
+    switch (v.t) {
+      case "point":
+        return v;
+      case "lineseg":
+        return lerp(v.a, v.b, 0.5);
+      case "circle":
+        return point(v.c.x, v.c.y - v.r);
+      case "triangle":
+      case "polygon":
+        return centroid(v);
+      default:
+        return null;
+    }
+  };
+
+  // clip the line through two points to the viewbox, starting from the first point for rays
+  function clipLine(a, b, isRay) {
+    const d = { x: b.x - a.x, y: b.y - a.y };
+    let lo = isRay ? 0 : -Infinity, hi = Infinity;
+    [[d.x, a.x, view.minX, view.maxX], [d.y, a.y, view.minY, view.maxY]].forEach(([dv, av, min, max]) => {
+      if (dv === 0) {
+        if (av < min || av > max) hi = -Infinity;
+        return;
+      }
+      const [t0, t1] = [(min - av) / dv, (max - av) / dv].sort((p, q) => p - q);
+      lo = Math.max(lo, t0);
+      hi = Math.min(hi, t1);
+    });
+    return lo <= hi ? [lerp(a, b, lo), lerp(a, b, hi)] : null;
+  }
+
+  const set = (el, attrs) => Object.entries(attrs).forEach(([k, v]) => el.setAttribute(k, v));
+
+  // move one drawn element to match its value, returning false if it cannot be
+  function redraw(el, v) {
+    switch (el.tagName + " " + v.t) {
+      case "circle point":
+        return set(el, { cx: v.x, cy: v.y }), true;
+      case "circle circle":
+        return set(el, { cx: v.c.x, cy: v.c.y, r: v.r }), true;
+      case "line lineseg":
+        return set(el, { x1: v.a.x, y1: v.a.y, x2: v.b.x, y2: v.b.y }), true;
+      case "line line":
+      case "line ray": {
+        const ends = clipLine(v.a, v.b, v.t === "ray");
+        return ends && (set(el, { x1: ends[0].x, y1: ends[0].y, x2: ends[1].x, y2: ends[1].y }), true);
+      }
+      case "polygon triangle":
+      case "polygon polygon":
+        return set(el, { points: vertices(v).map((p) => p.x + "," + p.y).join(" ") }), true;
+      default:
+        return false;
+    }
+  }
+
+  // labels keep their offset from the value they are attached to
+  function redrawGroup(group, value, start) {
+    let shown = value !== null;
+    for (const el of group.children) {
+      if (!shown) break;
+      if (el.tagName === "text") {
+        const [a, b] = [anchor(value), anchor(start)];
+        shown = !!(a && b);
+        if (shown) group.setAttribute("transform", "translate(" + (a.x - b.x) + " " + (a.y - b.y) + ")");
+      } else {
+        shown = redraw(el, value);
+      }
+    }
+    group.style.display = shown ? "" : "none";
+  }
+
+  const groups = (index) => svg.querySelectorAll('g[data-value="' + index + '"]');
+  const initial = run();
+
+  // labels added for point variables follow the variable with the same starting position
+  const labels = data.labels.map(([index, x, y]) => {
+    for (const [name, v] of initial.env) {
+      if (isPoint(v) && Math.abs(v.x - x) < 1e-9 && Math.abs(v.y - y) < 1e-9) return { index, name };
+    }
+    return null;
+  });
+
+  function update(moved) {
+    const current = run();
+    current.values.forEach((value, i) => {
+      if (!formDeps[i].has(moved)) return;
+      groups(i).forEach((group) => redrawGroup(group, value, initial.values[i]));
+    });
+    labels.forEach((label) => {
+      if (!label || !(dependencies.get(label.name) || new Set()).has(moved)) return;
+      const value = current.env.has(label.name) ? current.env.get(label.name) : null;
+      groups(label.index).forEach((group) => redrawGroup(group, value, initial.env.get(label.name)));
+    });
+  }
+
+  /* Dragging */
+
+  const handleRadius = Math.max(view.maxX - view.minX, view.maxY - view.minY) / 60;
+  const toFigure = (event) => {
+    const p = svg.createSVGPoint();
+    p.x = event.clientX;
+    p.y = event.clientY;
+    return p.matrixTransform(root.getScreenCTM().inverse());
+  };
+
+  free.forEach((start, name) => {
+    const handle = document.createElementNS("http://www.w3.org/2000/svg", "circle");
+    set(handle, { cx: start.x, cy: start.y, r: handleRadius, fill: "#1e90ff", "fill-opacity": 0.35, class: "handle" });
+    root.appendChild(handle);
+
+    handle.addEventListener("pointerdown", (event) => {
+      handle.setPointerCapture(event.pointerId);
+      event.preventDefault();
+    });
+    handle.addEventListener("pointermove", (event) => {
+      if (!handle.hasPointerCapture(event.pointerId)) return;
+      const p = toFigure(event);
+      free.set(name, point(p.x, p.y));
+      set(handle, { cx: p.x, cy: p.y });
+      update(name);
+    });
+    handle.addEventListener("pointerup", (event) => handle.releasePointerCapture(event.pointerId));
+  });
+})();
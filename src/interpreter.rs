@@ -1,7 +1,12 @@
-use crate::lang::types::Value;
-use crate::lexer::{Function, Literal, Token};
-
-use std::collections::HashMap;
+use crate::diagnostic::{closest, Diagnostic};
+use crate::error::ElementsError;
+use crate::lang::environment::Environment;
+use crate::lang::types::{Lambda, Point, Value};
+use crate::lexer::{is_function, Function, Literal, Span, Token, Variable, FUNCTION_NAMES};
+use crate::utils::claims::{self, Claim};
+use crate::utils::solver::least_squares;
+use crate::utils::tolerance::{self, tolerance};
+use crate::utils::{limits, params, random};
 
 /// Given a string, determine if it is a valid variable name
 pub fn is_valid_variable(name: &str) -> bool {
@@ -20,10 +25,12 @@ pub fn is_valid_variable(name: &str) -> bool {
 }
 
 /// Given a list of tokens, return a subset with matching parentheses
-fn get_section(tokens: Vec<Token>) -> Result<Vec<Token>, String> {
+pub(crate) fn get_section(tokens: Vec<Token>) -> Result<Vec<Token>, ElementsError> {
     // check if first token is a left paren
     if tokens[0] != Token::LeftParen {
-        return Err("Expected left parenthesis".to_string());
+        return Err(ElementsError::Parse(
+            "Expected left parenthesis".to_string(),
+        ));
     }
 
     let mut paren_count = 0;
@@ -52,29 +59,39 @@ fn get_section(tokens: Vec<Token>) -> Result<Vec<Token>, String> {
             return Ok(section);
         }
     }
-    Err("Mismatched parentheses".to_string())
+    Err(ElementsError::Parse("Mismatched parentheses".to_string()))
 }
 
+/// Most random guesses solve starts from before finding that no points satisfy its constraints
+const MAX_GUESSES: usize = 20;
+
 /// Given a function with matching parantheses, reduce it to a value
-fn reduce(tokens: Vec<Token>, variables: &mut HashMap<String, Value>) -> Result<Value, String> {
+fn reduce(tokens: Vec<Token>, variables: &mut Environment) -> Result<Value, ElementsError> {
     // check for empty tokens
     if tokens.is_empty() {
-        return Err("Empty tokens".to_string());
+        return Err(ElementsError::Parse("Empty tokens".to_string()));
     }
 
     // check for a single token
     if tokens.len() == 1 {
         return match &tokens[0] {
             Token::Literal(l) => Ok(l.value.clone()),
-            _ => Err("Single token must be a literal".to_string()),
+            _ => Err(ElementsError::Parse(
+                "Single token must be a literal".to_string(),
+            )),
         };
     }
 
     // check if first token is a left paren
     if tokens[0] != Token::LeftParen {
-        return Err("Expected left parenthesis".to_string());
+        return Err(ElementsError::Parse(
+            "Expected left parenthesis".to_string(),
+        ));
     }
 
+    // count the call against the limits of the evaluation until it returns
+    let _depth = limits::enter()?;
+
     // get current function
     let mut func: Function;
     match &tokens[1] {
@@ -82,11 +99,30 @@ fn reduce(tokens: Vec<Token>, variables: &mut HashMap<String, Value>) -> Result<
             func = f.clone();
         }
         _ => {
-            return Err("Expected function".to_string());
+            return Err(ElementsError::Parse("Expected function".to_string()));
         }
     }
 
-    // iterate through tokens and reduce
+    // a name that is not a function is most likely a misspelling of one
+    if !is_function(&func.name) {
+        return Err(ElementsError::Parse(format!(
+            "Unknown function: {}",
+            func.name
+        )));
+    }
+
+    // handle lambda function, capturing its body unevaluated
+    if func.name == "lambda" {
+        return make_lambda(&tokens[2..tokens.len() - 1], variables);
+    }
+
+    // handle solve, evaluating its constraints again for each guess at its unknown points
+    if func.name == "solve" {
+        return solve(&tokens[2..tokens.len() - 1], variables);
+    }
+
+    // iterate through tokens and reduce, remembering which arguments were read from variables
+    let mut names: Vec<Option<String>> = Vec::new();
     let mut i = 2;
     while i < tokens.len() - 1 {
         match &tokens[i] {
@@ -95,24 +131,34 @@ fn reduce(tokens: Vec<Token>, variables: &mut HashMap<String, Value>) -> Result<
                 let length = section.len();
                 let value = reduce(section, variables)?;
                 func.args.push(Token::Literal(Literal { value }));
+                names.push(None);
                 i += length;
             }
             Token::Literal(l) => {
                 func.args.push(Token::Literal(l.clone()));
+                names.push(None);
                 i += 1;
             }
             Token::Variable(v) => {
                 // check if variable exists
-                if !variables.contains_key(&v.name) {
-                    func.args.push(Token::Variable(v.clone()));
-                } else {
-                    let value = variables.get(&v.name).unwrap().clone();
-                    func.args.push(Token::Literal(Literal { value }));
+                match variables.get(&v.name) {
+                    Some(value) => {
+                        let value = value.clone();
+                        func.args.push(Token::Literal(Literal { value }));
+                        names.push(Some(v.name.clone()));
+                    }
+                    None => {
+                        func.args.push(Token::Variable(v.clone()));
+                        names.push(None);
+                    }
                 }
                 i += 1;
             }
             _ => {
-                return Err(format!("Unexpected token: {:?}", tokens[i]));
+                return Err(ElementsError::Parse(format!(
+                    "Unexpected token: {:?}",
+                    tokens[i]
+                )));
             }
         }
     }
@@ -129,17 +175,27 @@ fn reduce(tokens: Vec<Token>, variables: &mut HashMap<String, Value>) -> Result<
                 value_args.push(Value::String(name))
             }
             _ => {
-                return Err("Expected literal".to_string());
+                return Err(ElementsError::Parse("Expected literal".to_string()));
             }
         }
     }
 
-    // handle setq function
-    if func.name == "setq" {
+    // strip styles from the arguments unless the function handles them
+    if !func.function.keeps_style() {
+        value_args = value_args.iter().map(|v| v.unstyled().clone()).collect();
+    }
+
+    // check the arguments against the function's signature, if it declares one
+    if let Some(signature) = func.function.signature() {
+        signature.check(&value_args)?;
+    }
+
+    // handle setq, param, and define-style functions, which bind their result to a name
+    if matches!(func.name.as_str(), "setq" | "param" | "define-style") {
         match func.function.call(&value_args) {
             Ok(value) => {
                 if let Value::String(name) = &value_args[0] {
-                    variables.insert(name.clone(), value.clone());
+                    variables.define(name.clone(), value.clone());
                     return Ok(Value::Undefined);
                 }
             }
@@ -150,17 +206,298 @@ fn reduce(tokens: Vec<Token>, variables: &mut HashMap<String, Value>) -> Result<
     }
 
     // call the function
-    match func.function.call(&value_args) {
-        Ok(value) => Ok(value),
-        Err(e) => Err(e),
+    func.function.call_named(&value_args, &names)
+}
+
+/// Given the tokens of a lambda after its name, return a lambda value closing over the variables
+fn make_lambda(tokens: &[Token], variables: &mut Environment) -> Result<Value, ElementsError> {
+    // check for a parameter list
+    if tokens.is_empty() || tokens[0] != Token::LeftParen {
+        return Err(ElementsError::Parse(
+            "Lambda requires a parameter list".to_string(),
+        ));
+    }
+
+    // collect the parameter names until the closing paren
+    let mut params: Vec<String> = Vec::new();
+    let mut i = 1;
+    loop {
+        match tokens.get(i) {
+            Some(Token::Function(f)) => params.push(f.name.clone()),
+            Some(Token::Variable(v)) => params.push(v.name.clone()),
+            Some(Token::RightParen) => break,
+            _ => {
+                return Err(ElementsError::Parse(
+                    "Invalid lambda parameter list".to_string(),
+                ))
+            }
+        }
+        i += 1;
+    }
+
+    // check that every parameter is a valid variable name
+    for param in &params {
+        if !is_valid_variable(param) {
+            return Err(ElementsError::Parse(format!(
+                "Invalid lambda parameter: {}",
+                param
+            )));
+        }
+    }
+
+    // check for a body
+    let body = tokens[i + 1..].to_vec();
+    if body.is_empty() {
+        return Err(ElementsError::Parse("Lambda requires a body".to_string()));
+    }
+
+    Ok(Value::Lambda(Lambda {
+        params,
+        body,
+        env: variables.snapshot(),
+    }))
+}
+
+/// Given the tokens of a solve after its name, a list of unknown points and a list of constraints, find
+/// points satisfying every constraint from several random guesses and set them as variables
+fn solve(tokens: &[Token], variables: &mut Environment) -> Result<Value, ElementsError> {
+    // check for a list of unknown points
+    if tokens.first() != Some(&Token::LeftParen) {
+        return Err(ElementsError::Parse(
+            "Solve requires a list of unknown points".to_string(),
+        ));
+    }
+
+    // collect the names of the unknown points until the closing paren
+    let mut unknowns: Vec<String> = Vec::new();
+    let mut i = 1;
+    loop {
+        match tokens.get(i) {
+            Some(Token::Function(f)) => unknowns.push(f.name.clone()),
+            Some(Token::Variable(v)) => unknowns.push(v.name.clone()),
+            Some(Token::RightParen) => break,
+            _ => {
+                return Err(ElementsError::Parse(
+                    "Invalid list of unknown points".to_string(),
+                ))
+            }
+        }
+        i += 1;
+    }
+    if unknowns.is_empty() {
+        return Err(ElementsError::Parse(
+            "Solve requires at least one unknown point".to_string(),
+        ));
+    }
+    for name in &unknowns {
+        if !is_valid_variable(name) {
+            return Err(ElementsError::Parse(format!(
+                "Invalid unknown point: {}",
+                name
+            )));
+        }
+        if variables.contains(name) {
+            return Err(ElementsError::Type(format!(
+                "Unknown point {} is already set",
+                name
+            )));
+        }
+    }
+
+    // collect each constraint of the list, which must be calls
+    let constraints = match &tokens[i + 1..] {
+        [Token::LeftParen, Token::Function(f), body @ .., Token::RightParen]
+            if f.name == "constraints" =>
+        {
+            let mut constraints: Vec<&[Token]> = Vec::new();
+            let mut j = 0;
+            while j < body.len() {
+                if body[j] != Token::LeftParen {
+                    return Err(ElementsError::Parse(
+                        "Each constraint must be a call, such as (on? P c)".to_string(),
+                    ));
+                }
+                let length = get_section(body[j..].to_vec())?.len();
+                constraints.push(&body[j..j + length]);
+                j += length;
+            }
+            constraints
+        }
+        _ => {
+            return Err(ElementsError::Parse(
+                "Solve requires a list of constraints, such as (constraints (on? P c))".to_string(),
+            ))
+        }
+    };
+
+    // the residuals of the constraints are found with the unknown points set on top of the variables
+    let scope = variables.snapshot().child();
+    let mut residuals = |x: &[f64]| -> Result<Vec<f64>, ElementsError> {
+        limits::step()?;
+        let mut scope = scope.clone();
+        for (k, name) in unknowns.iter().enumerate() {
+            let point = Point {
+                x: x[2 * k],
+                y: x[2 * k + 1],
+            };
+            scope.define(name.clone(), Value::Point(point));
+        }
+        let mut r: Vec<f64> = Vec::new();
+        for constraint in &constraints {
+            r.extend(constraint_residuals(constraint, &mut scope)?);
+        }
+        Ok(r)
+    };
+
+    // guesses are drawn around the points already set, so solutions near the figure are found first
+    let points: Vec<Point> = variables
+        .names()
+        .into_iter()
+        .filter_map(|name| match variables.get(name).map(Value::unstyled) {
+            Some(Value::Point(p)) => Some(*p),
+            _ => None,
+        })
+        .collect();
+    let (min, max) = match points.first() {
+        Some(first) => points.iter().fold((*first, *first), |(min, max), p| {
+            (
+                Point {
+                    x: min.x.min(p.x),
+                    y: min.y.min(p.y),
+                },
+                Point {
+                    x: max.x.max(p.x),
+                    y: max.y.max(p.y),
+                },
+            )
+        }),
+        None => (Point { x: -5.0, y: -5.0 }, Point { x: 5.0, y: 5.0 }),
+    };
+    let size = (max.x - min.x).max(max.y - min.y).max(1.0);
+
+    // a guess is kept once every residual is within the square root of the tolerance, which allows for
+    // the slower convergence at tangencies, where two solutions meet
+    for _ in 0..MAX_GUESSES {
+        let guess: Vec<f64> = (0..unknowns.len())
+            .flat_map(|_| {
+                let x = min.x - size / 2.0 + random::random() * (max.x - min.x + size);
+                let y = min.y - size / 2.0 + random::random() * (max.y - min.y + size);
+                [x, y]
+            })
+            .collect();
+        if let Some((x, largest)) = least_squares(&mut residuals, guess)? {
+            if largest <= tolerance().sqrt() {
+                for (k, name) in unknowns.iter().enumerate() {
+                    let point = Point {
+                        x: x[2 * k],
+                        y: x[2 * k + 1],
+                    };
+                    variables.define(name.clone(), Value::Point(point));
+                }
+                return Ok(Value::Undefined);
+            }
+        }
+    }
+    Err(ElementsError::Geometry(
+        "No points satisfy the constraints of solve".to_string(),
+    ))
+}
+
+/// Given the tokens of a constraint, evaluate its arguments and return how far they are from satisfying it
+fn constraint_residuals(
+    tokens: &[Token],
+    variables: &mut Environment,
+) -> Result<Vec<f64>, ElementsError> {
+    let function = match tokens.get(1) {
+        Some(Token::Function(f)) => f,
+        _ => return Err(ElementsError::Parse("Expected function".to_string())),
+    };
+    let mut args: Vec<Value> = Vec::new();
+    let mut i = 2;
+    while i < tokens.len() - 1 {
+        let length = match tokens[i] {
+            Token::LeftParen => get_section(tokens[i..].to_vec())?.len(),
+            _ => 1,
+        };
+        let value = evaluate_expression(&tokens[i..i + length], variables)?;
+        args.push(value.unstyled().clone());
+        i += length;
+    }
+    if let Some(signature) = function.function.signature() {
+        signature.check(&args)?;
+    }
+    function.function.residuals(&args).ok_or_else(|| {
+        ElementsError::Type(format!(
+            "{} cannot be used as a constraint of solve",
+            function.name
+        ))
+    })
+}
+
+/// Given the tokens of a single expression, evaluate it to a value
+pub fn evaluate_expression(
+    tokens: &[Token],
+    variables: &mut Environment,
+) -> Result<Value, ElementsError> {
+    match tokens.first() {
+        Some(Token::LeftParen) => {
+            let section = get_section(tokens.to_vec())?;
+            if section.len() != tokens.len() {
+                return Err(ElementsError::Parse(
+                    "Expected a single expression".to_string(),
+                ));
+            }
+            reduce(section, variables)
+        }
+        Some(Token::Literal(l)) if tokens.len() == 1 => Ok(l.value.clone()),
+        Some(Token::Variable(v)) if tokens.len() == 1 => match variables.get(&v.name) {
+            Some(value) => Ok(value.clone()),
+            None => Err(ElementsError::Parse(format!(
+                "Undefined variable: {}",
+                v.name
+            ))),
+        },
+        _ => Err(ElementsError::Parse(
+            "Expected a single expression".to_string(),
+        )),
     }
 }
 
 /// Given a vector of tokens, evaluate it to a vector of values
-pub fn evaluate(tokens: Vec<Token>) -> Result<Vec<Value>, String> {
+pub fn evaluate(tokens: Vec<Token>) -> Result<Vec<Value>, ElementsError> {
+    evaluate_variables(tokens).map(|(values, _)| values)
+}
+
+/// Given a vector of tokens, evaluate it to a vector of values, along with the top-level variables it set
+pub fn evaluate_variables(tokens: Vec<Token>) -> Result<(Vec<Value>, Environment), ElementsError> {
+    let mut variables = Environment::new();
+    params::start();
+    claims::start();
+    tolerance::start();
+    let mut values = evaluate_with(&tokens, &mut variables)?;
+    values.extend(variable_labels(&variables));
+    log::info!("evaluated {} values", values.len());
+    Ok((values, variables))
+}
+
+/// Given a slice of tokens and the variables set so far, evaluate it to a vector of values, keeping any
+/// variables it sets for later evaluations, and stopping once it goes over the limits of the thread
+pub fn evaluate_with(
+    tokens: &[Token],
+    variables: &mut Environment,
+) -> Result<Vec<Value>, ElementsError> {
+    limits::start();
+    evaluate_expressions(tokens, variables)
+}
+
+/// Given a slice of tokens and the variables set so far, evaluate it to a vector of values within the limits
+/// of the evaluation already started
+fn evaluate_expressions(
+    tokens: &[Token],
+    variables: &mut Environment,
+) -> Result<Vec<Value>, ElementsError> {
     let mut values: Vec<Value> = Vec::new();
     let mut i = 0;
-    let mut variables: HashMap<String, Value> = HashMap::new();
 
     // iterate through all the tokens, calling reduce when a function is detected
     while i < tokens.len() {
@@ -168,7 +505,7 @@ pub fn evaluate(tokens: Vec<Token>) -> Result<Vec<Value>, String> {
             Token::LeftParen => {
                 let section = get_section(tokens[i..].to_vec())?;
                 let length = section.len();
-                let value = reduce(section, &mut variables)?;
+                let value = reduce(section, variables)?;
                 values.push(value);
                 i += length;
             }
@@ -177,23 +514,208 @@ pub fn evaluate(tokens: Vec<Token>) -> Result<Vec<Value>, String> {
                 i += 1;
             }
             Token::Variable(v) => {
-                if !variables.contains_key(&v.name) {
-                    return Err("Undefined variable when evaluating".to_string());
-                } else {
-                    let value = variables.get(&v.name).unwrap().clone();
-                    values.push(value);
+                match variables.get(&v.name) {
+                    Some(value) => values.push(value.clone()),
+                    None => {
+                        return Err(ElementsError::Parse(format!(
+                            "Undefined variable: {}",
+                            v.name
+                        )))
+                    }
                 }
                 i += 1;
             }
             _ => {
-                return Err("Unexpected token when evaluating".to_string());
+                return Err(ElementsError::Parse(
+                    "Unexpected token when evaluating".to_string(),
+                ));
             }
         }
     }
 
-    // for each of the variables containing a point, add a svg label element
-    for (name, value) in &variables {
-        if let Value::Point(p) = value {
+    Ok(values)
+}
+
+/// Given a slice of tokens and the span of each top-level expression, evaluate each expression on its own
+/// and return every error found, so that one mistake does not hide the ones after it
+pub fn check(tokens: &[Token], spans: &[Span]) -> Vec<Diagnostic> {
+    evaluate_each(tokens, spans, |_| false)
+}
+
+/// Given a slice of tokens and the span of each top-level expression, evaluate each expression on its own
+/// and return every claim or assertion checked, along with the span of the expression that checked it, and
+/// every error found other than the assertions that failed
+pub fn verify(tokens: &[Token], spans: &[Span]) -> (Vec<(Option<Span>, Claim)>, Vec<Diagnostic>) {
+    let mut checked: Vec<(Option<Span>, Claim)> = Vec::new();
+    params::start();
+    claims::start();
+    tolerance::start();
+    let diagnostics = evaluate_each(tokens, spans, |span| {
+        // an assertion that fails stops its expression, so it is the last claim checked there
+        let claims = claims::take();
+        let is_failed = claims.last().is_some_and(|c| c.is_assert && !c.holds);
+        checked.extend(claims.into_iter().map(|claim| (span, claim)));
+        is_failed
+    });
+    (checked, diagnostics)
+}
+
+/// Evaluate each top-level expression on its own, calling back with the span of each after evaluating it,
+/// which returns whether an error in that expression is already reported, and return every other error
+fn evaluate_each(
+    tokens: &[Token],
+    spans: &[Span],
+    mut evaluated: impl FnMut(Option<Span>) -> bool,
+) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut variables = Environment::new();
+    let mut i = 0;
+    let mut index = 0;
+
+    // the expressions share the limits and tolerance of the program, as they would when it is evaluated whole
+    tolerance::start();
+    limits::start();
+    while i < tokens.len() {
+        let start = i;
+        let error = match expression_length(tokens, i) {
+            Ok(length) => {
+                i += length;
+                evaluate_expressions(&tokens[start..i], &mut variables).err()
+            }
+            Err(e) => {
+                i = tokens.len();
+                Some(e)
+            }
+        };
+        let span = spans.get(index).copied();
+        let is_reported = evaluated(span);
+        if let Some(error) = error.filter(|_| !is_reported) {
+            diagnostics.push(locate(error, &tokens[start..i], &variables, span));
+        }
+        index += 1;
+    }
+    diagnostics
+}
+
+/// Given a vector of tokens and the span of each top-level expression, evaluate it to a vector of values
+/// along with the top-level variables it set, as evaluate_variables does, returning any error with the span
+/// of the expression it was found in
+pub fn evaluate_located(
+    tokens: Vec<Token>,
+    spans: &[Span],
+) -> Result<(Vec<Value>, Environment), Box<Diagnostic>> {
+    let mut variables = Environment::new();
+    let mut values: Vec<Value> = Vec::new();
+    let mut i = 0;
+    let mut index = 0;
+    params::start();
+    claims::start();
+    tolerance::start();
+    limits::start();
+    while i < tokens.len() {
+        let span = spans.get(index).copied();
+        let length = expression_length(&tokens, i)
+            .map_err(|e| Box::new(locate(e, &tokens[i..], &variables, span)))?;
+        let expression = &tokens[i..i + length];
+        match evaluate_expressions(expression, &mut variables) {
+            Ok(evaluated) => values.extend(evaluated),
+            Err(e) => return Err(Box::new(locate(e, expression, &variables, span))),
+        }
+        i += length;
+        index += 1;
+    }
+    values.extend(variable_labels(&variables));
+    log::info!("evaluated {} values", values.len());
+    Ok((values, variables))
+}
+
+/// Return the number of tokens in the top-level expression starting at the given token, which is a single
+/// token unless it starts with a parenthesis
+fn expression_length(tokens: &[Token], i: usize) -> Result<usize, ElementsError> {
+    match &tokens[i] {
+        Token::LeftParen => get_section(tokens[i..].to_vec()).map(|section| section.len()),
+        _ => Ok(1),
+    }
+}
+
+/// Turn an error found in a top-level expression into a diagnostic at the span of the expression
+fn locate(
+    error: ElementsError,
+    expression: &[Token],
+    variables: &Environment,
+    span: Option<Span>,
+) -> Diagnostic {
+    Diagnostic {
+        span,
+        suggestion: suggest(&error, expression, variables),
+        ..Diagnostic::error(error.to_string())
+    }
+}
+
+/// Given an error in an expression, suggest the function that an unknown one in it may be a misspelling
+/// of, or else the defined variable that an undefined one may be a misspelling of
+fn suggest(error: &ElementsError, expression: &[Token], variables: &Environment) -> Option<String> {
+    let is_unknown = matches!(
+        error,
+        ElementsError::Parse(message) if message.starts_with("Unknown function")
+    );
+    let names = variables.names();
+    let mut bound: Vec<&str> = Vec::new();
+    let mut i = 0;
+    while i < expression.len() {
+        // the parameters of a lambda and the unknowns of solve are names rather than calls or variables
+        if let Token::Function(f) = &expression[i] {
+            if matches!(f.name.as_str(), "lambda" | "solve")
+                && expression.get(i + 1) == Some(&Token::LeftParen)
+            {
+                i += 2;
+                while let Some(Token::Function(Function { name, .. }))
+                | Some(Token::Variable(Variable { name, .. })) = expression.get(i)
+                {
+                    bound.push(name);
+                    i += 1;
+                }
+                continue;
+            }
+        }
+        let closest = match &expression[i] {
+            Token::Function(f) if is_unknown && !is_function(&f.name) => {
+                closest(&f.name, FUNCTION_NAMES)
+            }
+            Token::Variable(v)
+                if !is_unknown
+                    && is_valid_variable(&v.name)
+                    && !variables.contains(&v.name)
+                    && !bound.contains(&v.name.as_str()) =>
+            {
+                closest(&v.name, names.iter().copied())
+            }
+            _ => None,
+        };
+        if let Some(name) = closest {
+            return Some(format!("did you mean `{}`?", name));
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Given the variables, return a label for each top-level one containing a point, placed at the point, where
+/// variables bound inside nested scopes, such as lambda parameters, are not labelled
+pub fn variable_labels(variables: &Environment) -> Vec<Value> {
+    // for each of the variables containing a point, add a svg label element, in order of name so the output
+    // is the same on every run
+    let mut values: Vec<Value> = Vec::new();
+    let mut variables: Vec<(&String, &Value)> = variables.locals().collect();
+    variables.sort_by_key(|(name, _)| *name);
+    for (name, value) in variables {
+        // a label attached to the point replaces the variable name, and hidden points are not labelled
+        let name = match value {
+            Value::Styled(_, style) if style.hidden == Some(true) => continue,
+            Value::Styled(_, style) => style.label.as_ref().unwrap_or(name),
+            _ => name,
+        };
+        if let Value::Point(p) = value.unstyled() {
             // extract the x and y values
             let mut loc: String = " ".to_string() + &p.x.to_string();
             loc += " ";
@@ -202,5 +724,33 @@ pub fn evaluate(tokens: Vec<Token>) -> Result<Vec<Value>, String> {
         }
     }
 
-    Ok(values)
+    values
+}
+
+/// Given a vector of tokens, return the name of the variable each top-level value is read from,
+/// in the order the values are evaluated, or None for values built by an expression
+pub fn value_names(tokens: &[Token]) -> Vec<Option<String>> {
+    let mut names: Vec<Option<String>> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::LeftParen => match get_section(tokens[i..].to_vec()) {
+                Ok(section) => {
+                    names.push(None);
+                    i += section.len();
+                }
+                Err(_) => break,
+            },
+            Token::Literal(_) => {
+                names.push(None);
+                i += 1;
+            }
+            Token::Variable(v) => {
+                names.push(Some(v.name.clone()));
+                i += 1;
+            }
+            _ => break,
+        }
+    }
+    names
 }
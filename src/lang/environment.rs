@@ -0,0 +1,95 @@
+use crate::lang::types::Value;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// The variables visible to an expression, where a scope sees its own variables and those of every scope
+/// it is nested in, and setting a variable only changes the innermost scope
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Environment {
+    variables: HashMap<String, Value>,
+    parent: Option<Rc<Environment>>,
+    // whether the parent holds the earlier variables of this same scope, frozen by a snapshot
+    is_continued: bool,
+}
+
+impl Environment {
+    /// Create an empty top-level scope
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a scope nested in this one, which shares it rather than copying it, so the scope of each call
+    /// of a lambda costs the same however many variables are around it
+    pub fn child(self: &Rc<Self>) -> Self {
+        Environment {
+            variables: HashMap::new(),
+            parent: Some(Rc::clone(self)),
+            is_continued: false,
+        }
+    }
+
+    /// Return the variables as they are now, which later changes to this scope do not affect, by freezing
+    /// them where they are shared rather than copying them, so a lambda closes over its variables at the
+    /// same cost however many there are
+    pub fn snapshot(&mut self) -> Rc<Self> {
+        // nothing has been set since the last snapshot, so it still holds every variable
+        if self.is_continued && self.variables.is_empty() {
+            if let Some(parent) = &self.parent {
+                return Rc::clone(parent);
+            }
+        }
+        let frozen = Rc::new(std::mem::take(self));
+        *self = Environment {
+            variables: HashMap::new(),
+            parent: Some(Rc::clone(&frozen)),
+            is_continued: true,
+        };
+        frozen
+    }
+
+    /// Return the value of a variable from the innermost scope that has it
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self.variables.get(name) {
+            Some(value) => Some(value),
+            None => self.parent.as_ref()?.get(name),
+        }
+    }
+
+    /// Return whether a variable is visible from this scope
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Set a variable in this scope, hiding any variable of the same name in the scopes around it
+    pub fn define(&mut self, name: String, value: Value) {
+        self.variables.insert(name, value);
+    }
+
+    /// Return the names of every variable visible from this scope, each once
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.variables.keys().map(String::as_str).collect();
+        if let Some(parent) = &self.parent {
+            names.extend(
+                parent
+                    .names()
+                    .into_iter()
+                    .filter(|name| !self.variables.contains_key(*name)),
+            );
+        }
+        names
+    }
+
+    /// Return the variables set in this scope, leaving out those of the scopes around it
+    pub fn locals(&self) -> impl Iterator<Item = (&String, &Value)> {
+        let mut locals: Vec<(&String, &Value)> = self.variables.iter().collect();
+        if let Some(parent) = self.parent.as_ref().filter(|_| self.is_continued) {
+            locals.extend(
+                parent
+                    .locals()
+                    .filter(|(name, _)| !self.variables.contains_key(*name)),
+            );
+        }
+        locals.into_iter()
+    }
+}
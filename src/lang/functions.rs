@@ -1,7 +1,57 @@
+use crate::error::ElementsError;
 use crate::interpreter::is_valid_variable;
-use crate::lang::types::Angle;
-use crate::lang::types::{Circle, Lineseg, Operation, Point, Triangle, Value};
-use crate::utils::geometry::{distance, midpoint};
+use crate::lang::types::{Angle, Corner, Directive, Element};
+use crate::lang::types::{
+    Arc, Bezier, Circle, Ellipse, Hyperbola, Line, Lineseg, Locus, Operation, Parabola, Param,
+    Point, Polygon, RawSvg, Ray, Region, Signature, Triangle, Value,
+};
+use crate::renderer::{parse_color, Blend, Hatch, Render, Rounded, Style, Svg};
+use crate::utils::claims::{self, Claim};
+use crate::utils::geometry::{
+    closest_point, convex_hull, distance, invert_point, lerp, marching_squares, midpoint,
+};
+use crate::utils::params::{declare, override_of, Parameter};
+#[cfg(feature = "high-precision")]
+use crate::utils::precise;
+use crate::utils::random::{self, sample, Constraint};
+use crate::utils::tolerance::{is_near, set_tolerance, tolerance};
+use crate::utils::transform::Transform;
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+/// The maximum number of samples drawn when a random construction must satisfy constraints
+const MAX_TRIES: usize = 1000;
+
+/// The parameter types shared by the signatures of the functions
+const ANY: Param = Param::Any;
+const INT: Param = Param::OneOf(&["Int"]);
+const NUMBER: Param = Param::OneOf(&["Int", "Float"]);
+const STRING: Param = Param::OneOf(&["String"]);
+const POINT: Param = Param::OneOf(&["Point"]);
+const CIRCLE: Param = Param::OneOf(&["Circle"]);
+const TRIANGLE: Param = Param::OneOf(&["Triangle"]);
+const LINESEG: Param = Param::OneOf(&["Lineseg"]);
+const LAMBDA: Param = Param::OneOf(&["Lambda"]);
+const ANGLE: Param = Param::OneOf(&["Angle"]);
+const ARC: Param = Param::OneOf(&["Arc"]);
+const FLOAT: Param = Param::OneOf(&["Float"]);
+const BOOL: Param = Param::OneOf(&["Bool"]);
+const LINE: Param = Param::OneOf(&["Line"]);
+const RAY: Param = Param::OneOf(&["Ray"]);
+const POLYGON: Param = Param::OneOf(&["Polygon"]);
+const REGION: Param = Param::OneOf(&["Region"]);
+const DIRECTIVE: Param = Param::OneOf(&["Directive"]);
+/// A value that lies on a line, which is extended to the whole line
+const LINEAR: Param = Param::OneOf(&["Line", "Lineseg", "Ray"]);
+/// A vertex of a triangle, given as the point or its index
+const VERTEX: Param = Param::OneOf(&["Point", "Int"]);
+/// A value a point can be measured from
+const MEASURABLE: Param = Param::OneOf(&["Point", "Line", "Lineseg", "Ray", "Circle"]);
+/// A value a point can lie on
+const ON: Param = Param::OneOf(&["Line", "Lineseg", "Ray", "Circle"]);
+/// A value that can be inverted in a circle
+const INVERTIBLE: Param = Param::OneOf(&["Point", "Line", "Lineseg", "Ray", "Circle"]);
 
 /// Macro to implement cloning a boxed trait object
 macro_rules! clone_impl {
@@ -12,6 +62,44 @@ macro_rules! clone_impl {
     };
 }
 
+/// Helper function to split arguments into positional arguments and :keyword value pairs
+fn keyword_args(args: &[Value]) -> Result<(Vec<Value>, HashMap<String, Value>), ElementsError> {
+    let mut positional: Vec<Value> = Vec::new();
+    let mut keywords: HashMap<String, Value> = HashMap::new();
+    let mut i = 0;
+    while i < args.len() {
+        match &args[i] {
+            Value::String(s) if s.starts_with(':') => {
+                let value = match args.get(i + 1) {
+                    Some(value) => value.clone(),
+                    None => {
+                        return Err(ElementsError::Arity(format!(
+                            "Missing value for keyword {}",
+                            s
+                        )))
+                    }
+                };
+                keywords.insert(s[1..].to_string(), value);
+                i += 2;
+            }
+            value => {
+                positional.push(value.clone());
+                i += 1;
+            }
+        }
+    }
+    Ok((positional, keywords))
+}
+
+/// Helper function to force a numeric value into a float
+fn to_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
 /*
 Function to set a variable
 */
@@ -20,21 +108,109 @@ Function to set a variable
 pub struct FnSet;
 impl Operation for FnSet {
     clone_impl!(FnSet);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        if args.len() != 2 {
-            return Err("setq requires exactly 2 arguments".to_string());
-        }
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("setq", &[ANY, ANY]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
         let var_name = match &args[0] {
             Value::String(s) => s,
-            _ => return Err("Invalid variable name".to_string()),
+            _ => return Err(ElementsError::Type("Invalid variable name".to_string())),
         };
         if !is_valid_variable(var_name) {
-            return Err("Invalid variable name".to_string());
+            return Err(ElementsError::Type("Invalid variable name".to_string()));
         }
         Ok(args[1].clone())
     }
 }
 
+#[derive(Clone)]
+pub struct FnParam;
+impl Operation for FnParam {
+    clone_impl!(FnParam);
+    fn overloads(&self) -> Vec<Signature> {
+        vec![
+            Signature::new("param", &[ANY, NUMBER]).returns(NUMBER),
+            Signature::new("param", &[ANY, NUMBER, NUMBER, NUMBER]).returns(NUMBER),
+        ]
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        Signature::resolve(&self.overloads(), args)?;
+        let name = match &args[0] {
+            Value::String(s) if is_valid_variable(s) => s.clone(),
+            _ => return Err(ElementsError::Type("Invalid parameter name".to_string())),
+        };
+        let numbers: Vec<f64> = args[1..].iter().filter_map(to_float).collect();
+
+        // the default must lie in the range, if one is given
+        let range = match numbers[..] {
+            [_, min, max] if min > max => {
+                return Err(ElementsError::Type(format!(
+                    "param {}: minimum {} is greater than maximum {}",
+                    name, min, max
+                )))
+            }
+            [_, min, max] => Some((min, max)),
+            _ => None,
+        };
+        let in_range = |n: f64| range.is_none_or(|(min, max)| n >= min && n <= max);
+        if !in_range(numbers[0]) {
+            return Err(ElementsError::Type(format!(
+                "param {}: default {} is outside of its range",
+                name, numbers[0]
+            )));
+        }
+
+        // a value given from outside the figure takes the place of the default
+        let value = match override_of(&name) {
+            Some(text) => match (text.parse::<i64>(), text.parse::<f64>()) {
+                (Ok(i), _) => Value::Int(i),
+                (_, Ok(f)) if f.is_finite() => Value::Float(f),
+                _ => {
+                    return Err(ElementsError::Type(format!(
+                        "Invalid value for parameter {}: {}",
+                        name, text
+                    )))
+                }
+            },
+            None => args[1].clone(),
+        };
+        if let (Some((min, max)), Some(n)) = (range, to_float(&value)) {
+            if !in_range(n) {
+                return Err(ElementsError::Type(format!(
+                    "Parameter {} must be between {} and {}, got {}",
+                    name, min, max, n
+                )));
+            }
+        }
+        declare(Parameter {
+            name,
+            default: numbers[0],
+            range,
+            is_integer: args[1..].iter().all(|arg| matches!(arg, Value::Int(_))),
+        });
+        Ok(value)
+    }
+}
+
+/*
+Lists
+*/
+
+#[derive(Clone)]
+pub struct FnList;
+impl Operation for FnList {
+    clone_impl!(FnList);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        Ok(Value::List(args.to_vec()))
+    }
+}
+
 /*
 Basic arithmetic functions
 */
@@ -43,15 +219,11 @@ Basic arithmetic functions
 pub struct FnAdd;
 impl Operation for FnAdd {
     clone_impl!(FnAdd);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        if args.len() != 2 {
-            return Err("Add requires exactly 2 arguments".to_string());
-        }
-        match (&args[0], &args[1]) {
-            (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a + b)),
-            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
-            _ => Err("Invalid types for addition".to_string()),
-        }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("+", &[NUMBER, NUMBER]).returns(NUMBER))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        arithmetic(args, "addition", i64::checked_add, |a, b| a + b)
     }
 }
 
@@ -59,15 +231,11 @@ impl Operation for FnAdd {
 pub struct FnSub;
 impl Operation for FnSub {
     clone_impl!(FnSub);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        if args.len() != 2 {
-            return Err("Sub requires exactly 2 arguments".to_string());
-        }
-        match (&args[0], &args[1]) {
-            (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a - b)),
-            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a - b)),
-            _ => Err("Invalid types for subtraction".to_string()),
-        }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("-", &[NUMBER, NUMBER]).returns(NUMBER))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        arithmetic(args, "subtraction", i64::checked_sub, |a, b| a - b)
     }
 }
 
@@ -75,15 +243,11 @@ impl Operation for FnSub {
 pub struct FnMul;
 impl Operation for FnMul {
     clone_impl!(FnMul);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        if args.len() != 2 {
-            return Err("Mul requires exactly 2 arguments".to_string());
-        }
-        match (&args[0], &args[1]) {
-            (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a * b)),
-            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a * b)),
-            _ => Err("Invalid types for multiplication".to_string()),
-        }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("*", &[NUMBER, NUMBER]).returns(NUMBER))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        arithmetic(args, "multiplication", i64::checked_mul, |a, b| a * b)
     }
 }
 
@@ -91,24 +255,461 @@ impl Operation for FnMul {
 pub struct FnDiv;
 impl Operation for FnDiv {
     clone_impl!(FnDiv);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        if args.len() != 2 {
-            return Err("Div requires exactly 2 arguments".to_string());
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("/", &[NUMBER, NUMBER]).returns(NUMBER))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        arithmetic(args, "division", i64::checked_div, |a, b| a / b)
+    }
+}
+
+/// Helper function to apply an arithmetic operator to two numbers, where two Ints give an Int and an Int
+/// paired with a Float is promoted to a Float, and Ints that overflow or are divided by zero are errors
+fn arithmetic(
+    args: &[Value],
+    name: &str,
+    int: fn(i64, i64) -> Option<i64>,
+    float: fn(f64, f64) -> f64,
+) -> Result<Value, ElementsError> {
+    match (&args[0], &args[1]) {
+        (Value::Int(_), Value::Int(0)) if name == "division" => {
+            Err(ElementsError::Type("Division by zero".to_string()))
+        }
+        (Value::Int(a), Value::Int(b)) => int(*a, *b)
+            .map(Value::Int)
+            .ok_or_else(|| ElementsError::Type(format!("Integer overflow in {}", name))),
+        (Value::Int(a), Value::Float(b)) => Ok(Value::Float(float(*a as f64, *b))),
+        (Value::Float(a), Value::Int(b)) => Ok(Value::Float(float(*a, *b as f64))),
+        (Value::Float(a), Value::Float(b)) => Ok(Value::Float(float(*a, *b))),
+        _ => Err(ElementsError::Type(format!("Invalid types for {}", name))),
+    }
+}
+
+/// Helper function to convert a line, line segment, or ray into the line it lies on
+fn to_line(value: &Value) -> Result<Line, ElementsError> {
+    match value {
+        Value::Line(l) => Ok(*l),
+        Value::Lineseg(l) => l.line(),
+        Value::Ray(r) => Line::from_points(r.origin, r.through),
+        _ => Err(ElementsError::Type("Invalid types for line".to_string())),
+    }
+}
+
+/// Helper function to find the spiral similarity taking one line segment to another
+fn segment_mapping(from: &Value, to: &Value) -> Result<Transform, ElementsError> {
+    match (from.unstyled(), to.unstyled()) {
+        (Value::Lineseg(from), Value::Lineseg(to)) => {
+            match Transform::mapping((from.start, from.end), (to.start, to.end)) {
+                Some(t) if t.scale() > 0.0 => Ok(t),
+                _ => Err(ElementsError::Geometry(
+                    "Line segments must have nonzero length".to_string(),
+                )),
+            }
+        }
+        _ => Err(ElementsError::Type(
+            "Invalid types for line segment".to_string(),
+        )),
+    }
+}
+
+/// Helper function to return the size of a figure from the largest coordinate of its points, which the
+/// floating point error in distances measured across it grows with
+fn coordinate_scale(points: &[Point]) -> f64 {
+    points
+        .iter()
+        .fold(0.0, |scale: f64, p| scale.max(p.x.abs()).max(p.y.abs()))
+}
+
+/// Helper function to check that every argument is a point
+fn to_points(args: &[Value]) -> Result<Vec<Point>, ElementsError> {
+    let mut points: Vec<Point> = Vec::new();
+    for arg in args {
+        match arg {
+            Value::Point(p) => points.push(*p),
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+        }
+    }
+    Ok(points)
+}
+
+/// Helper function to read an optional side argument, 1 for counterclockwise and -1 for clockwise
+fn side_arg(value: Option<&Value>) -> Result<f64, ElementsError> {
+    match value {
+        None | Some(Value::Int(1)) => Ok(1.0),
+        Some(Value::Int(-1)) => Ok(-1.0),
+        _ => Err(ElementsError::Type(
+            "Side must be either 1 or -1".to_string(),
+        )),
+    }
+}
+
+/// Helper function to read a triangle vertex given as either a point or an index from 0 to 2
+fn triangle_vertex(triangle: &Triangle, value: &Value) -> Result<Point, ElementsError> {
+    match value {
+        Value::Point(p) => triangle.others(*p).map(|_| *p),
+        Value::Int(0) => Ok(triangle.a),
+        Value::Int(1) => Ok(triangle.b),
+        Value::Int(2) => Ok(triangle.c),
+        Value::Int(_) => Err(ElementsError::Type(
+            "Vertex index must be 0, 1, or 2".to_string(),
+        )),
+        _ => Err(ElementsError::Type("Invalid types for vertex".to_string())),
+    }
+}
+
+/// Helper function to force two numeric arguments into floats for comparison
+fn compare_args(args: &[Value], name: &str) -> Result<(f64, f64), ElementsError> {
+    let mut floats = Vec::new();
+    for arg in args {
+        match arg {
+            Value::Int(i) => floats.push(*i as f64),
+            Value::Float(f) => floats.push(*f),
+            _ => return Err(ElementsError::Type(format!("Invalid types for {}", name))),
+        }
+    }
+    Ok((floats[0], floats[1]))
+}
+
+#[derive(Clone)]
+pub struct FnLess;
+impl Operation for FnLess {
+    clone_impl!(FnLess);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("<", &[NUMBER, NUMBER]).returns(BOOL))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        let (a, b) = compare_args(args, "Less")?;
+        Ok(Value::Bool(a < b))
+    }
+    fn residuals(&self, args: &[Value]) -> Option<Vec<f64>> {
+        let (a, b) = compare_args(args, "Less").ok()?;
+        Some(vec![(a - b).max(0.0)])
+    }
+}
+
+#[derive(Clone)]
+pub struct FnGreater;
+impl Operation for FnGreater {
+    clone_impl!(FnGreater);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new(">", &[NUMBER, NUMBER]).returns(BOOL))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        let (a, b) = compare_args(args, "Greater")?;
+        Ok(Value::Bool(a > b))
+    }
+    fn residuals(&self, args: &[Value]) -> Option<Vec<f64>> {
+        let (a, b) = compare_args(args, "Greater").ok()?;
+        Some(vec![(b - a).max(0.0)])
+    }
+}
+
+#[derive(Clone)]
+pub struct FnLessEqual;
+impl Operation for FnLessEqual {
+    clone_impl!(FnLessEqual);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("<=", &[NUMBER, NUMBER]).returns(BOOL))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        let (a, b) = compare_args(args, "Less or equal")?;
+        Ok(Value::Bool(a <= b))
+    }
+    fn residuals(&self, args: &[Value]) -> Option<Vec<f64>> {
+        let (a, b) = compare_args(args, "Less or equal").ok()?;
+        Some(vec![(a - b).max(0.0)])
+    }
+}
+
+#[derive(Clone)]
+pub struct FnGreaterEqual;
+impl Operation for FnGreaterEqual {
+    clone_impl!(FnGreaterEqual);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new(">=", &[NUMBER, NUMBER]).returns(BOOL))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        let (a, b) = compare_args(args, "Greater or equal")?;
+        Ok(Value::Bool(a >= b))
+    }
+    fn residuals(&self, args: &[Value]) -> Option<Vec<f64>> {
+        let (a, b) = compare_args(args, "Greater or equal").ok()?;
+        Some(vec![(b - a).max(0.0)])
+    }
+}
+
+#[derive(Clone)]
+pub struct FnEqual;
+impl Operation for FnEqual {
+    clone_impl!(FnEqual);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("=", &[NUMBER, NUMBER])
+                .rest(NUMBER)
+                .returns(BOOL),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // computed numbers are compared allowing for floating point error relative to their size, such as
+        // two lengths
+        let numbers: Vec<f64> = args.iter().filter_map(to_float).collect();
+        let scale = numbers.iter().fold(0.0, |scale: f64, n| scale.max(n.abs()));
+        Ok(Value::Bool(
+            numbers.iter().all(|n| is_near(*n, numbers[0], scale)),
+        ))
+    }
+    fn residuals(&self, args: &[Value]) -> Option<Vec<f64>> {
+        let numbers: Vec<f64> = args.iter().filter_map(to_float).collect();
+        Some(numbers[1..].iter().map(|n| n - numbers[0]).collect())
+    }
+}
+
+#[derive(Clone)]
+pub struct FnCollinear;
+impl Operation for FnCollinear {
+    clone_impl!(FnCollinear);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("collinear?", &[POINT, POINT, POINT])
+                .rest(POINT)
+                .returns(BOOL),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        let points = to_points(args)?;
+
+        // find a line through two distinct points, which exists unless every point coincides
+        let line = match points
+            .iter()
+            .find_map(|q| Line::from_points(points[0], *q).ok())
+        {
+            Some(line) => line,
+            None => return Ok(Value::Bool(true)),
+        };
+
+        // check every point against the line, within the tolerance relative to the size of the figure
+        let scale = coordinate_scale(&points);
+        Ok(Value::Bool(
+            points
+                .iter()
+                .all(|p| is_near(line.signed_distance(p), 0.0, scale)),
+        ))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnConcyclic;
+impl Operation for FnConcyclic {
+    clone_impl!(FnConcyclic);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("concyclic?", &[POINT, POINT, POINT, POINT])
+                .rest(POINT)
+                .returns(BOOL),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        let points = to_points(args)?;
+
+        // points that coincide with an earlier one lie on any circle through it, so are skipped
+        let mut distinct: Vec<Point> = Vec::new();
+        for p in points {
+            if distinct.iter().all(|q| distance(p, *q) >= tolerance()) {
+                distinct.push(p);
+            }
+        }
+        if distinct.len() < 3 {
+            return Ok(Value::Bool(true));
+        }
+
+        // the first three distinct points fix the only possible circle
+        let circle = match Triangle::new(distinct[0], distinct[1], distinct[2]) {
+            Ok(triangle) => triangle.circumcircle(),
+            Err(_) => return Ok(Value::Bool(false)),
+        };
+
+        // check every remaining point against the circle, within the tolerance relative to the size of the
+        // figure
+        let scale = coordinate_scale(&distinct).max(circle.radius);
+        Ok(Value::Bool(distinct[3..].iter().all(|p| {
+            is_near(distance(*p, circle.center), circle.radius, scale)
+        })))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnParallel;
+impl Operation for FnParallel {
+    clone_impl!(FnParallel);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("parallel?", &[LINEAR, LINEAR]).returns(BOOL))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // compare the unit normals of the two lines, which are the same size in any figure
+        let l = to_line(&args[0])?;
+        let m = to_line(&args[1])?;
+        Ok(Value::Bool(is_near(l.a * m.b - l.b * m.a, 0.0, 1.0)))
+    }
+    fn residuals(&self, args: &[Value]) -> Option<Vec<f64>> {
+        let l = to_line(&args[0]).ok()?;
+        let m = to_line(&args[1]).ok()?;
+        Some(vec![l.a * m.b - l.b * m.a])
+    }
+}
+
+#[derive(Clone)]
+pub struct FnPerpendicular;
+impl Operation for FnPerpendicular {
+    clone_impl!(FnPerpendicular);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("perpendicular?", &[LINEAR, LINEAR]).returns(BOOL))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // compare the unit normals of the two lines, which are the same size in any figure
+        let l = to_line(&args[0])?;
+        let m = to_line(&args[1])?;
+        Ok(Value::Bool(is_near(l.a * m.a + l.b * m.b, 0.0, 1.0)))
+    }
+    fn residuals(&self, args: &[Value]) -> Option<Vec<f64>> {
+        let l = to_line(&args[0]).ok()?;
+        let m = to_line(&args[1]).ok()?;
+        Some(vec![l.a * m.a + l.b * m.b])
+    }
+}
+
+/// Helper function to find how far a point is from lying on a line, segment, ray, or circle, as its
+/// signed distance across it and, for segments and rays, its distance past their ends along the line
+fn on_residuals(point: Point, value: &Value) -> Option<Vec<f64>> {
+    let (start, end, t_max) = match value {
+        Value::Line(l) => return Some(vec![l.signed_distance(&point)]),
+        Value::Circle(c) => return Some(vec![distance(point, c.center) - c.radius]),
+        Value::Lineseg(l) => (l.start, l.end, 1.0),
+        Value::Ray(r) => (r.origin, r.through, f64::INFINITY),
+        _ => return None,
+    };
+    let line = Line::from_points(start, end).ok()?;
+    let along = closest_point(point, start, end, f64::NEG_INFINITY, f64::INFINITY);
+    let past = distance(along, closest_point(point, start, end, 0.0, t_max));
+    Some(vec![line.signed_distance(&point), past])
+}
+
+#[derive(Clone)]
+pub struct FnOn;
+impl Operation for FnOn {
+    clone_impl!(FnOn);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("on?", &[POINT, ON]).returns(BOOL))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // the point is compared within the tolerance relative to the size of the point and the value
+        let scale = match (&args[0], &args[1]) {
+            (Value::Point(p), Value::Circle(c)) => coordinate_scale(&[*p, c.center]).max(c.radius),
+            (Value::Point(p), Value::Lineseg(l)) => coordinate_scale(&[*p, l.start, l.end]),
+            (Value::Point(p), Value::Ray(r)) => coordinate_scale(&[*p, r.origin, r.through]),
+            (Value::Point(p), _) => coordinate_scale(&[*p]),
+            _ => 1.0,
+        };
+        match self.residuals(args) {
+            Some(residuals) => Ok(Value::Bool(
+                residuals.iter().all(|r| is_near(*r, 0.0, scale)),
+            )),
+            None => Err(ElementsError::Type("Invalid types for on?".to_string())),
         }
-        match (&args[0], &args[1]) {
-            (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a / b)),
-            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a / b)),
-            _ => Err("Invalid types for division".to_string()),
+    }
+    fn residuals(&self, args: &[Value]) -> Option<Vec<f64>> {
+        match &args[0] {
+            Value::Point(p) => on_residuals(*p, &args[1]),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnConcurrent;
+impl Operation for FnConcurrent {
+    clone_impl!(FnConcurrent);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("concurrent?", &[LINEAR, LINEAR, LINEAR])
+                .rest(LINEAR)
+                .returns(BOOL),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        let lines = args.iter().map(to_line).collect::<Result<Vec<Line>, _>>()?;
+
+        // the first two lines meet at the only possible point, and parallel lines never meet
+        let point = match lines[0].intersect(&lines[1]) {
+            Some(point) => point,
+            None => return Ok(Value::Bool(false)),
+        };
+
+        // check every remaining line against the point, within the tolerance relative to its size
+        let scale = coordinate_scale(&[point]);
+        Ok(Value::Bool(
+            lines[2..]
+                .iter()
+                .all(|l| is_near(l.signed_distance(&point), 0.0, scale)),
+        ))
+    }
+}
+
+/// Helper function to check a claim about the figure, recording whether it held under the given name,
+/// if any, and returning whether it held and its name
+fn check_claim(args: &[Value], is_assert: bool) -> (bool, Option<String>) {
+    let holds = matches!(args[0], Value::Bool(true));
+    let name = match args.get(1) {
+        Some(Value::String(s)) => Some(s.trim_matches('"').to_string()),
+        _ => None,
+    };
+    claims::record(Claim {
+        name: name.clone(),
+        holds,
+        is_assert,
+    });
+    (holds, name)
+}
+
+#[derive(Clone)]
+pub struct FnAssert;
+impl Operation for FnAssert {
+    clone_impl!(FnAssert);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("assert", &[BOOL]).optional(&[STRING]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // a figure that breaks an assertion is not drawn
+        match check_claim(args, true) {
+            (true, _) => Ok(Value::Undefined),
+            (false, Some(name)) => Err(ElementsError::Geometry(format!(
+                "Assertion failed: {}",
+                name
+            ))),
+            (false, None) => Err(ElementsError::Geometry("Assertion failed".to_string())),
         }
     }
 }
 
 #[derive(Clone)]
-pub struct FnNop;
-impl Operation for FnNop {
-    clone_impl!(FnNop);
-    fn call(&self, _: &[Value]) -> Result<Value, String> {
-        Ok(Value::Int(0))
+pub struct FnClaim;
+impl Operation for FnClaim {
+    clone_impl!(FnClaim);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("claim", &[BOOL]).optional(&[STRING]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // a claim is only reported by verify, so the figure is drawn whether or not it holds
+        check_claim(args, false);
+        Ok(Value::Undefined)
+    }
+}
+
+#[derive(Clone)]
+pub struct FnUnknown;
+impl Operation for FnUnknown {
+    clone_impl!(FnUnknown);
+    fn call(&self, _: &[Value]) -> Result<Value, ElementsError> {
+        Err(ElementsError::Parse("Unknown function".to_string()))
     }
 }
 
@@ -120,59 +721,77 @@ Basic geometric components
 pub struct FnInscribedAngle;
 impl FnInscribedAngle {
     /// Case 1: create an inscribed angle given a circle and an degree value
-    fn from_circle_degrees(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 2 arguments
-        if args.len() < 2 {
-            return Err("Inscribed angle requires exactly 2 arguments".to_string());
-        }
-
+    fn from_circle_degrees(args: &[Value]) -> Result<Value, ElementsError> {
         // check for circle and degree
         let circle = match &args[0] {
             Value::Circle(c) => c,
-            _ => return Err("Invalid types for circle".to_string()),
+            _ => return Err(ElementsError::Type("Invalid types for circle".to_string())),
         };
         let degree: f64 = match &args[1] {
             Value::Int(i) => *i as f64,
             Value::Float(f) => *f,
-            _ => return Err("Invalid types for degree".to_string()),
+            _ => return Err(ElementsError::Type("Invalid types for degree".to_string())),
         };
 
         // check if degree exceeds 180 degrees on the circle
         if degree > 180.0 {
-            return Err("Degree exceeds 180 degrees".to_string());
+            return Err(ElementsError::Type(
+                "Degree exceeds 180 degrees".to_string(),
+            ));
         }
 
-        // get two random points on the circle to create first line
-        let mut start = circle.get_point();
-        let mut center = circle.get_point();
-
-        // limit the maximum distance between the two points if angle is greater than 90 degrees
+        // the chord from the start to the vertex spans a central angle between these, so pick one directly,
+        // and the end is then twice the degrees around the circle from the start, away from the vertex
         let max_distance = (180.0 - degree).to_radians().sin() * circle.radius * 2.0;
-        while distance(start, center) > max_distance && degree > 90.0 {
-            start = circle.get_point();
-            center = circle.get_point();
-        }
-
-        // if maximum distance is not less than the radius, limit the minimum distance to the radius
-        while distance(start, center) < circle.radius && max_distance > circle.radius {
-            start = circle.get_point();
-            center = circle.get_point();
-        }
-
-        // get the end point of the angle, always choosing the larger arc
-        let end = match circle.get_point_on_arc(start, center, degree as f64) {
-            Ok(p) => p,
-            Err(e) => return Err(e),
+        let min_arc = match max_distance > circle.radius {
+            true => PI / 3.0,
+            false => 0.0,
+        };
+        let max_arc = match degree > 90.0 {
+            true => 2.0 * (180.0 - degree).to_radians(),
+            false => PI,
         };
+        let (start, center, end) = sample(
+            MAX_TRIES,
+            || {
+                let start = random::random() * 2.0 * PI;
+                let arc = min_arc + (max_arc - min_arc) * random::random();
+                let side = if random::random() < 0.5 { 1.0 } else { -1.0 };
+                (
+                    circle.point_at(start),
+                    circle.point_at(start + side * arc),
+                    circle.point_at(start - side * 2.0 * degree.to_radians()),
+                )
+            },
+            &[
+                // limit the maximum distance between the two points if angle is greater than 90 degrees
+                Constraint::new(
+                    "maximum chord length",
+                    |(start, center, _): &(Point, Point, Point)| {
+                        degree <= 90.0 || distance(*start, *center) <= max_distance
+                    },
+                ),
+                // if maximum distance is not less than the radius, limit the minimum distance to the radius
+                Constraint::new(
+                    "minimum chord length",
+                    |(start, center, _): &(Point, Point, Point)| {
+                        max_distance <= circle.radius || distance(*start, *center) >= circle.radius
+                    },
+                ),
+            ],
+        )?;
 
-        Ok(Value::Angle(Angle { start, center, end }))
+        Ok(Value::Angle(Angle::new(start, center, end)))
     }
 }
 
 impl Operation for FnInscribedAngle {
     clone_impl!(FnInscribedAngle);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        match self.from_circle_degrees(args) {
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("iangle", &[CIRCLE, NUMBER]).returns(ANGLE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match Self::from_circle_degrees(args) {
             Ok(angle) => Ok(angle),
             Err(e) => Err(e),
         }
@@ -183,36 +802,105 @@ impl Operation for FnInscribedAngle {
 pub struct FnAngle;
 impl FnAngle {
     /// Case 1: create an angle from three points
-    fn from_points(&self, args: &[Value]) -> Result<Value, String> {
+    fn from_points(args: &[Value]) -> Result<Value, ElementsError> {
         // check for 3 arguments
         if args.len() != 3 {
-            return Err("Angle requires exactly 3 arguments".to_string());
+            return Err(ElementsError::Arity(
+                "Angle requires exactly 3 arguments".to_string(),
+            ));
         }
 
         // check for 3 points
         let mut points: Vec<Point> = Vec::new();
         for arg in args {
             match arg {
-                Value::Point(p) => points.push(p.clone()),
-                _ => return Err("Invalid types for point".to_string()),
+                Value::Point(p) => points.push(*p),
+                _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
             }
         }
 
         // try creating the angle
-        Ok(Value::Angle(Angle {
-            start: points[0],
-            center: points[1],
-            end: points[2],
-        }))
+        Ok(Value::Angle(Angle::new(points[0], points[1], points[2])))
+    }
+
+    /// Set the arc marker of an angle from the :radius and :arcs keywords
+    fn with_marker(
+        angle: Angle,
+        keywords: &HashMap<String, Value>,
+    ) -> Result<Angle, ElementsError> {
+        let mut angle = angle;
+        for (name, value) in keywords {
+            match name.as_str() {
+                "radius" => match to_float(value) {
+                    Some(radius) if radius > 0.0 => angle.radius = Some(radius),
+                    _ => {
+                        return Err(ElementsError::Type(
+                            "Marker radius must be a positive number".to_string(),
+                        ))
+                    }
+                },
+                "arcs" => match value {
+                    Value::Int(i) if (0..=3).contains(i) => angle.arcs = *i as u8,
+                    _ => {
+                        return Err(ElementsError::Type(
+                            "Marker arcs must be 0, 1, 2, or 3".to_string(),
+                        ))
+                    }
+                },
+                _ => {
+                    return Err(ElementsError::Type(format!(
+                        "Unknown angle keyword :{}",
+                        name
+                    )))
+                }
+            }
+        }
+        Ok(angle)
     }
 }
 
 impl Operation for FnAngle {
     clone_impl!(FnAngle);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        match self.from_points(args) {
-            Ok(angle) => Ok(angle),
-            _ => Err("Invalid arguments for angle".to_string()),
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        let (positional, keywords) = keyword_args(args)?;
+        let angle = match Self::from_points(&positional)? {
+            Value::Angle(a) => a,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid arguments for angle".to_string(),
+                ))
+            }
+        };
+        Ok(Value::Angle(Self::with_marker(angle, &keywords)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnMarkAngle;
+impl Operation for FnMarkAngle {
+    clone_impl!(FnMarkAngle);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // split the angle from the marker keywords
+        let (positional, keywords) = keyword_args(args)?;
+        if positional.len() != 1 {
+            return Err(ElementsError::Arity(
+                "mark-angle requires exactly 1 positional argument".to_string(),
+            ));
+        }
+
+        // keep any style on the angle while replacing its marker
+        match &positional[0] {
+            Value::Angle(a) => Ok(Value::Angle(FnAngle::with_marker(*a, &keywords)?)),
+            Value::Styled(v, style) => match v.as_ref() {
+                Value::Angle(a) => {
+                    Ok(Value::Angle(FnAngle::with_marker(*a, &keywords)?).with_style(style))
+                }
+                _ => Err(ElementsError::Type("Invalid types for angle".to_string())),
+            },
+            _ => Err(ElementsError::Type("Invalid types for angle".to_string())),
         }
     }
 }
@@ -222,18 +910,20 @@ pub struct FnLineseg;
 
 impl FnLineseg {
     /// Case 1: create a line segment from two points
-    fn from_points(&self, args: &[Value]) -> Result<Value, String> {
+    fn from_points(args: &[Value]) -> Result<Value, ElementsError> {
         // check for 2 arguments
         if args.len() != 2 {
-            return Err("Line segment requires exactly 2 arguments".to_string());
+            return Err(ElementsError::Arity(
+                "Line segment requires exactly 2 arguments".to_string(),
+            ));
         }
 
         // check for 2 points
         let mut points: Vec<Point> = Vec::new();
         for arg in args {
             match arg {
-                Value::Point(p) => points.push(p.clone()),
-                _ => return Err("Invalid types for point".to_string()),
+                Value::Point(p) => points.push(*p),
+                _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
             }
         }
 
@@ -247,454 +937,3381 @@ impl FnLineseg {
 
 impl Operation for FnLineseg {
     clone_impl!(FnLineseg);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        match self.from_points(args) {
-            Ok(lineseg) => Ok(lineseg),
-            _ => Err("Invalid arguments for line segment".to_string()),
-        }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("lineseg", &[POINT, POINT]).returns(LINESEG))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        Self::from_points(args)
     }
 }
 
 #[derive(Clone)]
-pub struct FnMidpoint;
-impl Operation for FnMidpoint {
-    clone_impl!(FnMidpoint);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 2 arguments
-        if args.len() != 2 {
-            return Err("Midpoint requires exactly 2 arguments".to_string());
+pub struct FnLine;
+
+impl FnLine {
+    /// Case 1: Two points
+    fn from_points(args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 2 points
+        let mut points: Vec<Point> = Vec::new();
+        for arg in args {
+            match arg {
+                Value::Point(p) => points.push(*p),
+                _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+            }
         }
 
-        // Extract the two points from the arguments
-        let p1 = match &args[0] {
-            Value::Point(p) => p.clone(),
-            _ => return Err("Invalid type for first argument, expected a Point".to_string()),
-        };
-        let p2 = match &args[1] {
-            Value::Point(p) => p.clone(),
-            _ => return Err("Invalid type for second argument, expected a Point".to_string()),
-        };
+        // try creating the line
+        Ok(Value::Line(Line::from_points(points[0], points[1])?))
+    }
 
-        // try getting the midpoint
-        return Ok(Value::Point(midpoint(p1, p2)));
+    /// Case 2: A line segment or ray to extend
+    fn from_lineseg(args: &[Value]) -> Result<Value, ElementsError> {
+        Ok(Value::Line(to_line(&args[0])?))
     }
 }
 
-#[derive(Clone)]
-pub struct FnCircumcenter;
-impl Operation for FnCircumcenter {
-    clone_impl!(FnCircumcenter);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 1 argument
-        if args.len() != 1 {
-            return Err("Circumcenter requires exactly 1 argument".to_string());
+impl Operation for FnLine {
+    clone_impl!(FnLine);
+    fn overloads(&self) -> Vec<Signature> {
+        vec![
+            Signature::new("line", &[POINT, POINT]).returns(LINE),
+            Signature::new("line", &[LINEAR]).returns(LINE),
+        ]
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match Signature::resolve(&self.overloads(), args)? {
+            0 => Self::from_points(args),
+            _ => Self::from_lineseg(args),
         }
+    }
+}
 
-        // check for 1 triangle
-        let triangle = match &args[0] {
-            Value::Triangle(t) => t.clone(),
-            _ => return Err("Invalid types for triangle".to_string()),
-        };
+#[derive(Clone)]
+pub struct FnRay;
+impl Operation for FnRay {
+    clone_impl!(FnRay);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("ray", &[POINT, POINT]).returns(RAY))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 2 points
+        let mut points: Vec<Point> = Vec::new();
+        for arg in args {
+            match arg {
+                Value::Point(p) => points.push(*p),
+                _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+            }
+        }
 
-        // try getting the circumcenter
-        return Ok(Value::Point(triangle.circumcenter()));
+        // try creating the ray
+        Ok(Value::Ray(Ray::new(points[0], points[1])?))
     }
 }
 
 #[derive(Clone)]
-pub struct FnIncenter;
-impl Operation for FnIncenter {
-    clone_impl!(FnIncenter);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 1 argument
-        if args.len() != 1 {
-            return Err("Incenter requires exactly 1 argument".to_string());
+pub struct FnFreePoint;
+impl FnFreePoint {
+    /// Return a point sampler for the value the free point lies on
+    fn sampler(value: &Value) -> Result<Box<dyn Fn() -> Point>, ElementsError> {
+        match value {
+            // a point on the circle
+            Value::Circle(c) => {
+                let circle = *c;
+                Ok(Box::new(move || circle.get_point()))
+            }
+            // a point on the line segment
+            Value::Lineseg(l) => {
+                let lineseg = *l;
+                Ok(Box::new(move || {
+                    let t = random::random();
+                    Point {
+                        x: lineseg.start.x + t * (lineseg.end.x - lineseg.start.x),
+                        y: lineseg.start.y + t * (lineseg.end.y - lineseg.start.y),
+                    }
+                }))
+            }
+            // a point inside the triangle
+            Value::Triangle(t) => {
+                let triangle = *t;
+                Ok(Box::new(move || {
+                    let (mut u, mut v) = (random::random(), random::random());
+                    if u + v > 1.0 {
+                        u = 1.0 - u;
+                        v = 1.0 - v;
+                    }
+                    Point {
+                        x: triangle.a.x
+                            + u * (triangle.b.x - triangle.a.x)
+                            + v * (triangle.c.x - triangle.a.x),
+                        y: triangle.a.y
+                            + u * (triangle.b.y - triangle.a.y)
+                            + v * (triangle.c.y - triangle.a.y),
+                    }
+                }))
+            }
+            _ => Err(ElementsError::Type(
+                "Invalid types for free point, expected a Circle, Lineseg, or Triangle".to_string(),
+            )),
         }
+    }
 
-        // check for 1 triangle
-        let triangle = match &args[0] {
-            Value::Triangle(t) => t.clone(),
-            _ => return Err("Invalid types for triangle".to_string()),
+    /// Helper function to extract a list of values of one kind from a keyword argument
+    fn list_of<T>(
+        value: Option<&Value>,
+        name: &str,
+        extract: impl Fn(&Value) -> Option<T>,
+    ) -> Result<Vec<T>, ElementsError> {
+        let values = match value {
+            None => return Ok(Vec::new()),
+            Some(Value::List(values)) => values.clone(),
+            Some(value) => vec![value.clone()],
         };
-
-        // try getting the incenter
-        return Ok(Value::Point(triangle.incenter()));
+        values
+            .iter()
+            .map(|v| {
+                extract(v)
+                    .ok_or_else(|| ElementsError::Type(format!("Invalid types for :{}", name)))
+            })
+            .collect()
     }
 }
 
-#[derive(Clone)]
-pub struct FnOrthocenter;
-impl Operation for FnOrthocenter {
-    clone_impl!(FnOrthocenter);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 1 argument
-        if args.len() != 1 {
-            return Err("Orthocenter requires exactly 1 argument".to_string());
+impl Operation for FnFreePoint {
+    clone_impl!(FnFreePoint);
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // split the object to sample on from the constraints
+        let (positional, keywords) = keyword_args(args)?;
+        if positional.len() != 1 {
+            return Err(ElementsError::Arity(
+                "Free point requires exactly 1 positional argument".to_string(),
+            ));
         }
+        let generate = Self::sampler(&positional[0])?;
 
-        // check for 1 triangle
+        // extract the constraints
+        let avoid = Self::list_of(keywords.get("avoid"), "avoid", |v| match v {
+            Value::Point(p) => Some(*p),
+            _ => None,
+        })?;
+        let keep_out = Self::list_of(keywords.get("keep-out"), "keep-out", |v| match v {
+            Value::Circle(c) => Some(*c),
+            _ => None,
+        })?;
+        let angle_of = Self::list_of(keywords.get("angle-of"), "angle-of", |v| match v {
+            Value::Point(p) => Some(*p),
+            _ => None,
+        })?;
+        let number = |name: &str, default: f64| -> Result<f64, ElementsError> {
+            match keywords.get(name) {
+                None => Ok(default),
+                Some(v) => to_float(v)
+                    .ok_or_else(|| ElementsError::Type(format!("Invalid types for :{}", name))),
+            }
+        };
+        let min_dist = number("min-dist", 0.0)?;
+        let min_angle = number("min-angle", 0.0)?;
+        let max_angle = number("max-angle", 180.0)?;
+        let max_tries = match keywords.get("max-tries") {
+            None => MAX_TRIES,
+            Some(Value::Int(i)) if *i > 0 => *i as usize,
+            Some(_) => {
+                return Err(ElementsError::Type(
+                    "Invalid types for :max-tries".to_string(),
+                ))
+            }
+        };
+        if !angle_of.is_empty() && angle_of.len() != 2 {
+            return Err(ElementsError::Arity(
+                ":angle-of requires exactly 2 points".to_string(),
+            ));
+        }
+
+        // build the named constraints
+        let mut constraints: Vec<Constraint<Point>> = Vec::new();
+        for (i, point) in avoid.iter().enumerate() {
+            let point = *point;
+            constraints.push(Constraint::new(
+                format!("min-dist {} from avoided point {}", min_dist, i + 1),
+                move |p: &Point| distance(*p, point) >= min_dist,
+            ));
+        }
+        for (i, circle) in keep_out.iter().enumerate() {
+            let circle = *circle;
+            constraints.push(Constraint::new(
+                format!("keep-out circle {}", i + 1),
+                move |p: &Point| distance(*p, circle.center) > circle.radius,
+            ));
+        }
+        if angle_of.len() == 2 {
+            let (a, b) = (angle_of[0], angle_of[1]);
+            constraints.push(Constraint::new(
+                format!("angle between {} and {} degrees", min_angle, max_angle),
+                move |p: &Point| {
+                    let angle = Angle::new(a, *p, b).degrees();
+                    angle >= min_angle && angle <= max_angle
+                },
+            ));
+        }
+
+        // sample the point
+        match sample(max_tries, generate, &constraints) {
+            Ok(point) => Ok(Value::Point(point)),
+            Err(e) => Err(e.context("free-point")),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnMidpoint;
+impl Operation for FnMidpoint {
+    clone_impl!(FnMidpoint);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("midpoint", &[POINT, POINT]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // Extract the two points from the arguments
+        let p1 = match &args[0] {
+            Value::Point(p) => *p,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid type for first argument, expected a Point".to_string(),
+                ))
+            }
+        };
+        let p2 = match &args[1] {
+            Value::Point(p) => *p,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid type for second argument, expected a Point".to_string(),
+                ))
+            }
+        };
+
+        // try getting the midpoint
+        Ok(Value::Point(midpoint(p1, p2)))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnLerp;
+impl Operation for FnLerp {
+    clone_impl!(FnLerp);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("lerp", &[POINT, POINT, NUMBER]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 2 points and 1 parameter
+        let (p, q) = match (&args[0], &args[1]) {
+            (Value::Point(p), Value::Point(q)) => (*p, *q),
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+        };
+        let t = match to_float(&args[2]) {
+            Some(t) => t,
+            None => {
+                return Err(ElementsError::Type(
+                    "Invalid types for parameter".to_string(),
+                ))
+            }
+        };
+
+        Ok(Value::Point(lerp(p, q, t)))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnDivideSegment;
+impl Operation for FnDivideSegment {
+    clone_impl!(FnDivideSegment);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("divide-segment", &[POINT, POINT, NUMBER, NUMBER]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 2 points and 2 ratio parts
+        let (p, q) = match (&args[0], &args[1]) {
+            (Value::Point(p), Value::Point(q)) => (*p, *q),
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+        };
+        let (m, n) = match (to_float(&args[2]), to_float(&args[3])) {
+            (Some(m), Some(n)) => (m, n),
+            _ => return Err(ElementsError::Type("Invalid types for ratio".to_string())),
+        };
+        if m + n == 0.0 {
+            return Err(ElementsError::Geometry(
+                "Ratio parts must not sum to zero".to_string(),
+            ));
+        }
+
+        Ok(Value::Point(lerp(p, q, m / (m + n))))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnDistance;
+impl Operation for FnDistance {
+    clone_impl!(FnDistance);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("distance", &[MEASURABLE, MEASURABLE]).returns(FLOAT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // the point may be given on either side
+        let (point, other) = match (&args[0], &args[1]) {
+            (Value::Point(p), other) => (*p, other),
+            (other, Value::Point(p)) => (*p, other),
+            _ => {
+                return Err(ElementsError::Arity(
+                    "Distance requires at least 1 point".to_string(),
+                ))
+            }
+        };
+
+        // measure to the nearest part of the other value
+        let d = match other {
+            Value::Point(q) => distance(point, *q),
+            Value::Line(l) => l.signed_distance(&point).abs(),
+            Value::Lineseg(l) => distance(point, closest_point(point, l.start, l.end, 0.0, 1.0)),
+            Value::Ray(r) => distance(
+                point,
+                closest_point(point, r.origin, r.through, 0.0, f64::INFINITY),
+            ),
+            Value::Circle(c) => (distance(point, c.center) - c.radius).abs(),
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for distance".to_string(),
+                ))
+            }
+        };
+        Ok(Value::Float(d))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnProject;
+impl Operation for FnProject {
+    clone_impl!(FnProject);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("project", &[POINT, LINEAR]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 point
+        let point = match &args[0] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+        };
+
+        // find the closest point, staying within the segment or ray
+        let closest = match &args[1] {
+            Value::Line(l) => l.project(&point),
+            Value::Lineseg(l) => closest_point(point, l.start, l.end, 0.0, 1.0),
+            Value::Ray(r) => closest_point(point, r.origin, r.through, 0.0, f64::INFINITY),
+            _ => return Err(ElementsError::Type("Invalid types for line".to_string())),
+        };
+        Ok(Value::Point(closest))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnClosestPoint;
+impl Operation for FnClosestPoint {
+    clone_impl!(FnClosestPoint);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("closest-point", &[CIRCLE, POINT]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 circle and 1 point
+        let circle = match &args[0] {
+            Value::Circle(c) => *c,
+            _ => return Err(ElementsError::Type("Invalid types for circle".to_string())),
+        };
+        let point = match &args[1] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+        };
+
+        // every point of the circle is equally close to its center
+        let d = distance(point, circle.center);
+        if d == 0.0 {
+            return Err(ElementsError::Geometry(
+                "Every point on the circle is closest to its center".to_string(),
+            ));
+        }
+        Ok(Value::Point(lerp(circle.center, point, circle.radius / d)))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnTangentCircle;
+impl Operation for FnTangentCircle {
+    clone_impl!(FnTangentCircle);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("tangent-circle", &[LINEAR, LINEAR, NUMBER])
+                .optional(&[POINT])
+                .returns(CIRCLE),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 2 lines, a positive radius, and an optional point picking the angle
+        let l = to_line(&args[0])?;
+        let m = to_line(&args[1])?;
+        let radius = match to_float(&args[2]) {
+            Some(r) if r > 0.0 => r,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Radius must be a positive number".to_string(),
+                ))
+            }
+        };
+        let (sl, sm) = match args.get(3) {
+            Some(Value::Point(p)) => (l.signed_distance(p).signum(), m.signed_distance(p).signum()),
+            Some(_) => return Err(ElementsError::Type("Invalid types for point".to_string())),
+            None => (1.0, 1.0),
+        };
+
+        // the center lies on both lines shifted towards the chosen side by the radius
+        let shifted = |line: &Line, side: f64| Line {
+            c: line.c - side * radius,
+            ..*line
+        };
+        match shifted(&l, sl).intersect(&shifted(&m, sm)) {
+            Some(center) => Ok(Value::Circle(Circle::new(center, radius)?)),
+            None => Err(ElementsError::Geometry("Lines are parallel".to_string())),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnTangentCircleAt;
+impl Operation for FnTangentCircleAt {
+    clone_impl!(FnTangentCircleAt);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("tangent-circle-at", &[CIRCLE, POINT, NUMBER])
+                .optional(&[STRING])
+                .returns(CIRCLE),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 circle, 1 point, a positive radius, and an optional kind of tangency
+        let circle = match &args[0] {
+            Value::Circle(c) => *c,
+            _ => return Err(ElementsError::Type("Invalid types for circle".to_string())),
+        };
+        let point = match &args[1] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+        };
+        let radius = match to_float(&args[2]) {
+            Some(r) if r > 0.0 => r,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Radius must be a positive number".to_string(),
+                ))
+            }
+        };
+        // the tangency may be quoted, as in "internal", or given as a bare name
+        let external = match args.get(3) {
+            None => true,
+            Some(Value::String(s)) if s.trim_matches('"') == "external" => true,
+            Some(Value::String(s)) if s.trim_matches('"') == "internal" => false,
+            Some(_) => {
+                return Err(ElementsError::Type(
+                    "Tangency must be either internal or external".to_string(),
+                ))
+            }
+        };
+
+        // the center lies on the ray from the given center through the point of tangency, which must be on
+        // the circle for both circles to pass through it
+        if !circle.is_point_on_circle(point) {
+            return Err(ElementsError::Geometry(
+                "Point of tangency must lie on the circle".to_string(),
+            ));
+        }
+        let d = distance(point, circle.center);
+        let offset = if external {
+            circle.radius + radius
+        } else {
+            circle.radius - radius
+        };
+        Ok(Value::Circle(Circle::new(
+            lerp(circle.center, point, offset / d),
+            radius,
+        )?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnCircumcenter;
+impl Operation for FnCircumcenter {
+    clone_impl!(FnCircumcenter);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("circumcenter", &[TRIANGLE]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the circumcenter
+        Ok(Value::Point(triangle.circumcenter()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnIncenter;
+impl Operation for FnIncenter {
+    clone_impl!(FnIncenter);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("incenter", &[TRIANGLE]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the incenter
+        Ok(Value::Point(triangle.incenter()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnOrthocenter;
+impl Operation for FnOrthocenter {
+    clone_impl!(FnOrthocenter);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("orthocenter", &[TRIANGLE]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the orthocenter
+        Ok(Value::Point(triangle.orthocenter()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnCentroid;
+impl Operation for FnCentroid {
+    clone_impl!(FnCentroid);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("centroid", &[Param::OneOf(&["Triangle", "Polygon"])]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // try getting the centroid of the shape
+        match &args[0] {
+            Value::Triangle(t) => Ok(Value::Point(t.centroid())),
+            Value::Polygon(p) => Ok(Value::Point(p.centroid())),
+            _ => Err(ElementsError::Type(
+                "Invalid types for centroid, expected a Triangle or Polygon".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnFermatPoint;
+impl Operation for FnFermatPoint {
+    clone_impl!(FnFermatPoint);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("fermat-point", &[TRIANGLE]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the Fermat point
+        Ok(Value::Point(triangle.fermat_point()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnSymmedianPoint;
+impl Operation for FnSymmedianPoint {
+    clone_impl!(FnSymmedianPoint);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("symmedian-point", &[TRIANGLE]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the symmedian point
+        Ok(Value::Point(triangle.symmedian_point()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnGergonne;
+impl Operation for FnGergonne {
+    clone_impl!(FnGergonne);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("gergonne", &[TRIANGLE]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the Gergonne point
+        Ok(Value::Point(triangle.gergonne_point()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnNagel;
+impl Operation for FnNagel {
+    clone_impl!(FnNagel);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("nagel", &[TRIANGLE]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the Nagel point
+        Ok(Value::Point(triangle.nagel_point()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnBrocard;
+impl Operation for FnBrocard {
+    clone_impl!(FnBrocard);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("brocard", &[TRIANGLE, INT]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle and 1 index
         let triangle = match &args[0] {
-            Value::Triangle(t) => t.clone(),
-            _ => return Err("Invalid types for triangle".to_string()),
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+        let index = match &args[1] {
+            Value::Int(i) => *i,
+            _ => return Err(ElementsError::Type("Invalid types for index".to_string())),
+        };
+
+        // try getting the Brocard point
+        Ok(Value::Point(triangle.brocard_point(index)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnPoint;
+impl Operation for FnPoint {
+    clone_impl!(FnPoint);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("point", &[NUMBER, NUMBER]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // try forcing the arguments into floats
+        let mut floats = Vec::new();
+        for arg in args {
+            match arg {
+                Value::Int(i) => floats.push(*i as f64),
+                Value::Float(f) => floats.push(*f),
+                _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+            }
+        }
+
+        // return the point
+        Ok(Value::Point(Point {
+            x: floats[0],
+            y: floats[1],
+        }))
+    }
+}
+
+/*
+Functions that return properties
+*/
+
+#[derive(Clone)]
+pub struct FnIntersect;
+
+impl FnIntersect {
+    /// Case 1: Two lines
+    fn from_lines(args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 2 lines
+        let line1 = to_line(&args[0])?;
+        let line2 = to_line(&args[1])?;
+
+        // use the high precision kernel if selected
+        #[cfg(feature = "high-precision")]
+        if precise::is_enabled() {
+            let (p1, q1) = line1.points();
+            let (p2, q2) = line2.points();
+            return match precise::intersect_lines(p1, q1, p2, q2) {
+                Some(point) => Ok(Value::Point(point)),
+                None => Err(ElementsError::Geometry("Lines are parallel".to_string())),
+            };
+        }
+
+        // find the intersection point
+        match line1.intersect(&line2) {
+            Some(point) => Ok(Value::Point(point)),
+            None => Err(ElementsError::Geometry("Lines are parallel".to_string())),
+        }
+    }
+
+    /// Case 2: One line and one circle
+    fn from_line_circle(args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 line, 1 circle, and 1 index either 0 or 1
+        let line = to_line(&args[0])?;
+        let circle = match &args[1] {
+            Value::Circle(c) => *c,
+            _ => return Err(ElementsError::Type("Invalid types for circle".to_string())),
+        };
+        let index = match &args[2] {
+            Value::Int(i) => *i,
+            _ => return Err(ElementsError::Type("Invalid types for index".to_string())),
+        };
+        if index != 0 && index != 1 {
+            return Err(ElementsError::Type(
+                "Index must be either 0 or 1".to_string(),
+            ));
+        }
+
+        // use the high precision kernel if selected
+        #[cfg(feature = "high-precision")]
+        if precise::is_enabled() {
+            let (p, q) = line.points();
+            return match precise::intersect_line_circle(p, q, circle.center, circle.radius) {
+                Some((first, _)) if index == 0 => Ok(Value::Point(first)),
+                Some((_, second)) => Ok(Value::Point(second)),
+                None => Err(ElementsError::Geometry(
+                    "No intersection points".to_string(),
+                )),
+            };
+        }
+
+        // return the intersection point
+        match line.intersect_circle(&circle) {
+            Some((first, _)) if index == 0 => Ok(Value::Point(first)),
+            Some((_, second)) => Ok(Value::Point(second)),
+            None => Err(ElementsError::Geometry(
+                "No intersection points".to_string(),
+            )),
+        }
+    }
+}
+
+impl Operation for FnIntersect {
+    clone_impl!(FnIntersect);
+    fn overloads(&self) -> Vec<Signature> {
+        vec![
+            Signature::new("intersect", &[LINEAR, LINEAR]).returns(POINT),
+            Signature::new("intersect", &[LINEAR, CIRCLE, INT]).returns(POINT),
+        ]
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match Signature::resolve(&self.overloads(), args)? {
+            0 => Self::from_lines(args),
+            _ => Self::from_line_circle(args),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnInradius;
+impl Operation for FnInradius {
+    clone_impl!(FnInradius);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("inradius", &[TRIANGLE]).returns(FLOAT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the inradius
+        Ok(Value::Float(triangle.inradius()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnCircumradius;
+impl Operation for FnCircumradius {
+    clone_impl!(FnCircumradius);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("circumradius", &[TRIANGLE]).returns(FLOAT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the circumradius
+        Ok(Value::Float(triangle.circumradius()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnMedian;
+impl Operation for FnMedian {
+    clone_impl!(FnMedian);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("median", &[TRIANGLE, VERTEX]).returns(LINESEG))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle and 1 vertex
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+        let vertex = triangle_vertex(&triangle, &args[1])?;
+
+        // join the vertex to the midpoint of the opposite side
+        let (b, c) = triangle.others(vertex)?;
+        Ok(Value::Lineseg(Lineseg {
+            start: vertex,
+            end: midpoint(b, c),
+        }))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnCevian;
+impl Operation for FnCevian {
+    clone_impl!(FnCevian);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("cevian", &[TRIANGLE, VERTEX, POINT]).returns(LINESEG))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle, 1 vertex, and 1 point
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+        let vertex = triangle_vertex(&triangle, &args[1])?;
+        let point = match &args[2] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+        };
+
+        // check that the point lies on the opposite side, both on its line and between its ends, within the
+        // tolerance relative to the length of the side
+        let (b, c) = triangle.others(vertex)?;
+        let length = distance(b, c);
+        let along = ((point.x - b.x) * (c.x - b.x) + (point.y - b.y) * (c.y - b.y)) / length;
+        let is_on_line = is_near(
+            Line::from_points(b, c)?.signed_distance(&point),
+            0.0,
+            length,
+        );
+        let is_between = (0.0..=length).contains(&along)
+            || is_near(along, 0.0, length)
+            || is_near(along, length, length);
+        if !is_on_line || !is_between {
+            return Err(ElementsError::Geometry(
+                "Point does not lie on the opposite side".to_string(),
+            ));
+        }
+
+        Ok(Value::Lineseg(Lineseg {
+            start: vertex,
+            end: point,
+        }))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnEulerLine;
+impl Operation for FnEulerLine {
+    clone_impl!(FnEulerLine);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("euler-line", &[TRIANGLE]).returns(LINE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // the centers coincide for an equilateral triangle, leaving no line
+        match Line::from_points(triangle.circumcenter(), triangle.centroid()) {
+            Ok(line) => Ok(Value::Line(line)),
+            Err(_) => Err(ElementsError::Geometry(
+                "Euler line is undefined for an equilateral triangle".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnNinePointCircle;
+impl Operation for FnNinePointCircle {
+    clone_impl!(FnNinePointCircle);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("nine-point-circle", &[TRIANGLE]).returns(CIRCLE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the nine-point circle
+        Ok(Value::Circle(triangle.nine_point_circle()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnNinePoints;
+impl Operation for FnNinePoints {
+    clone_impl!(FnNinePoints);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("nine-points", &[TRIANGLE])
+                .optional(&[INT])
+                .returns(Param::OneOf(&["Point", "List"])),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+        let points = triangle.nine_points();
+
+        // the points are left unlabeled, as nine labels would crowd the circle and the names of the points
+        // differ between texts, so a single point is returned for an index to be bound and labeled by name
+        match args.get(1) {
+            Some(Value::Int(i)) if (0..9).contains(i) => Ok(Value::Point(points[*i as usize])),
+            Some(Value::Int(_)) => Err(ElementsError::Type(
+                "Index must be between 0 and 8".to_string(),
+            )),
+            Some(_) => Err(ElementsError::Type("Invalid types for index".to_string())),
+            None => Ok(Value::List(points.into_iter().map(Value::Point).collect())),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnExcenter;
+impl Operation for FnExcenter {
+    clone_impl!(FnExcenter);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("excenter", &[TRIANGLE, VERTEX]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle and 1 vertex
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+        let vertex = triangle_vertex(&triangle, &args[1])?;
+
+        // try getting the excenter
+        Ok(Value::Point(triangle.excenter(vertex)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnExcircle;
+impl Operation for FnExcircle {
+    clone_impl!(FnExcircle);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("excircle", &[TRIANGLE, VERTEX]).returns(CIRCLE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle and 1 vertex
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+        let vertex = triangle_vertex(&triangle, &args[1])?;
+
+        // try getting the excircle
+        Ok(Value::Circle(triangle.excircle(vertex)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnMedial;
+impl Operation for FnMedial {
+    clone_impl!(FnMedial);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("medial", &[TRIANGLE]).returns(TRIANGLE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the medial triangle
+        Ok(Value::Triangle(triangle.medial()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnOrthic;
+impl Operation for FnOrthic {
+    clone_impl!(FnOrthic);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("orthic", &[TRIANGLE]).returns(TRIANGLE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the orthic triangle
+        Ok(Value::Triangle(triangle.orthic()?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnContact;
+impl Operation for FnContact {
+    clone_impl!(FnContact);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("contact", &[TRIANGLE]).returns(TRIANGLE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the contact triangle
+        Ok(Value::Triangle(triangle.contact()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnPedal;
+impl Operation for FnPedal {
+    clone_impl!(FnPedal);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("pedal", &[TRIANGLE, POINT]).returns(TRIANGLE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle and 1 point
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+        let point = match &args[1] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+        };
+
+        // try getting the pedal triangle
+        Ok(Value::Triangle(triangle.pedal(point)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnMixtilinear;
+impl Operation for FnMixtilinear {
+    clone_impl!(FnMixtilinear);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("mixtilinear", &[TRIANGLE, VERTEX]).returns(CIRCLE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle and 1 vertex
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+        let vertex = triangle_vertex(&triangle, &args[1])?;
+
+        // try getting the mixtilinear incircle
+        Ok(Value::Circle(triangle.mixtilinear(vertex)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnArea;
+impl Operation for FnArea {
+    clone_impl!(FnArea);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new(
+                "area",
+                &[Param::OneOf(&["Triangle", "Circle", "Ellipse", "Polygon"])],
+            )
+            .returns(FLOAT),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // try getting the area of the shape
+        match &args[0] {
+            Value::Triangle(t) => Ok(Value::Float(t.area())),
+            Value::Circle(c) => Ok(Value::Float(c.area())),
+            Value::Ellipse(e) => Ok(Value::Float(e.area())),
+            Value::Polygon(p) => Ok(Value::Float(p.area())),
+            _ => Err(ElementsError::Type(
+                "Invalid types for area, expected a Triangle, Circle, Ellipse, or Polygon"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnPerimeter;
+impl Operation for FnPerimeter {
+    clone_impl!(FnPerimeter);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new(
+                "perimeter",
+                &[Param::OneOf(&["Triangle", "Circle", "Polygon"])],
+            )
+            .returns(FLOAT),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // try getting the perimeter of the shape
+        match &args[0] {
+            Value::Triangle(t) => Ok(Value::Float(t.perimeter())),
+            Value::Circle(c) => Ok(Value::Float(c.perimeter())),
+            Value::Polygon(p) => Ok(Value::Float(p.perimeter())),
+            _ => Err(ElementsError::Type(
+                "Invalid types for perimeter, expected a Triangle, Circle, or Polygon".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnDegrees;
+impl FnDegrees {
+    /// Case 1: the measure of an angle
+    fn from_angle(args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 angle
+        let angle = match &args[0] {
+            Value::Angle(a) => *a,
+            _ => return Err(ElementsError::Type("Invalid types for angle".to_string())),
+        };
+
+        Ok(Value::Float(angle.degrees()))
+    }
+
+    /// Case 2: the measure of the angle formed by three points, with the vertex in the middle
+    fn from_points(args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 3 points
+        let mut points: Vec<Point> = Vec::new();
+        for arg in args {
+            match arg {
+                Value::Point(p) => points.push(*p),
+                _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+            }
+        }
+
+        let angle = Angle::new(points[0], points[1], points[2]);
+        Ok(Value::Float(angle.degrees()))
+    }
+}
+
+impl Operation for FnDegrees {
+    clone_impl!(FnDegrees);
+    fn overloads(&self) -> Vec<Signature> {
+        vec![
+            Signature::new("degrees", &[ANGLE]).returns(FLOAT),
+            Signature::new("degrees", &[POINT, POINT, POINT]).returns(FLOAT),
+        ]
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match Signature::resolve(&self.overloads(), args)? {
+            0 => Self::from_angle(args),
+            _ => Self::from_points(args),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnIncircle;
+impl Operation for FnIncircle {
+    clone_impl!(FnIncircle);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("incircle", &[TRIANGLE]).returns(CIRCLE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the incircle
+        Ok(Value::Circle(triangle.incircle()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnCircumcircle;
+impl Operation for FnCircumcircle {
+    clone_impl!(FnCircumcircle);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("circumcircle", &[TRIANGLE]).returns(CIRCLE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 triangle
+        let triangle = match &args[0] {
+            Value::Triangle(t) => *t,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for triangle".to_string(),
+                ))
+            }
+        };
+
+        // try getting the circumcircle
+        Ok(Value::Circle(triangle.circumcircle()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnPower;
+impl Operation for FnPower {
+    clone_impl!(FnPower);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("power", &[POINT, CIRCLE]).returns(FLOAT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a point and a circle
+        let point = match &args[0] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+        };
+        let circle = match &args[1] {
+            Value::Circle(c) => *c,
+            _ => return Err(ElementsError::Type("Invalid types for circle".to_string())),
+        };
+
+        // return the power of the point with respect to the circle
+        Ok(Value::Float(
+            distance(point, circle.center).powi(2) - circle.radius.powi(2),
+        ))
+    }
+}
+
+/*
+Basic geometric shapes
+*/
+
+#[derive(Clone)]
+pub struct FnCircle;
+impl FnCircle {
+    /// Case 1: create a circle from a point and a radius
+    fn from_point_radius(args: &[Value]) -> Result<Value, ElementsError> {
+        // check for point and radius
+        let point = match &args[0] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+        };
+        let radius = match &args[1] {
+            Value::Int(r) => *r as f64,
+            Value::Float(r) => *r,
+            _ => return Err(ElementsError::Type("Invalid types for radius".to_string())),
+        };
+
+        // try creating the circle
+        match Circle::new(point, radius) {
+            Ok(circle) => Ok(Value::Circle(circle)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Case 2: create a standard circle if no arguments provided
+    fn standard() -> Result<Value, ElementsError> {
+        // try creating the circle
+        match Circle::new(Point { x: 0.0, y: 0.0 }, 5.0) {
+            Ok(circle) => Ok(Value::Circle(circle)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Operation for FnCircle {
+    clone_impl!(FnCircle);
+    fn overloads(&self) -> Vec<Signature> {
+        vec![
+            Signature::new("circle", &[POINT, NUMBER]).returns(CIRCLE),
+            Signature::new("circle", &[]).returns(CIRCLE),
+        ]
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match Signature::resolve(&self.overloads(), args)? {
+            0 => Self::from_point_radius(args),
+            _ => Self::standard(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnEllipse;
+impl Operation for FnEllipse {
+    clone_impl!(FnEllipse);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("ellipse", &[POINT, NUMBER, NUMBER])
+                .optional(&[NUMBER])
+                .returns(Param::OneOf(&["Ellipse"])),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a center point
+        let center = match &args[0] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for center".to_string())),
+        };
+
+        // try forcing the radii and optional rotation into floats
+        let mut floats = Vec::new();
+        for arg in &args[1..] {
+            match to_float(arg) {
+                Some(f) => floats.push(f),
+                None => {
+                    return Err(ElementsError::Type(
+                        "Invalid types for ellipse radius or rotation".to_string(),
+                    ))
+                }
+            }
+        }
+        let rotation = floats.get(2).copied().unwrap_or(0.0);
+
+        // try creating the ellipse
+        Ok(Value::Ellipse(Ellipse::new(
+            center, floats[0], floats[1], rotation,
+        )?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnParabola;
+impl Operation for FnParabola {
+    clone_impl!(FnParabola);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("parabola", &[POINT, LINEAR]).returns(Param::OneOf(&["Parabola"])))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a focus and a directrix
+        let focus = match &args[0] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for focus".to_string())),
+        };
+        let directrix = to_line(&args[1])?;
+
+        // try creating the parabola
+        Ok(Value::Parabola(Parabola::new(focus, directrix)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnHyperbola;
+impl Operation for FnHyperbola {
+    clone_impl!(FnHyperbola);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("hyperbola", &[POINT, POINT, NUMBER])
+                .returns(Param::OneOf(&["Hyperbola"])),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 2 foci and a difference of distances
+        let foci = to_points(&args[..2])?;
+        let difference = match to_float(&args[2]) {
+            Some(d) => d,
+            None => {
+                return Err(ElementsError::Type(
+                    "Invalid types for difference".to_string(),
+                ))
+            }
+        };
+
+        // try creating the hyperbola
+        Ok(Value::Hyperbola(Hyperbola::new(
+            foci[0], foci[1], difference,
+        )?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnBezier;
+impl Operation for FnBezier {
+    clone_impl!(FnBezier);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("bezier", &[POINT, POINT, POINT])
+                .optional(&[POINT])
+                .returns(Param::OneOf(&["Bezier"])),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // try creating the curve
+        Ok(Value::Bezier(Bezier::new(to_points(args)?)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnRawSvg;
+impl Operation for FnRawSvg {
+    clone_impl!(FnRawSvg);
+    fn overloads(&self) -> Vec<Signature> {
+        vec![
+            Signature::new("raw-svg", &[STRING]).returns(Param::OneOf(&["RawSvg"])),
+            Signature::new("raw-svg", &[STRING, POINT, POINT]).returns(Param::OneOf(&["RawSvg"])),
+        ]
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for the fragment and optionally two corners of the area it covers
+        Signature::resolve(&self.overloads(), args)?;
+        let svg = match &args[0] {
+            Value::String(s) => s.trim_matches('"').to_string(),
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for raw-svg fragment".to_string(),
+                ))
+            }
+        };
+        if !svg.trim_start().starts_with('<') {
+            return Err(ElementsError::Type(
+                "Raw-svg requires a fragment of SVG, such as \"<rect ... />\"".to_string(),
+            ));
+        }
+        let extents = match to_points(&args[1..])?[..] {
+            [first, second] => Some((first, second)),
+            _ => None,
+        };
+        Ok(Value::RawSvg(RawSvg::new(svg, extents)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnTriangle;
+impl FnTriangle {
+    /// Case 1: create a triangle from three points
+    fn from_points(args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 3 points
+        let mut points: Vec<Point> = Vec::new();
+        for arg in args {
+            match arg {
+                Value::Point(p) => points.push(*p),
+                _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+            }
+        }
+
+        // try creating the triangle
+        Triangle::new(points[0], points[1], points[2]).map(Value::Triangle)
+    }
+
+    /// Case 2: create a triangle from an angle
+    fn from_angle(args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 angle
+        let angle = match &args[0] {
+            Value::Angle(a) => *a,
+            _ => return Err(ElementsError::Type("Invalid types for angle".to_string())),
+        };
+
+        // extract points for the angle
+        let start = angle.start;
+        let center = angle.center;
+        let end = angle.end;
+
+        // try creating the triangle
+        Triangle::new(start, center, end).map(Value::Triangle)
+    }
+
+    /// Case 3: create a triangle from a circle
+    fn from_circle(args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 circle
+        let circle = match &args[0] {
+            Value::Circle(c) => *c,
+            _ => return Err(ElementsError::Type("Invalid types for circle".to_string())),
+        };
+
+        // the vertices are at least half the radius apart exactly when the arcs between them are at least
+        // this wide, so share out the rest of the circle between the three arcs at two random cuts
+        let min_arc = 2.0 * 0.25f64.asin();
+        let spare = 2.0 * PI - 3.0 * min_arc;
+        let (first, second, third) = sample(
+            MAX_TRIES,
+            || {
+                let (a, b) = (random::random(), random::random());
+                let first = random::random() * 2.0 * PI;
+                let second = first + min_arc + spare * a.min(b);
+                let third = second + min_arc + spare * (a.max(b) - a.min(b));
+                (
+                    circle.point_at(first),
+                    circle.point_at(second),
+                    circle.point_at(third),
+                )
+            },
+            &[Constraint::new(
+                "minimum vertex distance",
+                |(first, second, third): &(Point, Point, Point)| {
+                    distance(*first, *second) >= circle.radius / 2.0
+                        && distance(*second, *third) >= circle.radius / 2.0
+                        && distance(*third, *first) >= circle.radius / 2.0
+                },
+            )],
+        )?;
+
+        // try creating the triangle
+        Triangle::new(first, second, third).map(Value::Triangle)
+    }
+}
+
+impl Operation for FnTriangle {
+    clone_impl!(FnTriangle);
+    fn call_named(&self, args: &[Value], names: &[Option<String>]) -> Result<Value, ElementsError> {
+        // check for a trailing :label keyword
+        let (args, is_label) = match args {
+            [rest @ .., Value::String(k), value] if k == ":label" => match value {
+                Value::Bool(b) => (rest, *b),
+                Value::String(s) if s == "t" || s == "true" => (rest, true),
+                Value::String(s) if s == "nil" || s == "false" => (rest, false),
+                _ => return Err(ElementsError::Type("Invalid types for :label".to_string())),
+            },
+            _ => (args, false),
+        };
+        let triangle = self.call(args)?;
+        if !is_label {
+            return Ok(triangle);
+        }
+
+        // label the vertices with the names of the points they were built from
+        let vertex_labels = match names {
+            [Some(a), Some(b), Some(c), ..] if args.len() == 3 => {
+                vec![a.clone(), b.clone(), c.clone()]
+            }
+            _ => {
+                return Err(ElementsError::Type(
+                    ":label requires the vertices to be point variables".to_string(),
+                ))
+            }
+        };
+        Ok(triangle.with_style(&Style {
+            vertex_labels: Some(vertex_labels),
+            ..Style::default()
+        }))
+    }
+
+    fn overloads(&self) -> Vec<Signature> {
+        vec![
+            Signature::new("triangle", &[POINT, POINT, POINT]).returns(TRIANGLE),
+            Signature::new("triangle", &[ANGLE]).returns(TRIANGLE),
+            Signature::new("triangle", &[CIRCLE]).returns(TRIANGLE),
+        ]
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match Signature::resolve(&self.overloads(), args)? {
+            0 => Self::from_points(args),
+            1 => Self::from_angle(args),
+            _ => Self::from_circle(args),
+        }
+    }
+}
+
+/*
+Transformations
+*/
+
+#[derive(Clone)]
+pub struct FnRotate;
+impl Operation for FnRotate {
+    clone_impl!(FnRotate);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("rotate", &[ANY, POINT, NUMBER]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a center point and a degree value
+        let center = match &args[1] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for center".to_string())),
+        };
+        let degree: f64 = match &args[2] {
+            Value::Int(i) => *i as f64,
+            Value::Float(f) => *f,
+            _ => return Err(ElementsError::Type("Invalid types for degree".to_string())),
+        };
+
+        // try rotating the value
+        args[0].transform(&Transform::rotation(center, degree))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnTranslate;
+impl FnTranslate {
+    /// Case 1: translate a value by an x and y offset
+    fn from_offsets(args: &[Value]) -> Result<Value, ElementsError> {
+        // try forcing the offsets into floats
+        let mut offsets = Vec::new();
+        for arg in &args[1..] {
+            match arg {
+                Value::Int(i) => offsets.push(*i as f64),
+                Value::Float(f) => offsets.push(*f),
+                _ => return Err(ElementsError::Type("Invalid types for offset".to_string())),
+            }
+        }
+
+        // try translating the value
+        args[0].transform(&Transform::translation(offsets[0], offsets[1]))
+    }
+
+    /// Case 2: translate a value by the vector from the start to the end of a line segment
+    fn from_lineseg(args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a line segment
+        let vector = match &args[1] {
+            Value::Lineseg(l) => *l,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for line segment".to_string(),
+                ))
+            }
+        };
+
+        // try translating the value
+        args[0].transform(&Transform::translation(
+            vector.end.x - vector.start.x,
+            vector.end.y - vector.start.y,
+        ))
+    }
+}
+
+impl Operation for FnTranslate {
+    clone_impl!(FnTranslate);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn overloads(&self) -> Vec<Signature> {
+        vec![
+            Signature::new("translate", &[ANY, NUMBER, NUMBER]),
+            Signature::new("translate", &[ANY, LINESEG]),
+        ]
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match Signature::resolve(&self.overloads(), args)? {
+            0 => Self::from_offsets(args),
+            _ => Self::from_lineseg(args),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnInvert;
+
+impl FnInvert {
+    /// Case 1: A point, which must not be the center
+    fn from_point(p: Point, center: Point, radius: f64) -> Result<Value, ElementsError> {
+        match invert_point(p, center, radius) {
+            Some(point) => Ok(Value::Point(point)),
+            None => Err(ElementsError::Geometry(
+                "Cannot invert the center of inversion".to_string(),
+            )),
+        }
+    }
+
+    /// Case 2: A line, which maps to itself through the center and to a circle otherwise
+    fn from_line(line: Line, center: Point, radius: f64) -> Result<Value, ElementsError> {
+        if line.signed_distance(&center).abs() < tolerance() {
+            return Ok(Value::Line(line));
+        }
+
+        // the foot of the perpendicular maps to the point opposite the center
+        let far = invert_point(line.project(&center), center, radius).ok_or(
+            ElementsError::Geometry("Cannot invert the center of inversion".to_string()),
+        )?;
+        Ok(Value::Circle(Circle::new(
+            midpoint(center, far),
+            distance(center, far) / 2.0,
+        )?))
+    }
+
+    /// Case 3: A line segment, which maps to a segment through the center and to an arc otherwise
+    fn from_lineseg(seg: Lineseg, center: Point, radius: f64) -> Result<Value, ElementsError> {
+        if distance(center, closest_point(center, seg.start, seg.end, 0.0, 1.0)) < tolerance() {
+            return Err(ElementsError::Geometry(
+                "Segment passes through the center of inversion".to_string(),
+            ));
+        }
+        let invert = |p: Point| {
+            invert_point(p, center, radius).ok_or(ElementsError::Geometry(
+                "Cannot invert the center of inversion".to_string(),
+            ))
+        };
+        let start = invert(seg.start)?;
+        let end = invert(seg.end)?;
+
+        if seg.line()?.signed_distance(&center).abs() < tolerance() {
+            return Ok(Value::Lineseg(Lineseg { start, end }));
+        }
+        let middle = invert(midpoint(seg.start, seg.end))?;
+        Ok(Value::Arc(Arc::through(start, middle, end)?))
+    }
+
+    /// Case 4: A ray, whose far end maps to the center
+    fn from_ray(ray: Ray, center: Point, radius: f64) -> Result<Value, ElementsError> {
+        let closest = closest_point(center, ray.origin, ray.through, 0.0, f64::INFINITY);
+        if distance(center, closest) < tolerance() {
+            return Err(ElementsError::Geometry(
+                "Ray passes through the center of inversion".to_string(),
+            ));
+        }
+        let invert = |p: Point| {
+            invert_point(p, center, radius).ok_or(ElementsError::Geometry(
+                "Cannot invert the center of inversion".to_string(),
+            ))
+        };
+        let start = invert(ray.origin)?;
+
+        if Line::from_points(ray.origin, ray.through)?
+            .signed_distance(&center)
+            .abs()
+            < tolerance()
+        {
+            return Ok(Value::Lineseg(Lineseg { start, end: center }));
+        }
+        Ok(Value::Arc(Arc::through(
+            start,
+            invert(ray.through)?,
+            center,
+        )?))
+    }
+
+    /// Case 5: A circle, which maps to a line through the center and to a circle otherwise
+    fn from_circle(circle: Circle, center: Point, radius: f64) -> Result<Value, ElementsError> {
+        let dx = circle.center.x - center.x;
+        let dy = circle.center.y - center.y;
+        let d = dx.hypot(dy);
+
+        if (d - circle.radius).abs() < tolerance() {
+            // the point opposite the center maps to the foot of the image line
+            let far = Point {
+                x: center.x + 2.0 * dx,
+                y: center.y + 2.0 * dy,
+            };
+            let foot = invert_point(far, center, radius).ok_or(ElementsError::Geometry(
+                "Cannot invert the center of inversion".to_string(),
+            ))?;
+            let along = Point {
+                x: foot.x - dy,
+                y: foot.y + dx,
+            };
+            return Ok(Value::Line(Line::from_points(foot, along)?));
+        }
+
+        // scale the center about the center of inversion
+        let s = radius * radius / (d * d - circle.radius * circle.radius);
+        Ok(Value::Circle(Circle::new(
+            Point {
+                x: center.x + s * dx,
+                y: center.y + s * dy,
+            },
+            s.abs() * circle.radius,
+        )?))
+    }
+}
+
+impl Operation for FnInvert {
+    clone_impl!(FnInvert);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("invert", &[INVERTIBLE, POINT, NUMBER])
+                .returns(Param::OneOf(&["Point", "Line", "Lineseg", "Arc", "Circle"])),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a center and a positive radius
+        let center = match &args[1] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for center".to_string())),
+        };
+        let radius = match to_float(&args[2]) {
+            Some(r) if r > 0.0 => r,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Radius must be a positive number".to_string(),
+                ))
+            }
+        };
+
+        match &args[0] {
+            Value::Point(p) => Self::from_point(*p, center, radius),
+            Value::Line(l) => Self::from_line(*l, center, radius),
+            Value::Lineseg(l) => Self::from_lineseg(*l, center, radius),
+            Value::Ray(r) => Self::from_ray(*r, center, radius),
+            Value::Circle(c) => Self::from_circle(*c, center, radius),
+            _ => Err(ElementsError::Type(
+                "Invalid types for inversion".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnPolar;
+impl Operation for FnPolar {
+    clone_impl!(FnPolar);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("polar", &[POINT, CIRCLE]).returns(LINE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 point and 1 circle
+        let p = match &args[0] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+        };
+        let circle = match &args[1] {
+            Value::Circle(c) => *c,
+            _ => return Err(ElementsError::Type("Invalid types for circle".to_string())),
+        };
+
+        // the polar is perpendicular to the center's direction through the inverse point
+        let foot = match invert_point(p, circle.center, circle.radius) {
+            Some(foot) => foot,
+            None => {
+                return Err(ElementsError::Geometry(
+                    "The center of the circle has no polar".to_string(),
+                ))
+            }
+        };
+        let along = Point {
+            x: foot.x - (p.y - circle.center.y),
+            y: foot.y + (p.x - circle.center.x),
+        };
+        Ok(Value::Line(Line::from_points(foot, along)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnPole;
+impl Operation for FnPole {
+    clone_impl!(FnPole);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("pole", &[LINEAR, CIRCLE]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 line and 1 circle
+        let line = to_line(&args[0])?;
+        let circle = match &args[1] {
+            Value::Circle(c) => *c,
+            _ => return Err(ElementsError::Type("Invalid types for circle".to_string())),
+        };
+
+        // the pole is the inverse of the foot of the perpendicular from the center
+        match invert_point(line.project(&circle.center), circle.center, circle.radius) {
+            Some(pole) if line.signed_distance(&circle.center).abs() >= tolerance() => {
+                Ok(Value::Point(pole))
+            }
+            _ => Err(ElementsError::Geometry(
+                "A line through the center of the circle has no pole".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnSpiralSimilarity;
+impl FnSpiralSimilarity {
+    /// Case 1: rotate and dilate a value about a center
+    fn from_center(args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a center point, a degree value, and a positive ratio
+        let center = match &args[1] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for center".to_string())),
+        };
+        let degree = match to_float(&args[2]) {
+            Some(d) => d,
+            None => return Err(ElementsError::Type("Invalid types for degree".to_string())),
+        };
+        let ratio = match to_float(&args[3]) {
+            Some(r) if r > 0.0 => r,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Ratio must be a positive number".to_string(),
+                ))
+            }
+        };
+
+        // try transforming the value
+        args[0].transform(&Transform::spiral_similarity(center, degree, ratio))
+    }
+
+    /// Case 2: apply the spiral similarity taking one line segment to another
+    fn from_linesegs(args: &[Value]) -> Result<Value, ElementsError> {
+        // try transforming the value
+        args[0].transform(&segment_mapping(&args[1], &args[2])?)
+    }
+}
+
+impl Operation for FnSpiralSimilarity {
+    clone_impl!(FnSpiralSimilarity);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn overloads(&self) -> Vec<Signature> {
+        vec![
+            Signature::new("spiral-similarity", &[ANY, POINT, NUMBER, NUMBER]),
+            Signature::new("spiral-similarity", &[ANY, LINESEG, LINESEG]),
+        ]
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match Signature::resolve(&self.overloads(), args)? {
+            0 => Self::from_center(args),
+            _ => Self::from_linesegs(args),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnSpiralCenter;
+impl Operation for FnSpiralCenter {
+    clone_impl!(FnSpiralCenter);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("spiral-center", &[LINESEG, LINESEG]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // a translation has no center
+        match segment_mapping(&args[0], &args[1])?.fixed_point() {
+            Some(center) => Ok(Value::Point(center)),
+            None => Err(ElementsError::Geometry(
+                "Line segments are related by a translation".to_string(),
+            )),
+        }
+    }
+}
+
+/*
+Shaded regions
+*/
+
+#[derive(Clone)]
+pub struct FnRegionWhere;
+impl Operation for FnRegionWhere {
+    clone_impl!(FnRegionWhere);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("region-where", &[LAMBDA, ANY, INT]).returns(REGION))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a predicate, a value to take the bounding box of, and a resolution
+        let predicate = match &args[0] {
+            Value::Lambda(l) => l,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for predicate".to_string(),
+                ))
+            }
+        };
+        let (min, max) = Svg::new(args[1].to_svg()).get_bounds();
+        if !(min.x < max.x && min.y < max.y) {
+            return Err(ElementsError::Geometry(
+                "Bounding box of region is empty".to_string(),
+            ));
+        }
+        let resolution = match &args[2] {
+            Value::Int(i) if *i > 0 && *i <= 1000 => *i as usize,
+            Value::Int(_) => {
+                return Err(ElementsError::Type(
+                    "Resolution must be between 1 and 1000".to_string(),
+                ))
+            }
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for resolution".to_string(),
+                ))
+            }
+        };
+
+        // sample the predicate over the grid
+        let mut inside: Vec<Vec<bool>> = Vec::new();
+        for j in 0..=resolution {
+            let mut row: Vec<bool> = Vec::new();
+            for i in 0..=resolution {
+                let point = Point {
+                    x: min.x + i as f64 * (max.x - min.x) / resolution as f64,
+                    y: min.y + j as f64 * (max.y - min.y) / resolution as f64,
+                };
+                match predicate.call(&[Value::Point(point)])? {
+                    Value::Bool(b) => row.push(b),
+                    _ => {
+                        return Err(ElementsError::Type(
+                            "Predicate must return a boolean".to_string(),
+                        ))
+                    }
+                }
+            }
+            inside.push(row);
+        }
+
+        // trace the boundary of the region
+        Ok(Value::Region(Region {
+            contours: marching_squares(&inside, min, max),
+        }))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnLocus;
+impl Operation for FnLocus {
+    clone_impl!(FnLocus);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("locus", &[LAMBDA, NUMBER, NUMBER, INT])
+                .returns(Param::OneOf(&["Locus"])),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a point expression, a parameter range, and a number of samples
+        let expression = match &args[0] {
+            Value::Lambda(l) => l,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for point expression".to_string(),
+                ))
+            }
+        };
+        let (t_min, t_max) = match (to_float(&args[1]), to_float(&args[2])) {
+            (Some(a), Some(b)) => (a, b),
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for parameter range".to_string(),
+                ))
+            }
+        };
+        let samples = match &args[3] {
+            Value::Int(i) if *i >= 2 && *i <= 10000 => *i as usize,
+            Value::Int(_) => {
+                return Err(ElementsError::Type(
+                    "Samples must be between 2 and 10000".to_string(),
+                ))
+            }
+            _ => return Err(ElementsError::Type("Invalid types for samples".to_string())),
+        };
+
+        // sample the expression, starting a new piece wherever it cannot be evaluated
+        let mut runs: Vec<Vec<Point>> = vec![Vec::new()];
+        let mut first_error: Option<ElementsError> = None;
+        for i in 0..samples {
+            let t = t_min + (t_max - t_min) * i as f64 / (samples - 1) as f64;
+            match expression.call(&[Value::Float(t)]) {
+                Ok(Value::Point(p)) => runs.last_mut().unwrap().push(p),
+                Ok(_) => {
+                    return Err(ElementsError::Type(
+                        "Point expression must return a point".to_string(),
+                    ))
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                    if !runs.last().unwrap().is_empty() {
+                        runs.push(Vec::new());
+                    }
+                }
+            }
+        }
+        runs.retain(|run| !run.is_empty());
+
+        // report the failure if no sample could be evaluated at all
+        match first_error {
+            Some(e) if runs.is_empty() => Err(e),
+            _ => Ok(Value::Locus(Locus { runs })),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FnIntersection;
+impl Operation for FnIntersection {
+    clone_impl!(FnIntersection);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("intersection", &[ANY, ANY])
+                .rest(ANY)
+                .returns(REGION),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // try turning every argument into a region and overlapping them in turn
+        let mut region = Region::from_value(&args[0])?;
+        for arg in &args[1..] {
+            region = region.intersection(&Region::from_value(arg)?);
+        }
+        Ok(Value::Region(region))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnUnion;
+impl Operation for FnUnion {
+    clone_impl!(FnUnion);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("union", &[ANY, ANY])
+                .rest(ANY)
+                .returns(REGION),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // try turning every argument into a region and combining them in turn
+        let mut region = Region::from_value(&args[0])?;
+        for arg in &args[1..] {
+            region = region.union(&Region::from_value(arg)?);
+        }
+        Ok(Value::Region(region))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnSegment;
+impl Operation for FnSegment {
+    clone_impl!(FnSegment);
+    fn overloads(&self) -> Vec<Signature> {
+        vec![
+            Signature::new("segment", &[ARC]).returns(REGION),
+            Signature::new("segment", &[CIRCLE, POINT, POINT]).returns(REGION),
+        ]
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for an arc, or a circle and the two ends of the arc counterclockwise around it
+        Signature::resolve(&self.overloads(), args)?;
+        let arc = match args {
+            [Value::Arc(a)] => *a,
+            [Value::Circle(c), Value::Point(p), Value::Point(q)] => {
+                let angle = |p: &Point| (p.y - c.center.y).atan2(p.x - c.center.x);
+                Arc {
+                    center: c.center,
+                    radius: c.radius,
+                    start: angle(p),
+                    sweep: (angle(q) - angle(p)).rem_euclid(2.0 * PI),
+                }
+            }
+            _ => return Err(ElementsError::Type("Invalid types for segment".to_string())),
+        };
+
+        Ok(Value::Region(Region::segment(&arc)))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnShade;
+impl Operation for FnShade {
+    clone_impl!(FnShade);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("shade", &[ANY])
+                .optional(&[STRING, NUMBER])
+                .returns(REGION),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // try turning the first argument into a region
+        let region = Value::Region(Region::from_value(&args[0])?);
+        if args.len() == 1 {
+            return Ok(region);
+        }
+
+        // fill it with the given color and optional opacity
+        let mut fill_args = vec![region];
+        fill_args.extend_from_slice(&args[1..]);
+        FnFill.call(&fill_args)
+    }
+}
+
+/*
+Styling
+*/
+
+#[derive(Clone)]
+pub struct FnOpacity;
+impl Operation for FnOpacity {
+    clone_impl!(FnOpacity);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("opacity", &[ANY, NUMBER]).optional(&[NUMBER]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // try forcing the opacities into floats between 0 and 1
+        let mut opacities = Vec::new();
+        for arg in &args[1..] {
+            let opacity = match arg {
+                Value::Int(i) => *i as f64,
+                Value::Float(f) => *f,
+                _ => return Err(ElementsError::Type("Invalid types for opacity".to_string())),
+            };
+            if !(0.0..=1.0).contains(&opacity) {
+                return Err(ElementsError::Type(
+                    "Opacity must be between 0 and 1".to_string(),
+                ));
+            }
+            opacities.push(opacity);
+        }
+
+        // a single opacity applies to both the fill and the stroke
+        let fill_opacity = opacities[0];
+        let stroke_opacity = *opacities.last().unwrap();
+        Ok(args[0].with_style(&Style {
+            fill_opacity: Some(fill_opacity),
+            stroke_opacity: Some(stroke_opacity),
+            ..Style::default()
+        }))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnBlend;
+impl Operation for FnBlend {
+    clone_impl!(FnBlend);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("blend", &[ANY, STRING]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a blend mode name
+        let blend = match &args[1] {
+            Value::String(s) => Blend::from_name(s)?,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for blend mode".to_string(),
+                ))
+            }
+        };
+
+        Ok(args[0].with_style(&Style {
+            blend: Some(blend),
+            ..Style::default()
+        }))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnColor;
+impl Operation for FnColor {
+    clone_impl!(FnColor);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("color", &[ANY, STRING]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a color name or hex code
+        let color = match &args[1] {
+            Value::String(s) => parse_color(s)?,
+            _ => return Err(ElementsError::Type("Invalid types for color".to_string())),
+        };
+
+        Ok(args[0].with_style(&Style {
+            stroke: Some(color),
+            ..Style::default()
+        }))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnStyle;
+impl FnStyle {
+    /// Build a style from :keyword value pairs
+    fn from_keywords(keywords: &HashMap<String, Value>) -> Result<Style, ElementsError> {
+        let mut style = Style::default();
+        for (name, value) in keywords {
+            match name.as_str() {
+                "color" => {
+                    style.stroke = match value {
+                        Value::String(s) => Some(parse_color(s)?),
+                        _ => {
+                            return Err(ElementsError::Type("Invalid types for :color".to_string()))
+                        }
+                    }
+                }
+                "fill" => {
+                    style.fill = match value {
+                        Value::String(s) => Some(parse_color(s)?),
+                        _ => {
+                            return Err(ElementsError::Type("Invalid types for :fill".to_string()))
+                        }
+                    }
+                }
+                "layer" => {
+                    style.layer = match value {
+                        Value::Int(i) => Some(i32::try_from(*i).map_err(|_| {
+                            ElementsError::Type("Layer is out of range".to_string())
+                        })?),
+                        _ => {
+                            return Err(ElementsError::Type("Invalid types for :layer".to_string()))
+                        }
+                    }
+                }
+                "width" => {
+                    let width = to_float(value)
+                        .ok_or(ElementsError::Type("Invalid types for :width".to_string()))?;
+                    if width <= 0.0 {
+                        return Err(ElementsError::Type(
+                            "Stroke width must be positive".to_string(),
+                        ));
+                    }
+                    style.stroke_width = Some(width);
+                }
+                "dash" => {
+                    // a single length gives dashes and gaps of equal length, and a string holds the
+                    // lengths as SVG writes them, apart by spaces or commas
+                    let values = match value {
+                        Value::List(values) => values.clone(),
+                        Value::String(s) => s
+                            .trim_matches('"')
+                            .split(|c: char| c.is_whitespace() || c == ',')
+                            .filter(|length| !length.is_empty())
+                            .map(|length| match length.parse::<f64>() {
+                                Ok(length) => Value::Float(length),
+                                Err(_) => Value::String(length.to_string()),
+                            })
+                            .collect(),
+                        value => vec![value.clone()],
+                    };
+                    let mut lengths = Vec::new();
+                    for v in &values {
+                        match to_float(v) {
+                            Some(length) if length > 0.0 => lengths.push(length),
+                            _ => {
+                                return Err(ElementsError::Type(
+                                    "Dash lengths must be positive numbers".to_string(),
+                                ))
+                            }
+                        }
+                    }
+                    if lengths.is_empty() {
+                        return Err(ElementsError::Arity(
+                            "Dash requires at least one length".to_string(),
+                        ));
+                    }
+                    style.dash = Some(lengths);
+                }
+                _ => {
+                    return Err(ElementsError::Type(format!(
+                        "Unknown style keyword :{}",
+                        name
+                    )))
+                }
+            }
+        }
+        Ok(style)
+    }
+}
+
+impl Operation for FnStyle {
+    clone_impl!(FnStyle);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // split the value to style from any named styles and the style keywords
+        let (positional, keywords) = keyword_args(args)?;
+        if positional.is_empty() {
+            return Err(ElementsError::Arity(
+                "Style requires at least 1 positional argument".to_string(),
+            ));
+        }
+
+        // named styles apply in order, with the keywords applied last
+        let mut style = Style::default();
+        for named in &positional[1..] {
+            match named {
+                Value::Style(s) => style.merge(s),
+                Value::String(s) => {
+                    return Err(ElementsError::Type(format!("Undefined style: {}", s)))
+                }
+                _ => return Err(ElementsError::Type("Invalid types for style".to_string())),
+            }
+        }
+        style.merge(&Self::from_keywords(&keywords)?);
+
+        Ok(positional[0].with_style(&style))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnDefineStyle;
+impl Operation for FnDefineStyle {
+    clone_impl!(FnDefineStyle);
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // split the style name from the style keywords
+        let (positional, keywords) = keyword_args(args)?;
+        if positional.len() != 1 {
+            return Err(ElementsError::Arity(
+                "define-style requires exactly 1 positional argument".to_string(),
+            ));
+        }
+        let name = match &positional[0] {
+            Value::String(s) => s,
+            _ => return Err(ElementsError::Type("Invalid style name".to_string())),
+        };
+        if !is_valid_variable(name) {
+            return Err(ElementsError::Type("Invalid style name".to_string()));
+        }
+
+        Ok(Value::Style(FnStyle::from_keywords(&keywords)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnFill;
+impl Operation for FnFill {
+    clone_impl!(FnFill);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("fill", &[ANY, STRING]).optional(&[NUMBER]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for a color name or hex code
+        let fill = match &args[1] {
+            Value::String(s) => parse_color(s)?,
+            _ => return Err(ElementsError::Type("Invalid types for fill".to_string())),
+        };
+
+        // try forcing the optional opacity into a float between 0 and 1
+        let fill_opacity = match args.get(2) {
+            None => None,
+            Some(v) => match to_float(v) {
+                Some(opacity) if (0.0..=1.0).contains(&opacity) => Some(opacity),
+                Some(_) => {
+                    return Err(ElementsError::Type(
+                        "Opacity must be between 0 and 1".to_string(),
+                    ))
+                }
+                None => return Err(ElementsError::Type("Invalid types for opacity".to_string())),
+            },
         };
 
-        // try getting the orthocenter
-        return Ok(Value::Point(triangle.orthocenter()));
+        Ok(args[0].with_style(&Style {
+            fill: Some(fill),
+            fill_opacity,
+            ..Style::default()
+        }))
     }
 }
 
 #[derive(Clone)]
-pub struct FnCentroid;
-impl Operation for FnCentroid {
-    clone_impl!(FnCentroid);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 1 argument
-        if args.len() != 1 {
-            return Err("Centroid requires exactly 1 argument".to_string());
-        }
+pub struct FnHatch;
+impl Operation for FnHatch {
+    clone_impl!(FnHatch);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("hatch", &[ANY]).optional(&[STRING, NUMBER, NUMBER]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for an optional color name or hex code
+        let color = match args.get(1) {
+            None => "black".to_string(),
+            Some(Value::String(s)) => parse_color(s)?,
+            Some(_) => {
+                return Err(ElementsError::Type(
+                    "Invalid types for hatch color".to_string(),
+                ))
+            }
+        };
 
-        // check for 1 triangle
-        let triangle = match &args[0] {
-            Value::Triangle(t) => t.clone(),
-            _ => return Err("Invalid types for triangle".to_string()),
+        // try forcing the optional angle in degrees and spacing into floats
+        let angle = match args.get(2) {
+            None => 45.0,
+            Some(v) => to_float(v).ok_or(ElementsError::Type(
+                "Invalid types for hatch angle".to_string(),
+            ))?,
+        };
+        let spacing = match args.get(3) {
+            None => 0.25,
+            Some(v) => match to_float(v) {
+                Some(spacing) if spacing > 0.0 => spacing,
+                Some(_) => {
+                    return Err(ElementsError::Type(
+                        "Hatch spacing must be positive".to_string(),
+                    ))
+                }
+                None => {
+                    return Err(ElementsError::Type(
+                        "Invalid types for hatch spacing".to_string(),
+                    ))
+                }
+            },
         };
 
-        // try getting the centroid
-        return Ok(Value::Point(triangle.centroid()));
+        Ok(args[0].with_style(&Style {
+            hatch: Some(Hatch {
+                color,
+                angle,
+                spacing,
+            }),
+            ..Style::default()
+        }))
     }
 }
 
 #[derive(Clone)]
-pub struct FnPoint;
-impl Operation for FnPoint {
-    clone_impl!(FnPoint);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 2 arguments
-        if args.len() != 2 {
-            return Err("Point requires exactly 2 arguments".to_string());
+pub struct FnLabel;
+impl Operation for FnLabel {
+    clone_impl!(FnLabel);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("label", &[ANY, Param::OneOf(&["String", "Int", "Float"])])
+                .optional(&[POINT]),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check that the value has somewhere to put a label
+        if args[0].label_anchor().is_none() {
+            return Err(ElementsError::Type(format!(
+                "{} cannot be labeled",
+                args[0].type_name()
+            )));
         }
 
-        // try forcing the arguments into floats
-        let mut floats = Vec::new();
-        for arg in args {
-            match arg {
-                Value::Int(i) => floats.push(*i as f64),
-                Value::Float(f) => floats.push(*f),
-                _ => return Err("Invalid types for point".to_string()),
+        // allow the text to be quoted, or to be a number such as a length
+        let text = match &args[1] {
+            Value::String(s) => s.trim_matches('"').to_string(),
+            Value::Int(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for label text".to_string(),
+                ))
             }
+        };
+        if text.is_empty() {
+            return Err(ElementsError::Type(
+                "Label text cannot be empty".to_string(),
+            ));
         }
 
-        // return the point
-        Ok(Value::Point(Point {
-            x: floats[0],
-            y: floats[1],
+        // an optional offset from the anchor fixes the label in place
+        let label_offset = match args.get(2) {
+            None => None,
+            Some(Value::Point(p)) => Some(*p),
+            Some(_) => {
+                return Err(ElementsError::Type(
+                    "Invalid types for label offset".to_string(),
+                ))
+            }
+        };
+
+        Ok(args[0].with_style(&Style {
+            label: Some(text),
+            label_offset,
+            ..Style::default()
         }))
     }
 }
 
-/*
-Functions that return properties
-*/
+/// Helper function to read the :precision and :unit keywords of an annotation
+fn annotation_format(
+    keywords: &HashMap<String, Value>,
+    default_unit: &str,
+) -> Result<(usize, String), ElementsError> {
+    let mut precision = 2;
+    let mut unit = default_unit.to_string();
+    for (name, value) in keywords {
+        match name.as_str() {
+            "precision" => match value {
+                Value::Int(i) if (0..=10).contains(i) => precision = *i as usize,
+                _ => {
+                    return Err(ElementsError::Type(
+                        "Annotation precision must be an integer from 0 to 10".to_string(),
+                    ))
+                }
+            },
+            "unit" => match value {
+                Value::String(s) => unit = s.trim_matches('"').to_string(),
+                _ => {
+                    return Err(ElementsError::Type(
+                        "Annotation unit must be a string".to_string(),
+                    ))
+                }
+            },
+            _ => {
+                return Err(ElementsError::Type(format!(
+                    "Unknown annotation keyword :{}",
+                    name
+                )))
+            }
+        }
+    }
+    Ok((precision, unit))
+}
 
 #[derive(Clone)]
-pub struct FnIntersect;
-
-impl FnIntersect {
-    /// Case 1: Two line segments
-    fn from_linesegs(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 2 arguments
-        if args.len() != 2 {
-            return Err("Intersect requires exactly 2 arguments".to_string());
+pub struct FnAnnotateLength;
+impl Operation for FnAnnotateLength {
+    clone_impl!(FnAnnotateLength);
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 line segment, followed by the format keywords
+        let (positional, keywords) = keyword_args(args)?;
+        if positional.len() != 1 {
+            return Err(ElementsError::Arity(
+                "annotate-length requires exactly 1 positional argument".to_string(),
+            ));
         }
-
-        // check for 2 line segments
-        let lineseg1 = match &args[0] {
-            Value::Lineseg(l) => l.clone(),
-            _ => return Err("Invalid types for line segment".to_string()),
-        };
-        let lineseg2 = match &args[1] {
-            Value::Lineseg(l) => l.clone(),
-            _ => return Err("Invalid types for line segment".to_string()),
+        let lineseg = match &positional[0] {
+            Value::Lineseg(l) => *l,
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for line segment".to_string(),
+                ))
+            }
         };
 
-        // check if line segments are parallel
-        if lineseg1.slope() == lineseg2.slope() {
-            return Err("Line segments are parallel".to_string());
+        // label the segment with its length, followed by the unit if one is given
+        let (precision, unit) = annotation_format(&keywords, "")?;
+        let mut text = Rounded(distance(lineseg.start, lineseg.end), precision).to_string();
+        if !unit.is_empty() {
+            text = format!("{} {}", text, unit);
         }
+        Ok(Value::Lineseg(lineseg).with_style(&Style {
+            label: Some(text),
+            ..Style::default()
+        }))
+    }
+}
 
-        // handle vertical line segments
-        if lineseg1.slope().abs() == f64::INFINITY {
-            let x = lineseg1.start.x;
-            let y = lineseg2.slope() * x + lineseg2.y_intercept();
-            return Ok(Value::Point(Point { x, y }));
-        } else if lineseg2.slope().abs() == f64::INFINITY {
-            let x = lineseg2.start.x;
-            let y = lineseg1.slope() * x + lineseg1.y_intercept();
-            return Ok(Value::Point(Point { x, y }));
+#[derive(Clone)]
+pub struct FnAnnotateAngle;
+impl Operation for FnAnnotateAngle {
+    clone_impl!(FnAnnotateAngle);
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 3 points, followed by the format keywords
+        let (positional, keywords) = keyword_args(args)?;
+        if positional.len() != 3 {
+            return Err(ElementsError::Arity(
+                "annotate-angle requires exactly 3 positional arguments".to_string(),
+            ));
+        }
+        let mut points: Vec<Point> = Vec::new();
+        for arg in &positional {
+            match arg {
+                Value::Point(p) => points.push(*p),
+                _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+            }
         }
+        let angle = Angle::new(points[0], points[1], points[2]);
+
+        // label the angle with its measure in degrees, or in radians if asked
+        let (precision, unit) = annotation_format(&keywords, "deg")?;
+        let text = match unit.as_str() {
+            "deg" => format!("{}\u{b0}", Rounded(angle.degrees(), precision)),
+            "rad" => format!("{} rad", Rounded(angle.degrees().to_radians(), precision)),
+            _ => {
+                return Err(ElementsError::Type(
+                    "Angle unit must be deg or rad".to_string(),
+                ))
+            }
+        };
+        Ok(Value::Angle(angle).with_style(&Style {
+            label: Some(text),
+            ..Style::default()
+        }))
+    }
+}
 
-        // otherwise, find the intersection point
-        let x = (lineseg2.y_intercept() - lineseg1.y_intercept())
-            / (lineseg1.slope() - lineseg2.slope());
-        let y = lineseg1.slope() * x + lineseg1.y_intercept();
+#[derive(Clone)]
+pub struct FnLayer;
+impl Operation for FnLayer {
+    clone_impl!(FnLayer);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("layer", &[ANY, INT]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for an integer layer
+        let layer = match &args[1] {
+            Value::Int(i) => i32::try_from(*i)
+                .map_err(|_| ElementsError::Type("Layer is out of range".to_string()))?,
+            _ => return Err(ElementsError::Type("Invalid types for layer".to_string())),
+        };
 
-        Ok(Value::Point(Point { x, y }))
+        Ok(args[0].with_style(&Style {
+            layer: Some(layer),
+            ..Style::default()
+        }))
     }
+}
 
-    /// Case 2: One line segment and one circle
-    fn from_lineseg_circle(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 3 arguments
-        if args.len() != 3 {
-            return Err("Intersect requires exactly 3 arguments".to_string());
+#[derive(Clone)]
+pub struct FnHide;
+impl Operation for FnHide {
+    clone_impl!(FnHide);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("hide", &[ANY]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        Ok(args[0].with_style(&Style {
+            hidden: Some(true),
+            ..Style::default()
+        }))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnNoBounds;
+impl Operation for FnNoBounds {
+    clone_impl!(FnNoBounds);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("no-bounds", &[ANY]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        Ok(args[0].with_style(&Style {
+            unbounded: Some(true),
+            ..Style::default()
+        }))
+    }
+}
+
+/*
+Polygons
+*/
+
+#[derive(Clone)]
+pub struct FnPolygon;
+impl Operation for FnPolygon {
+    clone_impl!(FnPolygon);
+    fn keeps_style(&self) -> bool {
+        true
+    }
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("polygon", &[]).rest(POINT).returns(POLYGON))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for points
+        let mut points: Vec<Point> = Vec::new();
+        for arg in args {
+            match arg {
+                Value::Point(p) => points.push(*p),
+                _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+            }
         }
 
-        // check for 1 line segment, 1 circle, and 1 index either 0 or 1
-        let lineseg = match &args[0] {
-            Value::Lineseg(l) => l.clone(),
-            _ => return Err("Invalid types for line segment".to_string()),
-        };
-        let circle = match &args[1] {
-            Value::Circle(c) => c.clone(),
-            _ => return Err("Invalid types for circle".to_string()),
-        };
-        let index = match &args[2] {
+        // try creating the polygon
+        Ok(Value::Polygon(Polygon::new(points)?))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnVertex;
+impl Operation for FnVertex {
+    clone_impl!(FnVertex);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("vertex", &[Param::OneOf(&["Polygon", "Triangle"]), INT]).returns(POINT),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for an index
+        let index = match &args[1] {
             Value::Int(i) => *i,
-            _ => return Err("Invalid types for index".to_string()),
+            _ => return Err(ElementsError::Type("Invalid types for index".to_string())),
         };
-        if index != 0 && index != 1 {
-            return Err("Index must be either 0 or 1".to_string());
-        }
-
-        // calculate the intersection points without methods
-        let a = lineseg.start.y;
-        let b = lineseg.end.y;
-        let c = circle.center.x;
-        let d = circle.center.y;
-        let r = circle.radius;
-        let m = (b - a) / (lineseg.start.x - lineseg.end.x);
-        let n = (a * lineseg.end.y - b * lineseg.start.y) / (lineseg.end.x - lineseg.start.x);
-        let A = 1.0 + m * m;
-        let B = 2.0 * (m * n - m * d - c);
-        let C = c * c + d * d + n * n - 2.0 * n * d - r * r;
-        let D = B * B - 4.0 * A * C;
-        if D < 0.0 {
-            return Err("No intersection points".to_string());
-        }
-        let x1 = (-B + D.sqrt()) / (2.0 * A);
-        let x2 = (-B - D.sqrt()) / (2.0 * A);
-        let y1 = m * x1 + n;
-        let y2 = m * x2 + n;
 
-        // return the intersection point
-        if index == 0 {
-            Ok(Value::Point(Point { x: x1, y: y1 }))
-        } else {
-            Ok(Value::Point(Point { x: x2, y: y2 }))
+        // check for a polygon or a triangle
+        match &args[0] {
+            Value::Polygon(p) => Ok(Value::Point(p.vertex(index)?)),
+            Value::Triangle(t) => Ok(Value::Point(triangle_vertex(t, &args[1])?)),
+            _ => Err(ElementsError::Type(
+                "Invalid types for polygon or triangle".to_string(),
+            )),
         }
     }
 }
 
-impl Operation for FnIntersect {
-    clone_impl!(FnIntersect);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        match self.from_linesegs(args) {
-            Ok(point) => return Ok(point),
-            _ => {}
+#[derive(Clone)]
+pub struct FnConvexHull;
+impl Operation for FnConvexHull {
+    clone_impl!(FnConvexHull);
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // accept either the points themselves or a single list of points
+        let values = match args {
+            [Value::List(values)] => values.as_slice(),
+            _ => args,
+        };
+
+        // check for points
+        let mut points: Vec<Point> = Vec::new();
+        for value in values {
+            match value {
+                Value::Point(p) => points.push(*p),
+                _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
+            }
         }
 
-        match self.from_lineseg_circle(args) {
-            Ok(point) => Ok(point),
-            Err(e) => Err(e),
+        // compute the hull, which needs three corners to be a polygon
+        let hull = convex_hull(&points);
+        if hull.len() < 3 {
+            return Err(ElementsError::Arity(
+                "Convex hull requires at least 3 points that are not collinear".to_string(),
+            ));
         }
+        Ok(Value::Polygon(Polygon::new(hull)?))
     }
 }
 
 #[derive(Clone)]
-pub struct FnInradius;
-impl Operation for FnInradius {
-    clone_impl!(FnInradius);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 1 argument
-        if args.len() != 1 {
-            return Err("Inradius requires exactly 1 argument".to_string());
+pub struct FnEquilateral;
+impl Operation for FnEquilateral {
+    clone_impl!(FnEquilateral);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("equilateral", &[POINT, POINT])
+                .optional(&[INT])
+                .returns(TRIANGLE),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 2 points and an optional side
+        let points = to_points(&args[..2])?;
+        let (p, q) = (points[0], points[1]);
+        let side = side_arg(args.get(2))?;
+        if p == q {
+            return Err(ElementsError::Geometry(
+                "Points must be distinct".to_string(),
+            ));
         }
 
-        // check for 1 triangle
-        let triangle = match &args[0] {
-            Value::Triangle(t) => t.clone(),
-            _ => return Err("Invalid types for triangle".to_string()),
+        // step from the midpoint along the normal by the height of the triangle
+        let m = midpoint(p, q);
+        let h = side * 3.0_f64.sqrt() / 2.0;
+        let r = Point {
+            x: m.x - h * (q.y - p.y),
+            y: m.y + h * (q.x - p.x),
         };
-
-        // try getting the inradius
-        return Ok(Value::Float(triangle.inradius()));
+        Ok(Value::Triangle(Triangle::new(p, q, r)?))
     }
 }
 
-/*
-Basic geometric shapes
-*/
-
 #[derive(Clone)]
-pub struct FnCircle;
-impl FnCircle {
-    /// Case 1: create a circle from a point and a radius
-    fn from_point_radius(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 2 arguments
-        if args.len() != 2 {
-            return Err("Circle requires exactly 2 arguments".to_string());
+pub struct FnSquareOn;
+impl Operation for FnSquareOn {
+    clone_impl!(FnSquareOn);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("square-on", &[POINT, POINT])
+                .optional(&[INT])
+                .returns(POLYGON),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 2 points and an optional side
+        let points = to_points(&args[..2])?;
+        let (p, q) = (points[0], points[1]);
+        let side = side_arg(args.get(2))?;
+        if p == q {
+            return Err(ElementsError::Geometry(
+                "Points must be distinct".to_string(),
+            ));
         }
 
-        // check for point and radius
-        let point = match &args[0] {
-            Value::Point(p) => p.clone(),
-            _ => return Err("Invalid types for point".to_string()),
+        // offset both points along the normal by the side length
+        let (nx, ny) = (-side * (q.y - p.y), side * (q.x - p.x));
+        let polygon = Polygon::new(vec![
+            p,
+            q,
+            Point {
+                x: q.x + nx,
+                y: q.y + ny,
+            },
+            Point {
+                x: p.x + nx,
+                y: p.y + ny,
+            },
+        ])?;
+        Ok(Value::Polygon(polygon))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnRectangle;
+impl Operation for FnRectangle {
+    clone_impl!(FnRectangle);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("rectangle", &[POINT, NUMBER, NUMBER]).returns(POLYGON))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 1 corner and 2 positive dimensions
+        let p = match &args[0] {
+            Value::Point(p) => *p,
+            _ => return Err(ElementsError::Type("Invalid types for point".to_string())),
         };
-        let radius = match &args[1] {
-            Value::Int(r) => *r as f64,
-            Value::Float(r) => *r,
-            _ => return Err("Invalid types for radius".to_string()),
+        let (width, height) = match (to_float(&args[1]), to_float(&args[2])) {
+            (Some(w), Some(h)) => (w, h),
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for dimensions".to_string(),
+                ))
+            }
         };
-
-        // try creating the circle
-        match Circle::new(point, radius) {
-            Ok(circle) => Ok(Value::Circle(circle)),
-            Err(e) => Err(e),
+        if width <= 0.0 || height <= 0.0 {
+            return Err(ElementsError::Type(
+                "Width and height must be positive".to_string(),
+            ));
         }
+
+        // walk around the corners starting from the given one
+        let polygon = Polygon::new(vec![
+            p,
+            Point {
+                x: p.x + width,
+                y: p.y,
+            },
+            Point {
+                x: p.x + width,
+                y: p.y + height,
+            },
+            Point {
+                x: p.x,
+                y: p.y + height,
+            },
+        ])?;
+        Ok(Value::Polygon(polygon))
     }
+}
 
-    /// Case 2 [ambiguous]: create a standard circle if no arguments provided
-    fn new(&self, args: &[Value]) -> Result<Value, String> {
-        // check for no arguments
-        if args.len() != 0 {
-            return Err("Circle requires no elements".to_string());
-        }
+#[derive(Clone)]
+pub struct FnParallelogram;
+impl Operation for FnParallelogram {
+    clone_impl!(FnParallelogram);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("parallelogram", &[POINT, POINT, POINT]).returns(POLYGON))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for 3 points that are not collinear
+        let points = to_points(args)?;
+        let (a, b, c) = (points[0], points[1], points[2]);
+        Triangle::new(a, b, c)?;
 
-        // try creating the circle
-        match Circle::new(Point { x: 0.0, y: 0.0 }, 5.0) {
-            Ok(circle) => Ok(Value::Circle(circle)),
-            Err(e) => Err(e),
-        }
+        // the fourth vertex completes the diagonal from b
+        let d = Point {
+            x: a.x + c.x - b.x,
+            y: a.y + c.y - b.y,
+        };
+        Ok(Value::Polygon(Polygon::new(vec![a, b, c, d])?))
     }
 }
 
-impl Operation for FnCircle {
-    clone_impl!(FnCircle);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        match self.new(args) {
-            Ok(circle) => return Ok(circle),
-            _ => {}
-        }
+/*
+Accessors
+*/
 
-        match self.from_point_radius(args) {
-            Ok(circle) => Ok(circle),
-            Err(e) => Err(e),
+#[derive(Clone)]
+pub struct FnPointX;
+impl Operation for FnPointX {
+    clone_impl!(FnPointX);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("point-x", &[POINT]).returns(FLOAT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match &args[0] {
+            Value::Point(x) => Ok(Value::Float(x.x)),
+            _ => Err(ElementsError::Type("Invalid types for point".to_string())),
         }
     }
 }
 
 #[derive(Clone)]
-pub struct FnTriangle;
-impl FnTriangle {
-    /// Case 1: create a triangle from three points
-    fn from_points(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 3 arguments
-        if args.len() != 3 {
-            return Err("Triangle requires exactly 3 arguments".to_string());
+pub struct FnPointY;
+impl Operation for FnPointY {
+    clone_impl!(FnPointY);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("point-y", &[POINT]).returns(FLOAT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match &args[0] {
+            Value::Point(x) => Ok(Value::Float(x.y)),
+            _ => Err(ElementsError::Type("Invalid types for point".to_string())),
         }
+    }
+}
 
-        // check for 3 points
-        let mut points: Vec<Point> = Vec::new();
-        for arg in args {
-            match arg {
-                Value::Point(p) => points.push(p.clone()),
-                _ => return Err("Invalid types for point".to_string()),
-            }
+#[derive(Clone)]
+pub struct FnCircleCenter;
+impl Operation for FnCircleCenter {
+    clone_impl!(FnCircleCenter);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("circle-center", &[CIRCLE]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match &args[0] {
+            Value::Circle(x) => Ok(Value::Point(x.center)),
+            _ => Err(ElementsError::Type("Invalid types for circle".to_string())),
         }
+    }
+}
 
-        // try creating the triangle
-        match Triangle::new(points[0], points[1], points[2]) {
-            Ok(triangle) => Ok(Value::Triangle(triangle)),
-            Err(e) => Err(e),
+#[derive(Clone)]
+pub struct FnCircleRadius;
+impl Operation for FnCircleRadius {
+    clone_impl!(FnCircleRadius);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("circle-radius", &[CIRCLE]).returns(FLOAT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match &args[0] {
+            Value::Circle(x) => Ok(Value::Float(x.radius)),
+            _ => Err(ElementsError::Type("Invalid types for circle".to_string())),
         }
     }
+}
 
-    /// Case 2: create a triangle from an angle
-    fn from_angle(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 1 argument
-        if args.len() != 1 {
-            return Err("Triangle requires exactly 1 argument".to_string());
+#[derive(Clone)]
+pub struct FnSegStart;
+impl Operation for FnSegStart {
+    clone_impl!(FnSegStart);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("seg-start", &[LINESEG]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match &args[0] {
+            Value::Lineseg(x) => Ok(Value::Point(x.start)),
+            _ => Err(ElementsError::Type(
+                "Invalid types for line segment".to_string(),
+            )),
         }
+    }
+}
 
-        // check for 1 angle
-        let angle = match &args[0] {
-            Value::Angle(a) => a.clone(),
-            _ => return Err("Invalid types for angle".to_string()),
-        };
-
-        // extract points for the angle
-        let start = angle.start;
-        let center = angle.center;
-        let end = angle.end;
-
-        // try creating the triangle
-        match Triangle::new(start, center, end) {
-            Ok(triangle) => Ok(Value::Triangle(triangle)),
-            Err(e) => Err(e),
+#[derive(Clone)]
+pub struct FnSegEnd;
+impl Operation for FnSegEnd {
+    clone_impl!(FnSegEnd);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("seg-end", &[LINESEG]).returns(POINT))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        match &args[0] {
+            Value::Lineseg(x) => Ok(Value::Point(x.end)),
+            _ => Err(ElementsError::Type(
+                "Invalid types for line segment".to_string(),
+            )),
         }
     }
+}
+
+/*
+Figure directives
+*/
 
-    /// Case 3 [ambiguous]: create a triangle from a circle
-    fn from_circle(&self, args: &[Value]) -> Result<Value, String> {
-        // check for 1 argument
-        if args.len() != 1 {
-            return Err("Triangle requires exactly 1 argument".to_string());
+#[derive(Clone)]
+pub struct FnViewbox;
+impl Operation for FnViewbox {
+    clone_impl!(FnViewbox);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("viewbox", &[NUMBER, NUMBER, NUMBER, NUMBER]).returns(DIRECTIVE))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // try forcing the corner and size into floats
+        let mut numbers: Vec<f64> = Vec::new();
+        for arg in args {
+            match to_float(arg) {
+                Some(n) => numbers.push(n),
+                None => return Err(ElementsError::Type("Invalid types for viewbox".to_string())),
+            }
+        }
+        if numbers[2] <= 0.0 || numbers[3] <= 0.0 {
+            return Err(ElementsError::Type(
+                "Viewbox width and height must be positive".to_string(),
+            ));
         }
 
-        // check for 1 circle
-        let circle = match &args[0] {
-            Value::Circle(c) => c.clone(),
-            _ => return Err("Invalid types for circle".to_string()),
+        let min = Point {
+            x: numbers[0],
+            y: numbers[1],
         };
+        let max = Point {
+            x: numbers[0] + numbers[2],
+            y: numbers[1] + numbers[3],
+        };
+        Ok(Value::Directive(Directive::Viewbox { min, max }))
+    }
+}
 
-        // extract points for the circle
-        let mut first = circle.get_point();
-        let mut second = circle.get_point();
-        let mut third = circle.get_point();
-
-        // make sure the points are greater than half the radius apart
-        while distance(first, second) < circle.radius / 2.0
-            || distance(second, third) < circle.radius / 2.0
-            || distance(third, first) < circle.radius / 2.0
-        {
-            first = circle.get_point();
-            second = circle.get_point();
-            third = circle.get_point();
-        }
+#[derive(Clone)]
+pub struct FnGrid;
+impl Operation for FnGrid {
+    clone_impl!(FnGrid);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("grid", &[])
+                .optional(&[NUMBER])
+                .returns(DIRECTIVE),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // the spacing defaults to 1
+        let spacing = match args.first() {
+            None => 1.0,
+            Some(arg) => match to_float(arg) {
+                Some(spacing) if spacing > 0.0 => spacing,
+                Some(_) => {
+                    return Err(ElementsError::Type(
+                        "Grid spacing must be positive".to_string(),
+                    ))
+                }
+                None => return Err(ElementsError::Type("Invalid types for grid".to_string())),
+            },
+        };
+        Ok(Value::Directive(Directive::Grid { spacing }))
+    }
+}
 
-        // try creating the triangle
-        match Triangle::new(first, second, third) {
-            Ok(triangle) => Ok(Value::Triangle(triangle)),
-            Err(e) => Err(e),
-        }
+#[derive(Clone)]
+pub struct FnLegend;
+impl Operation for FnLegend {
+    clone_impl!(FnLegend);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("legend", &[])
+                .optional(&[STRING])
+                .returns(DIRECTIVE),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // the corner is chosen automatically by default
+        let corner = match args.first() {
+            None => None,
+            Some(Value::String(s)) => match s.trim_matches('"') {
+                "top-left" => Some(Corner::TopLeft),
+                "top-right" => Some(Corner::TopRight),
+                "bottom-left" => Some(Corner::BottomLeft),
+                "bottom-right" => Some(Corner::BottomRight),
+                _ => return Err(ElementsError::Type(format!("Unknown legend corner: {}", s))),
+            },
+            Some(_) => return Err(ElementsError::Type("Invalid types for legend".to_string())),
+        };
+        Ok(Value::Directive(Directive::Legend { corner }))
     }
 }
 
-impl Operation for FnTriangle {
-    clone_impl!(FnTriangle);
-    fn call(&self, args: &[Value]) -> Result<Value, String> {
-        match self.from_points(args) {
-            Ok(triangle) => return Ok(triangle),
-            _ => {}
+#[derive(Clone)]
+pub struct FnFigure;
+impl Operation for FnFigure {
+    clone_impl!(FnFigure);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("figure", &[STRING])
+                .rest(ANY)
+                .returns(DIRECTIVE),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // the name is used as a file name, so it is kept to characters that are safe in one
+        let name = match &args[0] {
+            Value::String(s) => s.trim_matches('"').to_string(),
+            _ => {
+                return Err(ElementsError::Type(
+                    "Invalid types for figure name".to_string(),
+                ))
+            }
+        };
+        let is_safe = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if name.is_empty() || !name.chars().all(is_safe) {
+            return Err(ElementsError::Type(format!(
+                "Figure name must be letters, digits, - and _: {}",
+                name
+            )));
         }
 
-        match self.from_circle(args) {
-            Ok(triangle) => return Ok(triangle),
-            _ => {}
+        // figures cannot be nested, as each is written to its own file
+        let values = args[1..].to_vec();
+        if values
+            .iter()
+            .any(|v| matches!(v, Value::Directive(Directive::Figure { .. })))
+        {
+            return Err(ElementsError::Type("Figures cannot be nested".to_string()));
         }
+        Ok(Value::Directive(Directive::Figure { name, values }))
+    }
+}
+
+#[derive(Clone)]
+pub struct FnExportCoords;
+impl Operation for FnExportCoords {
+    clone_impl!(FnExportCoords);
+    fn signature(&self) -> Option<Signature> {
+        Some(
+            Signature::new("export-coords", &[])
+                .optional(&[STRING])
+                .returns(DIRECTIVE),
+        )
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // the values are written as csv by default
+        let format = match args.first() {
+            None => "csv".to_string(),
+            Some(Value::String(s)) => match s.trim_matches('"') {
+                format @ ("csv" | "json") => format.to_string(),
+                _ => {
+                    return Err(ElementsError::Type(format!(
+                        "Unknown coordinate format: {}",
+                        s
+                    )))
+                }
+            },
+            Some(_) => {
+                return Err(ElementsError::Type(
+                    "Invalid types for export-coords".to_string(),
+                ))
+            }
+        };
+        Ok(Value::Directive(Directive::ExportCoords { format }))
+    }
+}
 
-        match self.from_angle(args) {
-            Ok(triangle) => Ok(triangle),
-            _ => Err("Invalid arguments for triangle".to_string()),
+#[derive(Clone)]
+pub struct FnSetTolerance;
+impl Operation for FnSetTolerance {
+    clone_impl!(FnSetTolerance);
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::new("set-tolerance", &[NUMBER]))
+    }
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // the tolerance applies to every check after it, and draws nothing itself
+        match to_float(&args[0]) {
+            Some(epsilon) if epsilon >= 0.0 && epsilon.is_finite() => {
+                set_tolerance(epsilon);
+                Ok(Value::Undefined)
+            }
+            _ => Err(ElementsError::Type(
+                "Tolerance must be a non-negative number".to_string(),
+            )),
         }
     }
 }
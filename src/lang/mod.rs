@@ -1,2 +1,3 @@
+pub mod environment;
 pub mod functions;
 pub mod types;
@@ -1,9 +1,25 @@
+#[cfg(feature = "high-precision")]
+use crate::utils::precise;
 use crate::{
-    renderer::{Render, SvgCircle, SvgLabel, SvgLine, SvgNothing, SvgPolygon},
-    TOLERANCE,
+    error::ElementsError,
+    interpreter::evaluate_expression,
+    lang::environment::Environment,
+    lexer::Token,
+    renderer::{
+        Font, PathCommand, Render, Style, SvgArc, SvgCircle, SvgCurve, SvgEllipse, SvgInfiniteLine,
+        SvgLabel, SvgLine, SvgNothing, SvgPath, SvgPolygon, SvgRaw, SvgRay, SvgUnbounded,
+    },
+    utils::{
+        geometry::{closest_point, combine_contours, distance, midpoint},
+        random,
+        tolerance::{is_near, tolerance},
+        transform::Transform,
+    },
 };
 
 use std::f64::consts::PI;
+use std::fmt;
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
@@ -17,7 +33,23 @@ pub enum Value {
     Triangle(Triangle),
     Angle(Angle),
     Circle(Circle),
+    Arc(Arc),
+    Ellipse(Ellipse),
+    Parabola(Parabola),
+    Hyperbola(Hyperbola),
     Lineseg(Lineseg),
+    Line(Line),
+    Ray(Ray),
+    Polygon(Polygon),
+    Region(Region),
+    Locus(Locus),
+    Bezier(Bezier),
+    RawSvg(RawSvg),
+    Lambda(Lambda),
+    List(Vec<Value>),
+    Styled(Box<Value>, Style),
+    Style(Style),
+    Directive(Directive),
 }
 
 impl Element for Value {
@@ -28,23 +60,581 @@ impl Element for Value {
             Value::Triangle(t) => t.to_svg(),
             Value::Angle(a) => a.to_svg(),
             Value::Circle(c) => c.to_svg(),
+            Value::Arc(a) => a.to_svg(),
+            Value::Ellipse(e) => e.to_svg(),
+            Value::Parabola(p) => p.to_svg(),
+            Value::Hyperbola(h) => h.to_svg(),
             Value::String(s) => s.to_svg(),
             Value::Lineseg(l) => l.to_svg(),
-            Value::Undefined => vec![Box::new(SvgNothing)],
-            _ => vec![Box::new(SvgPolygon { points: vec![] })],
+            Value::Line(l) => l.to_svg(),
+            Value::Ray(r) => r.to_svg(),
+            Value::Polygon(p) => p.to_svg(),
+            Value::Region(r) => r.to_svg(),
+            Value::Locus(l) => l.to_svg(),
+            Value::Bezier(b) => b.to_svg(),
+            Value::RawSvg(r) => r.to_svg(),
+            Value::List(values) => values.iter().flat_map(|v| v.to_svg()).collect(),
+            // hidden values are only used to build others, so they draw nothing
+            Value::Styled(_, style) if style.hidden == Some(true) => vec![Box::new(SvgNothing)],
+            Value::Styled(v, style) => {
+                let mut elements = v.to_svg();
+                for element in &mut elements {
+                    element.apply_style(style);
+                }
+
+                // unbounded values are clipped to the viewbox instead of widening it
+                if style.unbounded == Some(true) {
+                    elements = elements
+                        .into_iter()
+                        .map(|mut element| -> Box<dyn Render> {
+                            let any = element.as_any_mut();
+                            if any.is::<SvgLabel>() || any.is::<SvgNothing>() {
+                                element
+                            } else {
+                                Box::new(SvgUnbounded {
+                                    element,
+                                    area: None,
+                                })
+                            }
+                        })
+                        .collect();
+                }
+
+                // an attached label is placed at the anchor, or fixed by its offset
+                if let (Some(text), Some(anchor)) = (&style.label, v.label_anchor()) {
+                    elements.push(Box::new(SvgLabel {
+                        text: text.clone(),
+                        pt: anchor,
+                        position: style.label_offset.map(|o| Point {
+                            x: anchor.x + o.x,
+                            y: anchor.y + o.y,
+                        }),
+                        auto: false,
+                        font: Font::default(),
+                        flip: false,
+                    }));
+                }
+
+                // vertex labels are placed around each vertex of the triangle
+                if let (Some(names), Value::Triangle(t)) = (&style.vertex_labels, v.unstyled()) {
+                    for (name, vertex) in names.iter().zip([t.a, t.b, t.c]) {
+                        elements.push(Box::new(SvgLabel {
+                            text: name.clone(),
+                            pt: vertex,
+                            position: None,
+                            auto: false,
+                            font: Font::default(),
+                            flip: false,
+                        }));
+                    }
+                }
+                elements
+            }
+            Value::Undefined | Value::Style(_) | Value::Directive(_) => {
+                vec![Box::new(SvgNothing)]
+            }
+            _ => vec![Box::new(SvgPolygon {
+                points: vec![],
+                style: Style::default(),
+            })],
+        }
+    }
+}
+
+impl Value {
+    /// Apply a transformation to a geometric value
+    pub fn transform(&self, t: &Transform) -> Result<Value, ElementsError> {
+        match self {
+            Value::Point(p) => Ok(Value::Point(p.transform(t)?)),
+            Value::Triangle(tri) => Ok(Value::Triangle(tri.transform(t)?)),
+            Value::Angle(a) => Ok(Value::Angle(a.transform(t)?)),
+            Value::Circle(c) => Ok(Value::Circle(c.transform(t)?)),
+            Value::Arc(a) => Ok(Value::Arc(a.transform(t)?)),
+            Value::Ellipse(e) => Ok(Value::Ellipse(e.transform(t)?)),
+            Value::Parabola(p) => Ok(Value::Parabola(p.transform(t)?)),
+            Value::Hyperbola(h) => Ok(Value::Hyperbola(h.transform(t)?)),
+            Value::Lineseg(l) => Ok(Value::Lineseg(l.transform(t)?)),
+            Value::Line(l) => Ok(Value::Line(l.transform(t)?)),
+            Value::Ray(r) => Ok(Value::Ray(r.transform(t)?)),
+            Value::Polygon(p) => Ok(Value::Polygon(p.transform(t)?)),
+            Value::Region(r) => Ok(Value::Region(r.transform(t)?)),
+            Value::Locus(l) => Ok(Value::Locus(l.transform(t)?)),
+            Value::Bezier(b) => Ok(Value::Bezier(b.transform(t)?)),
+            Value::List(values) => Ok(Value::List(
+                values
+                    .iter()
+                    .map(|v| v.transform(t))
+                    .collect::<Result<Vec<Value>, ElementsError>>()?,
+            )),
+            Value::Styled(v, style) => Ok(Value::Styled(Box::new(v.transform(t)?), style.clone())),
+            _ => Err(ElementsError::Type(
+                "Value cannot be transformed".to_string(),
+            )),
+        }
+    }
+
+    /// Return the name of the type of the value
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "Int",
+            Value::Float(_) => "Float",
+            Value::String(_) => "String",
+            Value::Bool(_) => "Bool",
+            Value::Indeterminate => "Indeterminate",
+            Value::Undefined => "Undefined",
+            Value::Point(_) => "Point",
+            Value::Triangle(_) => "Triangle",
+            Value::Angle(_) => "Angle",
+            Value::Circle(_) => "Circle",
+            Value::Arc(_) => "Arc",
+            Value::Ellipse(_) => "Ellipse",
+            Value::Parabola(_) => "Parabola",
+            Value::Hyperbola(_) => "Hyperbola",
+            Value::Lineseg(_) => "Lineseg",
+            Value::Line(_) => "Line",
+            Value::Ray(_) => "Ray",
+            Value::Polygon(_) => "Polygon",
+            Value::Region(_) => "Region",
+            Value::Locus(_) => "Locus",
+            Value::Bezier(_) => "Bezier",
+            Value::RawSvg(_) => "RawSvg",
+            Value::Lambda(_) => "Lambda",
+            Value::List(_) => "List",
+            Value::Styled(v, _) => v.type_name(),
+            Value::Style(_) => "Style",
+            Value::Directive(_) => "Directive",
+        }
+    }
+
+    /// Return the derived properties of the value as name and value pairs
+    pub fn properties(&self) -> Vec<(String, String)> {
+        let point = |name: &str, p: &Point| (name.to_string(), format!("({}, {})", p.x, p.y));
+        match self {
+            Value::Int(i) => vec![("value".to_string(), i.to_string())],
+            Value::Float(f) => vec![("value".to_string(), f.to_string())],
+            Value::String(s) => vec![("value".to_string(), s.clone())],
+            Value::Bool(b) => vec![("value".to_string(), b.to_string())],
+            Value::Point(p) => vec![
+                ("x".to_string(), p.x.to_string()),
+                ("y".to_string(), p.y.to_string()),
+            ],
+            Value::Triangle(t) => vec![
+                point("a", &t.a),
+                point("b", &t.b),
+                point("c", &t.c),
+                ("area".to_string(), t.area().to_string()),
+                ("perimeter".to_string(), t.perimeter().to_string()),
+                ("inradius".to_string(), t.inradius().to_string()),
+            ],
+            Value::Angle(a) => vec![
+                point("start", &a.start),
+                point("center", &a.center),
+                point("end", &a.end),
+                ("degrees".to_string(), a.degrees().to_string()),
+            ],
+            Value::Circle(c) => vec![
+                point("center", &c.center),
+                ("radius".to_string(), c.radius.to_string()),
+                ("area".to_string(), c.area().to_string()),
+            ],
+            Value::Arc(a) => vec![
+                point("center", &a.center),
+                ("radius".to_string(), a.radius.to_string()),
+                point("start", &a.start_point()),
+                point("end", &a.end_point()),
+                (
+                    "degrees".to_string(),
+                    a.sweep.abs().to_degrees().to_string(),
+                ),
+            ],
+            Value::Ellipse(e) => vec![
+                point("center", &e.center),
+                ("rx".to_string(), e.rx.to_string()),
+                ("ry".to_string(), e.ry.to_string()),
+                ("rotation".to_string(), e.rotation.to_string()),
+                ("area".to_string(), e.area().to_string()),
+            ],
+            Value::Parabola(p) => vec![
+                point("focus", &p.focus),
+                point("vertex", &p.vertex()),
+                ("focal length".to_string(), p.focal_length().to_string()),
+            ],
+            Value::Hyperbola(h) => vec![
+                point("first focus", &h.first),
+                point("second focus", &h.second),
+                point("center", &midpoint(h.first, h.second)),
+                ("a".to_string(), h.a.to_string()),
+                ("b".to_string(), h.b().to_string()),
+            ],
+            Value::Lineseg(l) => vec![
+                point("start", &l.start),
+                point("end", &l.end),
+                (
+                    "length".to_string(),
+                    (l.end.x - l.start.x).hypot(l.end.y - l.start.y).to_string(),
+                ),
+            ],
+            Value::Line(l) => vec![
+                ("a".to_string(), l.a.to_string()),
+                ("b".to_string(), l.b.to_string()),
+                ("c".to_string(), l.c.to_string()),
+            ],
+            Value::Ray(r) => vec![point("origin", &r.origin), point("through", &r.through)],
+            Value::Polygon(p) => {
+                let mut properties: Vec<(String, String)> = p
+                    .points
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| point(&i.to_string(), v))
+                    .collect();
+                properties.push(("area".to_string(), p.area().to_string()));
+                properties.push(("perimeter".to_string(), p.perimeter().to_string()));
+                properties
+            }
+            Value::Region(r) => vec![("contours".to_string(), r.contours.len().to_string())],
+            Value::Bezier(b) => b
+                .points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| point(&format!("p{}", i), p))
+                .collect(),
+            Value::RawSvg(r) => {
+                let mut properties = vec![("svg".to_string(), r.svg.clone())];
+                if let Some((first, second)) = &r.extents {
+                    properties.push(point("first corner", first));
+                    properties.push(point("second corner", second));
+                }
+                properties
+            }
+            Value::Locus(l) => vec![
+                ("pieces".to_string(), l.runs.len().to_string()),
+                (
+                    "samples".to_string(),
+                    l.runs.iter().map(Vec::len).sum::<usize>().to_string(),
+                ),
+            ],
+            Value::Lambda(l) => vec![("params".to_string(), l.params.join(" "))],
+            Value::List(values) => values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i.to_string(), format!("{:?}", v)))
+                .collect(),
+            Value::Styled(v, style) => {
+                let mut properties = v.properties();
+                properties.push(("style".to_string(), format!("{:?}", style)));
+                properties
+            }
+            Value::Style(style) => vec![("style".to_string(), format!("{:?}", style))],
+            Value::Directive(d) => vec![("directive".to_string(), format!("{:?}", d))],
+            Value::Indeterminate | Value::Undefined => Vec::new(),
+        }
+    }
+
+    /// Return the point a label attached to the value is placed around
+    pub fn label_anchor(&self) -> Option<Point> {
+        match self {
+            Value::Point(p) => Some(*p),
+            Value::Lineseg(l) => Some(midpoint(l.start, l.end)),
+            Value::Circle(c) => Some(Point {
+                x: c.center.x,
+                y: c.center.y - c.radius,
+            }),
+            Value::Arc(a) => Some(a.middle_point()),
+            Value::Triangle(t) => Some(t.centroid()),
+            Value::Polygon(p) => Some(p.centroid()),
+            Value::Angle(a) => Some(a.label_point()),
+            Value::Styled(v, _) => v.label_anchor(),
+            _ => None,
+        }
+    }
+
+    /// Return the value with any style stripped off
+    pub fn unstyled(&self) -> &Value {
+        match self {
+            Value::Styled(v, _) => v.unstyled(),
+            _ => self,
+        }
+    }
+
+    /// Return the value with a style applied on top of any existing style
+    pub fn with_style(&self, style: &Style) -> Value {
+        match self {
+            Value::Styled(v, existing) => {
+                let mut merged = existing.clone();
+                merged.merge(style);
+                Value::Styled(v.clone(), merged)
+            }
+            _ => Value::Styled(Box::new(self.clone()), style.clone()),
         }
     }
 }
 
 pub trait Operation {
     fn box_clone(&self) -> Box<dyn Operation>;
-    fn call(&self, args: &[Value]) -> Result<Value, String>;
+    fn call(&self, args: &[Value]) -> Result<Value, ElementsError>;
+    /// Whether the operation receives styled arguments as-is instead of their unstyled values
+    fn keeps_style(&self) -> bool {
+        false
+    }
+    /// Call the operation along with the names of the variables each argument was read from
+    fn call_named(&self, args: &[Value], _: &[Option<String>]) -> Result<Value, ElementsError> {
+        self.call(args)
+    }
+    /// The name, arity, and parameter types the arguments are checked against before the operation is
+    /// called, or None if the operation checks its own arguments
+    fn signature(&self) -> Option<Signature> {
+        None
+    }
+    /// The signatures of the cases of an operation taking several kinds of arguments, which it picks
+    /// between with `Signature::resolve`
+    fn overloads(&self) -> Vec<Signature> {
+        Vec::new()
+    }
+    /// How far the arguments are from satisfying the predicate, as amounts that are all zero exactly when
+    /// it holds, which `solve` drives to zero, or None if the operation cannot be used as a constraint
+    fn residuals(&self, _: &[Value]) -> Option<Vec<f64>> {
+        None
+    }
+}
+
+/// The types of value a parameter accepts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Param {
+    /// Any value
+    Any,
+    /// A value of one of the named types, as given by `Value::type_name`
+    OneOf(&'static [&'static str]),
+}
+
+impl Param {
+    /// Return whether the parameter accepts the value
+    pub fn accepts(&self, value: &Value) -> bool {
+        match self {
+            Param::Any => true,
+            Param::OneOf(types) => types.contains(&value.type_name()),
+        }
+    }
+
+    /// Return whether a value of one of these types may also be of one of the other's
+    pub fn overlaps(&self, other: &Param) -> bool {
+        match (self, other) {
+            (Param::OneOf(types), Param::OneOf(others)) => types.iter().any(|t| others.contains(t)),
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    /// Write the accepted types as in the reference, such as Int/Float
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Param::Any => write!(f, "any value"),
+            Param::OneOf(types) => write!(f, "{}", types.join("/")),
+        }
+    }
+}
+
+/// An argument as far as its type is known, which is its value when evaluating and the types it may have
+/// when checking source without evaluating it
+pub trait Argument {
+    /// Return whether the parameter may accept the argument
+    fn fits(&self, param: &Param) -> bool;
+    /// Return the type of the argument, as written in errors
+    fn type_names(&self) -> String;
+}
+
+impl Argument for Value {
+    fn fits(&self, param: &Param) -> bool {
+        param.accepts(self)
+    }
+    fn type_names(&self) -> String {
+        self.type_name().to_string()
+    }
+}
+
+impl Argument for Param {
+    fn fits(&self, param: &Param) -> bool {
+        self.overlaps(param)
+    }
+    fn type_names(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// The arguments an operation takes: the parameters that must be given, then those that may be given in
+/// order, then any number of arguments of one more type, along with the type of value it returns
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Signature {
+    pub name: &'static str,
+    pub params: &'static [Param],
+    pub optional: &'static [Param],
+    pub rest: Option<Param>,
+    pub returns: Param,
+}
+
+impl Signature {
+    /// Create a signature taking exactly the given parameters
+    pub const fn new(name: &'static str, params: &'static [Param]) -> Self {
+        Signature {
+            name,
+            params,
+            optional: &[],
+            rest: None,
+            returns: Param::Any,
+        }
+    }
+
+    /// Return the signature also taking the given parameters after the required ones
+    pub const fn optional(self, optional: &'static [Param]) -> Self {
+        Signature { optional, ..self }
+    }
+
+    /// Return the signature also taking any number of arguments of the given type at the end
+    pub const fn rest(self, rest: Param) -> Self {
+        Signature {
+            rest: Some(rest),
+            ..self
+        }
+    }
+
+    /// Return the signature returning a value of the given type
+    pub const fn returns(self, returns: Param) -> Self {
+        Signature { returns, ..self }
+    }
+
+    /// Return whether the signature takes the given number of arguments
+    pub fn takes(&self, count: usize) -> bool {
+        count >= self.params.len()
+            && (self.rest.is_some() || count <= self.params.len() + self.optional.len())
+    }
+
+    /// Return the parameter of the argument at the given index, if there is one
+    pub fn param(&self, index: usize) -> Option<Param> {
+        let params = self.params.iter().chain(self.optional);
+        params.copied().nth(index).or(self.rest)
+    }
+
+    /// Describe the number of arguments taken, such as "2 or 3 arguments"
+    fn arity(&self) -> String {
+        let min = self.params.len();
+        let max = min + self.optional.len();
+        let plural = if min == 1 { "" } else { "s" };
+        match (self.rest, self.optional.len()) {
+            (Some(_), _) => format!("at least {} argument{}", min, plural),
+            (None, 0) => format!("{} argument{}", min, plural),
+            (None, 1) => format!("{} or {} arguments", min, max),
+            (None, _) => format!("{} to {} arguments", min, max),
+        }
+    }
+
+    /// Return the index of the first argument the signature does not accept
+    fn mismatch(&self, args: &[impl Argument]) -> Option<usize> {
+        (0..args.len()).find(|&i| !self.param(i).is_some_and(|param| args[i].fits(&param)))
+    }
+
+    /// Check the number and types of the arguments, naming the operation and the first argument that
+    /// does not fit
+    pub fn check(&self, args: &[impl Argument]) -> Result<(), ElementsError> {
+        if !self.takes(args.len()) {
+            return Err(ElementsError::Arity(format!(
+                "{}: expected {}, got {}",
+                self.name,
+                self.arity(),
+                args.len()
+            )));
+        }
+        match self.mismatch(args) {
+            Some(i) => {
+                let expected = self
+                    .param(i)
+                    .map_or(String::new(), |param| param.to_string());
+                Err(type_error(self.name, &expected, i, &args[i]))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Return the index of the first of the signatures of an operation's cases that the arguments fit,
+    /// or else an error naming what the cases taking that many arguments expect where the arguments
+    /// stop fitting all of them
+    pub fn resolve(
+        overloads: &[Signature],
+        args: &[impl Argument],
+    ) -> Result<usize, ElementsError> {
+        if let Some(index) = overloads.iter().position(|s| s.check(args).is_ok()) {
+            return Ok(index);
+        }
+        let name = overloads.first().map_or("", |s| s.name);
+
+        // with no case taking that many arguments, list the numbers that are taken
+        let taking: Vec<&Signature> = overloads.iter().filter(|s| s.takes(args.len())).collect();
+        if taking.is_empty() {
+            let is_exact = overloads
+                .iter()
+                .all(|s| s.optional.is_empty() && s.rest.is_none());
+            let expected = if is_exact {
+                let mut counts: Vec<usize> = overloads.iter().map(|s| s.params.len()).collect();
+                counts.sort_unstable();
+                counts.dedup();
+                let counts: Vec<String> = counts.iter().map(|n| n.to_string()).collect();
+                match counts.split_last() {
+                    Some((last, [])) if last == "1" => "1 argument".to_string(),
+                    Some((last, [])) => format!("{} arguments", last),
+                    Some((last, [first])) => format!("{} or {} arguments", first, last),
+                    Some((last, rest)) => format!("{}, or {} arguments", rest.join(", "), last),
+                    None => "no arguments".to_string(),
+                }
+            } else {
+                let arities: Vec<String> = overloads.iter().map(|s| s.arity()).collect();
+                arities.join(" or ")
+            };
+            return Err(ElementsError::Arity(format!(
+                "{}: expected {}, got {}",
+                name,
+                expected,
+                args.len()
+            )));
+        }
+
+        // report the argument the closest cases fail at, with every type they would accept there
+        let index = taking
+            .iter()
+            .filter_map(|s| s.mismatch(args))
+            .max()
+            .unwrap_or(0);
+        let mut types: Vec<&str> = Vec::new();
+        for signature in taking.iter().filter(|s| s.mismatch(args) == Some(index)) {
+            if let Some(Param::OneOf(accepted)) = signature.param(index) {
+                for t in accepted {
+                    if !types.contains(t) {
+                        types.push(t);
+                    }
+                }
+            }
+        }
+        Err(type_error(name, &types.join("/"), index, &args[index]))
+    }
+}
+
+/// Return the error for an argument of an operation that is not of the expected type
+fn type_error(name: &str, expected: &str, index: usize, arg: &impl Argument) -> ElementsError {
+    ElementsError::Type(format!(
+        "{}: expected {} for argument {}, got {}",
+        name,
+        expected,
+        index + 1,
+        arg.type_names()
+    ))
 }
 
 pub trait Element {
     fn to_svg(&self) -> Vec<Box<dyn Render>>;
 }
 
+pub trait Transformable: Sized {
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError>;
+}
+
 /// Implement Element for string labels
 impl Element for String {
     fn to_svg(&self) -> Vec<Box<dyn Render>> {
@@ -59,10 +649,73 @@ impl Element for String {
             text: name.to_string(),
             pt: loc,
             position: None,
+            auto: true,
+            font: Font::default(),
+            flip: false,
         })]
     }
 }
 
+/*
+Figure directives
+*/
+
+/// Settings for the whole figure given in the language, which are applied by the renderer
+#[derive(Debug, Clone, PartialEq)]
+pub enum Directive {
+    /// Explicit viewbox from its minimum to maximum corner, replacing the computed bounds
+    Viewbox { min: Point, max: Point },
+    /// Coordinate grid with axes and tick labels, with lines the given distance apart
+    Grid { spacing: f64 },
+    /// Key naming the styled values, in the given corner or else in whichever corner is clearest
+    Legend { corner: Option<Corner> },
+    /// Separate figure drawn from the given values, written to its own file named after it
+    Figure { name: String, values: Vec<Value> },
+    /// Values of the named variables written beside the figure, as csv or json
+    ExportCoords { format: String },
+}
+
+/// Corner of the figure as it is displayed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/*
+Functional types
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lambda {
+    pub params: Vec<String>,
+    pub body: Vec<Token>,
+    pub env: Rc<Environment>,
+}
+
+impl Lambda {
+    /// Call the lambda with the given arguments bound to its parameters
+    pub fn call(&self, args: &[Value]) -> Result<Value, ElementsError> {
+        // check for the right number of arguments
+        if args.len() != self.params.len() {
+            return Err(ElementsError::Arity(format!(
+                "Lambda requires exactly {} arguments",
+                self.params.len()
+            )));
+        }
+
+        // bind the arguments in a scope nested in the captured variables
+        let mut variables = self.env.child();
+        for (param, arg) in self.params.iter().zip(args) {
+            variables.define(param.clone(), arg.clone());
+        }
+
+        evaluate_expression(&self.body, &mut variables)
+    }
+}
+
 /*
 Basic geometric types
 */
@@ -74,14 +727,9 @@ pub struct Lineseg {
 }
 
 impl Lineseg {
-    /// Return the slope of the lineseg
-    pub fn slope(&self) -> f64 {
-        (self.end.y - self.start.y) / (self.end.x - self.start.x)
-    }
-
-    /// Return the y intercept of the lineseg
-    pub fn y_intercept(&self) -> f64 {
-        self.start.y - self.slope() * self.start.x
+    /// Return the line that the lineseg lies on
+    pub fn line(&self) -> Result<Line, ElementsError> {
+        Line::from_points(self.start, self.end)
     }
 }
 
@@ -91,10 +739,184 @@ impl Element for Lineseg {
         vec![Box::new(SvgLine {
             start: self.start,
             end: self.end,
+            style: Style::default(),
+        })]
+    }
+}
+
+impl Transformable for Lineseg {
+    /// Transform both endpoints of the lineseg
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Lineseg {
+            start: t.apply(self.start),
+            end: t.apply(self.end),
+        })
+    }
+}
+
+/// An infinite line stored as ax + by + c = 0, with (a, b) a unit normal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Line {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+impl Line {
+    /// Create the line passing through two distinct points
+    pub fn from_points(p: Point, q: Point) -> Result<Self, ElementsError> {
+        // check for distinct points
+        let length = (q.x - p.x).hypot(q.y - p.y);
+        if length < tolerance() {
+            return Err(ElementsError::Geometry(
+                "Line requires two distinct points".to_string(),
+            ));
+        }
+
+        // normalize so that distances can be read off directly
+        let a = (p.y - q.y) / length;
+        let b = (q.x - p.x) / length;
+        Ok(Self {
+            a,
+            b,
+            c: -(a * p.x + b * p.y),
+        })
+    }
+
+    /// Return two distinct points on the line
+    pub fn points(&self) -> (Point, Point) {
+        // closest point to the origin, then one unit along the direction
+        let p = Point {
+            x: -self.a * self.c,
+            y: -self.b * self.c,
+        };
+        let q = Point {
+            x: p.x - self.b,
+            y: p.y + self.a,
+        };
+        (p, q)
+    }
+
+    /// Return the signed distance from a point to the line
+    pub fn signed_distance(&self, p: &Point) -> f64 {
+        self.a * p.x + self.b * p.y + self.c
+    }
+
+    /// Return the foot of the perpendicular from a point to the line
+    pub fn project(&self, p: &Point) -> Point {
+        let d = self.signed_distance(p);
+        Point {
+            x: p.x - d * self.a,
+            y: p.y - d * self.b,
+        }
+    }
+
+    /// Return the intersection with another line, or None if they are parallel
+    pub fn intersect(&self, other: &Line) -> Option<Point> {
+        let det = self.a * other.b - other.a * self.b;
+        if det.abs() < tolerance() {
+            return None;
+        }
+        Some(Point {
+            x: (self.b * other.c - other.b * self.c) / det,
+            y: (other.a * self.c - self.a * other.c) / det,
+        })
+    }
+
+    /// Return the intersections with a circle, ordered by larger x and then larger y
+    pub fn intersect_circle(&self, circle: &Circle) -> Option<(Point, Point)> {
+        // find the foot of the perpendicular from the center
+        let d = self.signed_distance(&circle.center);
+        if d.abs() > circle.radius + tolerance() {
+            return None;
+        }
+        let foot = self.project(&circle.center);
+
+        // step along the line in both directions
+        let h = (circle.radius * circle.radius - d * d).max(0.0).sqrt();
+        let first = Point {
+            x: foot.x - h * self.b,
+            y: foot.y + h * self.a,
+        };
+        let second = Point {
+            x: foot.x + h * self.b,
+            y: foot.y - h * self.a,
+        };
+        if (first.x, first.y) >= (second.x, second.y) {
+            Some((first, second))
+        } else {
+            Some((second, first))
+        }
+    }
+}
+
+impl Element for Line {
+    /// Turn line into a SVG element, clipped to the viewbox when rendered
+    fn to_svg(&self) -> Vec<Box<dyn Render>> {
+        let (p, q) = self.points();
+        vec![Box::new(SvgInfiniteLine {
+            p,
+            q,
+            ends: None,
+            style: Style::default(),
+        })]
+    }
+}
+
+impl Transformable for Line {
+    /// Transform two points on the line and rebuild it
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        // a transform that collapses the plane to a point leaves no line to rebuild
+        let (p, q) = self.points();
+        Line::from_points(t.apply(p), t.apply(q)).map_err(|_| {
+            ElementsError::Geometry("Transform collapses the line to a point".to_string())
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Point,
+    pub through: Point,
+}
+
+impl Ray {
+    /// Create a new ray given its origin and a point it passes through
+    pub fn new(origin: Point, through: Point) -> Result<Self, ElementsError> {
+        // check for a direction
+        if origin == through {
+            return Err(ElementsError::Geometry(
+                "Ray requires two distinct points".to_string(),
+            ));
+        }
+
+        // otherwise, return the ray
+        Ok(Self { origin, through })
+    }
+}
+
+impl Element for Ray {
+    /// Turn ray into a SVG element, extended to the viewbox when rendered
+    fn to_svg(&self) -> Vec<Box<dyn Render>> {
+        vec![Box::new(SvgRay {
+            origin: self.origin,
+            through: self.through,
+            end: None,
+            style: Style::default(),
         })]
     }
 }
 
+impl Transformable for Ray {
+    /// Transform the origin and the point the ray passes through
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Ray {
+            origin: t.apply(self.origin),
+            through: t.apply(self.through),
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Point {
     pub x: f64,
@@ -108,15 +930,74 @@ impl Element for Point {
             center: *self,
             radius: 0.05,
             fill: true,
+            style: Style::default(),
         })]
     }
 }
 
+impl Transformable for Point {
+    /// Transform the point
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(t.apply(*self))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Angle {
     pub start: Point,
     pub center: Point,
     pub end: Point,
+    /// Radius of the arc marker at the vertex, or None to size it from the arms
+    pub radius: Option<f64>,
+    /// Number of arcs in the marker, used to indicate equal angles
+    pub arcs: u8,
+}
+
+impl Angle {
+    /// Create an angle with a single arc marker sized from its arms
+    pub fn new(start: Point, center: Point, end: Point) -> Self {
+        Angle {
+            start,
+            center,
+            end,
+            radius: None,
+            arcs: 1,
+        }
+    }
+
+    /// Return the radius of the innermost marker arc
+    pub fn marker_radius(&self) -> f64 {
+        let shorter = distance(self.center, self.start).min(distance(self.center, self.end));
+        self.radius.unwrap_or((shorter / 3.0).min(0.3))
+    }
+
+    /// Return a point just outside the marker arcs along the angle bisector
+    pub fn label_point(&self) -> Point {
+        let first = (self.start.y - self.center.y).atan2(self.start.x - self.center.x);
+        let second = (self.end.y - self.center.y).atan2(self.end.x - self.center.x);
+        let mut sweep = (second - first).rem_euclid(2.0 * PI);
+        if sweep > PI {
+            sweep -= 2.0 * PI;
+        }
+        let bisector = first + sweep / 2.0;
+        let outer = self.marker_radius() * (1.0 + 0.2 * self.arcs.saturating_sub(1) as f64);
+        Point {
+            x: self.center.x + (outer + 0.25) * bisector.cos(),
+            y: self.center.y + (outer + 0.25) * bisector.sin(),
+        }
+    }
+
+    /// Return the measure of the angle in degrees, between 0 and 180
+    pub fn degrees(&self) -> f64 {
+        let first = (self.start.y - self.center.y).atan2(self.start.x - self.center.x);
+        let second = (self.end.y - self.center.y).atan2(self.end.x - self.center.x);
+        let angle = (first - second).abs().to_degrees();
+        if angle > 180.0 {
+            360.0 - angle
+        } else {
+            angle
+        }
+    }
 }
 
 impl Element for Angle {
@@ -125,12 +1006,45 @@ impl Element for Angle {
         let first: SvgLine = SvgLine {
             start: self.center,
             end: self.start,
+            style: Style::default(),
         };
         let second: SvgLine = SvgLine {
             start: self.center,
             end: self.end,
+            style: Style::default(),
         };
-        vec![Box::new(first), Box::new(second)]
+        let mut elements: Vec<Box<dyn Render>> = vec![Box::new(first), Box::new(second)];
+
+        // draw the marker arcs across the interior of the angle, spaced outwards from the vertex
+        let radius = self.marker_radius();
+        let start = (self.start.y - self.center.y).atan2(self.start.x - self.center.x);
+        let end = (self.end.y - self.center.y).atan2(self.end.x - self.center.x);
+        let mut sweep = (end - start).rem_euclid(2.0 * PI);
+        if sweep > PI {
+            sweep -= 2.0 * PI;
+        }
+        for i in 0..self.arcs {
+            elements.push(Box::new(SvgArc {
+                center: self.center,
+                radius: radius * (1.0 + 0.2 * i as f64),
+                start,
+                sweep,
+                style: Style::default(),
+            }));
+        }
+        elements
+    }
+}
+
+impl Transformable for Angle {
+    /// Transform the three points of the angle
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Angle {
+            start: t.apply(self.start),
+            center: t.apply(self.center),
+            end: t.apply(self.end),
+            ..*self
+        })
     }
 }
 
@@ -147,56 +1061,171 @@ impl Element for Circle {
             center: self.center,
             radius: self.radius,
             fill: false,
+            style: Style::default(),
         })]
     }
 }
 
-impl Circle {
-    /// Create a new circle given a center and radius
-    pub fn new(center: Point, radius: f64) -> Result<Self, String> {
-        // check for negative radius
-        if radius < 0.0 {
-            return Err("Radius is negative".to_string());
-        }
+/// An arc of a circle, starting at an angle in radians and sweeping a signed angle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arc {
+    pub center: Point,
+    pub radius: f64,
+    pub start: f64,
+    pub sweep: f64,
+}
 
-        // otherwise, return the circle
-        Ok(Self { center, radius })
+impl Arc {
+    /// Create the arc from the first point to the last passing through the middle point
+    pub fn through(start: Point, middle: Point, end: Point) -> Result<Self, ElementsError> {
+        // the arc lies on the circle through all three points
+        let circle = Triangle::new(start, middle, end)?.circumcircle();
+        let angle = |p: Point| (p.y - circle.center.y).atan2(p.x - circle.center.x);
+        let a0 = angle(start);
+
+        // sweep counterclockwise if that passes the middle point, otherwise clockwise
+        let full = 2.0 * PI;
+        let to_end = (angle(end) - a0).rem_euclid(full);
+        let to_middle = (angle(middle) - a0).rem_euclid(full);
+        let sweep = if to_middle < to_end {
+            to_end
+        } else {
+            to_end - full
+        };
+
+        Ok(Self {
+            center: circle.center,
+            radius: circle.radius,
+            start: a0,
+            sweep,
+        })
     }
 
-    /// Return a random point on the circle
-    pub fn get_point(&self) -> Point {
-        let angle = rand::random::<f64>() * 2.0 * PI;
+    /// Return the point on the arc's circle at the given angle in radians
+    fn point_at(&self, angle: f64) -> Point {
         Point {
             x: self.center.x + self.radius * angle.cos(),
             y: self.center.y + self.radius * angle.sin(),
         }
     }
 
-    /// Check if a point is on the circle
-    pub fn is_point_on_circle(&self, point: Point) -> bool {
-        let lhs: f64 = (point.x - self.center.x).powi(2) + (point.y - self.center.y).powi(2);
-        let rhs: f64 = self.radius.powi(2);
-        (lhs - rhs).abs() < TOLERANCE
+    /// Return the point the arc starts at
+    pub fn start_point(&self) -> Point {
+        self.point_at(self.start)
     }
 
-    /// Return the point on a specified arc from a given angle
-    pub fn get_point_on_arc(&self, start: Point, end: Point, deg: f64) -> Result<Point, String> {
-        // ensure that the points are on the circle
-        if !self.is_point_on_circle(start) || !self.is_point_on_circle(end) {
-            return Err("Points are not on the circle".to_string());
-        }
+    /// Return the point halfway along the arc
+    pub fn middle_point(&self) -> Point {
+        self.point_at(self.start + self.sweep / 2.0)
+    }
 
-        // initialize variables
-        let angle = deg.to_radians();
-        let center = self.center;
-        let radius = self.radius;
+    /// Return the point the arc ends at
+    pub fn end_point(&self) -> Point {
+        self.point_at(self.start + self.sweep)
+    }
+}
 
-        // calculate angles from center to start and end points
-        let start_angle = (start.y - center.y).atan2(start.x - center.x);
-        let end_angle = (end.y - center.y).atan2(end.x - center.x);
+impl Element for Arc {
+    /// Turn arc into a SVG element
+    fn to_svg(&self) -> Vec<Box<dyn Render>> {
+        vec![Box::new(SvgArc {
+            center: self.center,
+            radius: self.radius,
+            start: self.start,
+            sweep: self.sweep,
+            style: Style::default(),
+        })]
+    }
+}
 
-        // normalize angles to be between 0 and 2PI
-        let start_angle = if start_angle < 0.0 {
+impl Transformable for Arc {
+    /// Transform three points along the arc and rebuild it
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        // a transform that collapses the plane to a point leaves no arc to rebuild
+        Arc::through(
+            t.apply(self.start_point()),
+            t.apply(self.middle_point()),
+            t.apply(self.end_point()),
+        )
+        .map_err(|_| ElementsError::Geometry("Transform collapses the arc to a point".to_string()))
+    }
+}
+
+impl Transformable for Circle {
+    /// Transform the center and scale the radius of the circle
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Circle {
+            center: t.apply(self.center),
+            radius: self.radius * t.scale(),
+        })
+    }
+}
+
+impl Circle {
+    /// Create a new circle given a center and radius
+    pub fn new(center: Point, radius: f64) -> Result<Self, ElementsError> {
+        // check for negative radius
+        if radius < 0.0 {
+            return Err(ElementsError::Geometry("Radius is negative".to_string()));
+        }
+
+        // otherwise, return the circle
+        Ok(Self { center, radius })
+    }
+
+    /// Return the area of the circle
+    pub fn area(&self) -> f64 {
+        PI * self.radius.powi(2)
+    }
+
+    /// Return the perimeter of the circle
+    pub fn perimeter(&self) -> f64 {
+        2.0 * PI * self.radius
+    }
+
+    /// Return a random point on the circle
+    pub fn get_point(&self) -> Point {
+        self.point_at(random::random() * 2.0 * PI)
+    }
+
+    /// Return the point on the circle at an angle in radians, counterclockwise from the positive x-axis
+    pub fn point_at(&self, angle: f64) -> Point {
+        Point {
+            x: self.center.x + self.radius * angle.cos(),
+            y: self.center.y + self.radius * angle.sin(),
+        }
+    }
+
+    /// Check if a point is on the circle
+    pub fn is_point_on_circle(&self, point: Point) -> bool {
+        (distance(point, self.center) - self.radius).abs() < tolerance()
+    }
+
+    /// Return the point on a specified arc from a given angle
+    pub fn get_point_on_arc(
+        &self,
+        start: Point,
+        end: Point,
+        deg: f64,
+    ) -> Result<Point, ElementsError> {
+        // ensure that the points are on the circle
+        if !self.is_point_on_circle(start) || !self.is_point_on_circle(end) {
+            return Err(ElementsError::Geometry(
+                "Points are not on the circle".to_string(),
+            ));
+        }
+
+        // initialize variables
+        let angle = deg.to_radians();
+        let center = self.center;
+        let radius = self.radius;
+
+        // calculate angles from center to start and end points
+        let start_angle = (start.y - center.y).atan2(start.x - center.x);
+        let end_angle = (end.y - center.y).atan2(end.x - center.x);
+
+        // normalize angles to be between 0 and 2PI
+        let start_angle = if start_angle < 0.0 {
             start_angle + 2.0 * PI
         } else {
             start_angle
@@ -226,6 +1255,204 @@ impl Circle {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ellipse {
+    pub center: Point,
+    pub rx: f64,
+    pub ry: f64,
+    pub rotation: f64,
+}
+
+impl Element for Ellipse {
+    /// Turn ellipse into a SVG element
+    fn to_svg(&self) -> Vec<Box<dyn Render>> {
+        vec![Box::new(SvgEllipse {
+            center: self.center,
+            rx: self.rx,
+            ry: self.ry,
+            rotation: self.rotation,
+            style: Style::default(),
+        })]
+    }
+}
+
+impl Transformable for Ellipse {
+    /// Transform the center, scale the radii, and rotate the axes of the ellipse
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Ellipse {
+            center: t.apply(self.center),
+            rx: self.rx * t.scale(),
+            ry: self.ry * t.scale(),
+            rotation: self.rotation + t.rotation_degrees(),
+        })
+    }
+}
+
+/// A parabola given by its focus and directrix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parabola {
+    pub focus: Point,
+    pub directrix: Line,
+}
+
+impl Parabola {
+    /// Create a new parabola given a focus and a directrix not passing through it
+    pub fn new(focus: Point, directrix: Line) -> Result<Self, ElementsError> {
+        if directrix.signed_distance(&focus).abs() < tolerance() {
+            return Err(ElementsError::Geometry(
+                "Focus must not lie on the directrix".to_string(),
+            ));
+        }
+        Ok(Self { focus, directrix })
+    }
+
+    /// Return the vertex, halfway between the focus and the directrix
+    pub fn vertex(&self) -> Point {
+        midpoint(self.focus, self.directrix.project(&self.focus))
+    }
+
+    /// Return the distance from the vertex to the focus
+    pub fn focal_length(&self) -> f64 {
+        self.directrix.signed_distance(&self.focus).abs() / 2.0
+    }
+}
+
+impl Element for Parabola {
+    /// Turn parabola into a SVG element, traced across the viewbox when rendered
+    fn to_svg(&self) -> Vec<Box<dyn Render>> {
+        // step along the directrix and away from it towards the focus
+        let vertex = self.vertex();
+        let f = self.focal_length();
+        let side = self.directrix.signed_distance(&self.focus).signum();
+        let (nx, ny) = (side * self.directrix.a, side * self.directrix.b);
+        let branch = move |t: f64| Point {
+            x: vertex.x - t * ny + t * t / (4.0 * f) * nx,
+            y: vertex.y + t * nx + t * t / (4.0 * f) * ny,
+        };
+
+        vec![Box::new(SvgCurve {
+            branches: vec![Box::new(branch)],
+            anchors: vec![vertex, self.focus],
+            path: None,
+            style: Style::default(),
+        })]
+    }
+}
+
+impl Transformable for Parabola {
+    /// Transform the focus and the directrix
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Parabola {
+            focus: t.apply(self.focus),
+            directrix: self.directrix.transform(t)?,
+        })
+    }
+}
+
+/// A hyperbola given by its foci and the semi-major axis a, half the constant difference of distances
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hyperbola {
+    pub first: Point,
+    pub second: Point,
+    pub a: f64,
+}
+
+impl Hyperbola {
+    /// Create a new hyperbola given its foci and the difference of distances to them
+    pub fn new(first: Point, second: Point, difference: f64) -> Result<Self, ElementsError> {
+        let between = (second.x - first.x).hypot(second.y - first.y);
+        if difference <= 0.0 || difference >= between {
+            return Err(ElementsError::Geometry("Difference of distances must be positive and less than the distance between the foci"
+                    .to_string()));
+        }
+        Ok(Self {
+            first,
+            second,
+            a: difference / 2.0,
+        })
+    }
+
+    /// Return the semi-minor axis of the hyperbola
+    pub fn b(&self) -> f64 {
+        let c = (self.second.x - self.first.x).hypot(self.second.y - self.first.y) / 2.0;
+        (c * c - self.a * self.a).sqrt()
+    }
+}
+
+impl Element for Hyperbola {
+    /// Turn hyperbola into a SVG element, traced across the viewbox when rendered
+    fn to_svg(&self) -> Vec<Box<dyn Render>> {
+        // use the axis through the foci and its perpendicular
+        let center = midpoint(self.first, self.second);
+        let length = (self.second.x - self.first.x).hypot(self.second.y - self.first.y);
+        let (ex, ey) = (
+            (self.second.x - self.first.x) / length,
+            (self.second.y - self.first.y) / length,
+        );
+        let (a, b) = (self.a, self.b());
+
+        // trace one branch around each focus
+        let branches: Vec<Box<dyn Fn(f64) -> Point>> = [1.0, -1.0]
+            .into_iter()
+            .map(|side: f64| {
+                Box::new(move |t: f64| {
+                    let u = side * a * t.cosh();
+                    let v = b * t.sinh();
+                    Point {
+                        x: center.x + u * ex - v * ey,
+                        y: center.y + u * ey + v * ex,
+                    }
+                }) as Box<dyn Fn(f64) -> Point>
+            })
+            .collect();
+
+        let vertex = |side: f64| Point {
+            x: center.x + side * a * ex,
+            y: center.y + side * a * ey,
+        };
+        vec![Box::new(SvgCurve {
+            branches,
+            anchors: vec![self.first, self.second, vertex(1.0), vertex(-1.0)],
+            path: None,
+            style: Style::default(),
+        })]
+    }
+}
+
+impl Transformable for Hyperbola {
+    /// Transform the foci and scale the semi-major axis
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Hyperbola {
+            first: t.apply(self.first),
+            second: t.apply(self.second),
+            a: self.a * t.scale(),
+        })
+    }
+}
+
+impl Ellipse {
+    /// Create a new ellipse given a center, radii, and a rotation in degrees
+    pub fn new(center: Point, rx: f64, ry: f64, rotation: f64) -> Result<Self, ElementsError> {
+        // check for negative radii
+        if rx < 0.0 || ry < 0.0 {
+            return Err(ElementsError::Geometry("Radius is negative".to_string()));
+        }
+
+        // otherwise, return the ellipse
+        Ok(Self {
+            center,
+            rx,
+            ry,
+            rotation,
+        })
+    }
+
+    /// Return the area of the ellipse
+    pub fn area(&self) -> f64 {
+        PI * self.rx * self.ry
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Triangle {
     pub a: Point,
@@ -238,22 +1465,66 @@ impl Element for Triangle {
     fn to_svg(&self) -> Vec<Box<dyn Render>> {
         vec![Box::new(SvgPolygon {
             points: vec![self.a, self.b, self.c],
+            style: Style::default(),
         })]
     }
 }
 
+impl Transformable for Triangle {
+    /// Transform the three vertices of the triangle
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Triangle {
+            a: t.apply(self.a),
+            b: t.apply(self.b),
+            c: t.apply(self.c),
+        })
+    }
+}
+
 impl Triangle {
     /// Create a new triangle given three points
-    pub fn new(a: Point, b: Point, c: Point) -> Result<Self, String> {
-        // check for collinear points
-        if (a.x - b.x) * (a.y - c.y) == (a.x - c.x) * (a.y - b.y) {
-            return Err("Points are collinear".to_string());
+    pub fn new(a: Point, b: Point, c: Point) -> Result<Self, ElementsError> {
+        // check for collinear points, where the third is within the tolerance of the line through the others
+        let cross = (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y);
+        if cross.abs() <= tolerance() * distance(a, b) {
+            return Err(ElementsError::Geometry("Points are collinear".to_string()));
         }
 
         // otherwise, return the triangle
         Ok(Self { a, b, c })
     }
 
+    /// Return the other two vertices, in order, given one vertex of the triangle
+    pub fn others(&self, vertex: Point) -> Result<(Point, Point), ElementsError> {
+        let close = |p: Point| (p.x - vertex.x).hypot(p.y - vertex.y) < tolerance();
+        if close(self.a) {
+            Ok((self.b, self.c))
+        } else if close(self.b) {
+            Ok((self.c, self.a))
+        } else if close(self.c) {
+            Ok((self.a, self.b))
+        } else {
+            Err(ElementsError::Geometry(
+                "Point is not a vertex of the triangle".to_string(),
+            ))
+        }
+    }
+
+    /// Return the area of the triangle
+    pub fn area(&self) -> f64 {
+        ((self.b.x - self.a.x) * (self.c.y - self.a.y)
+            - (self.c.x - self.a.x) * (self.b.y - self.a.y))
+            .abs()
+            / 2.0
+    }
+
+    /// Return the perimeter of the triangle
+    pub fn perimeter(&self) -> f64 {
+        (self.a.x - self.b.x).hypot(self.a.y - self.b.y)
+            + (self.b.x - self.c.x).hypot(self.b.y - self.c.y)
+            + (self.c.x - self.a.x).hypot(self.c.y - self.a.y)
+    }
+
     /// Return the inradius of the triangle
     pub fn inradius(&self) -> f64 {
         // calculate the side lengths
@@ -268,8 +1539,172 @@ impl Triangle {
         (s * (s - a) * (s - b) * (s - c)).sqrt() / s
     }
 
+    /// Return the incircle of the triangle
+    pub fn incircle(&self) -> Circle {
+        Circle {
+            center: self.incenter(),
+            radius: self.inradius(),
+        }
+    }
+
+    /// Return the circumcircle of the triangle
+    pub fn circumcircle(&self) -> Circle {
+        let center = self.circumcenter();
+        Circle {
+            center,
+            radius: (self.a.x - center.x).hypot(self.a.y - center.y),
+        }
+    }
+
+    /// Return the circumradius of the triangle
+    pub fn circumradius(&self) -> f64 {
+        self.circumcircle().radius
+    }
+
+    /// Return the center of the excircle opposite the given vertex
+    pub fn excenter(&self, vertex: Point) -> Result<Point, ElementsError> {
+        let (q, r) = self.others(vertex)?;
+
+        // weight each vertex by the length of its opposite side, negating the given vertex
+        let a = (q.x - r.x).hypot(q.y - r.y);
+        let b = (vertex.x - r.x).hypot(vertex.y - r.y);
+        let c = (vertex.x - q.x).hypot(vertex.y - q.y);
+        let w = -a + b + c;
+        Ok(Point {
+            x: (-a * vertex.x + b * q.x + c * r.x) / w,
+            y: (-a * vertex.y + b * q.y + c * r.y) / w,
+        })
+    }
+
+    /// Return the excircle opposite the given vertex
+    pub fn excircle(&self, vertex: Point) -> Result<Circle, ElementsError> {
+        let (q, r) = self.others(vertex)?;
+
+        // the exradius is the area divided by the semiperimeter less the opposite side
+        let a = (q.x - r.x).hypot(q.y - r.y);
+        let s = self.perimeter() / 2.0;
+        Ok(Circle {
+            center: self.excenter(vertex)?,
+            radius: self.area() / (s - a),
+        })
+    }
+
+    /// Return the mixtilinear incircle at the given vertex, tangent to its two sides and internally to the circumcircle
+    pub fn mixtilinear(&self, vertex: Point) -> Result<Circle, ElementsError> {
+        let (q, r) = self.others(vertex)?;
+
+        // find the half angle at the vertex from the side lengths
+        let a = (q.x - r.x).hypot(q.y - r.y);
+        let b = (vertex.x - r.x).hypot(vertex.y - r.y);
+        let c = (vertex.x - q.x).hypot(vertex.y - q.y);
+        let half = ((b * b + c * c - a * a) / (2.0 * b * c)).acos() / 2.0;
+
+        // the radius is the inradius over the squared cosine of the half angle
+        let radius = self.inradius() / half.cos().powi(2);
+
+        // the center lies along the angle bisector, which passes through the incenter
+        let i = self.incenter();
+        let along = (i.x - vertex.x).hypot(i.y - vertex.y);
+        let t = radius / half.sin() / along;
+        Ok(Circle {
+            center: Point {
+                x: vertex.x + t * (i.x - vertex.x),
+                y: vertex.y + t * (i.y - vertex.y),
+            },
+            radius,
+        })
+    }
+
+    /// Return the nine-point circle of the triangle
+    pub fn nine_point_circle(&self) -> Circle {
+        Circle {
+            center: midpoint(self.circumcenter(), self.orthocenter()),
+            radius: self.circumradius() / 2.0,
+        }
+    }
+
+    /// Return the side midpoints, altitude feet, and vertex-orthocenter midpoints of the triangle
+    pub fn nine_points(&self) -> Vec<Point> {
+        let h = self.orthocenter();
+        let sides = [
+            (self.a, self.b, self.c),
+            (self.b, self.c, self.a),
+            (self.c, self.a, self.b),
+        ];
+
+        // the foot of the altitude from p onto the side qr
+        let foot =
+            |p: Point, q: Point, r: Point| closest_point(p, q, r, f64::NEG_INFINITY, f64::INFINITY);
+
+        let mut points: Vec<Point> = sides.iter().map(|(_, q, r)| midpoint(*q, *r)).collect();
+        points.extend(sides.iter().map(|(p, q, r)| foot(*p, *q, *r)));
+        points.extend(sides.iter().map(|(p, _, _)| midpoint(*p, h)));
+        points
+    }
+
+    /// Return the medial triangle, whose vertices are the midpoints of the sides
+    pub fn medial(&self) -> Triangle {
+        Triangle {
+            a: midpoint(self.b, self.c),
+            b: midpoint(self.c, self.a),
+            c: midpoint(self.a, self.b),
+        }
+    }
+
+    /// Return the orthic triangle, whose vertices are the feet of the altitudes
+    pub fn orthic(&self) -> Result<Triangle, ElementsError> {
+        // a right triangle has two feet at the right angle, so there is no triangle
+        let points = self.nine_points();
+        Triangle::new(points[3], points[4], points[5]).map_err(|_| {
+            ElementsError::Geometry(
+                "Orthic triangle is degenerate for a right triangle".to_string(),
+            )
+        })
+    }
+
+    /// Return the contact triangle, whose vertices are where the incircle touches the sides
+    pub fn contact(&self) -> Triangle {
+        // project the incenter onto each side, opposite a, b, and c in turn
+        let i = self.incenter();
+        let touch = |q: Point, r: Point| closest_point(i, q, r, f64::NEG_INFINITY, f64::INFINITY);
+        Triangle {
+            a: touch(self.b, self.c),
+            b: touch(self.c, self.a),
+            c: touch(self.a, self.b),
+        }
+    }
+
+    /// Return the pedal triangle of a point, whose vertices are the feet of the perpendiculars to the sides
+    pub fn pedal(&self, p: Point) -> Result<Triangle, ElementsError> {
+        let foot = |q: Point, r: Point| closest_point(p, q, r, f64::NEG_INFINITY, f64::INFINITY);
+        let degenerate = || {
+            ElementsError::Geometry(
+                "Pedal triangle is degenerate for a point on the circumcircle".to_string(),
+            )
+        };
+
+        // the feet are collinear exactly when the point is on the circumcircle, which is checked directly
+        // since feet computed from a point only close to it are not quite collinear
+        let circumcircle = self.circumcircle();
+        let d = distance(p, circumcircle.center);
+        if is_near(d, circumcircle.radius, circumcircle.radius) {
+            return Err(degenerate());
+        }
+        Triangle::new(
+            foot(self.b, self.c),
+            foot(self.c, self.a),
+            foot(self.a, self.b),
+        )
+        .map_err(|_| degenerate())
+    }
+
     /// Return the incenter of the triangle
     pub fn incenter(&self) -> Point {
+        #[cfg(feature = "high-precision")]
+        if precise::is_enabled() {
+            return precise::incenter(self.a, self.b, self.c);
+        }
+
         // calculate the side lengths
         let a = (self.b.x - self.c.x).hypot(self.b.y - self.c.y);
         let b = (self.a.x - self.c.x).hypot(self.a.y - self.c.y);
@@ -284,19 +1719,17 @@ impl Triangle {
 
     /// Return the orthocenter of the triangle
     pub fn orthocenter(&self) -> Point {
-        // calculate the slopes of the sides
-        let m1: f64 = (self.b.y - self.a.y) / (self.b.x - self.a.x);
-        let m2: f64 = (self.c.y - self.b.y) / (self.c.x - self.b.x);
-
-        // calculate the perpendicular slopes
-        let p1 = -1.0 / m1;
-        let p2 = -1.0 / m2;
-
-        // calculate the coordinates of the orthocenter
-        let x = (-p1 * self.c.x + p2 * self.a.x + self.c.y - self.a.y) / (p2 - p1);
-        let y = p1 * (x - self.c.x) + self.c.y;
+        #[cfg(feature = "high-precision")]
+        if precise::is_enabled() {
+            return precise::orthocenter(self.a, self.b, self.c);
+        }
 
-        Point { x, y }
+        // the orthocenter is the reflection of the circumcenter through the centroid, scaled by two
+        let o = self.circumcenter();
+        Point {
+            x: self.a.x + self.b.x + self.c.x - 2.0 * o.x,
+            y: self.a.y + self.b.y + self.c.y - 2.0 * o.y,
+        }
     }
 
     /// Return the centroid of the triangle
@@ -307,30 +1740,470 @@ impl Triangle {
         }
     }
 
+    /// Return the side lengths opposite a, b, and c
+    fn side_lengths(&self) -> (f64, f64, f64) {
+        (
+            (self.b.x - self.c.x).hypot(self.b.y - self.c.y),
+            (self.a.x - self.c.x).hypot(self.a.y - self.c.y),
+            (self.a.x - self.b.x).hypot(self.a.y - self.b.y),
+        )
+    }
+
+    /// Return the point with the given barycentric weights on a, b, and c
+    fn barycentric(&self, wa: f64, wb: f64, wc: f64) -> Point {
+        let w = wa + wb + wc;
+        Point {
+            x: (wa * self.a.x + wb * self.b.x + wc * self.c.x) / w,
+            y: (wa * self.a.y + wb * self.b.y + wc * self.c.y) / w,
+        }
+    }
+
+    /// Return the symmedian point of the triangle
+    pub fn symmedian_point(&self) -> Point {
+        let (a, b, c) = self.side_lengths();
+        self.barycentric(a * a, b * b, c * c)
+    }
+
+    /// Return the Gergonne point of the triangle, where the lines to the incircle's touch points meet
+    pub fn gergonne_point(&self) -> Point {
+        let (a, b, c) = self.side_lengths();
+        let s = (a + b + c) / 2.0;
+        self.barycentric(1.0 / (s - a), 1.0 / (s - b), 1.0 / (s - c))
+    }
+
+    /// Return the Nagel point of the triangle, where the lines to the excircles' touch points meet
+    pub fn nagel_point(&self) -> Point {
+        let (a, b, c) = self.side_lengths();
+        let s = (a + b + c) / 2.0;
+        self.barycentric(s - a, s - b, s - c)
+    }
+
+    /// Return the first or second Brocard point of the triangle
+    pub fn brocard_point(&self, index: i64) -> Result<Point, ElementsError> {
+        let (a, b, c) = self.side_lengths();
+        let (a2, b2, c2) = (a * a, b * b, c * c);
+        match index {
+            1 => Ok(self.barycentric(a2 * c2, a2 * b2, b2 * c2)),
+            2 => Ok(self.barycentric(a2 * b2, b2 * c2, a2 * c2)),
+            _ => Err(ElementsError::Type(
+                "Brocard index must be either 1 or 2".to_string(),
+            )),
+        }
+    }
+
+    /// Return the Fermat point of the triangle, which minimizes the total distance to the vertices
+    pub fn fermat_point(&self) -> Point {
+        // a vertex with an angle of at least 120 degrees is the Fermat point itself
+        let (a, b, c) = self.side_lengths();
+        for (vertex, opposite, x, y) in [(self.a, a, b, c), (self.b, b, c, a), (self.c, c, a, b)] {
+            if opposite * opposite >= x * x + y * y + x * y {
+                return vertex;
+            }
+        }
+
+        // otherwise, weight each vertex by its opposite side over the sine of its angle plus 60 degrees
+        let weight = |opposite: f64, x: f64, y: f64| {
+            let angle = ((x * x + y * y - opposite * opposite) / (2.0 * x * y)).acos();
+            opposite / (angle + PI / 3.0).sin()
+        };
+        self.barycentric(weight(a, b, c), weight(b, c, a), weight(c, a, b))
+    }
+
     /// Return the circumcenter of the triangle
     pub fn circumcenter(&self) -> Point {
-        // calculate the midpoints of the sides
-        let m1 = Point {
-            x: (self.a.x + self.b.x) / 2.0,
-            y: (self.a.y + self.b.y) / 2.0,
+        #[cfg(feature = "high-precision")]
+        if precise::is_enabled() {
+            return precise::circumcenter(self.a, self.b, self.c);
+        }
+
+        // translate so that a is the origin, then use the closed form
+        let bx = self.b.x - self.a.x;
+        let by = self.b.y - self.a.y;
+        let cx = self.c.x - self.a.x;
+        let cy = self.c.y - self.a.y;
+        let d = 2.0 * (bx * cy - by * cx);
+        let b2 = bx * bx + by * by;
+        let c2 = cx * cx + cy * cy;
+
+        // calculate the circumcenter
+        let x = self.a.x + (cy * b2 - by * c2) / d;
+        let y = self.a.y + (bx * c2 - cx * b2) / d;
+
+        Point { x, y }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    pub points: Vec<Point>,
+}
+
+impl Element for Polygon {
+    /// Turn polygon into a SVG element
+    fn to_svg(&self) -> Vec<Box<dyn Render>> {
+        vec![Box::new(SvgPolygon {
+            points: self.points.clone(),
+            style: Style::default(),
+        })]
+    }
+}
+
+impl Transformable for Polygon {
+    /// Transform every vertex of the polygon
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Polygon {
+            points: self.points.iter().map(|p| t.apply(*p)).collect(),
+        })
+    }
+}
+
+impl Polygon {
+    /// Create a new polygon given its vertices in order
+    pub fn new(points: Vec<Point>) -> Result<Self, ElementsError> {
+        // check for enough vertices
+        if points.len() < 3 {
+            return Err(ElementsError::Arity(
+                "Polygon requires at least 3 points".to_string(),
+            ));
+        }
+
+        // otherwise, return the polygon
+        Ok(Self { points })
+    }
+
+    /// Return the signed area of the polygon using the shoelace formula
+    fn signed_area(&self) -> f64 {
+        let n = self.points.len();
+        let mut sum = 0.0;
+        for i in 0..n {
+            let p = self.points[i];
+            let q = self.points[(i + 1) % n];
+            sum += p.x * q.y - q.x * p.y;
+        }
+        sum / 2.0
+    }
+
+    /// Return the area of the polygon
+    pub fn area(&self) -> f64 {
+        self.signed_area().abs()
+    }
+
+    /// Return the perimeter of the polygon
+    pub fn perimeter(&self) -> f64 {
+        let n = self.points.len();
+        (0..n)
+            .map(|i| {
+                let p = self.points[i];
+                let q = self.points[(i + 1) % n];
+                (p.x - q.x).hypot(p.y - q.y)
+            })
+            .sum()
+    }
+
+    /// Return the centroid of the region enclosed by the polygon
+    pub fn centroid(&self) -> Point {
+        let n = self.points.len();
+        let area = self.signed_area();
+
+        // fall back to the average of the vertices for degenerate polygons
+        if area.abs() < tolerance() {
+            return Point {
+                x: self.points.iter().map(|p| p.x).sum::<f64>() / n as f64,
+                y: self.points.iter().map(|p| p.y).sum::<f64>() / n as f64,
+            };
+        }
+
+        let mut x = 0.0;
+        let mut y = 0.0;
+        for i in 0..n {
+            let p = self.points[i];
+            let q = self.points[(i + 1) % n];
+            let cross = p.x * q.y - q.x * p.y;
+            x += (p.x + q.x) * cross;
+            y += (p.y + q.y) * cross;
+        }
+        Point {
+            x: x / (6.0 * area),
+            y: y / (6.0 * area),
+        }
+    }
+
+    /// Return the vertex at the given index
+    pub fn vertex(&self, index: i64) -> Result<Point, ElementsError> {
+        if index < 0 || index as usize >= self.points.len() {
+            return Err(ElementsError::Type(format!(
+                "Vertex index must be between 0 and {}",
+                self.points.len() - 1
+            )));
+        }
+        Ok(self.points[index as usize])
+    }
+}
+
+/*
+Shaded regions
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub contours: Vec<Vec<Point>>,
+}
+
+impl Region {
+    /// Number of sides used to approximate a full circle or ellipse
+    const CURVE_STEPS: usize = 180;
+
+    /// Return the region inside a closed shape, approximating curved boundaries with polygons
+    pub fn from_value(value: &Value) -> Result<Self, ElementsError> {
+        let steps = Self::CURVE_STEPS;
+        let contour = match value {
+            Value::Region(r) => return Ok(r.clone()),
+            Value::Triangle(t) => vec![t.a, t.b, t.c],
+            Value::Polygon(p) => p.points.clone(),
+            Value::Circle(c) => (0..steps)
+                .map(|i| {
+                    let angle = 2.0 * PI * i as f64 / steps as f64;
+                    Point {
+                        x: c.center.x + c.radius * angle.cos(),
+                        y: c.center.y + c.radius * angle.sin(),
+                    }
+                })
+                .collect(),
+            Value::Ellipse(e) => {
+                let rotation = e.rotation.to_radians();
+                (0..steps)
+                    .map(|i| {
+                        let angle = 2.0 * PI * i as f64 / steps as f64;
+                        let (x, y) = (e.rx * angle.cos(), e.ry * angle.sin());
+                        Point {
+                            x: e.center.x + x * rotation.cos() - y * rotation.sin(),
+                            y: e.center.y + x * rotation.sin() + y * rotation.cos(),
+                        }
+                    })
+                    .collect()
+            }
+            _ => {
+                return Err(ElementsError::Type(
+                    "Value does not enclose a region".to_string(),
+                ))
+            }
         };
-        let m2 = Point {
-            x: (self.b.x + self.c.x) / 2.0,
-            y: (self.b.y + self.c.y) / 2.0,
+        Ok(Region {
+            contours: vec![contour],
+        })
+    }
+
+    /// Return the circular segment between an arc and its chord
+    pub fn segment(arc: &Arc) -> Self {
+        let steps =
+            ((arc.sweep.abs() / (2.0 * PI) * Self::CURVE_STEPS as f64).ceil() as usize).max(1);
+        let contour = (0..=steps)
+            .map(|i| arc.point_at(arc.start + arc.sweep * i as f64 / steps as f64))
+            .collect();
+        Region {
+            contours: vec![contour],
+        }
+    }
+
+    /// Return the region covered by both regions
+    pub fn intersection(&self, other: &Region) -> Self {
+        Region {
+            contours: combine_contours(&self.contours, &other.contours, false),
+        }
+    }
+
+    /// Return the region covered by either region
+    pub fn union(&self, other: &Region) -> Self {
+        Region {
+            contours: combine_contours(&self.contours, &other.contours, true),
+        }
+    }
+}
+
+impl Element for Region {
+    /// Turn region into a SVG element
+    fn to_svg(&self) -> Vec<Box<dyn Render>> {
+        let mut commands: Vec<PathCommand> = Vec::new();
+        for contour in &self.contours {
+            for (i, point) in contour.iter().enumerate() {
+                if i == 0 {
+                    commands.push(PathCommand::Move(*point));
+                } else {
+                    commands.push(PathCommand::Line(*point));
+                }
+            }
+            commands.push(PathCommand::Close);
+        }
+        vec![Box::new(SvgPath {
+            commands,
+            fill: true,
+            // shaded regions are translucent by default so that overlaps stay readable
+            style: Style {
+                fill_opacity: Some(0.5),
+                ..Style::default()
+            },
+        })]
+    }
+}
+
+/// A quadratic or cubic Bezier curve given by its start, control, and end points
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bezier {
+    pub points: Vec<Point>,
+}
+
+impl Bezier {
+    /// Create a new Bezier curve from three points for a quadratic or four for a cubic
+    pub fn new(points: Vec<Point>) -> Result<Self, ElementsError> {
+        if points.len() != 3 && points.len() != 4 {
+            return Err(ElementsError::Arity(
+                "Bezier requires 3 or 4 points".to_string(),
+            ));
+        }
+        Ok(Self { points })
+    }
+}
+
+impl Element for Bezier {
+    /// Turn Bezier curve into a SVG element
+    fn to_svg(&self) -> Vec<Box<dyn Render>> {
+        let p = &self.points;
+        let curve = match p.len() {
+            3 => PathCommand::Quadratic(p[1], p[2]),
+            _ => PathCommand::Cubic(p[1], p[2], p[3]),
         };
+        vec![Box::new(SvgPath {
+            commands: vec![PathCommand::Move(p[0]), curve],
+            fill: false,
+            style: Style::default(),
+        })]
+    }
+}
 
-        // calculate the slopes of the sides
-        let s1 = (self.b.y - self.a.y) / (self.b.x - self.a.x);
-        let s2 = (self.c.y - self.b.y) / (self.c.x - self.b.x);
+impl Transformable for Bezier {
+    /// Transform every control point, which transforms the curve with them
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Bezier {
+            points: self.points.iter().map(|p| t.apply(*p)).collect(),
+        })
+    }
+}
 
-        // calculate the perpendicular slopes
-        let p1 = -1.0 / s1;
-        let p2 = -1.0 / s2;
+/// A fragment of SVG written to the output as given, for visuals that cannot be built from other values
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawSvg {
+    pub svg: String,
+    /// Opposite corners of the area the fragment covers, if known, which the figure is framed to contain
+    pub extents: Option<(Point, Point)>,
+}
 
-        // calculate the circumcenter
-        let x = (m2.y - m1.y + p1 * m1.x - p2 * m2.x) / (p1 - p2);
-        let y = p1 * (x - m1.x) + m1.y;
+impl RawSvg {
+    /// Create a new fragment of SVG, which must be well-formed and must not run scripts, so that a figure
+    /// cannot break out of the element it is written in or run code where the output is shown
+    pub fn new(svg: String, extents: Option<(Point, Point)>) -> Result<Self, ElementsError> {
+        // the fragment is read inside an element of its own, so closing tags without opening ones are errors
+        let wrapped = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\">{}</svg>",
+            svg
+        );
+        let document = roxmltree::Document::parse(&wrapped).map_err(|e| {
+            ElementsError::Type(format!(
+                "Raw-svg requires a well-formed fragment of SVG: {}",
+                e
+            ))
+        })?;
+        for node in document
+            .root_element()
+            .descendants()
+            .filter(|n| n.is_element())
+        {
+            let name = node.tag_name().name();
+            if name.eq_ignore_ascii_case("script") || name.eq_ignore_ascii_case("foreignObject") {
+                return Err(ElementsError::Type(format!(
+                    "Raw-svg fragments cannot contain {} elements",
+                    name
+                )));
+            }
+            for attribute in node.attributes() {
+                // links and animations can run scripts through javascript: urls in any attribute they set
+                let value: String = attribute
+                    .value()
+                    .chars()
+                    .filter(|c| !c.is_whitespace() && !c.is_control())
+                    .collect();
+                let is_handler = attribute.name().to_ascii_lowercase().starts_with("on");
+                if is_handler || value.to_ascii_lowercase().contains("javascript:") {
+                    return Err(ElementsError::Type(format!(
+                        "Raw-svg fragments cannot run scripts, as the {} attribute of {} would",
+                        attribute.name(),
+                        name
+                    )));
+                }
+            }
+        }
+        Ok(Self { svg, extents })
+    }
+}
 
-        Point { x, y }
+impl Element for RawSvg {
+    /// Turn the fragment into a SVG element that writes it unchanged
+    fn to_svg(&self) -> Vec<Box<dyn Render>> {
+        vec![Box::new(SvgRaw {
+            svg: self.svg.clone(),
+            extents: self.extents,
+        })]
+    }
+}
+
+/// A sampled curve, split into pieces wherever a sample could not be evaluated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locus {
+    pub runs: Vec<Vec<Point>>,
+}
+
+impl Element for Locus {
+    /// Turn locus into a SVG element
+    fn to_svg(&self) -> Vec<Box<dyn Render>> {
+        let mut commands: Vec<PathCommand> = Vec::new();
+        for run in &self.runs {
+            for (i, point) in run.iter().enumerate() {
+                if i == 0 {
+                    commands.push(PathCommand::Move(*point));
+                } else {
+                    commands.push(PathCommand::Line(*point));
+                }
+            }
+        }
+        vec![Box::new(SvgPath {
+            commands,
+            fill: false,
+            style: Style::default(),
+        })]
+    }
+}
+
+impl Transformable for Locus {
+    /// Transform every sampled point of the locus
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Locus {
+            runs: self
+                .runs
+                .iter()
+                .map(|run| run.iter().map(|p| t.apply(*p)).collect())
+                .collect(),
+        })
+    }
+}
+
+impl Transformable for Region {
+    /// Transform every point of the region boundary
+    fn transform(&self, t: &Transform) -> Result<Self, ElementsError> {
+        Ok(Region {
+            contours: self
+                .contours
+                .iter()
+                .map(|contour| contour.iter().map(|p| t.apply(*p)).collect())
+                .collect(),
+        })
     }
 }
@@ -50,6 +50,136 @@ impl PartialEq for Function {
     }
 }
 
+/// The name of every function, including the special forms evaluated before their arguments, in the order
+/// match_fn knows them
+pub const FUNCTION_NAMES: [&str; 120] = [
+    "+",
+    "-",
+    "*",
+    "/",
+    "<",
+    ">",
+    "<=",
+    ">=",
+    "=",
+    "collinear?",
+    "concyclic?",
+    "parallel?",
+    "perpendicular?",
+    "on?",
+    "concurrent?",
+    "assert",
+    "claim",
+    "list",
+    "setq",
+    "param",
+    "angle",
+    "iangle",
+    "mark-angle",
+    "point",
+    "free-point",
+    "midpoint",
+    "lerp",
+    "divide-segment",
+    "distance",
+    "project",
+    "closest-point",
+    "tangent-circle",
+    "tangent-circle-at",
+    "lineseg",
+    "line",
+    "ray",
+    "circumcenter",
+    "incenter",
+    "orthocenter",
+    "centroid",
+    "fermat-point",
+    "symmedian-point",
+    "gergonne",
+    "nagel",
+    "brocard",
+    "intersect",
+    "inradius",
+    "circumradius",
+    "median",
+    "cevian",
+    "euler-line",
+    "nine-point-circle",
+    "nine-points",
+    "excenter",
+    "excircle",
+    "medial",
+    "orthic",
+    "contact",
+    "pedal",
+    "mixtilinear",
+    "area",
+    "perimeter",
+    "degrees",
+    "incircle",
+    "circumcircle",
+    "power",
+    "circle",
+    "ellipse",
+    "parabola",
+    "hyperbola",
+    "bezier",
+    "raw-svg",
+    "triangle",
+    "polygon",
+    "vertex",
+    "convex-hull",
+    "equilateral",
+    "square-on",
+    "rectangle",
+    "parallelogram",
+    "rotate",
+    "translate",
+    "invert",
+    "polar",
+    "pole",
+    "spiral-similarity",
+    "spiral-center",
+    "region-where",
+    "locus",
+    "intersection",
+    "union",
+    "segment",
+    "shade",
+    "opacity",
+    "blend",
+    "color",
+    "style",
+    "define-style",
+    "fill",
+    "hatch",
+    "label",
+    "annotate-length",
+    "annotate-angle",
+    "layer",
+    "hide",
+    "no-bounds",
+    "point-x",
+    "point-y",
+    "circle-center",
+    "circle-radius",
+    "seg-start",
+    "seg-end",
+    "viewbox",
+    "grid",
+    "legend",
+    "figure",
+    "export-coords",
+    "set-tolerance",
+    "lambda",
+    "solve",
+];
+
+/// Return whether a name is the name of a function
+pub fn is_function(name: &str) -> bool {
+    FUNCTION_NAMES.contains(&name)
+}
+
 /// Given the name of a function, return the appropriate function struct
 fn match_fn(name: String) -> Function {
     match name.as_str() {
@@ -75,12 +205,95 @@ fn match_fn(name: String) -> Function {
             function: Box::new(functions::FnDiv),
         },
 
+        // comparison functions
+        "<" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnLess),
+        },
+        ">" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnGreater),
+        },
+        "<=" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnLessEqual),
+        },
+        ">=" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnGreaterEqual),
+        },
+        "=" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnEqual),
+        },
+
+        // geometric predicates
+        "collinear?" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnCollinear),
+        },
+        "concyclic?" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnConcyclic),
+        },
+        "parallel?" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnParallel),
+        },
+        "perpendicular?" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnPerpendicular),
+        },
+        "on?" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnOn),
+        },
+        "concurrent?" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnConcurrent),
+        },
+
+        // checks of facts about the figure
+        "assert" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnAssert),
+        },
+        "claim" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnClaim),
+        },
+
+        // list function
+        "list" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnList),
+        },
+
         // setq function
         "setq" => Function {
             name,
             args: Vec::new(),
             function: Box::new(functions::FnSet),
         },
+        "param" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnParam),
+        },
 
         // basic geometric components
         "angle" => Function {
@@ -93,21 +306,76 @@ fn match_fn(name: String) -> Function {
             args: Vec::new(),
             function: Box::new(functions::FnInscribedAngle),
         },
+        "mark-angle" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnMarkAngle),
+        },
         "point" => Function {
             name,
             args: Vec::new(),
             function: Box::new(functions::FnPoint),
         },
+        "free-point" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnFreePoint),
+        },
         "midpoint" => Function {
             name,
             args: Vec::new(),
             function: Box::new(functions::FnMidpoint),
         },
+        "lerp" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnLerp),
+        },
+        "divide-segment" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnDivideSegment),
+        },
+        "distance" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnDistance),
+        },
+        "project" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnProject),
+        },
+        "closest-point" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnClosestPoint),
+        },
+        "tangent-circle" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnTangentCircle),
+        },
+        "tangent-circle-at" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnTangentCircleAt),
+        },
         "lineseg" => Function {
             name,
             args: Vec::new(),
             function: Box::new(functions::FnLineseg),
         },
+        "line" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnLine),
+        },
+        "ray" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnRay),
+        },
         "circumcenter" => Function {
             name,
             args: Vec::new(),
@@ -128,6 +396,31 @@ fn match_fn(name: String) -> Function {
             args: Vec::new(),
             function: Box::new(functions::FnCentroid),
         },
+        "fermat-point" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnFermatPoint),
+        },
+        "symmedian-point" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnSymmedianPoint),
+        },
+        "gergonne" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnGergonne),
+        },
+        "nagel" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnNagel),
+        },
+        "brocard" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnBrocard),
+        },
 
         // functions that return properties
         "intersect" => Function {
@@ -140,6 +433,101 @@ fn match_fn(name: String) -> Function {
             args: Vec::new(),
             function: Box::new(functions::FnInradius),
         },
+        "circumradius" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnCircumradius),
+        },
+        "median" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnMedian),
+        },
+        "cevian" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnCevian),
+        },
+        "euler-line" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnEulerLine),
+        },
+        "nine-point-circle" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnNinePointCircle),
+        },
+        "nine-points" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnNinePoints),
+        },
+        "excenter" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnExcenter),
+        },
+        "excircle" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnExcircle),
+        },
+        "medial" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnMedial),
+        },
+        "orthic" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnOrthic),
+        },
+        "contact" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnContact),
+        },
+        "pedal" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnPedal),
+        },
+        "mixtilinear" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnMixtilinear),
+        },
+        "area" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnArea),
+        },
+        "perimeter" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnPerimeter),
+        },
+        "degrees" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnDegrees),
+        },
+        "incircle" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnIncircle),
+        },
+        "circumcircle" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnCircumcircle),
+        },
+        "power" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnPower),
+        },
 
         // basic geometric functions
         "circle" => Function {
@@ -147,19 +535,289 @@ fn match_fn(name: String) -> Function {
             args: Vec::new(),
             function: Box::new(functions::FnCircle),
         },
+        "ellipse" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnEllipse),
+        },
+        "parabola" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnParabola),
+        },
+        "hyperbola" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnHyperbola),
+        },
+        "bezier" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnBezier),
+        },
+        "raw-svg" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnRawSvg),
+        },
         "triangle" => Function {
             name,
             args: Vec::new(),
             function: Box::new(functions::FnTriangle),
         },
+        "polygon" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnPolygon),
+        },
+        "vertex" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnVertex),
+        },
+        "convex-hull" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnConvexHull),
+        },
+        "equilateral" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnEquilateral),
+        },
+        "square-on" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnSquareOn),
+        },
+        "rectangle" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnRectangle),
+        },
+        "parallelogram" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnParallelogram),
+        },
+
+        // transformations
+        "rotate" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnRotate),
+        },
+        "translate" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnTranslate),
+        },
+        "invert" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnInvert),
+        },
+        "polar" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnPolar),
+        },
+        "pole" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnPole),
+        },
+        "spiral-similarity" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnSpiralSimilarity),
+        },
+        "spiral-center" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnSpiralCenter),
+        },
+
+        // shaded regions
+        "region-where" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnRegionWhere),
+        },
+        "locus" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnLocus),
+        },
+        "intersection" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnIntersection),
+        },
+        "union" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnUnion),
+        },
+        "segment" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnSegment),
+        },
+        "shade" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnShade),
+        },
+
+        // styling
+        "opacity" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnOpacity),
+        },
+        "blend" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnBlend),
+        },
+        "color" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnColor),
+        },
+        "style" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnStyle),
+        },
+        "define-style" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnDefineStyle),
+        },
+        "fill" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnFill),
+        },
+        "hatch" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnHatch),
+        },
+        "label" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnLabel),
+        },
+        "annotate-length" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnAnnotateLength),
+        },
+        "annotate-angle" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnAnnotateAngle),
+        },
+        "layer" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnLayer),
+        },
+        "hide" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnHide),
+        },
+        "no-bounds" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnNoBounds),
+        },
+
+        // accessor functions
+        "point-x" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnPointX),
+        },
+        "point-y" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnPointY),
+        },
+        "circle-center" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnCircleCenter),
+        },
+        "circle-radius" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnCircleRadius),
+        },
+        "seg-start" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnSegStart),
+        },
+        "seg-end" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnSegEnd),
+        },
+
+        // figure directives
+        "viewbox" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnViewbox),
+        },
+        "grid" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnGrid),
+        },
+        "legend" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnLegend),
+        },
+        "figure" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnFigure),
+        },
+        "export-coords" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnExportCoords),
+        },
+        "set-tolerance" => Function {
+            name,
+            args: Vec::new(),
+            function: Box::new(functions::FnSetTolerance),
+        },
         _ => Function {
             name,
             args: Vec::new(),
-            function: Box::new(functions::FnNop),
+            function: Box::new(functions::FnUnknown),
         },
     }
 }
 
+/// Given a token string, determine if it is a float literal such as 0.5, -2.25, or 1e-3
+fn is_float(token: &str) -> bool {
+    // rule out names such as inf and nan that rust would parse as floats
+    let digits = token.trim_start_matches(['-', '+']);
+    if !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return false;
+    }
+    token.parse::<f64>().is_ok_and(|f| f.is_finite())
+}
+
 /// Given a token string, and whether the previous token was a parentheses, return the appropriate token
 fn match_token(token: String, prev_paren: bool) -> Token {
     // if previous token was a left paren, this token must be a function
@@ -176,6 +834,10 @@ fn match_token(token: String, prev_paren: bool) -> Token {
                 Token::Literal(Literal {
                     value: Value::Int(token.parse::<i64>().unwrap()),
                 })
+            } else if is_float(&token) {
+                Token::Literal(Literal {
+                    value: Value::Float(token.parse::<f64>().unwrap()),
+                })
             } else {
                 Token::Variable(Variable {
                     name: token,
@@ -186,21 +848,154 @@ fn match_token(token: String, prev_paren: bool) -> Token {
     }
 }
 
+/// Position of a character in source code, with lines and columns counted from 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Part of source code, from its first character up to but not including its end
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Given a string, return the span of each top-level expression in the order they are evaluated, skipping
+/// comments and reading strings as tokenize does, where an expression left open runs to the end of the string
+pub fn expression_spans(s: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut depth = 0;
+    let mut start = Position { line: 1, column: 1 };
+    let mut position = start;
+    let mut is_comment = false;
+    let mut is_string = false;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let current = position;
+        match c {
+            '\n' => {
+                position = Position {
+                    line: position.line + 1,
+                    column: 1,
+                }
+            }
+            _ => position.column += 1,
+        }
+
+        // comments end at a newline or a parenthesis
+        if is_comment && !matches!(c, '\n' | '(' | ')') {
+            continue;
+        }
+        is_comment = false;
+
+        // strings run to their closing quote or the end of the line, so their parentheses are not counted
+        if is_string && c != '\n' {
+            is_string = c != '"';
+            continue;
+        }
+        is_string = false;
+
+        match c {
+            ';' => is_comment = true,
+            '(' => {
+                if depth == 0 {
+                    start = current;
+                }
+                depth += 1;
+            }
+            '"' if depth > 0 => is_string = true,
+            ')' if depth > 1 => depth -= 1,
+            ')' => {
+                // a closing parenthesis without an opening one is evaluated on its own
+                if depth == 0 {
+                    start = current;
+                }
+                depth = 0;
+                spans.push(Span {
+                    start,
+                    end: position,
+                });
+            }
+            c if c.is_whitespace() || depth > 0 => {}
+            _ => {
+                // a literal or variable outside of any expression is evaluated on its own
+                let mut is_quoted = c == '"';
+                while let Some(c) = chars.next_if(|c| {
+                    is_quoted && *c != '\n' || !c.is_whitespace() && !matches!(c, '(' | ')' | ';')
+                }) {
+                    is_quoted ^= c == '"';
+                    position.column += 1;
+                }
+                spans.push(Span {
+                    start: current,
+                    end: position,
+                });
+            }
+        }
+    }
+    if depth > 0 {
+        spans.push(Span {
+            start,
+            end: position,
+        });
+    }
+    spans
+}
+
+/// Given a string, split it into words, where parentheses, semicolons, and newlines are words of their own
+/// with newlines written as `\n`, and a quoted string is kept whole with any spaces or parentheses in it
+fn split_words(s: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut is_string = false;
+    let mut is_comment = false;
+    for c in s.chars() {
+        // strings run to their closing quote or the end of the line
+        if is_string && c != '\n' {
+            word.push(c);
+            is_string = c != '"';
+            continue;
+        }
+        is_string = false;
+
+        match c {
+            '(' | ')' | ';' | '\n' => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                words.push(match c {
+                    '\n' => "\\n".to_string(),
+                    c => c.to_string(),
+                });
+
+                // quotes in comments do not start strings, and comments end at a newline or a parenthesis
+                is_comment = c == ';';
+            }
+            c if c.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => {
+                is_string = c == '"' && !is_comment;
+                word.push(c);
+            }
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
 /// Given a string, tokenize it into a vector of tokens
-pub fn tokenize(s: String, is_debug: bool) -> Vec<Token> {
+pub fn tokenize(s: String) -> Vec<Token> {
     // split the string into a vector of strings based on whitespace
-    let separated: Vec<String> = s
-        .replace("(", " ( ")
-        .replace(")", " ) ")
-        .replace(";", " ; ")
-        .replace("\n", " \\n ")
-        .split_whitespace()
-        .map(String::from)
-        .collect();
-
-    if is_debug {
-        println!("{:?}", separated);
-    }
+    let separated: Vec<String> = split_words(&s);
+
+    log::trace!("words: {:?}", separated);
 
     // match the tokens
     let mut tokens: Vec<Token> = Vec::new();
@@ -233,5 +1028,6 @@ pub fn tokenize(s: String, is_debug: bool) -> Vec<Token> {
         tokens.push(token);
     }
 
+    log::info!("tokenized {} tokens", tokens.len());
     tokens
 }
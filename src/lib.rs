@@ -0,0 +1,33 @@
+pub mod analyzer;
+pub mod diagnostic;
+pub mod error;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod formatter;
+pub mod interpreter;
+pub mod lang;
+pub mod lexer;
+pub mod renderer;
+pub mod utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use error::ElementsError;
+
+use interpreter::{evaluate, value_names};
+use renderer::{render, RenderOptions};
+
+/// How far apart two values may be and still be taken as equal, unless set otherwise with
+/// `utils::tolerance::set_default_tolerance`
+pub const TOLERANCE: f64 = 1e-10;
+
+/// Given source code, tokenize, evaluate, and render it to an SVG string
+pub fn render_source(source: &str, options: &RenderOptions) -> Result<String, ElementsError> {
+    let tokens = lexer::tokenize(source.to_string());
+    let options = RenderOptions {
+        names: value_names(&tokens),
+        ..options.clone()
+    };
+    render(evaluate(tokens)?, &options)
+}
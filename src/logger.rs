@@ -0,0 +1,36 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Logger writing each message to stderr after its level, so that output written to stdout stays clean
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "{}: {}",
+                record.level().as_str().to_lowercase(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Start logging at the level given by the verbosity, where 0 shows warnings, each step above shows info,
+/// debug, and trace messages, and each step below shows only errors
+pub fn init(verbosity: i8) {
+    let level = match verbosity {
+        ..=-1 => LevelFilter::Error,
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    log::set_logger(&StderrLogger).expect("Failed to start logging");
+    log::set_max_level(level);
+}
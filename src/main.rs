@@ -1,76 +1,645 @@
-pub mod interpreter;
-pub mod lang;
-pub mod lexer;
-pub mod renderer;
-pub mod utils;
+pub mod cli;
+pub mod config;
+pub mod generate;
+pub mod logger;
+pub mod repl;
+pub mod serve;
+pub mod verify;
 
-use interpreter::evaluate;
-use lang::types::Value;
-use lexer::{tokenize, Token};
-use renderer::render;
+use cli::{CheckArgs, Cli, Command, ErrorFormat, ExplainArgs, FmtArgs, GenerateArgs, RenderArgs};
+use elements_lang::analyzer::analyze;
+use elements_lang::diagnostic::{allowed_lints, closest, Diagnostic, Severity};
+use elements_lang::interpreter::{self, evaluate, evaluate_located, value_names};
+use elements_lang::lang::types::{Directive, Value};
+use elements_lang::lexer::{expression_spans, tokenize, Token};
+use elements_lang::renderer::{
+    render, render_layout, set_precision, Length, Metadata, RenderOptions,
+};
+use elements_lang::utils::limits::{set_limits, Limits};
+use elements_lang::utils::params;
+use elements_lang::utils::tolerance::set_default_tolerance;
+use elements_lang::{export, formatter, utils};
 
+use clap::{CommandFactory, Parser};
 use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-const TOLERANCE: f64 = 1e-10;
+/// How often watch checks the input for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// What is done with the file to render
+enum Mode {
+    Render,
+    Watch,
+    Generate {
+        count: u32,
+        manifest: Option<String>,
+    },
+}
 
 fn main() {
-    // get args and check for at least 2
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <filename>", args[0]);
+    // parse the command, which renders a file when none is given
+    let cli = Cli::parse();
+    let error_format = cli.error_format;
+
+    // log at the requested verbosity, where --debug is kept for -vvv so it still shows the tokens, values, and
+    // SVG, and watch and serve report each render unless quiet
+    let is_debug = match (&cli.command, &cli.render) {
+        (Some(Command::Render(args) | Command::Watch(args)), _) => args.debug,
+        (Some(Command::Generate(args)), _) => args.render.debug,
+        (None, Some(args)) => args.debug,
+        _ => false,
+    };
+    let is_running = matches!(cli.command, Some(Command::Watch(_) | Command::Serve(_)));
+    let verbosity = (cli.verbose.min(3) as i8).max(if is_debug { 3 } else { 0 }) + is_running as i8;
+    logger::init(if cli.quiet { -1 } else { verbosity });
+    let (mut args, mode) = match (cli.command, cli.render) {
+        (Some(Command::Explain(args)), _) => {
+            if let Err(e) = explain(&args) {
+                report(&e, error_format);
+                std::process::exit(1);
+            }
+            return;
+        }
+        (Some(Command::Check(args)), _) => {
+            check(&args, error_format);
+            return;
+        }
+        (Some(Command::Verify(args)), _) => {
+            verify::verify(&args, error_format);
+            return;
+        }
+        (Some(Command::Fmt(args)), _) => {
+            fmt(&args, error_format);
+            return;
+        }
+        (Some(Command::Repl(args)), _) => {
+            repl::repl(&args);
+            return;
+        }
+        (Some(Command::Serve(args)), _) => {
+            if let Err(e) = serve::serve(&args) {
+                report(&Diagnostic::error(e), error_format);
+                std::process::exit(1);
+            }
+            return;
+        }
+        (Some(Command::Completions(args)), _) => {
+            // a pipeline that stops reading early, such as head, is not an error
+            let mut script = Vec::new();
+            clap_complete::generate(args.shell, &mut Cli::command(), "elements", &mut script);
+            let _ = std::io::stdout().write_all(&script);
+            return;
+        }
+        (Some(Command::Render(args)), _) => (*args, Mode::Render),
+        (Some(Command::Watch(args)), _) => (*args, Mode::Watch),
+        (Some(Command::Generate(args)), _) => {
+            let GenerateArgs {
+                render,
+                count,
+                manifest,
+            } = *args;
+            (render, Mode::Generate { count, manifest })
+        }
+        (None, Some(args)) => (args, Mode::Render),
+        (None, None) => {
+            Cli::command().print_help().expect("Failed to print help");
+            std::process::exit(1);
+        }
+    };
+
+    // fill in the options not given from the project's configuration
+    if let Err(e) = config::apply(&mut args) {
+        report(&Diagnostic::error(e), error_format);
         std::process::exit(1);
     }
 
-    // check if label and debug is enabled
-    let mut is_label = false;
-    let mut is_debug = false;
-    if args.len() > 2 && args[2] == "--label" {
-        is_label = true;
-    } else if args.len() > 2 && args[2] == "--debug" {
-        is_debug = true;
+    // set the seed, precision, tolerance, and limits shared by every render
+    if let Some(seed) = args.seed {
+        utils::random::set_seed(seed);
     }
-    if args.len() > 3 && args[3] == "--label" {
-        is_label = true;
-    } else if args.len() > 3 && args[3] == "--debug" {
-        is_debug = true;
+    set_precision(args.precision);
+    if let Some(tolerance) = args.tolerance {
+        set_default_tolerance(tolerance);
     }
+    params::set_overrides(args.params.clone());
+    let defaults = Limits::default();
+    set_limits(Limits {
+        max_depth: args.max_depth.unwrap_or(defaults.max_depth),
+        max_steps: args.max_steps.unwrap_or(defaults.max_steps),
+        timeout: args.timeout.map(Duration::from_secs_f64),
+    });
 
-    // see if file exists
-    let filename = &args[1];
-    if !std::path::Path::new(filename).exists() {
-        eprintln!("File not found: {}", filename);
+    // render the file once, every time it changes when watching, or once for each variant
+    let result = match mode {
+        Mode::Render => run(&args, args.force),
+        Mode::Watch => watch(&args, error_format),
+        Mode::Generate { count, manifest } => generate::generate(&args, count, manifest.as_deref()),
+    };
+    if let Err(e) = result {
+        report(&e, error_format);
         std::process::exit(1);
     }
+}
 
-    // open file and read into string
-    let contents = std::fs::read_to_string(filename).expect("Failed to read file");
+/// Print a diagnostic to stderr, as text or as a JSON object on one line
+fn report(diagnostic: &Diagnostic, format: ErrorFormat) {
+    match format {
+        ErrorFormat::Human => eprintln!("{}", diagnostic),
+        ErrorFormat::Json => eprintln!("{}", diagnostic.to_json()),
+    }
+}
 
-    // tokenize string
-    let tokens: Vec<Token> = tokenize(contents, is_debug);
+/// Render the input again whenever it is modified, keeping the same seed so random constructions stay in
+/// place, until interrupted
+fn watch(args: &RenderArgs, error_format: ErrorFormat) -> Result<(), Diagnostic> {
+    // the input is read again, so it must be a file, and the output replaces itself
+    if args.input == "-" || args.stdout {
+        return Err("watch requires an input file and an output file"
+            .to_string()
+            .into());
+    }
+    let seed = utils::random::seed();
+    let mut is_force = args.force;
+    let mut modified = None;
+    loop {
+        let time = fs::metadata(&args.input).and_then(|m| m.modified()).ok();
+        if time.is_some() && time != modified {
+            modified = time;
+            utils::random::set_seed(seed);
+            match run(args, is_force) {
+                Ok(()) => {
+                    log::info!("rendered {}", args.input);
+                    is_force = true;
+                }
+                Err(e) => report(&e, error_format),
+            }
+        }
+        std::thread::sleep(WATCH_INTERVAL);
+    }
+}
 
-    // evaluate tokens
-    let values: Vec<Value> = match evaluate(tokens) {
-        Ok(values) => values,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            std::process::exit(1);
+/// Read, evaluate, and render the input, then write each figure to its file or to stdout
+fn run(args: &RenderArgs, is_force: bool) -> Result<(), Diagnostic> {
+    // set the options from the flags
+    let mut options = RenderOptions {
+        is_label: args.label,
+        flip_y: args.flip_y,
+        grid: args.grid.then_some(1.0),
+        legend: args.legend,
+        animate: args.delay.or(args.animate.then_some(1.0)),
+        padding: args.padding,
+        width: args.width.clone(),
+        height: args.height.clone(),
+        font_family: args.font_family.clone(),
+        font_size: args.font_size,
+        font_color: args.font_color.clone(),
+        background: args.background.clone(),
+        border: args.border.clone(),
+        ..RenderOptions::default()
+    };
+    let output = args.output.as_deref();
+    let dpi = args.dpi;
+    let layout = args.layout;
+
+    let format = output_format(args);
+
+    // select the high precision kernel if requested
+    if args.high_precision {
+        #[cfg(feature = "high-precision")]
+        utils::precise::set_enabled(true);
+        #[cfg(not(feature = "high-precision"))]
+        return Err(
+            "--high-precision requires building with the high-precision feature"
+                .to_string()
+                .into(),
+        );
+    }
+
+    // only SVG output can be animated
+    if format != "svg" && options.animate.is_some() {
+        return Err("--animate requires svg output".to_string().into());
+    }
+
+    // printed formats need a physical size, so give figures without one a default width
+    if (format == "png" || format == "pdf") && options.width.is_none() && options.height.is_none() {
+        options.width = Length::parse(export::DEFAULT_EXPORT_WIDTH).ok();
+    }
+
+    // PDF figures are placed into documents, so they are framed closely
+    if format == "pdf" {
+        options.tight = true;
+    }
+
+    // interactive figures find the elements of each value to move them
+    if format == "html" {
+        options.tag_values = true;
+    }
+
+    // read the source from stdin if the filename is -, or else see if the file exists
+    let filename = args.input.as_str();
+    let contents = if filename == "-" {
+        std::io::read_to_string(std::io::stdin())
+            .map_err(|e| format!("failed to read stdin: {}", e))?
+    } else if Path::new(filename).exists() {
+        fs::read_to_string(filename).map_err(|e| format!("failed to read {}: {}", filename, e))?
+    } else {
+        return Err(format!("file not found: {}", filename).into());
+    };
+
+    // errors in the figure are reported against the input
+    let name = if filename == "-" { "stdin" } else { filename };
+    let in_input = |diagnostic: Diagnostic| Diagnostic {
+        file: Some(name.to_string()),
+        ..diagnostic
+    };
+
+    // describe the figure in the output, with the seed needed to draw it again
+    if !args.no_metadata {
+        let path = Path::new(name);
+        options.metadata = Some(Metadata {
+            title: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            description: format!(
+                "Generated by elements {} from {}",
+                env!("CARGO_PKG_VERSION"),
+                path.file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            ),
+            source: contents.clone(),
+            seed: utils::random::seed(),
+        });
+    }
+
+    // tokenize string, keeping where each expression is to point errors at it
+    let spans = expression_spans(&contents);
+    let tokens: Vec<Token> = tokenize(contents);
+
+    // name the group of each value read from a variable
+    options.names = value_names(&tokens);
+
+    // keep the source of interactive figures, which is evaluated again as points are dragged
+    let source = (format == "html").then(|| tokens.clone());
+
+    // evaluate tokens, keeping the variables for their coordinates
+    let (values, variables) = evaluate_located(tokens, &spans).map_err(|e| in_input(*e))?;
+
+    // a value given to a parameter the figure does not declare is most likely misspelled
+    if let Some(name) = params::undeclared().first() {
+        let declared = params::declared();
+        let suggestion = closest(name, declared.iter().map(|p| p.name.as_str()))
+            .map(|closest| format!(", did you mean `{}`?", closest))
+            .unwrap_or_default();
+        return Err(format!("unknown parameter: {}{}", name, suggestion).into());
+    }
+
+    // interactive figures are recomputed from the whole source, so they cannot be split up
+    let is_split = values
+        .iter()
+        .any(|v| matches!(v, Value::Directive(Directive::Figure { .. })));
+    if format == "html" && (is_split || layout.is_some()) {
+        return Err("--format html does not support figures".to_string().into());
+    }
+    let construction =
+        source.map(|tokens| export::construction_json(&tokens, &values, options.flip_y));
+
+    // the values of the named variables are written for answer keys to the path given, or else beside the
+    // figure when the source asks for them
+    let coords: Option<(PathBuf, bool)> = match &args.emit_coords {
+        Some(path) => {
+            let is_json = Path::new(path)
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+            Some((PathBuf::from(path), is_json))
         }
+        None => values.iter().find_map(|v| match v {
+            Value::Directive(Directive::ExportCoords { format: coords }) => {
+                let main = output_path(filename, output, None, format);
+                let stem = main.file_stem().unwrap_or_default().to_string_lossy();
+                Some((
+                    main.with_file_name(format!("{}-coords.{}", stem, coords)),
+                    coords == "json",
+                ))
+            }
+            _ => None,
+        }),
     };
 
-    // if debug is enabled, print the values
-    if is_debug {
-        println!("{:?}", values);
+    // render each figure to svg, titled by its name, or all of them into one svg when laid out together
+    let figures = split_figures(values);
+    let outputs: Vec<(Option<String>, String)> = match layout {
+        Some(layout) => vec![(
+            None,
+            render_layout(figures, layout, &options).map_err(|e| in_input(Diagnostic::error(e)))?,
+        )],
+        None => figures
+            .into_iter()
+            .map(|(name, values)| {
+                let mut options = options.clone();
+                if let Some(name) = &name {
+                    if let Some(metadata) = &mut options.metadata {
+                        metadata.title = name.clone();
+                    }
+                    options.names = Vec::new();
+                }
+                Ok((name, render(values, &options)?))
+            })
+            .collect::<Result<_, String>>()
+            .map_err(|e| in_input(Diagnostic::error(e)))?,
+    };
+
+    // standard output can only hold one figure
+    if args.stdout && outputs.len() > 1 {
+        return Err(
+            "--stdout writes one figure, so figures must be combined with --layout"
+                .to_string()
+                .into(),
+        );
+    }
+
+    // the main figure is named after the input unless an output is given, and other figures are written
+    // beside it, where no file is replaced unless forced
+    let paths: Vec<PathBuf> = outputs
+        .iter()
+        .map(|(name, _)| output_path(filename, output, name.as_deref(), format))
+        .collect();
+    if !is_force {
+        let written = paths.iter().filter(|_| !args.stdout);
+        let coords = coords.iter().map(|(path, _)| path);
+        if let Some(path) = written.chain(coords).find(|path| path.exists()) {
+            return Err(format!(
+                "{} already exists, pass --force to overwrite it",
+                path.display()
+            )
+            .into());
+        }
+    }
+
+    for ((_, svg), path) in outputs.into_iter().zip(paths) {
+        log::trace!("svg: {}", svg);
+
+        // convert the figure to the requested format
+        let bytes = match format {
+            "png" => encode_png(&svg, dpi)?,
+            "pdf" => encode_pdf(&svg)?,
+            "html" => {
+                export::to_html(&svg, construction.as_deref().unwrap_or_default()).into_bytes()
+            }
+            _ => svg.into_bytes(),
+        };
+
+        // write it to stdout or to file
+        if args.stdout {
+            // a pipeline that stops reading early is not an error
+            if let Err(e) = std::io::stdout().write_all(&bytes) {
+                if e.kind() != std::io::ErrorKind::BrokenPipe {
+                    return Err(format!("failed to write to stdout: {}", e).into());
+                }
+            }
+        } else {
+            fs::write(&path, bytes)
+                .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+            log::info!("wrote {}", path.display());
+        }
+    }
+    if let Some((path, is_json)) = coords {
+        let text = match is_json {
+            true => export::coords_json(&variables),
+            false => export::coords_csv(&variables),
+        };
+        fs::write(&path, text).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        log::info!("wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// Return the format of the output, which without --format is the one named by the extension of the output,
+/// or else svg
+fn output_format(args: &RenderArgs) -> &str {
+    args.format
+        .as_deref()
+        .or_else(|| {
+            let extension = Path::new(args.output.as_deref()?).extension()?.to_str()?;
+            ["svg", "png", "pdf", "html"]
+                .into_iter()
+                .find(|format| extension.eq_ignore_ascii_case(format))
+        })
+        .unwrap_or("svg")
+}
+
+/// Return the path a figure is written to, which for the main figure is the given output or else the
+/// input's name in the current directory, and for other figures is their name beside the main figure
+fn output_path(input: &str, output: Option<&str>, figure: Option<&str>, format: &str) -> PathBuf {
+    match (figure, output) {
+        (None, Some(output)) => PathBuf::from(output),
+        (None, None) => {
+            let stem = match input {
+                "-" => "out".as_ref(),
+                input => Path::new(input).file_stem().unwrap_or("out".as_ref()),
+            };
+            Path::new(stem).with_extension(format)
+        }
+        (Some(name), output) => {
+            let directory = output.and_then(|o| Path::new(o).parent());
+            directory
+                .unwrap_or("".as_ref())
+                .join(name)
+                .with_extension(format)
+        }
+    }
+}
+
+/// Split the values into the main figure, named by None, and the figures given with `figure`, where
+/// directives and point labels outside of any figure are shared by every figure
+fn split_figures(values: Vec<Value>) -> Vec<(Option<String>, Vec<Value>)> {
+    let shared: Vec<Value> = values
+        .iter()
+        .filter(|v| match v {
+            Value::Directive(Directive::Figure { .. }) => false,
+            Value::Directive(_) | Value::String(_) => true,
+            _ => false,
+        })
+        .cloned()
+        .collect();
+
+    // figures are replaced in the main figure so that values keep their index
+    let mut figures: Vec<(Option<String>, Vec<Value>)> = Vec::new();
+    let mut main: Vec<Value> = Vec::new();
+    for value in values {
+        match value {
+            Value::Directive(Directive::Figure { name, mut values }) => {
+                values.extend(shared.iter().cloned());
+                figures.push((Some(name), values));
+                main.push(Value::Undefined);
+            }
+            value => main.push(value),
+        }
+    }
+
+    // the main figure is only written when it draws something of its own, or there are no figures
+    let is_drawn = main.iter().any(|v| {
+        !matches!(
+            v,
+            Value::Directive(_) | Value::String(_) | Value::Undefined | Value::Style(_)
+        )
+    });
+    if is_drawn || figures.is_empty() {
+        figures.insert(0, (None, main));
+    }
+    figures
+}
+
+/// Rasterize the figure into a PNG image
+#[cfg(feature = "png")]
+fn encode_png(svg: &str, dpi: f64) -> Result<Vec<u8>, String> {
+    export::to_png(svg, dpi).map_err(String::from)
+}
+
+/// Report that PNG output was left out of this build
+#[cfg(not(feature = "png"))]
+fn encode_png(_: &str, _: f64) -> Result<Vec<u8>, String> {
+    Err("--format png requires building with the png feature".to_string())
+}
+
+/// Evaluate each file without rendering it, printing every error found and exiting with failure if there
+/// were any
+fn check(args: &CheckArgs, error_format: ErrorFormat) {
+    let mut is_valid = true;
+    for filename in &args.files {
+        // read the source from stdin if the filename is -
+        let contents = if filename == "-" {
+            std::io::read_to_string(std::io::stdin())
+        } else {
+            fs::read_to_string(filename)
+        };
+        let diagnostics = match contents {
+            Ok(contents) => {
+                let spans = expression_spans(&contents);
+                let tokens = tokenize(contents.clone());
+                let allowed = allowed_lints(&contents);
+                let mut diagnostics = analyze(&tokens, &spans);
+                if !args.analyze {
+                    diagnostics.retain(|d| d.severity == Severity::Warning);
+                }
+                diagnostics.retain(|d| !d.is_allowed(&allowed));
+
+                // evaluating would only report again the first of the errors already found
+                if diagnostics.iter().all(|d| d.severity != Severity::Error) {
+                    diagnostics.extend(interpreter::check(&tokens, &spans));
+                }
+                diagnostics
+            }
+            Err(e) => vec![Diagnostic::error(e.to_string())],
+        };
+        for diagnostic in diagnostics {
+            is_valid &= diagnostic.severity != Severity::Error;
+            let file = Some(filename.clone());
+            report(&Diagnostic { file, ..diagnostic }, error_format);
+        }
+    }
+    if !is_valid {
+        std::process::exit(1);
+    }
+}
+
+/// Format each file in place, or print it formatted if read from stdin, or with --check only report the
+/// files that are not formatted
+fn fmt(args: &FmtArgs, error_format: ErrorFormat) {
+    let report = |filename: &str, message: String| {
+        let diagnostic = Diagnostic {
+            file: Some(filename.to_string()),
+            ..Diagnostic::error(message)
+        };
+        report(&diagnostic, error_format);
+    };
+    let mut is_formatted = true;
+    for filename in &args.files {
+        // read the source from stdin if the filename is -
+        let contents = if filename == "-" {
+            std::io::read_to_string(std::io::stdin())
+        } else {
+            fs::read_to_string(filename)
+        };
+        let result = contents
+            .map_err(|e| e.to_string())
+            .and_then(|contents| Ok((formatter::format(&contents)?, contents)));
+        let (formatted, contents) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                report(filename, e);
+                is_formatted = false;
+                continue;
+            }
+        };
+
+        // report, print, or write the formatted source
+        if args.check {
+            if formatted != contents {
+                report(filename, "not formatted".to_string());
+                is_formatted = false;
+            }
+        } else if filename == "-" {
+            print!("{}", formatted);
+        } else if formatted != contents {
+            if let Err(e) = fs::write(filename, formatted) {
+                report(filename, e.to_string());
+                is_formatted = false;
+            }
+        }
+    }
+    if !is_formatted {
+        std::process::exit(1);
     }
+}
 
-    // render values to svg
-    let svg = render(values, is_label, is_debug).expect("Failed to render");
+/// Evaluate a single expression from the command line and print its value and properties
+fn explain(args: &ExplainArgs) -> Result<(), Diagnostic> {
+    // tokenize and evaluate the expression
+    let tokens: Vec<Token> = tokenize(args.expression.clone());
+    let values: Vec<Value> = evaluate(tokens)?;
+    if values.len() != 1 {
+        return Err("explain requires a single expression".to_string().into());
+    }
+    print_value(&values[0]);
 
-    // if debug is enabled, print the svg elements
-    if is_debug {
-        println!("{}", svg);
+    // render just this expression if requested, where no file is replaced unless forced
+    if args.render {
+        let path = Path::new(args.output.as_deref().unwrap_or("out.svg"));
+        if !args.force && path.exists() {
+            return Err(format!(
+                "{} already exists, pass --force to overwrite it",
+                path.display()
+            )
+            .into());
+        }
+        let svg = render(values, &RenderOptions::default())?;
+        fs::write(path, svg).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        log::info!("wrote {}", path.display());
     }
+    Ok(())
+}
+
+/// Print the type and derived properties of a value
+fn print_value(value: &Value) {
+    println!("{}", value.type_name());
+    for (name, property) in value.properties() {
+        println!("  {} = {}", name, property);
+    }
+}
+
+/// Convert the figure into a single page PDF document
+#[cfg(feature = "pdf")]
+fn encode_pdf(svg: &str) -> Result<Vec<u8>, String> {
+    export::to_pdf(svg).map_err(String::from)
+}
 
-    // write svg to file
-    let filename = "out.svg";
-    fs::write(filename, svg).expect("Failed to write file");
+/// Report that PDF output was left out of this build
+#[cfg(not(feature = "pdf"))]
+fn encode_pdf(_: &str) -> Result<Vec<u8>, String> {
+    Err("--format pdf requires building with the pdf feature".to_string())
 }
@@ -1,9 +1,79 @@
 use crate::{
-    lang::types::{Element, Point, Value},
-    utils::geometry::bresenham,
+    error::ElementsError,
+    lang::types::{Corner, Directive, Element, Point, Value},
+    utils::geometry::{bresenham, clip_line, clip_segment, ray_box_exit},
 };
 
 use std::any::Any;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+
+thread_local! {
+    /// Number of decimal places numbers are written with, or None to write them in full
+    static PRECISION: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Round every number written to the output to the given number of decimal places
+pub fn set_precision(precision: Option<usize>) {
+    PRECISION.with(|p| p.set(precision));
+}
+
+/// A number written to the output, rounded to the chosen precision without trailing zeros
+#[derive(Debug, Clone, Copy)]
+pub struct Num(pub f64);
+
+impl fmt::Display for Num {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match PRECISION.with(|p| p.get()) {
+            Some(precision) => write_rounded(f, self.0, precision),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// A size written to the output, such as a stroke width, which is rounded like a number
+/// but keeps its first significant digit so that it is never rounded away
+#[derive(Debug, Clone, Copy)]
+pub struct Size(pub f64);
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match PRECISION.with(|p| p.get()) {
+            Some(precision) if self.0 != 0.0 && self.0.is_finite() => {
+                let significant = (-self.0.abs().log10()).ceil().max(0.0) as usize;
+                write_rounded(f, self.0, precision.max(significant))
+            }
+            _ => Num(self.0).fmt(f),
+        }
+    }
+}
+
+/// A number rounded to the given decimal places whatever the chosen precision, such as a measurement
+/// written into a label
+#[derive(Debug, Clone, Copy)]
+pub struct Rounded(pub f64, pub usize);
+
+impl fmt::Display for Rounded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_rounded(f, self.0, self.1)
+    }
+}
+
+/// Write a number with the given decimal places, trimming trailing zeros
+fn write_rounded(f: &mut fmt::Formatter, value: f64, precision: usize) -> fmt::Result {
+    let rounded = format!("{:.*}", precision, value);
+    let trimmed = if rounded.contains('.') {
+        rounded.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &rounded
+    };
+    // rounding small negative numbers leaves a sign on zero
+    match trimmed {
+        "-0" => write!(f, "0"),
+        _ => write!(f, "{}", trimmed),
+    }
+}
 
 pub trait Render {
     /// Render the element as a SVG string
@@ -11,9 +81,271 @@ pub trait Render {
     /// Get the bounds of the element
     fn get_bounds(&self) -> (Point, Point);
     /// Mark on an array where pixels are
-    fn mark_pixels(&self, bitmap: &mut Vec<Vec<bool>>, scale: f64);
+    fn mark_pixels(&self, bitmap: &mut Bitmap);
     /// Return self for as_any
     fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Apply a style on top of the element's own style
+    fn apply_style(&mut self, _: &Style) {
+        // Do nothing by default
+    }
+    /// Clip an unbounded element to the final viewbox
+    fn clip(&mut self, _: Point, _: Point) {
+        // Do nothing by default
+    }
+    /// Layer the element is drawn in, where higher layers are drawn over lower ones
+    fn layer(&self) -> i32 {
+        STROKE_LAYER
+    }
+    /// Definitions the element refers to, such as hatch patterns
+    fn defs(&self) -> Vec<String> {
+        Vec::new()
+    }
+    /// Points an element left out of the bounds passes through, which frame a figure with nothing else in it
+    fn anchors(&self) -> Vec<Point> {
+        Vec::new()
+    }
+}
+
+/// Default layers, so that fills never cover outlines, outlines never cover points, and nothing covers labels
+pub const GRID_LAYER: i32 = -1;
+pub const FILL_LAYER: i32 = 0;
+pub const STROKE_LAYER: i32 = 1;
+pub const POINT_LAYER: i32 = 2;
+pub const LABEL_LAYER: i32 = 3;
+
+/// Macro to automatically implement apply_style and layer for a struct with a style field
+macro_rules! impl_apply_style {
+    () => {
+        fn apply_style(&mut self, style: &Style) {
+            self.style.merge(style);
+        }
+        fn layer(&self) -> i32 {
+            self.style.layer(STROKE_LAYER)
+        }
+        fn defs(&self) -> Vec<String> {
+            self.style.defs()
+        }
+    };
+    // elements that are filled by default, such as points, give their own default layer
+    ($filled:ident, $layer:expr) => {
+        fn apply_style(&mut self, style: &Style) {
+            self.style.merge(style);
+        }
+        fn layer(&self) -> i32 {
+            if self.$filled {
+                self.style.layer.unwrap_or($layer)
+            } else {
+                self.style.layer(STROKE_LAYER)
+            }
+        }
+        fn defs(&self) -> Vec<String> {
+            self.style.defs()
+        }
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Blend {
+    Normal,
+    Multiply,
+}
+
+impl Blend {
+    /// Parse a blend mode from its name
+    pub fn from_name(name: &str) -> Result<Self, ElementsError> {
+        match name {
+            "normal" => Ok(Blend::Normal),
+            "multiply" => Ok(Blend::Multiply),
+            _ => Err(ElementsError::Type(format!("Unknown blend mode: {}", name))),
+        }
+    }
+}
+
+/// Parse a color given either as a name such as red, or as a hex code such as #ff0000
+pub fn parse_color(name: &str) -> Result<String, ElementsError> {
+    // allow the color to be quoted
+    let color = name.trim_matches('"');
+    let is_name = !color.is_empty() && color.chars().all(|c| c.is_ascii_alphabetic());
+    let is_hex = color.starts_with('#')
+        && matches!(color.len(), 4 | 7)
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if is_name || is_hex {
+        Ok(color.to_lowercase())
+    } else {
+        Err(ElementsError::Type(format!("Invalid color: {}", name)))
+    }
+}
+
+/// Pattern of parallel lines used in place of a solid fill
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hatch {
+    pub color: String,
+    pub angle: f64,
+    pub spacing: f64,
+}
+
+impl Hatch {
+    /// Return the id of the pattern, which is shared by hatches that look the same
+    pub fn id(&self) -> String {
+        format!(
+            "hatch-{}-{}-{}",
+            self.color.trim_start_matches('#'),
+            self.angle,
+            self.spacing
+        )
+        .replace('.', "_")
+    }
+
+    /// Return the SVG pattern definition, with lines an eighth of the spacing wide
+    pub fn def(&self) -> String {
+        format!(
+            "\t<pattern id=\"{}\" patternUnits=\"userSpaceOnUse\" width=\"{}\" height=\"{}\" patternTransform=\"rotate({})\">\n\t\t<line x1=\"0\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\"/>\n\t</pattern>\n",
+            self.id(),
+            Size(self.spacing),
+            Size(self.spacing),
+            Num(self.angle),
+            Size(self.spacing / 2.0),
+            Size(self.spacing),
+            Size(self.spacing / 2.0),
+            self.color,
+            Size(self.spacing / 8.0)
+        )
+    }
+}
+
+/// Presentation attributes attached to an element, where unset fields use the element's defaults
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Style {
+    pub stroke: Option<String>,
+    pub stroke_width: Option<f64>,
+    pub fill: Option<String>,
+    pub dash: Option<Vec<f64>>,
+    pub fill_opacity: Option<f64>,
+    pub stroke_opacity: Option<f64>,
+    pub label: Option<String>,
+    pub label_offset: Option<Point>,
+    pub vertex_labels: Option<Vec<String>>,
+    pub layer: Option<i32>,
+    pub blend: Option<Blend>,
+    pub hatch: Option<Hatch>,
+    /// Whether the value is left out of the figure, while still being usable in the construction
+    pub hidden: Option<bool>,
+    /// Whether the value is left out of the bounds of the figure, and clipped to its viewbox instead
+    pub unbounded: Option<bool>,
+}
+
+impl Style {
+    /// Override the fields of this style with the fields set in another
+    pub fn merge(&mut self, other: &Style) {
+        if other.stroke.is_some() {
+            self.stroke = other.stroke.clone();
+        }
+        if other.stroke_width.is_some() {
+            self.stroke_width = other.stroke_width;
+        }
+        if other.dash.is_some() {
+            self.dash = other.dash.clone();
+        }
+        // a solid fill replaces a hatch, and the other way around
+        if other.fill.is_some() {
+            self.fill = other.fill.clone();
+            self.hatch = None;
+        }
+        if other.hatch.is_some() {
+            self.hatch = other.hatch.clone();
+        }
+        if other.label.is_some() {
+            self.label = other.label.clone();
+            self.label_offset = other.label_offset;
+        }
+        if other.vertex_labels.is_some() {
+            self.vertex_labels = other.vertex_labels.clone();
+        }
+        if other.layer.is_some() {
+            self.layer = other.layer;
+        }
+        if other.fill_opacity.is_some() {
+            self.fill_opacity = other.fill_opacity;
+        }
+        if other.stroke_opacity.is_some() {
+            self.stroke_opacity = other.stroke_opacity;
+        }
+        if other.blend.is_some() {
+            self.blend = other.blend;
+        }
+        if other.hidden.is_some() {
+            self.hidden = other.hidden;
+        }
+        if other.unbounded.is_some() {
+            self.unbounded = other.unbounded;
+        }
+    }
+
+    /// Return the layer the element is drawn in, with filled elements beneath the given default
+    pub fn layer(&self, default: i32) -> i32 {
+        match (self.layer, self.fill.is_some() || self.hatch.is_some()) {
+            (Some(layer), _) => layer,
+            (None, true) => FILL_LAYER,
+            (None, false) => default,
+        }
+    }
+
+    /// Return the stroke color, defaulting to black
+    pub fn stroke(&self) -> &str {
+        self.stroke.as_deref().unwrap_or("black")
+    }
+
+    /// Return the fill color, defaulting to no fill
+    pub fn fill(&self) -> String {
+        self.fill_or("none")
+    }
+
+    /// Return the fill color or hatch pattern, defaulting to the given color
+    pub fn fill_or(&self, default: &str) -> String {
+        match &self.hatch {
+            Some(hatch) => format!("url(#{})", hatch.id()),
+            None => self.fill.as_deref().unwrap_or(default).to_string(),
+        }
+    }
+
+    /// Return the definitions needed to draw the style
+    pub fn defs(&self) -> Vec<String> {
+        self.hatch.iter().map(Hatch::def).collect()
+    }
+
+    /// Return the stroke width, defaulting to 0.02
+    pub fn stroke_width(&self) -> f64 {
+        self.stroke_width.unwrap_or(0.02)
+    }
+
+    /// Return the blend mode, defaulting to multiply for hatches so overlapping hatches show both patterns,
+    /// and to normal otherwise
+    pub fn blend(&self) -> Blend {
+        match (self.blend, &self.hatch) {
+            (Some(blend), _) => blend,
+            (None, Some(_)) => Blend::Multiply,
+            (None, None) => Blend::Normal,
+        }
+    }
+
+    /// Render the style as SVG attributes
+    pub fn attributes(&self) -> String {
+        let mut attributes = String::new();
+        if let Some(dash) = &self.dash {
+            let lengths: Vec<String> = dash.iter().map(|l| Size(*l).to_string()).collect();
+            attributes.push_str(&format!(" stroke-dasharray=\"{}\"", lengths.join(" ")));
+        }
+        if let Some(opacity) = self.fill_opacity {
+            attributes.push_str(&format!(" fill-opacity=\"{}\"", Size(opacity)));
+        }
+        if let Some(opacity) = self.stroke_opacity {
+            attributes.push_str(&format!(" stroke-opacity=\"{}\"", Size(opacity)));
+        }
+        if self.blend() == Blend::Multiply {
+            attributes.push_str(" style=\"mix-blend-mode: multiply\"");
+        }
+        attributes
+    }
 }
 
 /// Macro to automatically implement as_any for a struct
@@ -25,30 +357,149 @@ macro_rules! impl_as_any {
     };
 }
 
+/// Length of an output dimension, such as 400 or 10cm
+#[derive(Debug, Clone, PartialEq)]
+pub struct Length {
+    pub value: f64,
+    pub unit: String,
+}
+
+impl Length {
+    /// Parse a positive length from a number with an optional CSS unit
+    pub fn parse(s: &str) -> Result<Self, ElementsError> {
+        let split = s
+            .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+            .unwrap_or(s.len());
+        let (value, unit) = s.split_at(split);
+        let value = match value.parse::<f64>() {
+            Ok(v) if v > 0.0 && v.is_finite() => v,
+            _ => return Err(ElementsError::Type(format!("Invalid length: {}", s))),
+        };
+        match unit {
+            "" | "px" | "pt" | "pc" | "mm" | "cm" | "in" | "em" | "%" => Ok(Length {
+                value,
+                unit: unit.to_string(),
+            }),
+            _ => Err(ElementsError::Type(format!(
+                "Unknown unit in length: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for Length {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}{}", Num(self.value), self.unit)
+    }
+}
+
+/// Margin around the figure on each side, in the figure's units
+pub const DEFAULT_PADDING: f64 = 5.0;
+
 pub struct Svg {
     elements: Vec<Box<dyn Render>>,
+    /// Margin added around the bounds on each side
+    padding: f64,
+    /// Viewbox given explicitly, used instead of the padded bounds
+    viewbox: Option<(Point, Point)>,
+    /// Width and height attributes of the output
+    width: Option<Length>,
+    height: Option<Length>,
+    /// Whether the y-axis points up, as in mathematics, instead of down
+    flip_y: bool,
+    /// Value each element was drawn from, or None for elements that are not part of the construction
+    sources: Vec<Option<Source>>,
+    /// Delay in seconds between revealing each step, when the figure is animated
+    step_delay: Option<f64>,
+    /// Whether to tag each element with the value it was drawn from
+    tag_sources: bool,
+    /// Description of where the figure came from, embedded at the start of the output
+    metadata: Option<Metadata>,
+    /// Semantic group of each value, by its index in evaluation order
+    groups: HashMap<usize, Group>,
+    /// Color filling the viewbox beneath the figure, which is transparent otherwise
+    background: Option<String>,
+    /// Color and width of a frame drawn just inside the edges of the viewbox
+    border: Option<(String, f64)>,
 }
 
-impl Render for Svg {
-    impl_as_any!(Svg);
+/// Group wrapping the elements drawn from one value, so they can be styled or toggled together
+#[derive(Debug, Clone)]
+struct Group {
+    /// Kind of value, such as triangle
+    class: String,
+    /// Variable the value was read from, used as the id of the group
+    name: Option<String>,
+}
+
+/// Where a figure came from, so the output can be identified and reproduced later
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    /// Short name of the figure
+    pub title: String,
+    /// Sentence describing how the figure was made
+    pub description: String,
+    /// Source code the figure was evaluated from
+    pub source: String,
+    /// Seed used for random constructions in the source
+    pub seed: u64,
+}
+
+impl Metadata {
+    /// Return the title and description elements, followed by a comment holding the source
     fn render(&self) -> String {
-        // get the SVG string for each element
-        let mut elements = String::new();
-        for element in &self.elements {
-            elements.push_str(&element.render());
+        // comments cannot contain a double hyphen, so split any in the source
+        let mut source = self.source.trim_end().to_string();
+        while source.contains("--") {
+            source = source.replace("--", "- -");
         }
+        format!(
+            "<title>{}</title>\n<desc>{}</desc>\n<!--\nSource, rendered with seed {}:\n\n{}\n-->\n",
+            escape_xml(&self.title),
+            escape_xml(&self.description),
+            self.seed,
+            source
+        )
+    }
+}
+
+/// Escape the characters that are not allowed in XML text
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Where an element of the figure was drawn from
+#[derive(Debug, Clone, Copy)]
+struct Source {
+    /// Index of the value in evaluation order
+    value: usize,
+    /// Construction step the value is revealed in, or None if it draws nothing
+    step: Option<usize>,
+}
 
+impl Render for Svg {
+    impl_as_any!(Svg);
+    fn render(&self) -> String {
         // calculate the appropriate viewBox
-        let (min, max): (Point, Point) = self.get_bounds();
-        let padding: f64 = 10.0;
-        let width: f64 = max.x - min.x + padding;
-        let height: f64 = max.y - min.y + padding;
-        let min_x: f64 = min.x - padding / 2.0;
-        let min_y: f64 = min.y - padding / 2.0;
+        let (min, max): (Point, Point) = self.get_viewbox();
+        let ratio = (max.x - min.x) / (max.y - min.y);
+
+        // describe the figure before anything else
+        let metadata = self
+            .metadata
+            .as_ref()
+            .map(Metadata::render)
+            .unwrap_or_default();
 
         format!(
-            "<svg viewBox=\"{} {} {} {}\" xmlns=\"http://www.w3.org/2000/svg\">\n{}</svg>",
-            min_x, min_y, width, height, elements
+            "<svg{} viewBox=\"{}\" xmlns=\"http://www.w3.org/2000/svg\">\n{}{}</svg>",
+            size_attributes(&self.width, &self.height, ratio),
+            self.viewbox_attribute(),
+            metadata,
+            self.body()
         )
     }
 
@@ -62,10 +513,6 @@ impl Render for Svg {
             y: f64::NEG_INFINITY,
         };
         for element in &self.elements {
-            // Make exception for SvgNothing
-            if element.render().is_empty() {
-                continue;
-            }
             let (element_min, element_max) = element.get_bounds();
             if element_min.x < min.x {
                 min.x = element_min.x;
@@ -83,35 +530,247 @@ impl Render for Svg {
         (min, max)
     }
 
-    fn mark_pixels(&self, bitmap: &mut Vec<Vec<bool>>, scale: f64) {
+    fn mark_pixels(&self, bitmap: &mut Bitmap) {
         for element in &self.elements {
-            element.mark_pixels(bitmap, scale);
+            element.mark_pixels(bitmap);
         }
     }
 }
 
 impl Svg {
+    /// Create a new SVG container from a list of elements
+    pub fn new(elements: Vec<Box<dyn Render>>) -> Self {
+        Self {
+            elements,
+            padding: DEFAULT_PADDING,
+            viewbox: None,
+            width: None,
+            height: None,
+            flip_y: false,
+            sources: Vec::new(),
+            step_delay: None,
+            tag_sources: false,
+            metadata: None,
+            groups: HashMap::new(),
+            background: None,
+            border: None,
+        }
+    }
+
+    /// Return the elements as SVG, after the patterns they refer to, mirrored if the y-axis points up
+    fn body(&self) -> String {
+        // get the SVG string for each element, hiding animated elements until their step
+        let mut elements = String::new();
+        let mut open_group: Option<usize> = None;
+        let mut ids: HashMap<String, usize> = HashMap::new();
+        for (i, element) in self.elements.iter().enumerate() {
+            let mut rendered = element.render();
+            let source = self.sources.get(i).copied().flatten();
+            if rendered.is_empty() {
+                continue;
+            }
+            if let (Some(delay), Some(step)) = (self.step_delay, source.and_then(|s| s.step)) {
+                rendered = format!(
+                    "<g opacity=\"0\">\n\t<set attributeName=\"opacity\" to=\"1\" begin=\"{}s\" fill=\"freeze\"/>\n{}</g>\n",
+                    Num(step as f64 * delay),
+                    rendered
+                );
+            }
+            if let Some(source) = source.filter(|_| self.tag_sources) {
+                rendered = format!("<g data-value=\"{}\">\n{}</g>\n", source.value, rendered);
+            }
+
+            // wrap each run of elements drawn from the same value in the value's group, where a value
+            // split across layers has its later runs numbered so that ids stay unique
+            let value = source.map(|s| s.value);
+            if value != open_group {
+                if open_group.is_some() {
+                    elements.push_str("</g>\n");
+                }
+                open_group = value.filter(|v| self.groups.contains_key(v));
+                if let Some(group) = open_group.and_then(|v| self.groups.get(&v)) {
+                    let id = group.name.as_ref().map(|name| {
+                        let count = ids.entry(name.clone()).or_insert(0);
+                        *count += 1;
+                        match count {
+                            1 => format!(" id=\"{}\"", name),
+                            n => format!(" id=\"{}-{}\"", name, n),
+                        }
+                    });
+                    elements.push_str(&format!(
+                        "<g class=\"{}\"{}>\n",
+                        group.class,
+                        id.unwrap_or_default()
+                    ));
+                }
+            }
+            elements.push_str(&rendered);
+        }
+        if open_group.is_some() {
+            elements.push_str("</g>\n");
+        }
+
+        // define each pattern the elements refer to once, before the elements
+        let mut defs: Vec<String> = Vec::new();
+        for def in self.elements.iter().flat_map(|e| e.defs()) {
+            if !defs.contains(&def) {
+                defs.push(def);
+            }
+        }
+        // fill the viewbox beneath everything, and frame it above everything
+        let (min, max) = self.get_viewbox();
+        if let Some(color) = &self.background {
+            elements = format!(
+                "\t<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n{}",
+                Num(min.x),
+                Num(min.y),
+                Size(max.x - min.x),
+                Size(max.y - min.y),
+                color,
+                elements
+            );
+        }
+        if let Some((color, width)) = &self.border {
+            elements.push_str(&format!(
+                "\t<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                Num(min.x + width / 2.0),
+                Num(min.y + width / 2.0),
+                Size(max.x - min.x - width),
+                Size(max.y - min.y - width),
+                color,
+                Size(*width)
+            ));
+        }
+
+        if !defs.is_empty() {
+            elements = format!("<defs>\n{}</defs>\n{}", defs.concat(), elements);
+        }
+
+        // mirror the figure vertically, so the viewbox covers the mirrored bounds
+        if self.flip_y {
+            return format!("<g transform=\"scale(1 -1)\">\n{}</g>\n", elements);
+        }
+        elements
+    }
+
+    /// Return the value of the viewBox attribute, which covers the mirrored bounds if the y-axis points up
+    fn viewbox_attribute(&self) -> String {
+        let (min, max): (Point, Point) = self.get_viewbox();
+        let top = if self.flip_y { -max.y } else { min.y };
+        format!(
+            "{} {} {} {}",
+            Num(min.x),
+            Num(top),
+            Num(max.x - min.x),
+            Num(max.y - min.y)
+        )
+    }
+
+    /// Clip unbounded elements, such as rays, to the viewbox
+    pub fn clip_to_viewbox(&mut self) {
+        let (min, max) = self.get_viewbox();
+        for element in &mut self.elements {
+            element.clip(min, max);
+        }
+    }
+
+    /// Return the bounds of the points the unbounded elements pass through, or of the origin if there are none
+    fn anchor_bounds(&self) -> (Point, Point) {
+        let anchors: Vec<Point> = self.elements.iter().flat_map(|e| e.anchors()).collect();
+        if anchors.is_empty() {
+            return (Point { x: 0.0, y: 0.0 }, Point { x: 0.0, y: 0.0 });
+        }
+        let mut min = empty_bounds().0;
+        let mut max = empty_bounds().1;
+        for point in anchors {
+            min.x = min.x.min(point.x);
+            min.y = min.y.min(point.y);
+            max.x = max.x.max(point.x);
+            max.y = max.y.max(point.y);
+        }
+        (min, max)
+    }
+
     /// Get the minimum and maximum points of the viewbox
     pub fn get_viewbox(&self) -> (Point, Point) {
-        // calculate the appropriate viewBox
-        let (min, max): (Point, Point) = self.get_bounds();
-        let padding: f64 = 10.0;
-        let min_x: f64 = min.x - padding / 2.0;
-        let min_y: f64 = min.y - padding / 2.0;
-        let width: f64 = max.x - min.x + padding;
-        let height: f64 = max.y - min.y + padding;
-
-        // create the points
-        (
-            Point { x: min_x, y: min_y },
-            Point {
-                x: min_x + width,
-                y: min_y + height,
-            },
-        )
+        if let Some(viewbox) = self.viewbox {
+            return viewbox;
+        }
+
+        // calculate the appropriate viewBox, where a figure of only unbounded elements, such as lines, is
+        // framed around the points they pass through so they are clipped to a finite box
+        let (min, max): (Point, Point) = match self.get_bounds() {
+            (min, max) if min.x <= max.x && min.y <= max.y => (min, max),
+            _ => self.anchor_bounds(),
+        };
+        let mut min = Point {
+            x: min.x - self.padding,
+            y: min.y - self.padding,
+        };
+        let mut max = Point {
+            x: max.x + self.padding,
+            y: max.y + self.padding,
+        };
+
+        // widen the shorter side evenly to match the aspect ratio of the output dimensions
+        if let (Some(width), Some(height)) = (&self.width, &self.height) {
+            if width.unit == height.unit {
+                let aspect = width.value / height.value;
+                let (box_width, box_height) = (max.x - min.x, max.y - min.y);
+                if box_width / box_height < aspect {
+                    let extra = box_height * aspect - box_width;
+                    min.x -= extra / 2.0;
+                    max.x += extra / 2.0;
+                } else {
+                    let extra = box_width / aspect - box_height;
+                    min.y -= extra / 2.0;
+                    max.y += extra / 2.0;
+                }
+            }
+        }
+        (min, max)
     }
 }
 
+/// Return the width and height attributes of an output with the given aspect ratio, filling in a
+/// missing dimension from the ratio since not every renderer derives it from the viewbox
+fn size_attributes(width: &Option<Length>, height: &Option<Length>, ratio: f64) -> String {
+    let scaled = |length: &Length, ratio: f64| {
+        (length.unit != "%").then(|| Length {
+            value: length.value * ratio,
+            unit: length.unit.clone(),
+        })
+    };
+    let filled_width = width
+        .clone()
+        .or_else(|| height.as_ref().and_then(|h| scaled(h, ratio)));
+    let filled_height = height
+        .clone()
+        .or_else(|| width.as_ref().and_then(|w| scaled(w, 1.0 / ratio)));
+    let mut size = String::new();
+    if let Some(width) = filled_width {
+        size.push_str(&format!(" width=\"{}\"", width));
+    }
+    if let Some(height) = filled_height {
+        size.push_str(&format!(" height=\"{}\"", height));
+    }
+    size
+}
+
+/// Return bounds that contain nothing, for elements that should not affect the viewbox
+fn empty_bounds() -> (Point, Point) {
+    (
+        Point {
+            x: f64::INFINITY,
+            y: f64::INFINITY,
+        },
+        Point {
+            x: f64::NEG_INFINITY,
+            y: f64::NEG_INFINITY,
+        },
+    )
+}
+
 pub struct SvgNothing;
 
 impl Render for SvgNothing {
@@ -121,22 +780,520 @@ impl Render for SvgNothing {
     }
 
     fn get_bounds(&self) -> (Point, Point) {
-        (Point { x: 0.0, y: 0.0 }, Point { x: 0.0, y: 0.0 })
+        empty_bounds()
     }
 
-    fn mark_pixels(&self, _: &mut Vec<Vec<bool>>, _: f64) {
+    fn mark_pixels(&self, _: &mut Bitmap) {
         // Do nothing
     }
 }
 
+/// Element left out of the bounds of the figure, such as a long auxiliary line, which is clipped to the viewbox instead
+pub struct SvgUnbounded {
+    pub element: Box<dyn Render>,
+    /// Viewbox the element is clipped to, once it is known
+    pub area: Option<(Point, Point)>,
+}
+
+impl SvgUnbounded {
+    /// Id of the clip path covering the viewbox
+    const CLIP_ID: &'static str = "viewbox-clip";
+}
+
+impl Render for SvgUnbounded {
+    impl_as_any!(SvgUnbounded);
+    fn render(&self) -> String {
+        // lines are clipped exactly, and other elements are masked to the viewbox
+        let rendered = self.element.render();
+        if rendered.is_empty() || self.area.is_none() {
+            return rendered;
+        }
+        format!(
+            "<g clip-path=\"url(#{})\">\n{}</g>\n",
+            Self::CLIP_ID,
+            rendered
+        )
+    }
+
+    fn get_bounds(&self) -> (Point, Point) {
+        empty_bounds()
+    }
+
+    fn mark_pixels(&self, bitmap: &mut Bitmap) {
+        self.element.mark_pixels(bitmap);
+    }
+
+    fn apply_style(&mut self, style: &Style) {
+        self.element.apply_style(style);
+    }
+
+    fn clip(&mut self, min: Point, max: Point) {
+        self.element.clip(min, max);
+        if let Some(line) = self.element.as_any_mut().downcast_mut::<SvgLine>() {
+            match clip_segment(line.start, line.end, min, max) {
+                Some((start, end)) => (line.start, line.end) = (start, end),
+                None => self.element = Box::new(SvgNothing),
+            }
+        } else {
+            self.area = Some((min, max));
+        }
+    }
+
+    fn layer(&self) -> i32 {
+        self.element.layer()
+    }
+
+    fn anchors(&self) -> Vec<Point> {
+        self.element.anchors()
+    }
+
+    fn defs(&self) -> Vec<String> {
+        let mut defs = self.element.defs();
+        if let Some((min, max)) = self.area {
+            defs.push(format!(
+                "\t<clipPath id=\"{}\">\n\t\t<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n\t</clipPath>\n",
+                Self::CLIP_ID,
+                Num(min.x),
+                Num(min.y),
+                Num(max.x - min.x),
+                Num(max.y - min.y)
+            ));
+        }
+        defs
+    }
+}
+
+/// Coordinate grid and axes drawn beneath the figure, filling whatever viewbox it is clipped to
+pub struct SvgGrid {
+    pub spacing: f64,
+    pub area: Option<(Point, Point)>,
+    pub font_size: f64,
+    pub flip: bool,
+}
+
+impl SvgGrid {
+    /// Most grid lines drawn along each axis before the spacing is widened
+    const MAX_LINES: f64 = 100.0;
+    /// Most tick labels written along each axis
+    const MAX_TICKS: f64 = 20.0;
+
+    /// Format a tick value without floating point noise
+    fn tick(value: f64) -> String {
+        let rounded = (value * 1e9).round() / 1e9;
+        if rounded == 0.0 {
+            "0".to_string()
+        } else {
+            Num(rounded).to_string()
+        }
+    }
+
+    /// Render a tick label, mirrored back upright in a mirrored figure
+    fn text(&self, x: f64, y: f64, anchor: &str, value: f64) -> String {
+        let (y, transform) = if self.flip {
+            (-y, " transform=\"scale(1 -1)\"")
+        } else {
+            (y, "")
+        };
+        format!(
+            "\t<text x=\"{}\" y=\"{}\"{} text-anchor=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" fill=\"gray\">{}</text>\n",
+            Num(x),
+            Num(y),
+            transform,
+            anchor,
+            Size(self.font_size),
+            Self::tick(value)
+        )
+    }
+}
+
+impl Render for SvgGrid {
+    impl_as_any!(SvgGrid);
+    fn layer(&self) -> i32 {
+        GRID_LAYER
+    }
+    fn render(&self) -> String {
+        let (min, max) = match self.area {
+            Some(area) => area,
+            None => return String::new(),
+        };
+
+        // widen the spacing for large figures so the grid stays readable
+        let mut spacing = self.spacing;
+        while (max.x - min.x).max(max.y - min.y) / spacing > Self::MAX_LINES {
+            spacing *= 2.0;
+        }
+        let lines = |low: f64, high: f64| {
+            let first = (low / spacing).ceil() as i64;
+            let last = (high / spacing).floor() as i64;
+            first..=last
+        };
+
+        // draw the grid lines, with the axes darker where they are in view
+        let mut grid = String::new();
+        for k in lines(min.x, max.x) {
+            let x = k as f64 * spacing;
+            let (stroke, width) = if k == 0 {
+                ("gray", 0.02)
+            } else {
+                ("#ddd", 0.01)
+            };
+            grid.push_str(&format!(
+                "\t<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                Num(x),
+                Num(min.y),
+                Num(x),
+                Num(max.y),
+                stroke,
+                width
+            ));
+        }
+        for k in lines(min.y, max.y) {
+            let y = k as f64 * spacing;
+            let (stroke, width) = if k == 0 {
+                ("gray", 0.02)
+            } else {
+                ("#ddd", 0.01)
+            };
+            grid.push_str(&format!(
+                "\t<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                Num(min.x),
+                Num(y),
+                Num(max.x),
+                Num(y),
+                stroke,
+                width
+            ));
+        }
+
+        // write tick labels beside the axes, or just inside the edges when an axis is out of view
+        let gap = self.font_size * 0.3;
+        let x_in_view = min.x <= 0.0 && 0.0 <= max.x;
+        let y_in_view = min.y <= 0.0 && 0.0 <= max.y;
+        let (axis_x, anchor) = if x_in_view {
+            (-gap, "end")
+        } else {
+            (min.x + gap, "start")
+        };
+        let baseline = match (y_in_view, self.flip) {
+            // below the axis, which is towards negative y when mirrored
+            (true, false) => gap + self.font_size,
+            (true, true) => -(gap + self.font_size),
+            // inside the top edge, or inside the bottom edge when mirrored
+            (false, false) => min.y + gap + self.font_size,
+            (false, true) => min.y + gap,
+        };
+        let middle = if self.flip { -1.0 } else { 1.0 } * self.font_size / 3.0;
+        let x_lines = lines(min.x, max.x);
+        let y_lines = lines(min.y, max.y);
+        let x_stride = ((x_lines.clone().count() as f64 / Self::MAX_TICKS).ceil() as i64).max(1);
+        let y_stride = ((y_lines.clone().count() as f64 / Self::MAX_TICKS).ceil() as i64).max(1);
+        for k in x_lines.filter(|k| k % x_stride == 0 && *k != 0) {
+            let x = k as f64 * spacing;
+            grid.push_str(&self.text(x, baseline, "middle", x));
+        }
+        for k in y_lines.filter(|k| k % y_stride == 0 && *k != 0) {
+            let y = k as f64 * spacing;
+            grid.push_str(&self.text(axis_x, y + middle, anchor, y));
+        }
+        grid
+    }
+
+    fn get_bounds(&self) -> (Point, Point) {
+        // the grid fills the viewbox rather than deciding it
+        empty_bounds()
+    }
+
+    fn mark_pixels(&self, _: &mut Bitmap) {
+        // Do nothing, since labels may sit over the grid
+    }
+
+    fn clip(&mut self, min: Point, max: Point) {
+        self.area = Some((min, max));
+    }
+}
+
+/// Key of swatches and names for the styled values of a figure, placed in a corner clear of the geometry
+pub struct SvgLegend {
+    /// Name and style of each value in the key
+    pub entries: Vec<(String, Style)>,
+    /// Corner the key is placed in, or None to choose the clearest one
+    pub corner: Option<Corner>,
+    /// Minimum corner of the key, once it has been placed
+    pub position: Option<Point>,
+    pub font: Font,
+    pub flip: bool,
+}
+
+impl SvgLegend {
+    /// Margin inside the key and around the figure's edge, in font sizes
+    const MARGIN: f64 = 0.4;
+    /// Height of each row, in font sizes
+    const ROW: f64 = 1.2;
+    /// Width of each swatch, in font sizes
+    const SWATCH: f64 = 1.2;
+
+    /// Return the width and height of the key
+    fn size(&self) -> (f64, f64) {
+        let text = self
+            .entries
+            .iter()
+            .map(|(name, _)| label_width(name))
+            .fold(0.0, f64::max);
+        let width = self.font.size * (3.0 * Self::MARGIN + Self::SWATCH + text);
+        let height = self.font.size * (2.0 * Self::MARGIN + Self::ROW * self.entries.len() as f64);
+        (width, height)
+    }
+
+    /// Place the key in the corner of the viewbox that covers the fewest marked pixels, and mark it
+    pub fn place(&mut self, bitmap: &mut Bitmap, min: Point, max: Point) {
+        let (width, height) = self.size();
+        let margin = self.font.size * Self::MARGIN;
+        let (left, right) = (min.x + margin, max.x - margin - width);
+        let (low, high) = (min.y + margin, max.y - margin - height);
+
+        // the top of the figure is at its largest y when mirrored
+        let (top, bottom) = if self.flip { (high, low) } else { (low, high) };
+        let corners = [
+            (Corner::TopRight, right, top),
+            (Corner::TopLeft, left, top),
+            (Corner::BottomRight, right, bottom),
+            (Corner::BottomLeft, left, bottom),
+        ];
+
+        // count the pixels each candidate covers, keeping the first of any ties
+        let pixels = |x: f64, y: f64| {
+            let start = bitmap.to_pixel(Point { x, y });
+            let end = bitmap.to_pixel(Point {
+                x: x + width,
+                y: y + height,
+            });
+            (start.x as i32..=end.x as i32)
+                .flat_map(move |px| (start.y as i32..=end.y as i32).map(move |py| (px, py)))
+        };
+        let mut best: Option<(usize, Point)> = None;
+        for (corner, x, y) in corners {
+            if self.corner.is_some_and(|c| c != corner) {
+                continue;
+            }
+            let taken = pixels(x, y)
+                .filter(|(px, py)| bitmap.is_marked(*px, *py))
+                .count();
+            if best.is_none_or(|(fewest, _)| taken < fewest) {
+                best = Some((taken, Point { x, y }));
+            }
+        }
+
+        // labels are placed afterwards, so they avoid the key
+        if let Some((_, position)) = best {
+            for (px, py) in pixels(position.x, position.y).collect::<Vec<_>>() {
+                bitmap.mark(px, py);
+            }
+            self.position = Some(position);
+        }
+    }
+}
+
+impl Render for SvgLegend {
+    impl_as_any!(SvgLegend);
+    fn layer(&self) -> i32 {
+        LABEL_LAYER
+    }
+    fn render(&self) -> String {
+        let position = match self.position {
+            Some(position) if !self.entries.is_empty() => position,
+            _ => return String::new(),
+        };
+        let size = self.font.size;
+        let (width, height) = self.size();
+        let mut legend = format!(
+            "\t<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"white\" fill-opacity=\"0.8\" stroke=\"gray\" stroke-width=\"{}\"/>\n",
+            Num(position.x),
+            Num(position.y),
+            Size(width),
+            Size(height),
+            Size(size * 0.04)
+        );
+
+        // rows run down the figure as it is displayed, so up when mirrored
+        let x = position.x + size * Self::MARGIN;
+        for (i, (name, style)) in self.entries.iter().enumerate() {
+            let offset = size * (Self::MARGIN + Self::ROW * (i as f64 + 0.5));
+            let (middle, baseline) = if self.flip {
+                let middle = position.y + height - offset;
+                (middle, middle - size * 0.35)
+            } else {
+                let middle = position.y + offset;
+                (middle, middle + size * 0.35)
+            };
+
+            // filled values are shown by a filled box, and others by a stroke
+            if style.fill.is_some() || style.hatch.is_some() {
+                legend.push_str(&format!(
+                    "\t<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"{}/>\n",
+                    Num(x),
+                    Num(middle - size * 0.3),
+                    Size(size * Self::SWATCH),
+                    Size(size * 0.6),
+                    style.fill(),
+                    style.stroke(),
+                    Size(style.stroke_width()),
+                    style.attributes()
+                ));
+            } else {
+                legend.push_str(&format!(
+                    "\t<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\"{}/>\n",
+                    Num(x),
+                    Num(middle),
+                    Num(x + size * Self::SWATCH),
+                    Num(middle),
+                    style.stroke(),
+                    Size(style.stroke_width()),
+                    style.attributes()
+                ));
+            }
+
+            // in a mirrored figure, mirror the text back so it reads upright
+            let (y, transform) = if self.flip {
+                (-baseline, " transform=\"scale(1 -1)\"")
+            } else {
+                (baseline, "")
+            };
+            legend.push_str(&format!(
+                "\t<text x=\"{}\" y=\"{}\"{} font-family=\"{}\" font-size=\"{}\" fill=\"{}\">{}</text>\n",
+                Num(x + size * (Self::SWATCH + Self::MARGIN)),
+                Num(y),
+                transform,
+                self.font.family,
+                Size(size),
+                self.font.color,
+                format_label(name)
+            ));
+        }
+        legend
+    }
+
+    fn get_bounds(&self) -> (Point, Point) {
+        // the key is placed inside the viewbox rather than deciding it
+        empty_bounds()
+    }
+
+    fn mark_pixels(&self, _: &mut Bitmap) {
+        // Do nothing
+    }
+
+    fn defs(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .flat_map(|(_, style)| style.defs())
+            .collect()
+    }
+}
+
+/// Font used to draw labels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Font {
+    pub family: String,
+    pub size: f64,
+    pub color: String,
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Font {
+            family: "serif".to_string(),
+            size: 0.5,
+            color: "black".to_string(),
+        }
+    }
+}
+
+/// Return the unicode character for a LaTeX-style Greek letter name such as alpha or Omega
+fn greek_letter(name: &str) -> Option<char> {
+    let lower = [
+        "alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta", "iota", "kappa",
+        "lambda", "mu", "nu", "xi", "omicron", "pi", "rho", "", "sigma", "tau", "upsilon", "phi",
+        "chi", "psi", "omega",
+    ];
+    // the empty name skips the final sigma, so the upper and lower cases line up
+    let index = lower
+        .iter()
+        .position(|l| !l.is_empty() && l.eq_ignore_ascii_case(name))?;
+    let base = if name.starts_with(|c: char| c.is_ascii_uppercase()) {
+        0x391
+    } else {
+        0x3b1
+    };
+    char::from_u32(base + index as u32)
+}
+
+/// Format label text as SVG, turning _ and ^ into subscripts and superscripts, \name into Greek
+/// letters, and ' into primes
+fn format_label(text: &str) -> String {
+    let mut formatted = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '_' | '^' => {
+                // take a braced group, or else a single character
+                let mut script = String::new();
+                if chars.peek() == Some(&'{') {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            break;
+                        }
+                        script.push(c);
+                    }
+                } else if let Some(c) = chars.next() {
+                    script.push(c);
+                }
+                let shift = if c == '_' { "sub" } else { "super" };
+                formatted.push_str(&format!(
+                    "<tspan baseline-shift=\"{}\" font-size=\"70%\">{}</tspan>",
+                    shift,
+                    format_label(&script)
+                ));
+            }
+            '\\' => {
+                let mut name = String::new();
+                while let Some(c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                    name.push(*c);
+                    chars.next();
+                }
+                match greek_letter(&name) {
+                    Some(letter) => formatted.push(letter),
+                    None => {
+                        formatted.push('\\');
+                        formatted.push_str(&name);
+                    }
+                }
+            }
+            '\'' => formatted.push('\u{2032}'),
+            '&' => formatted.push_str("&amp;"),
+            '<' => formatted.push_str("&lt;"),
+            '>' => formatted.push_str("&gt;"),
+            c => formatted.push(c),
+        }
+    }
+    formatted
+}
+
 pub struct SvgLabel {
     pub text: String,
     pub pt: Point,
     pub position: Option<Point>,
+    /// Whether the label names a variable, and so is only shown when labelling is requested
+    pub auto: bool,
+    pub font: Font,
+    /// Whether the label is drawn in a vertically mirrored figure, and so must be mirrored back
+    pub flip: bool,
 }
 
 impl Render for SvgLabel {
     impl_as_any!(SvgLabel);
+    fn layer(&self) -> i32 {
+        LABEL_LAYER
+    }
     fn render(&self) -> String {
         // extract point from option
         let point = match self.position {
@@ -144,21 +1301,34 @@ impl Render for SvgLabel {
             None => return "".to_string(),
         };
 
+        // in a mirrored figure, mirror the text back so it reads upright
+        let (y, transform) = if self.flip {
+            (-point.y, " transform=\"scale(1 -1)\"")
+        } else {
+            (point.y, "")
+        };
+
         format!(
-            "\t<text x=\"{}\" y=\"{}\" font-family=\"serif\" font-size=\"0.5\" fill=\"black\">{}</text>\n",
-            point.x, point.y, self.text
+            "\t<text x=\"{}\" y=\"{}\"{} font-family=\"{}\" font-size=\"{}\" fill=\"{}\">{}</text>\n",
+            Num(point.x),
+            Num(y),
+            transform,
+            self.font.family,
+            Size(self.font.size),
+            self.font.color,
+            format_label(&self.text)
         )
     }
 
     fn get_bounds(&self) -> (Point, Point) {
-        let point = match self.position {
-            Some(point) => point,
-            None => Point { x: 0.0, y: 0.0 },
-        };
-        (point, point)
+        // a label is only placed after the viewbox is known
+        match self.position {
+            Some(point) => (point, point),
+            None => empty_bounds(),
+        }
     }
 
-    fn mark_pixels(&self, _: &mut Vec<Vec<bool>>, _: f64) {
+    fn mark_pixels(&self, _: &mut Bitmap) {
         // Do nothing
     }
 }
@@ -170,21 +1340,202 @@ impl SvgLabel {
     }
 }
 
+/// Fragment of SVG written as given, which only widens the viewbox to the extents given with it
+pub struct SvgRaw {
+    pub svg: String,
+    pub extents: Option<(Point, Point)>,
+}
+
+impl Render for SvgRaw {
+    impl_as_any!(SvgRaw);
+    fn render(&self) -> String {
+        format!("\t{}\n", self.svg.trim())
+    }
+
+    fn get_bounds(&self) -> (Point, Point) {
+        match self.extents {
+            Some((first, second)) => (
+                Point {
+                    x: first.x.min(second.x),
+                    y: first.y.min(second.y),
+                },
+                Point {
+                    x: first.x.max(second.x),
+                    y: first.y.max(second.y),
+                },
+            ),
+            None => empty_bounds(),
+        }
+    }
+
+    fn mark_pixels(&self, _: &mut Bitmap) {
+        // The contents of the fragment are unknown, so labels are not kept clear of it
+    }
+}
+
 pub struct SvgPolygon {
     pub points: Vec<Point>,
+    pub style: Style,
 }
 
 impl Render for SvgPolygon {
     impl_as_any!(SvgPolygon);
+    impl_apply_style!();
     fn render(&self) -> String {
         let mut points = String::new();
         for point in &self.points {
-            points.push_str(&format!("{},{} ", point.x, point.y));
+            points.push_str(&format!("{},{} ", Num(point.x), Num(point.y)));
+        }
+        format!(
+            "\t<polygon points=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"{}/>\n",
+            points,
+            self.style.fill(),
+            self.style.stroke(),
+            Size(self.style.stroke_width()),
+            self.style.attributes()
+        )
+    }
+
+    fn get_bounds(&self) -> (Point, Point) {
+        let mut min = Point {
+            x: f64::INFINITY,
+            y: f64::INFINITY,
+        };
+        let mut max = Point {
+            x: f64::NEG_INFINITY,
+            y: f64::NEG_INFINITY,
+        };
+        for point in &self.points {
+            if point.x < min.x {
+                min.x = point.x;
+            }
+            if point.y < min.y {
+                min.y = point.y;
+            }
+            if point.x > max.x {
+                max.x = point.x;
+            }
+            if point.y > max.y {
+                max.y = point.y;
+            }
+        }
+        (min, max)
+    }
+
+    fn mark_pixels(&self, bitmap: &mut Bitmap) {
+        // draw lines between consecutive points
+        for i in 0..self.points.len() {
+            // scale the points
+            let start = bitmap.to_pixel(self.points[i]);
+            let end = bitmap.to_pixel(self.points[(i + 1) % self.points.len()]);
+
+            // mark the line
+            let points: Vec<(i32, i32)> = bresenham(start, end);
+            for (x, y) in points {
+                bitmap.mark(x, y);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathCommand {
+    Move(Point),
+    Line(Point),
+    Quadratic(Point, Point),
+    Cubic(Point, Point, Point),
+    Close,
+}
+
+impl PathCommand {
+    /// Number of segments used to approximate a curved command
+    const CURVE_STEPS: usize = 16;
+
+    /// Return the points to draw lines through when following this command from the current point
+    fn trace(&self, current: Point) -> Vec<Point> {
+        let steps = Self::CURVE_STEPS;
+        let t_at = |i: usize| i as f64 / steps as f64;
+        match self {
+            PathCommand::Move(p) | PathCommand::Line(p) => vec![*p],
+            PathCommand::Quadratic(c, e) => (1..=steps)
+                .map(|i| {
+                    let t = t_at(i);
+                    let (u, v, w) = ((1.0 - t).powi(2), 2.0 * t * (1.0 - t), t * t);
+                    Point {
+                        x: u * current.x + v * c.x + w * e.x,
+                        y: u * current.y + v * c.y + w * e.y,
+                    }
+                })
+                .collect(),
+            PathCommand::Cubic(c1, c2, e) => (1..=steps)
+                .map(|i| {
+                    let t = t_at(i);
+                    let s = 1.0 - t;
+                    let (u, v, w, z) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+                    Point {
+                        x: u * current.x + v * c1.x + w * c2.x + z * e.x,
+                        y: u * current.y + v * c1.y + w * c2.y + z * e.y,
+                    }
+                })
+                .collect(),
+            PathCommand::Close => Vec::new(),
+        }
+    }
+}
+
+pub struct SvgPath {
+    pub commands: Vec<PathCommand>,
+    pub fill: bool,
+    pub style: Style,
+}
+
+impl Render for SvgPath {
+    impl_as_any!(SvgPath);
+    impl_apply_style!(fill, FILL_LAYER);
+    fn render(&self) -> String {
+        let mut data = String::new();
+        for command in &self.commands {
+            match command {
+                PathCommand::Move(p) => data.push_str(&format!("M {} {} ", Num(p.x), Num(p.y))),
+                PathCommand::Line(p) => data.push_str(&format!("L {} {} ", Num(p.x), Num(p.y))),
+                PathCommand::Quadratic(c, e) => data.push_str(&format!(
+                    "Q {} {} {} {} ",
+                    Num(c.x),
+                    Num(c.y),
+                    Num(e.x),
+                    Num(e.y)
+                )),
+                PathCommand::Cubic(c1, c2, e) => data.push_str(&format!(
+                    "C {} {} {} {} {} {} ",
+                    Num(c1.x),
+                    Num(c1.y),
+                    Num(c2.x),
+                    Num(c2.y),
+                    Num(e.x),
+                    Num(e.y)
+                )),
+                PathCommand::Close => data.push_str("Z "),
+            }
+        }
+
+        // filled paths are shaded regions, so they have no outline
+        if self.fill {
+            format!(
+                "\t<path d=\"{}\" fill=\"{}\" fill-rule=\"evenodd\" stroke=\"none\"{}/>\n",
+                data.trim_end(),
+                self.style.fill_or("lightgray"),
+                self.style.attributes()
+            )
+        } else {
+            format!(
+                "\t<path d=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"{}/>\n",
+                data.trim_end(),
+                self.style.fill(),
+                self.style.stroke(),
+                Size(self.style.stroke_width()),
+                self.style.attributes()
+            )
         }
-        format!(
-            "\t<polygon points=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"0.02\"/>\n",
-            points
-        )
     }
 
     fn get_bounds(&self) -> (Point, Point) {
@@ -196,51 +1547,54 @@ impl Render for SvgPolygon {
             x: f64::NEG_INFINITY,
             y: f64::NEG_INFINITY,
         };
-        for point in &self.points {
-            if point.x < min.x {
-                min.x = point.x;
-            }
-            if point.y < min.y {
-                min.y = point.y;
-            }
-            if point.x > max.x {
-                max.x = point.x;
-            }
-            if point.y > max.y {
-                max.y = point.y;
+        for command in &self.commands {
+            // curves stay within the hull of their control points
+            let points = match command {
+                PathCommand::Move(p) | PathCommand::Line(p) => vec![*p],
+                PathCommand::Quadratic(c, e) => vec![*c, *e],
+                PathCommand::Cubic(c1, c2, e) => vec![*c1, *c2, *e],
+                PathCommand::Close => Vec::new(),
+            };
+            for point in points {
+                min.x = min.x.min(point.x);
+                min.y = min.y.min(point.y);
+                max.x = max.x.max(point.x);
+                max.y = max.y.max(point.y);
             }
         }
         (min, max)
     }
 
-    fn mark_pixels(&self, bitmap: &mut Vec<Vec<bool>>, scale: f64) {
-        // set height and width of the bitmap
-        let height = bitmap.len();
-        let width = bitmap[0].len();
-
-        // helper function to mark a single pixel
-        let mut mark_pixel = |x: i32, y: i32| {
-            if x >= 0 && x < width as i32 && y >= 0 && y < height as i32 {
-                bitmap[y as usize][x as usize] = true;
-            }
-        };
-
-        // draw lines between consecutive points
-        for i in 0..self.points.len() {
-            // scale the points
-            let start = Point {
-                x: self.points[i].x * scale,
-                y: self.points[i].y * scale,
-            };
-            let end = Point {
-                x: self.points[(i + 1) % self.points.len()].x * scale,
-                y: self.points[(i + 1) % self.points.len()].y * scale,
+    fn mark_pixels(&self, bitmap: &mut Bitmap) {
+        // draw lines along the path, returning to the subpath start on close
+        let mut start: Option<Point> = None;
+        let mut current: Option<Point> = None;
+        for command in &self.commands {
+            let targets = match command {
+                PathCommand::Move(p) => {
+                    start = Some(*p);
+                    current = Some(*p);
+                    continue;
+                }
+                PathCommand::Close => match start {
+                    Some(p) => vec![p],
+                    None => continue,
+                },
+                // curves are approximated by lines from the current point
+                _ => match current {
+                    Some(prev) => command.trace(prev),
+                    None => continue,
+                },
             };
-
-            // mark the line
-            let points: Vec<(i32, i32)> = bresenham(start, end);
-            for (x, y) in points {
-                mark_pixel(x, y);
+            for next in targets {
+                if let Some(prev) = current {
+                    let from = bitmap.to_pixel(prev);
+                    let to = bitmap.to_pixel(next);
+                    for (x, y) in bresenham(from, to) {
+                        bitmap.mark(x, y);
+                    }
+                }
+                current = Some(next);
             }
         }
     }
@@ -249,14 +1603,22 @@ impl Render for SvgPolygon {
 pub struct SvgLine {
     pub start: Point,
     pub end: Point,
+    pub style: Style,
 }
 
 impl Render for SvgLine {
     impl_as_any!(SvgLine);
+    impl_apply_style!();
     fn render(&self) -> String {
         format!(
-            "\t<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\" stroke-width=\"0.02\"/>\n",
-            self.start.x, self.start.y, self.end.x, self.end.y
+            "\t<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\"{}/>\n",
+            Num(self.start.x),
+            Num(self.start.y),
+            Num(self.end.x),
+            Num(self.end.y),
+            self.style.stroke(),
+            Size(self.style.stroke_width()),
+            self.style.attributes()
         )
     }
 
@@ -272,32 +1634,15 @@ impl Render for SvgLine {
         (min, max)
     }
 
-    fn mark_pixels(&self, bitmap: &mut Vec<Vec<bool>>, scale: f64) {
-        // set height and width of the bitmap
-        let height = bitmap.len();
-        let width = bitmap[0].len();
-
-        // helper function to mark a single pixel
-        let mut mark_pixel = |x: i32, y: i32| {
-            if x >= 0 && x < width as i32 && y >= 0 && y < height as i32 {
-                bitmap[y as usize][x as usize] = true;
-            }
-        };
-
+    fn mark_pixels(&self, bitmap: &mut Bitmap) {
         // scale start and end points
-        let start = Point {
-            x: (self.start.x * scale).round(),
-            y: (self.start.y * scale).round(),
-        };
-        let end = Point {
-            x: (self.end.x * scale).round(),
-            y: (self.end.y * scale).round(),
-        };
+        let start = bitmap.to_pixel(self.start);
+        let end = bitmap.to_pixel(self.end);
 
         // draw line
         let points: Vec<(i32, i32)> = bresenham(start, end);
         for (x, y) in points {
-            mark_pixel(x, y);
+            bitmap.mark(x, y);
         }
     }
 }
@@ -306,19 +1651,28 @@ pub struct SvgCircle {
     pub center: Point,
     pub radius: f64,
     pub fill: bool,
+    pub style: Style,
 }
 
 impl Render for SvgCircle {
     impl_as_any!(SvgCircle);
+    impl_apply_style!(fill, POINT_LAYER);
     fn render(&self) -> String {
-        let mut fill_value = "none";
+        // filled circles are drawn points, so they take the stroke color unless given a fill
+        let mut fill_value = self.style.fill();
         if self.fill {
-            fill_value = "black";
+            fill_value = self.style.fill_or(self.style.stroke());
         }
 
         format!(
-            "\t<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" stroke=\"black\" stroke-width=\"0.02\"/>\n",
-            self.center.x, self.center.y, self.radius, fill_value
+            "\t<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"{}/>\n",
+            Num(self.center.x),
+            Num(self.center.y),
+            Size(self.radius),
+            fill_value,
+            self.style.stroke(),
+            Size(self.style.stroke_width()),
+            self.style.attributes()
         )
     }
 
@@ -334,35 +1688,26 @@ impl Render for SvgCircle {
         (min, max)
     }
 
-    fn mark_pixels(&self, bitmap: &mut Vec<Vec<bool>>, scale: f64) {
-        // set height and width of the bitmap
-        let height = bitmap.len();
-        let width = bitmap[0].len();
-
-        // helper function to mark a single pixel
-        let mut mark_pixel = |x: i32, y: i32| {
-            if x >= 0 && x < width as i32 && y >= 0 && y < height as i32 {
-                bitmap[y as usize][x as usize] = true;
-            }
-        };
-
+    fn mark_pixels(&self, bitmap: &mut Bitmap) {
         // scale center point
-        let center_x: i32 = (self.center.x * scale).round() as i32;
-        let center_y: i32 = (self.center.y * scale).round() as i32;
+        let center = bitmap.to_pixel(self.center);
+        let center_x: i32 = center.x.round() as i32;
+        let center_y: i32 = center.y.round() as i32;
 
         // draw circle
+        let radius = (self.radius * bitmap.scale()) as i32;
         let mut x = 0;
-        let mut y = (self.radius * scale) as i32;
-        let mut d = ((3.0 - 2.0 * self.radius) * scale) as i32;
+        let mut y = radius;
+        let mut d = 3 - 2 * radius;
         while x <= y {
-            mark_pixel(center_x + x, center_y + y);
-            mark_pixel(center_x + x, center_y - y);
-            mark_pixel(center_x - x, center_y + y);
-            mark_pixel(center_x - x, center_y - y);
-            mark_pixel(center_x + y, center_y + x);
-            mark_pixel(center_x + y, center_y - x);
-            mark_pixel(center_x - y, center_y + x);
-            mark_pixel(center_x - y, center_y - x);
+            bitmap.mark(center_x + x, center_y + y);
+            bitmap.mark(center_x + x, center_y - y);
+            bitmap.mark(center_x - x, center_y + y);
+            bitmap.mark(center_x - x, center_y - y);
+            bitmap.mark(center_x + y, center_y + x);
+            bitmap.mark(center_x + y, center_y - x);
+            bitmap.mark(center_x - y, center_y + x);
+            bitmap.mark(center_x - y, center_y - x);
             if d < 0 {
                 d += 4 * x + 6;
             } else {
@@ -374,87 +1719,826 @@ impl Render for SvgCircle {
     }
 }
 
-fn label(svg: &mut Svg) {
-    // mark pixels on bitmap
-    let (_, max_point): (Point, Point) = svg.get_viewbox();
-    let scale = 10.0;
-    let mut bitmap: Vec<Vec<bool>> =
-        vec![vec![false; (max_point.x * scale) as usize]; (max_point.y * scale) as usize];
-    svg.mark_pixels(&mut bitmap, scale);
+pub struct SvgRay {
+    pub origin: Point,
+    pub through: Point,
+    pub end: Option<Point>,
+    pub style: Style,
+}
+
+impl SvgRay {
+    /// Return the line the ray is drawn as, which runs to the clipped end if known
+    fn as_line(&self) -> SvgLine {
+        SvgLine {
+            start: self.origin,
+            end: self.end.unwrap_or(self.through),
+            style: self.style.clone(),
+        }
+    }
+}
+
+impl Render for SvgRay {
+    impl_as_any!(SvgRay);
+    impl_apply_style!();
+    fn render(&self) -> String {
+        self.as_line().render()
+    }
+
+    fn get_bounds(&self) -> (Point, Point) {
+        // only the origin counts towards the bounds, as the ray extends to the viewbox
+        (self.origin, self.origin)
+    }
+
+    fn mark_pixels(&self, bitmap: &mut Bitmap) {
+        self.as_line().mark_pixels(bitmap);
+    }
+
+    fn clip(&mut self, min: Point, max: Point) {
+        self.end = Some(ray_box_exit(self.origin, self.through, min, max));
+    }
+}
+
+pub struct SvgInfiniteLine {
+    pub p: Point,
+    pub q: Point,
+    pub ends: Option<(Point, Point)>,
+    pub style: Style,
+}
+
+impl Render for SvgInfiniteLine {
+    impl_as_any!(SvgInfiniteLine);
+    impl_apply_style!();
+    fn render(&self) -> String {
+        // the line is only drawn once it has been clipped to the viewbox
+        match self.ends {
+            Some((start, end)) => SvgLine {
+                start,
+                end,
+                style: self.style.clone(),
+            }
+            .render(),
+            None => String::new(),
+        }
+    }
+
+    fn get_bounds(&self) -> (Point, Point) {
+        // an infinite line does not count towards the bounds
+        empty_bounds()
+    }
+
+    fn mark_pixels(&self, bitmap: &mut Bitmap) {
+        if let Some((start, end)) = self.ends {
+            SvgLine {
+                start,
+                end,
+                style: self.style.clone(),
+            }
+            .mark_pixels(bitmap);
+        }
+    }
+
+    fn clip(&mut self, min: Point, max: Point) {
+        self.ends = clip_line(self.p, self.q, min, max);
+    }
+
+    fn anchors(&self) -> Vec<Point> {
+        vec![self.p, self.q]
+    }
+}
+
+/// A curve made of unbounded branches, traced as a path once the viewbox is known
+pub struct SvgCurve {
+    pub branches: Vec<Box<dyn Fn(f64) -> Point>>,
+    pub anchors: Vec<Point>,
+    pub path: Option<SvgPath>,
+    pub style: Style,
+}
+
+impl SvgCurve {
+    /// Number of segments used to trace each branch
+    const SAMPLES: usize = 512;
+}
+
+impl Render for SvgCurve {
+    impl_as_any!(SvgCurve);
+    impl_apply_style!();
+    fn render(&self) -> String {
+        match &self.path {
+            Some(path) => path.render(),
+            None => String::new(),
+        }
+    }
+
+    fn get_bounds(&self) -> (Point, Point) {
+        // only the anchors, such as foci and vertices, count towards the bounds
+        let mut min = empty_bounds().0;
+        let mut max = empty_bounds().1;
+        for point in &self.anchors {
+            min.x = min.x.min(point.x);
+            min.y = min.y.min(point.y);
+            max.x = max.x.max(point.x);
+            max.y = max.y.max(point.y);
+        }
+        (min, max)
+    }
+
+    fn mark_pixels(&self, bitmap: &mut Bitmap) {
+        if let Some(path) = &self.path {
+            path.mark_pixels(bitmap);
+        }
+    }
+
+    fn clip(&mut self, min: Point, max: Point) {
+        let outside = |p: Point| p.x < min.x || p.x > max.x || p.y < min.y || p.y > max.y;
+
+        let mut commands = Vec::new();
+        for branch in &self.branches {
+            // widen the parameter range until both ends of the branch leave the viewbox
+            let mut range = 1.0;
+            while (!outside(branch(range)) || !outside(branch(-range))) && range < 1e6 {
+                range *= 2.0;
+            }
+
+            // trace the branch, keeping only the segments that touch the viewbox
+            let mut previous: Option<Point> = None;
+            for i in 0..=Self::SAMPLES {
+                let point = branch(-range + 2.0 * range * i as f64 / Self::SAMPLES as f64);
+                match previous {
+                    Some(p) if !(outside(p) && outside(point)) => {
+                        if !matches!(commands.last(), Some(PathCommand::Line(q)) if *q == p) {
+                            commands.push(PathCommand::Move(p));
+                        }
+                        commands.push(PathCommand::Line(point));
+                    }
+                    _ => {}
+                }
+                previous = Some(point);
+            }
+        }
+
+        self.path = Some(SvgPath {
+            commands,
+            fill: false,
+            style: self.style.clone(),
+        });
+    }
+}
+
+pub struct SvgEllipse {
+    pub center: Point,
+    pub rx: f64,
+    pub ry: f64,
+    pub rotation: f64,
+    pub style: Style,
+}
+
+impl SvgEllipse {
+    /// Return the point on the ellipse at the given parameter in radians
+    fn point_at(&self, t: f64) -> Point {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let x = self.rx * t.cos();
+        let y = self.ry * t.sin();
+        Point {
+            x: self.center.x + x * cos - y * sin,
+            y: self.center.y + x * sin + y * cos,
+        }
+    }
+}
+
+impl Render for SvgEllipse {
+    impl_as_any!(SvgEllipse);
+    impl_apply_style!();
+    fn render(&self) -> String {
+        format!(
+            "\t<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" transform=\"rotate({} {} {})\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"{}/>\n",
+            Num(self.center.x),
+            Num(self.center.y),
+            Size(self.rx),
+            Size(self.ry),
+            Num(self.rotation),
+            Num(self.center.x),
+            Num(self.center.y),
+            self.style.fill(),
+            self.style.stroke(),
+            Size(self.style.stroke_width()),
+            self.style.attributes()
+        )
+    }
+
+    fn get_bounds(&self) -> (Point, Point) {
+        // calculate the half extents of the rotated ellipse
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let half_width = ((self.rx * cos).powi(2) + (self.ry * sin).powi(2)).sqrt();
+        let half_height = ((self.rx * sin).powi(2) + (self.ry * cos).powi(2)).sqrt();
+        let min = Point {
+            x: self.center.x - half_width,
+            y: self.center.y - half_height,
+        };
+        let max = Point {
+            x: self.center.x + half_width,
+            y: self.center.y + half_height,
+        };
+        (min, max)
+    }
+
+    fn mark_pixels(&self, bitmap: &mut Bitmap) {
+        // approximate the ellipse with line segments, enough to keep each under a pixel or two
+        let steps =
+            ((self.rx.max(self.ry) * bitmap.scale() * 2.0 * std::f64::consts::PI) as usize).max(16);
+        for i in 0..steps {
+            let t0 = i as f64 / steps as f64 * 2.0 * std::f64::consts::PI;
+            let t1 = (i + 1) as f64 / steps as f64 * 2.0 * std::f64::consts::PI;
+            let start = self.point_at(t0);
+            let end = self.point_at(t1);
+            let start = bitmap.to_pixel(start);
+            let end = bitmap.to_pixel(end);
+            for (x, y) in bresenham(start, end) {
+                bitmap.mark(x, y);
+            }
+        }
+    }
+}
+
+pub struct SvgArc {
+    pub center: Point,
+    pub radius: f64,
+    pub start: f64,
+    pub sweep: f64,
+    pub style: Style,
+}
+
+impl SvgArc {
+    /// Return the point on the arc's circle at the given angle in radians
+    fn point_at(&self, angle: f64) -> Point {
+        Point {
+            x: self.center.x + self.radius * angle.cos(),
+            y: self.center.y + self.radius * angle.sin(),
+        }
+    }
+}
+
+impl Render for SvgArc {
+    impl_as_any!(SvgArc);
+    impl_apply_style!();
+    fn render(&self) -> String {
+        let start = self.point_at(self.start);
+        let end = self.point_at(self.start + self.sweep);
+        let large_arc = (self.sweep.abs() > std::f64::consts::PI) as u8;
+        let sweep = (self.sweep > 0.0) as u8;
+        format!(
+            "\t<path d=\"M {} {} A {} {} 0 {} {} {} {}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"{}/>\n",
+            Num(start.x),
+            Num(start.y),
+            Size(self.radius),
+            Size(self.radius),
+            large_arc,
+            sweep,
+            Num(end.x),
+            Num(end.y),
+            self.style.fill(),
+            self.style.stroke(),
+            Size(self.style.stroke_width()),
+            self.style.attributes()
+        )
+    }
+
+    fn get_bounds(&self) -> (Point, Point) {
+        // the extremes are the endpoints and any axis directions the arc sweeps through
+        let mut points = vec![
+            self.point_at(self.start),
+            self.point_at(self.start + self.sweep),
+        ];
+        let (low, high) = if self.sweep > 0.0 {
+            (self.start, self.start + self.sweep)
+        } else {
+            (self.start + self.sweep, self.start)
+        };
+        let first = (low / std::f64::consts::FRAC_PI_2).ceil() as i64;
+        let last = (high / std::f64::consts::FRAC_PI_2).floor() as i64;
+        for k in first..=last {
+            points.push(self.point_at(k as f64 * std::f64::consts::FRAC_PI_2));
+        }
+
+        let mut min = points[0];
+        let mut max = points[0];
+        for p in points {
+            min.x = min.x.min(p.x);
+            min.y = min.y.min(p.y);
+            max.x = max.x.max(p.x);
+            max.y = max.y.max(p.y);
+        }
+        (min, max)
+    }
+
+    fn mark_pixels(&self, bitmap: &mut Bitmap) {
+        // approximate the arc with line segments, enough to keep each under a pixel or two
+        let steps = ((self.radius * bitmap.scale() * self.sweep.abs()) as usize).max(8);
+        for i in 0..steps {
+            let t0 = self.start + i as f64 / steps as f64 * self.sweep;
+            let t1 = self.start + (i + 1) as f64 / steps as f64 * self.sweep;
+            let start = self.point_at(t0);
+            let end = self.point_at(t1);
+            let start = bitmap.to_pixel(start);
+            let end = bitmap.to_pixel(end);
+            for (x, y) in bresenham(start, end) {
+                bitmap.mark(x, y);
+            }
+        }
+    }
+}
+
+/// Grid of pixels over the viewbox, marking where elements are drawn
+pub struct Bitmap {
+    pixels: Vec<Vec<bool>>,
+    origin: Point,
+    scale: f64,
+}
+
+impl Bitmap {
+    /// Create an empty bitmap covering the area between two points, with a number of pixels per unit
+    pub fn new(min: Point, max: Point, scale: f64) -> Self {
+        let width = ((max.x - min.x) * scale).ceil().max(1.0) as usize;
+        let height = ((max.y - min.y) * scale).ceil().max(1.0) as usize;
+        Bitmap {
+            pixels: vec![vec![false; width]; height],
+            origin: min,
+            scale,
+        }
+    }
+
+    /// Return the number of pixels per unit
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Convert a point in figure coordinates to pixel coordinates
+    pub fn to_pixel(&self, p: Point) -> Point {
+        Point {
+            x: (p.x - self.origin.x) * self.scale,
+            y: (p.y - self.origin.y) * self.scale,
+        }
+    }
+
+    /// Convert pixel coordinates back to a point in figure coordinates
+    pub fn to_point(&self, x: f64, y: f64) -> Point {
+        Point {
+            x: x / self.scale + self.origin.x,
+            y: y / self.scale + self.origin.y,
+        }
+    }
+
+    /// Mark a single pixel, ignoring pixels outside the bitmap
+    pub fn mark(&mut self, x: i32, y: i32) {
+        if x >= 0 && y >= 0 {
+            if let Some(pixel) = self
+                .pixels
+                .get_mut(y as usize)
+                .and_then(|row| row.get_mut(x as usize))
+            {
+                *pixel = true;
+            }
+        }
+    }
+
+    /// Whether a pixel is marked, where pixels outside the bitmap count as marked
+    pub fn is_marked(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 {
+            return true;
+        }
+        self.pixels
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .copied()
+            .unwrap_or(true)
+    }
+}
+
+/// Estimate the width of label text in ems, with subscripts and primes narrower than other characters
+fn label_width(text: &str) -> f64 {
+    let mut width = 0.0;
+    let mut chars = text.chars().peekable();
+    let mut in_group = false;
+    let mut in_script = false;
+    while let Some(c) = chars.next() {
+        let size = if in_script || in_group { 0.7 } else { 1.0 };
+        match c {
+            '_' | '^' => {
+                in_script = true;
+                if chars.peek() == Some(&'{') {
+                    chars.next();
+                    in_group = true;
+                }
+                continue;
+            }
+            '}' if in_group => in_group = false,
+            '\\' => {
+                // a Greek letter name is drawn as a single character
+                while chars.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                    chars.next();
+                }
+                width += 0.6 * size;
+            }
+            '\'' => width += 0.3 * size,
+            _ => width += 0.6 * size,
+        }
+        in_script = false;
+    }
+    width
+}
+
+fn label(svg: &mut Svg, is_label: bool) {
+    // mark pixels on a bitmap of the viewbox, keeping it to a reasonable size for large figures
+    let (min_point, max_point): (Point, Point) = svg.get_viewbox();
+    let extent = (max_point.x - min_point.x).max(max_point.y - min_point.y);
+    let scale = (1000.0 / extent).min(10.0);
+    let mut bitmap = Bitmap::new(min_point, max_point, scale);
+    svg.mark_pixels(&mut bitmap);
+    let svg_flip_y = svg.flip_y;
+
+    // place the legend first, so that labels avoid it
+    for element in &mut svg.elements {
+        if let Some(legend) = element.as_any_mut().downcast_mut::<SvgLegend>() {
+            legend.place(&mut bitmap, min_point, max_point);
+        }
+    }
+
+    // collect the explicit labels, so that variable names already labelled are not repeated
+    let mut explicit: Vec<(String, Point)> = Vec::new();
+    for element in &mut svg.elements {
+        if let Some(label) = element.as_any_mut().downcast_mut::<SvgLabel>() {
+            if !label.auto {
+                explicit.push((label.text.clone(), label.pt));
+            }
+        }
+    }
 
     // for each SvgLabel element, figure out best position to put the label
     for element in &mut svg.elements {
         if let Some(label) = element.as_any_mut().downcast_mut::<SvgLabel>() {
-            // get initial center position of element to be labelled
-            let center_x: f64 = label.pt.x.round();
-            let center_y: f64 = label.pt.y.round();
+            // skip labels with a fixed position, and variable names unless requested
+            if label.position.is_some() || (label.auto && !is_label) {
+                continue;
+            }
+            if label.auto && explicit.contains(&(label.text.clone(), label.pt)) {
+                continue;
+            }
+            // points outside the figure, such as those only drawn in another figure, are not labelled
+            let is_outside = label.pt.x < min_point.x
+                || label.pt.x > max_point.x
+                || label.pt.y < min_point.y
+                || label.pt.y > max_point.y;
+            if label.auto && is_outside {
+                continue;
+            }
 
-            // define search and label radii and initialize scores
-            let search_radius = 5;
-            let label_radius = 1;
-            let mut best_x = 0;
-            let mut best_y = 0;
-            let mut best_score = i32::MIN;
+            // estimate the size of the label's rectangle in pixels
+            let width = (label_width(&label.text) * label.font.size * scale).ceil() as i32;
+            let height = (0.75 * label.font.size * scale).ceil() as i32;
 
+            // search rectangles centered around the element, out to about the size of the label
+            let center = bitmap.to_pixel(label.pt);
+            let search_radius = width.max(height) + (label.font.size * scale) as i32;
+            let mut best: Option<(i32, i32)> = None;
+            let mut best_score = f64::NEG_INFINITY;
             for dy in -search_radius..=search_radius {
                 for dx in -search_radius..=search_radius {
-                    let x = (center_x * scale).round() as i32 + dx;
-                    let y = (center_y * scale).round() as i32 + dy;
-
-                    let mut score: i32 = 0;
-                    for ly in (y - label_radius)..(y + label_radius) {
-                        for lx in (x - label_radius)..(x + label_radius) {
-                            // if a pixel is taken, reduce the score
-                            if bitmap[ly as usize][lx as usize] {
-                                score -= 1;
-                            }
+                    // the rectangle's top left corner, from its center
+                    let left = center.x.round() as i32 + dx - width / 2;
+                    let top = center.y.round() as i32 + dy - height / 2;
 
-                            // prefer positions closer to the original center
-                            score -= (lx - x).abs() + (ly - y).abs();
+                    // overlapping taken pixels costs much more than moving away from the element
+                    let mut taken = 0;
+                    for y in top..top + height {
+                        for x in left..left + width {
+                            if bitmap.is_marked(x, y) {
+                                taken += 1;
+                            }
                         }
                     }
-
+                    let score = -10.0 * taken as f64 - ((dx * dx + dy * dy) as f64).sqrt();
                     if score > best_score {
                         best_score = score;
-                        best_x = x;
-                        best_y = y;
+                        best = Some((left, top));
                     }
                 }
             }
 
-            if best_score > i32::MIN {
-                label.set_position(Point {
-                    x: best_x as f64 / scale,
-                    y: best_y as f64 / scale,
-                });
-            } else {
+            // the text is drawn from the bottom left corner of its rectangle, which is at the top of
+            // the bitmap when the figure is mirrored
+            match best {
+                Some((left, top)) => {
+                    for y in top..top + height {
+                        for x in left..left + width {
+                            bitmap.mark(x, y);
+                        }
+                    }
+                    let baseline = if svg_flip_y { top } else { top + height };
+                    label.set_position(bitmap.to_point(left as f64, baseline as f64));
+                }
                 // Fallback to original position if no valid position found
-                label.set_position(Point {
-                    x: center_x,
-                    y: center_y,
-                });
+                None => label.set_position(label.pt),
             }
         }
     }
 }
 
-pub fn render(values: Vec<Value>, is_label: bool, is_debug: bool) -> Result<String, String> {
-    let mut elements: Vec<Box<dyn Render>> = Vec::new();
+/// Options controlling how values are rendered
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    pub is_label: bool,
+    /// Font family of labels, serif by default
+    pub font_family: Option<String>,
+    /// Font size of labels, scaled from the size of the figure by default
+    pub font_size: Option<f64>,
+    /// Text color of labels, black by default
+    pub font_color: Option<String>,
+    /// Margin around the figure on each side
+    pub padding: Option<f64>,
+    /// Width and height attributes of the output, where giving both pads the figure to their aspect ratio
+    pub width: Option<Length>,
+    pub height: Option<Length>,
+    /// Whether the y-axis points up, as in mathematics
+    pub flip_y: bool,
+    /// Spacing of a coordinate grid drawn beneath the figure
+    pub grid: Option<f64>,
+    /// Delay in seconds between revealing each value in evaluation order, when animating the construction
+    pub animate: Option<f64>,
+    /// Whether to tag each element with the index of the value it was drawn from, for interactive output
+    pub tag_values: bool,
+    /// Whether to frame the figure closely, leaving only enough margin for labels, unless a padding is given
+    pub tight: bool,
+    /// Title, description, and source embedded in the output
+    pub metadata: Option<Metadata>,
+    /// Variable each value was read from, by its index in evaluation order, naming its group
+    pub names: Vec<Option<String>>,
+    /// Whether to draw a key naming the styled values
+    pub legend: bool,
+    /// Color filling the background of the figure, which is transparent by default
+    pub background: Option<String>,
+    /// Color of a thin frame around the edges of the figure
+    pub border: Option<String>,
+}
+
+/// Return the size of labels, which keep their size relative to the figure but are never smaller than on a 10 unit figure
+fn label_size(svg: &Svg, options: &RenderOptions) -> f64 {
+    let (min, max) = svg.get_bounds();
+    let extent = (max.x - min.x).max(max.y - min.y);
+    options
+        .font_size
+        .unwrap_or(Font::default().size * (extent / 10.0).max(1.0))
+}
+
+pub fn render(values: Vec<Value>, options: &RenderOptions) -> Result<String, ElementsError> {
+    Ok(build(values, options)?.render())
+}
+
+/// Arrangement of several figures in one output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// All figures side by side
+    Row,
+    /// All figures one above another
+    Column,
+    /// Figures in rows of about the square root of their number
+    Grid,
+}
+
+/// Render several figures into one SVG, each framed on its own and placed in a cell as large as the largest
+/// figure in its row and column, so that every figure is drawn at the same scale
+pub fn render_layout(
+    figures: Vec<(Option<String>, Vec<Value>)>,
+    layout: Layout,
+    options: &RenderOptions,
+) -> Result<String, ElementsError> {
+    // the output size and description belong to the whole layout rather than each figure
+    let mut cells: Vec<(Option<String>, Svg)> = Vec::new();
+    for (name, values) in figures {
+        let figure_options = RenderOptions {
+            width: None,
+            height: None,
+            metadata: None,
+            background: None,
+            names: if name.is_none() {
+                options.names.clone()
+            } else {
+                Vec::new()
+            },
+            ..options.clone()
+        };
+        cells.push((name, build(values, &figure_options)?));
+    }
+    if cells.is_empty() {
+        return Err(ElementsError::Render(
+            "Layout requires at least 1 figure".to_string(),
+        ));
+    }
+
+    // size each column to its widest figure and each row to its tallest figure
+    let columns = match layout {
+        Layout::Row => cells.len(),
+        Layout::Column => 1,
+        Layout::Grid => (cells.len() as f64).sqrt().ceil() as usize,
+    };
+    let rows = cells.len().div_ceil(columns);
+    let mut widths = vec![0.0_f64; columns];
+    let mut heights = vec![0.0_f64; rows];
+    for (i, (_, svg)) in cells.iter().enumerate() {
+        let (min, max) = svg.get_viewbox();
+        widths[i % columns] = widths[i % columns].max(max.x - min.x);
+        heights[i / columns] = heights[i / columns].max(max.y - min.y);
+    }
+
+    // nest each figure in its cell, centered, with its own viewbox
+    let mut body = String::new();
+    for (i, (name, svg)) in cells.iter().enumerate() {
+        let (column, row) = (i % columns, i / columns);
+        let (min, max) = svg.get_viewbox();
+        let (width, height) = (max.x - min.x, max.y - min.y);
+        let x = widths[..column].iter().sum::<f64>() + (widths[column] - width) / 2.0;
+        let y = heights[..row].iter().sum::<f64>() + (heights[row] - height) / 2.0;
+        let title = name
+            .as_ref()
+            .map(|name| format!("<title>{}</title>\n", escape_xml(name)))
+            .unwrap_or_default();
+        body.push_str(&format!(
+            "<svg x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" viewBox=\"{}\">\n{}{}</svg>\n",
+            Num(x),
+            Num(y),
+            Num(width),
+            Num(height),
+            svg.viewbox_attribute(),
+            title,
+            svg.body()
+        ));
+    }
+
+    // describe the whole layout before anything else, and fill all of it beneath the figures
+    let (total_width, total_height) = (widths.iter().sum::<f64>(), heights.iter().sum::<f64>());
+    if let Some(color) = &options.background {
+        body = format!(
+            "\t<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n{}",
+            Size(total_width),
+            Size(total_height),
+            color,
+            body
+        );
+    }
+    let metadata = options
+        .metadata
+        .as_ref()
+        .map(Metadata::render)
+        .unwrap_or_default();
+    Ok(format!(
+        "<svg{} viewBox=\"0 0 {} {}\" xmlns=\"http://www.w3.org/2000/svg\">\n{}{}</svg>",
+        size_attributes(&options.width, &options.height, total_width / total_height),
+        Num(total_width),
+        Num(total_height),
+        metadata,
+        body
+    ))
+}
+
+/// Build the figure for a list of values, with the viewbox framed and labels placed
+fn build(values: Vec<Value>, options: &RenderOptions) -> Result<Svg, ElementsError> {
+    let mut svg = Svg::new(Vec::new());
+    if let Some(padding) = options.padding {
+        svg.padding = padding;
+    }
+    svg.width = options.width.clone();
+    svg.height = options.height.clone();
+    svg.flip_y = options.flip_y;
+    svg.step_delay = options.animate;
+    svg.tag_sources = options.tag_values;
+    svg.metadata = options.metadata.clone();
+    svg.background = options.background.clone();
+    let mut grid: Option<f64> = options.grid;
+    let mut legend: Option<Option<Corner>> = options.legend.then_some(None);
+    let mut entries: Vec<(String, Style)> = Vec::new();
+    let mut step = 0;
 
     // render the svg
-    for value in values {
-        // print values if debug is enabled
-        if is_debug {
-            println!("{:?}", value);
+    for (index, value) in values.into_iter().enumerate() {
+        log::debug!("value {}: {:?}", index, value);
+
+        // directives configure the figure instead of drawing anything
+        if let Value::Directive(directive) = &value {
+            match directive {
+                Directive::Viewbox { min, max } => svg.viewbox = Some((*min, *max)),
+                Directive::Grid { spacing } => grid = Some(*spacing),
+                Directive::Legend { corner } => legend = Some(*corner),
+                // figures are split out and rendered on their own before this
+                Directive::Figure { .. } | Directive::ExportCoords { .. } => {}
+            }
+            continue;
+        }
+        let mut svg_elements: Vec<Box<dyn Render>> = value.to_svg();
+
+        // list each colored value in the legend once, by its variable or else by its label
+        if let Value::Styled(_, style) = &value {
+            let is_colored =
+                (style.stroke.is_some() || style.fill.is_some() || style.hatch.is_some())
+                    && style.hidden != Some(true);
+            let name = options.names.get(index).cloned().flatten();
+            if let Some(name) = name.or(style.label.clone()).filter(|_| is_colored) {
+                if !entries.iter().any(|(n, _)| *n == name) {
+                    entries.push((name, style.clone()));
+                }
+            }
+        }
+
+        // group the elements of the value by its kind, and by name if it was read from a variable
+        let class = match value.unstyled() {
+            Value::String(_) => "label".to_string(),
+            value => value.type_name().to_lowercase(),
+        };
+        svg.groups.insert(
+            index,
+            Group {
+                class,
+                name: options.names.get(index).cloned().flatten(),
+            },
+        );
+
+        // each value that draws something is one step of the construction
+        let is_drawn = svg_elements
+            .iter_mut()
+            .any(|e| e.as_any_mut().downcast_mut::<SvgNothing>().is_none());
+        let source = Source {
+            value: index,
+            step: is_drawn.then_some(step),
+        };
+        svg.sources.extend(vec![Some(source); svg_elements.len()]);
+        if is_drawn {
+            step += 1;
         }
-        let svg_elements: Vec<Box<dyn Render>> = value.to_svg();
-        elements.extend(svg_elements);
+        svg.elements.extend(svg_elements);
+    }
+
+    // the grid is drawn beneath everything else
+    if let Some(spacing) = grid {
+        svg.sources.insert(0, None);
+        svg.elements.insert(
+            0,
+            Box::new(SvgGrid {
+                spacing,
+                area: None,
+                font_size: 0.0,
+                flip: svg.flip_y,
+            }),
+        );
+    }
+    // the legend is placed once the viewbox is known
+    if let Some(corner) = legend {
+        svg.sources.push(None);
+        svg.elements.push(Box::new(SvgLegend {
+            entries,
+            corner,
+            position: None,
+            font: Font::default(),
+            flip: svg.flip_y,
+        }));
+    }
+
+    // a tight frame leaves room for a label on each side
+    if options.tight && options.padding.is_none() {
+        svg.padding = 2.0 * label_size(&svg, options);
     }
+    svg.clip_to_viewbox();
 
-    let mut svg = Svg { elements };
-    if is_label {
-        label(&mut svg);
+    let font = Font {
+        family: options
+            .font_family
+            .clone()
+            .unwrap_or(Font::default().family),
+        size: label_size(&svg, options),
+        color: options.font_color.clone().unwrap_or(Font::default().color),
+    };
+    for element in &mut svg.elements {
+        if let Some(label) = element.as_any_mut().downcast_mut::<SvgLabel>() {
+            label.font = font.clone();
+            label.flip = svg.flip_y;
+        }
+        if let Some(grid) = element.as_any_mut().downcast_mut::<SvgGrid>() {
+            grid.font_size = font.size * 0.6;
+        }
+        if let Some(legend) = element.as_any_mut().downcast_mut::<SvgLegend>() {
+            legend.font = font.clone();
+        }
     }
+    label(&mut svg, options.is_label);
+
+    // the border is as thin as a stroke on a figure of the default size
+    svg.border = options
+        .border
+        .clone()
+        .map(|color| (color, font.size * 0.04));
+
+    // draw the elements from the lowest layer up, keeping the order of elements within a layer
+    let mut elements: Vec<(Box<dyn Render>, Option<Source>)> =
+        svg.elements.drain(..).zip(svg.sources.drain(..)).collect();
+    elements.sort_by_key(|(element, _)| element.layer());
+    (svg.elements, svg.sources) = elements.into_iter().unzip();
 
-    Ok(svg.render())
+    log::info!("rendered {} elements", svg.elements.len());
+    Ok(svg)
 }
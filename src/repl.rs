@@ -0,0 +1,124 @@
+use crate::cli::ReplArgs;
+use crate::print_value;
+use elements_lang::interpreter::{evaluate_with, value_names, variable_labels};
+use elements_lang::lang::environment::Environment;
+use elements_lang::lang::types::Value;
+use elements_lang::lexer::{tokenize, Token};
+use elements_lang::renderer::{render, RenderOptions};
+
+use std::fs;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+
+/// The expressions evaluated so far, whose values make up the scene that can be saved
+struct Scene {
+    tokens: Vec<Token>,
+    values: Vec<Value>,
+    variables: Environment,
+}
+
+/// Read expressions from stdin line by line, printing each value and keeping variables between lines,
+/// until the input ends
+pub fn repl(args: &ReplArgs) {
+    let mut scene = Scene {
+        tokens: Vec::new(),
+        values: Vec::new(),
+        variables: Environment::new(),
+    };
+
+    // only prompt when someone is typing
+    let is_terminal = std::io::stdin().is_terminal();
+    let mut lines = std::io::stdin().lock().lines();
+    let mut source = String::new();
+    loop {
+        if is_terminal {
+            print!("{}", if source.is_empty() { "> " } else { "... " });
+            std::io::stdout()
+                .flush()
+                .expect("Failed to write to stdout");
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+
+        // an expression can span several lines, so wait until its parentheses are closed
+        source.push_str(&line);
+        source.push('\n');
+        if source.matches('(').count() > source.matches(')').count() {
+            continue;
+        }
+        let tokens = tokenize(std::mem::take(&mut source));
+        if tokens.is_empty() {
+            continue;
+        }
+
+        // save the scene, or else evaluate the expressions, adding them to the scene
+        let result = match save_path(&tokens) {
+            Some(path) => save(&scene, &path, args),
+            None => evaluate_with(&tokens, &mut scene.variables)
+                .map(|values| {
+                    print_values(&tokens, &values, &scene.variables);
+                    scene.values.extend(values);
+                    scene.tokens.extend(tokens);
+                })
+                .map_err(String::from),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+        }
+    }
+}
+
+/// Print each value, where setting a variable prints the value it was set to
+fn print_values(tokens: &[Token], values: &[Value], variables: &Environment) {
+    for value in values.iter().filter(|v| **v != Value::Undefined) {
+        print_value(value);
+    }
+    for window in tokens.windows(2) {
+        if let [Token::Function(f), Token::Variable(v)] = window {
+            if let (true, Some(value)) = (f.name == "setq", variables.get(&v.name)) {
+                print!("{} = ", v.name);
+                print_value(value);
+            }
+        }
+    }
+}
+
+/// Return the path given by a `(save "path")` command, which is out.svg if none is given
+fn save_path(tokens: &[Token]) -> Option<String> {
+    match tokens {
+        [Token::LeftParen, Token::Function(f), rest @ .., Token::RightParen]
+            if f.name == "save" =>
+        {
+            match rest {
+                [Token::Variable(v)] => Some(v.name.trim_matches('"').to_string()),
+                _ => Some("out.svg".to_string()),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Render the scene, with a label for each point set to a variable, and write it to an SVG file
+fn save(scene: &Scene, path: &str, args: &ReplArgs) -> Result<(), String> {
+    if !Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"))
+    {
+        return Err("save requires a path ending in .svg".to_string());
+    }
+
+    let options = RenderOptions {
+        is_label: args.label,
+        flip_y: args.flip_y,
+        names: value_names(&scene.tokens),
+        ..RenderOptions::default()
+    };
+    let mut values = scene.values.clone();
+    values.extend(variable_labels(&scene.variables));
+    let svg = render(values, &options)?;
+    fs::write(path, svg).map_err(|e| format!("failed to write {}: {}", path, e))?;
+    eprintln!("Saved {}", path);
+    Ok(())
+}
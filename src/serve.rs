@@ -0,0 +1,312 @@
+use crate::cli::ServeArgs;
+use elements_lang::renderer::{set_precision, RenderOptions};
+use elements_lang::utils::limits::{set_limits, Limits};
+use elements_lang::utils::random::set_seed;
+use elements_lang::{render_source, ElementsError};
+
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+/// Most figures evaluated at once, which also bounds the figures left rendering after timing out
+const MAX_RUNNING: usize = 8;
+
+/// Stack of each evaluation, as large as that of the main thread so calls nest as deeply as on the command line
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Longest wait for the next bytes of a request, or for a reply to be taken, before the connection is closed
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest request line and headers accepted, in bytes
+const MAX_HEAD: u64 = 8 * 1024;
+
+/// Listen for figures sent to /render and reply with their SVG, until interrupted
+pub fn serve(args: &ServeArgs) -> Result<(), String> {
+    let address = format!("{}:{}", args.host, args.port);
+    let listener = TcpListener::bind(&address)
+        .map_err(|e| format!("failed to listen on {}: {}", address, e))?;
+    log::info!("listening on http://{}/render", address);
+
+    // each connection is answered on its own thread, so a slow figure does not hold up the others, and a
+    // place is only taken once its source has been read so clients that send slowly do not hold them all
+    let running = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+
+        // a client that stops sending or reading part way through is dropped instead of holding its thread
+        let timeouts = stream
+            .set_read_timeout(Some(SOCKET_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(SOCKET_TIMEOUT)));
+        if let Err(e) = timeouts {
+            log::warn!("failed to set the timeouts of a connection: {}", e);
+            continue;
+        }
+        let (max_size, timeout, running) = (args.max_size, args.timeout, Arc::clone(&running));
+        let spawned = std::thread::Builder::new().spawn(move || {
+            let Some(request) = read_request(stream) else {
+                return;
+            };
+            handle(request, max_size, timeout, &running)
+        });
+        if let Err(e) = spawned {
+            log::error!("failed to start answering a connection: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// A place among the figures being evaluated, given back when it is dropped
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    /// Take a place if fewer than the most figures are running, counting it in the same step as the check
+    fn reserve(running: &Arc<AtomicUsize>) -> Option<Slot> {
+        running
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < MAX_RUNNING).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Slot(Arc::clone(running)))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A request whose head has been read, with its body left to read from the connection
+struct Request {
+    method: String,
+    url: String,
+    body_length: Option<usize>,
+    reader: BufReader<TcpStream>,
+}
+
+/// Read the request line and headers of a request, replying with an error and returning None if they
+/// cannot be read
+fn read_request(stream: TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut head = String::new();
+    let mut lines = (&mut reader).take(MAX_HEAD);
+    let error = loop {
+        let mut line = String::new();
+        match lines.read_line(&mut line) {
+            Ok(0) if head.is_empty() => return None,
+            Ok(0) => break Some((400, "the request ended before its headers".to_string())),
+            Ok(_) if line == "\r\n" || line == "\n" => break None,
+            Ok(_) => head.push_str(&line),
+            Err(e) if is_timeout(&e) => {
+                break Some((408, "timed out reading the request".to_string()))
+            }
+            Err(e) => break Some((400, format!("failed to read the request: {}", e))),
+        }
+    };
+    if let Some((status, message)) = error {
+        log::warn!("{}", message);
+        write_response(reader.into_inner(), status, "text/plain", &message);
+        return None;
+    }
+
+    // the request line names the method and path, and only the length of the body is needed of the headers
+    let mut lines = head.lines();
+    let mut words = lines.next().unwrap_or_default().split_whitespace();
+    let (method, url) = (
+        words.next().unwrap_or_default(),
+        words.next().unwrap_or("/"),
+    );
+    let mut request = Request {
+        method: method.to_string(),
+        url: url.to_string(),
+        body_length: None,
+        reader,
+    };
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            match value.trim().parse() {
+                Ok(length) => request.body_length = Some(length),
+                Err(_) => {
+                    respond(request, 400, "invalid Content-Length");
+                    return None;
+                }
+            }
+        } else if name.trim().eq_ignore_ascii_case("transfer-encoding") {
+            respond(request, 411, "sources are sent with a Content-Length");
+            return None;
+        }
+    }
+    Some(request)
+}
+
+/// Reply to a request with the figure rendered from its body, or with an error, taking a place among the
+/// running figures once the body is read and holding it until the figure has finished evaluating
+fn handle(mut request: Request, max_size: usize, timeout: f64, running: &Arc<AtomicUsize>) {
+    // only figures are rendered, and their options are given in the query
+    let (path, query) = match request.url.split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (request.url.clone(), String::new()),
+    };
+    if path != "/render" {
+        return respond(request, 404, "not found, figures are rendered at /render");
+    }
+    if request.method != "POST" {
+        return respond(request, 405, "figures are rendered with POST");
+    }
+    let (options, precision, seed) = match parse_query(&query) {
+        Ok(options) => options,
+        Err(e) => return respond(request, 400, &e),
+    };
+
+    // refuse sources larger than the limit before reading them
+    let Some(length) = request.body_length else {
+        return respond(request, 411, "sources are sent with a Content-Length");
+    };
+    if length > max_size {
+        return respond(
+            request,
+            413,
+            &format!("source is larger than {} bytes", max_size),
+        );
+    }
+    let mut source = String::new();
+    let result = (&mut request.reader)
+        .take(length as u64)
+        .read_to_string(&mut source);
+    match result {
+        Err(e) if is_timeout(&e) => return respond(request, 408, "timed out reading the source"),
+        Err(e) => return respond(request, 400, &format!("failed to read source: {}", e)),
+        Ok(_) if source.len() < length => {
+            return respond(request, 400, "the request ended before its source")
+        }
+        Ok(_) => {}
+    }
+    let Some(slot) = Slot::reserve(running) else {
+        return respond(
+            request,
+            503,
+            "too many figures are being rendered, try again later",
+        );
+    };
+
+    // evaluate on another thread, which is abandoned if it takes too long and frees its place when it ends
+    let (sender, receiver) = mpsc::channel();
+    let thread = std::thread::Builder::new().stack_size(STACK_SIZE);
+    let spawned = thread.spawn(move || {
+        // stop evaluating once the reply has timed out, so the thread does not run on after it
+        set_limits(Limits {
+            timeout: Some(Duration::from_secs_f64(timeout)),
+            ..Limits::default()
+        });
+        set_precision(precision);
+        if let Some(seed) = seed {
+            set_seed(seed);
+        }
+        let result = panic::catch_unwind(|| render_source(&source, &options));
+        drop(slot);
+        let _ = sender.send(result);
+    });
+    if let Err(e) = spawned {
+        return respond(request, 500, &format!("failed to start evaluating: {}", e));
+    }
+    match receiver.recv_timeout(Duration::from_secs_f64(timeout)) {
+        Ok(Ok(Ok(svg))) => {
+            log::info!("rendered {} bytes of SVG", svg.len());
+            write_response(request.reader.into_inner(), 200, "image/svg+xml", &svg);
+        }
+        Ok(Ok(Err(e @ ElementsError::Limit(_)))) => respond(request, 503, &e.to_string()),
+        Ok(Ok(Err(e))) => respond(request, 400, &e.to_string()),
+        Ok(Err(_)) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+            respond(request, 500, "evaluation failed")
+        }
+        Err(mpsc::RecvTimeoutError::Timeout) => respond(
+            request,
+            503,
+            &format!("evaluation took longer than {} seconds", timeout),
+        ),
+    }
+}
+
+/// Reply with an error message and its status code
+fn respond(request: Request, status: u16, message: &str) {
+    log::warn!("{} {}: {}", request.method, request.url, message);
+    write_response(request.reader.into_inner(), status, "text/plain", message);
+}
+
+/// Write a response and close the connection, as each connection carries a single figure
+fn write_response(mut stream: TcpStream, status: u16, content_type: &str, body: &str) {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        content_type,
+        body.len()
+    );
+    let result = stream
+        .write_all(head.as_bytes())
+        .and_then(|_| stream.write_all(body.as_bytes()))
+        .and_then(|_| stream.flush());
+    if let Err(e) = result {
+        log::warn!("failed to send the reply: {}", e);
+    }
+}
+
+/// Return whether reading failed because the client sent nothing for too long
+fn is_timeout(error: &std::io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Parse the options of a figure from a query such as `label&flip-y&seed=3`, named like the command line
+/// flags, returning the options with the precision and seed
+fn parse_query(query: &str) -> Result<(RenderOptions, Option<usize>, Option<u64>), String> {
+    let mut options = RenderOptions::default();
+    let (mut precision, mut seed) = (None, None);
+    for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+        let (name, value) = parameter.split_once('=').unwrap_or((parameter, "true"));
+        let invalid = |expected: &str| format!("{} requires {}", name, expected);
+        let flag = || match value {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err(invalid("true or false")),
+        };
+        match name {
+            "label" => options.is_label = flag()?,
+            "flip-y" => options.flip_y = flag()?,
+            "grid" => options.grid = flag()?.then_some(1.0),
+            "legend" => options.legend = flag()?,
+            "padding" => match value.parse::<f64>() {
+                Ok(n) if n >= 0.0 && n.is_finite() => options.padding = Some(n),
+                _ => return Err(invalid("a non-negative number")),
+            },
+            "precision" => {
+                precision = Some(value.parse().map_err(|_| invalid("a number of digits"))?)
+            }
+            "seed" => seed = Some(value.parse().map_err(|_| invalid("a whole number"))?),
+            _ => return Err(format!("unknown option: {}", name)),
+        }
+    }
+    Ok((options, precision, seed))
+}
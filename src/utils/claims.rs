@@ -0,0 +1,30 @@
+use std::cell::RefCell;
+
+/// A fact about the figure checked with `claim` or `assert`, with the name it was given, if any, whether
+/// it held, and whether it was asserted, which stops the figure when it does not hold
+#[derive(Clone, Debug, PartialEq)]
+pub struct Claim {
+    pub name: Option<String>,
+    pub holds: bool,
+    pub is_assert: bool,
+}
+
+thread_local! {
+    /// The claims checked since the last evaluation started, in order
+    static CLAIMS: RefCell<Vec<Claim>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Forget the claims checked so far, when a new figure starts evaluating
+pub fn start() {
+    CLAIMS.with(|c| c.borrow_mut().clear());
+}
+
+/// Remember a claim checked by the figure
+pub fn record(claim: Claim) {
+    CLAIMS.with(|c| c.borrow_mut().push(claim));
+}
+
+/// Return the claims checked since they were last taken, forgetting them
+pub fn take() -> Vec<Claim> {
+    CLAIMS.with(|c| std::mem::take(&mut *c.borrow_mut()))
+}
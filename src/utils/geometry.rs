@@ -1,5 +1,7 @@
 use crate::lang::types::Point;
 
+use std::collections::HashMap;
+
 /// Function that returns the midpoint between two points
 pub fn midpoint(first: Point, second: Point) -> Point {
     Point {
@@ -8,11 +10,152 @@ pub fn midpoint(first: Point, second: Point) -> Point {
     }
 }
 
+/// Function that returns the point a fraction t of the way from the first point to the second
+pub fn lerp(first: Point, second: Point, t: f64) -> Point {
+    Point {
+        x: first.x + t * (second.x - first.x),
+        y: first.y + t * (second.y - first.y),
+    }
+}
+
+/// Function that returns the point on the line through a and b closest to p, with the parameter clamped to [t_min, t_max]
+pub fn closest_point(p: Point, a: Point, b: Point, t_min: f64, t_max: f64) -> Point {
+    let dx = b.x - a.x;
+    let dy = b.y - a.y;
+    let t = ((p.x - a.x) * dx + (p.y - a.y) * dy) / (dx * dx + dy * dy);
+    lerp(a, b, t.clamp(t_min, t_max))
+}
+
+/// Function that inverts a point in the circle with the given center and radius, returning None for the center itself
+pub fn invert_point(p: Point, center: Point, radius: f64) -> Option<Point> {
+    let dx = p.x - center.x;
+    let dy = p.y - center.y;
+    let d2 = dx * dx + dy * dy;
+    if d2 == 0.0 {
+        return None;
+    }
+    let k = radius * radius / d2;
+    Some(Point {
+        x: center.x + k * dx,
+        y: center.y + k * dy,
+    })
+}
+
 /// Function that returns the distance between two points
 pub fn distance(first: Point, second: Point) -> f64 {
     ((first.x - second.x).powi(2) + (first.y - second.y).powi(2)).sqrt()
 }
 
+/// Function that returns where a ray starting inside a box leaves it
+pub fn ray_box_exit(origin: Point, through: Point, min: Point, max: Point) -> Point {
+    let dx = through.x - origin.x;
+    let dy = through.y - origin.y;
+
+    // find the first time the ray reaches a side of the box along each axis
+    let mut t = f64::INFINITY;
+    if dx > 0.0 {
+        t = t.min((max.x - origin.x) / dx);
+    } else if dx < 0.0 {
+        t = t.min((min.x - origin.x) / dx);
+    }
+    if dy > 0.0 {
+        t = t.min((max.y - origin.y) / dy);
+    } else if dy < 0.0 {
+        t = t.min((min.y - origin.y) / dy);
+    }
+
+    // a degenerate ray does not leave the box
+    if !t.is_finite() {
+        return origin;
+    }
+    Point {
+        x: origin.x + t.max(0.0) * dx,
+        y: origin.y + t.max(0.0) * dy,
+    }
+}
+
+/// Function that returns the convex hull of a set of points in counterclockwise order, using Andrew's monotone chain
+pub fn convex_hull(points: &[Point]) -> Vec<Point> {
+    let mut sorted: Vec<Point> = points.to_vec();
+    sorted.sort_by(|p, q| p.x.total_cmp(&q.x).then(p.y.total_cmp(&q.y)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    // positive when o, a, b make a counterclockwise turn
+    let cross =
+        |o: Point, a: Point, b: Point| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+
+    // build the lower hull left to right, then the upper hull right to left
+    let mut hull: Vec<Point> = Vec::new();
+    for pass in [sorted.clone(), sorted.into_iter().rev().collect()] {
+        let start = hull.len();
+        for p in pass {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // the last point of each half starts the other half
+        hull.pop();
+    }
+    hull
+}
+
+/// Function that clips the line through two points to a box, returning None if it misses the box
+pub fn clip_line(p: Point, q: Point, min: Point, max: Point) -> Option<(Point, Point)> {
+    clip_parameters(p, q, min, max, f64::NEG_INFINITY, f64::INFINITY)
+}
+
+/// Function that clips the line segment between two points to a box, returning None if it misses the box
+pub fn clip_segment(p: Point, q: Point, min: Point, max: Point) -> Option<(Point, Point)> {
+    clip_parameters(p, q, min, max, 0.0, 1.0)
+}
+
+/// Clip the part of the line through two points between two parameters, where p is at 0 and q is at 1
+fn clip_parameters(
+    p: Point,
+    q: Point,
+    min: Point,
+    max: Point,
+    start: f64,
+    end: f64,
+) -> Option<(Point, Point)> {
+    let dx = q.x - p.x;
+    let dy = q.y - p.y;
+
+    // narrow the parameter range against each side of the box in turn
+    let mut t0 = start;
+    let mut t1 = end;
+    for (delta, low, high) in [
+        (dx, min.x - p.x, max.x - p.x),
+        (dy, min.y - p.y, max.y - p.y),
+    ] {
+        if delta == 0.0 {
+            // parallel to these sides, so the line must already lie between them
+            if low > 0.0 || high < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let (a, b) = (low / delta, high / delta);
+        t0 = t0.max(a.min(b));
+        t1 = t1.min(a.max(b));
+    }
+    if t0 > t1 || !t0.is_finite() || !t1.is_finite() {
+        return None;
+    }
+
+    let at = |t: f64| Point {
+        x: p.x + t * dx,
+        y: p.y + t * dy,
+    };
+    Some((at(t0), at(t1)))
+}
+
 /// Function that uses Bresenham's line algorithm to return a vector of coordinates
 pub fn bresenham(start: Point, end: Point) -> Vec<(i32, i32)> {
     // set initial and end points
@@ -48,3 +191,318 @@ pub fn bresenham(start: Point, end: Point) -> Vec<(i32, i32)> {
 
     result
 }
+
+/// Function that uses marching squares to trace the boundary of the inside samples of a grid into closed contours
+pub fn marching_squares(inside: &[Vec<bool>], min: Point, max: Point) -> Vec<Vec<Point>> {
+    // the grid is indexed by row then column, with rows running from min.y to max.y
+    let rows = inside.len() - 1;
+    let cols = inside[0].len() - 1;
+
+    // pad the grid with an outside ring of zero width, so contours close along the bounds
+    let sample = |i: usize, j: usize| -> bool {
+        if i == 0 || j == 0 || i == cols + 2 || j == rows + 2 {
+            return false;
+        }
+        inside[j - 1][i - 1]
+    };
+    let coord = |i: usize, j: usize| -> Point {
+        let i = i.saturating_sub(1).min(cols) as f64;
+        let j = j.saturating_sub(1).min(rows) as f64;
+        Point {
+            x: min.x + i * (max.x - min.x) / cols as f64,
+            y: min.y + j * (max.y - min.y) / rows as f64,
+        }
+    };
+
+    // an edge is identified by its direction and its lower left sample
+    type GridEdge = (bool, usize, usize);
+    let crossing = |edge: GridEdge| -> Point {
+        let (vertical, i, j) = edge;
+        let end = if vertical {
+            coord(i, j + 1)
+        } else {
+            coord(i + 1, j)
+        };
+        midpoint(coord(i, j), end)
+    };
+
+    // find the boundary segments within each cell
+    let mut segments: Vec<(GridEdge, GridEdge)> = Vec::new();
+    for j in 0..rows + 2 {
+        for i in 0..cols + 2 {
+            let corners = [
+                sample(i, j),
+                sample(i + 1, j),
+                sample(i + 1, j + 1),
+                sample(i, j + 1),
+            ];
+            let edges = [
+                (false, i, j),
+                (true, i + 1, j),
+                (false, i, j + 1),
+                (true, i, j),
+            ];
+            let crossed: Vec<GridEdge> = (0..4)
+                .filter(|&k| corners[k] != corners[(k + 1) % 4])
+                .map(|k| edges[k])
+                .collect();
+
+            match crossed.len() {
+                2 => segments.push((crossed[0], crossed[1])),
+                4 => {
+                    // saddle cell, so keep the inside corners connected
+                    if corners[0] {
+                        segments.push((edges[0], edges[1]));
+                        segments.push((edges[2], edges[3]));
+                    } else {
+                        segments.push((edges[3], edges[0]));
+                        segments.push((edges[1], edges[2]));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // index the segments by the edges they touch
+    let mut touching: HashMap<GridEdge, Vec<usize>> = HashMap::new();
+    for (k, (first, second)) in segments.iter().enumerate() {
+        touching.entry(*first).or_default().push(k);
+        touching.entry(*second).or_default().push(k);
+    }
+
+    // chain the segments into closed contours
+    let mut used = vec![false; segments.len()];
+    let mut contours: Vec<Vec<Point>> = Vec::new();
+    for k in 0..segments.len() {
+        if used[k] {
+            continue;
+        }
+        used[k] = true;
+        let (start, mut current) = segments[k];
+        let mut contour = vec![crossing(start)];
+        while current != start {
+            let point = crossing(current);
+            if contour.last() != Some(&point) {
+                contour.push(point);
+            }
+            let next = touching[&current].iter().find(|&&n| !used[n]);
+            let next = match next {
+                Some(&n) => n,
+                None => break,
+            };
+            used[next] = true;
+            current = if segments[next].0 == current {
+                segments[next].1
+            } else {
+                segments[next].0
+            };
+        }
+        if contour.len() > 2 {
+            contours.push(contour);
+        }
+    }
+
+    contours
+}
+
+/// Function that checks whether a point lies inside a set of closed contours by the even-odd rule
+pub fn inside_contours(p: Point, contours: &[Vec<Point>]) -> bool {
+    let mut inside = false;
+    for contour in contours {
+        for (k, a) in contour.iter().enumerate() {
+            let b = contour[(k + 1) % contour.len()];
+            if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y) {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Function that returns the edges of a set of closed contours
+fn contour_edges(contours: &[Vec<Point>]) -> Vec<(Point, Point)> {
+    contours
+        .iter()
+        .flat_map(|contour| {
+            (0..contour.len()).map(|k| (contour[k], contour[(k + 1) % contour.len()]))
+        })
+        .filter(|(a, b)| a != b)
+        .collect()
+}
+
+/// Function that returns where two segments cross as parameters along each, ignoring parallel segments
+fn segment_crossing(a: Point, b: Point, c: Point, d: Point) -> Option<(f64, f64)> {
+    let cross = |u: Point, v: Point| u.x * v.y - u.y * v.x;
+    let r = Point {
+        x: b.x - a.x,
+        y: b.y - a.y,
+    };
+    let s = Point {
+        x: d.x - c.x,
+        y: d.y - c.y,
+    };
+    let denom = cross(r, s);
+    if denom == 0.0 {
+        return None;
+    }
+    let ac = Point {
+        x: c.x - a.x,
+        y: c.y - a.y,
+    };
+    let t = cross(ac, s) / denom;
+    let u = cross(ac, r) / denom;
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+        Some((t, u))
+    } else {
+        None
+    }
+}
+
+/// Function that combines two sets of closed contours into the contours of their union or intersection
+pub fn combine_contours(
+    first: &[Vec<Point>],
+    second: &[Vec<Point>],
+    union: bool,
+) -> Vec<Vec<Point>> {
+    let first_edges = contour_edges(first);
+    let second_edges = contour_edges(second);
+
+    // the tolerance scales with the size of the shapes
+    let (mut min, mut max) = (
+        Point {
+            x: f64::MAX,
+            y: f64::MAX,
+        },
+        Point {
+            x: f64::MIN,
+            y: f64::MIN,
+        },
+    );
+    for (p, _) in first_edges.iter().chain(&second_edges) {
+        min = Point {
+            x: min.x.min(p.x),
+            y: min.y.min(p.y),
+        };
+        max = Point {
+            x: max.x.max(p.x),
+            y: max.y.max(p.y),
+        };
+    }
+    let tolerance = 1e-9 * (max.x - min.x).max(max.y - min.y).max(1.0);
+
+    // cut every edge where it crosses an edge of the other set, snapping cuts near an end to that end
+    let mut first_cuts: Vec<Vec<(f64, Point)>> = vec![Vec::new(); first_edges.len()];
+    let mut second_cuts: Vec<Vec<(f64, Point)>> = vec![Vec::new(); second_edges.len()];
+    for (i, &(a, b)) in first_edges.iter().enumerate() {
+        for (j, &(c, d)) in second_edges.iter().enumerate() {
+            if let Some((t, u)) = segment_crossing(a, b, c, d) {
+                let p = lerp(a, b, t);
+                let p = [a, b, c, d]
+                    .into_iter()
+                    .find(|q| distance(*q, p) < tolerance)
+                    .unwrap_or(p);
+                first_cuts[i].push((t, p));
+                second_cuts[j].push((u, p));
+            }
+        }
+    }
+
+    // split the edges into pieces between the cuts
+    let pieces =
+        |edges: &[(Point, Point)], cuts: &mut [Vec<(f64, Point)>]| -> Vec<(Point, Point)> {
+            let mut result = Vec::new();
+            for (&(a, b), cuts) in edges.iter().zip(cuts.iter_mut()) {
+                cuts.sort_by(|x, y| x.0.total_cmp(&y.0));
+                let mut points = vec![a];
+                points.extend(cuts.iter().map(|(_, p)| *p));
+                points.push(b);
+                points.dedup_by(|q, p| distance(*p, *q) < tolerance);
+                result.extend(points.windows(2).map(|w| (w[0], w[1])));
+            }
+            result
+        };
+    let first_pieces = pieces(&first_edges, &mut first_cuts);
+    let second_pieces = pieces(&second_edges, &mut second_cuts);
+
+    // keep a piece if the combined region is on exactly one side of it
+    let contains = |p: Point| {
+        let (a, b) = (inside_contours(p, first), inside_contours(p, second));
+        if union {
+            a || b
+        } else {
+            a && b
+        }
+    };
+    let on_boundary = |p: Point, edges: &[(Point, Point)]| {
+        edges
+            .iter()
+            .any(|&(a, b)| distance(p, closest_point(p, a, b, 0.0, 1.0)) < 1e3 * tolerance)
+    };
+    let offset = 1e3 * tolerance;
+    let keeps = |(p, q): (Point, Point)| {
+        let m = midpoint(p, q);
+        let length = distance(p, q);
+        let normal = Point {
+            x: -(q.y - p.y) / length * offset,
+            y: (q.x - p.x) / length * offset,
+        };
+        let left = Point {
+            x: m.x + normal.x,
+            y: m.y + normal.y,
+        };
+        let right = Point {
+            x: m.x - normal.x,
+            y: m.y - normal.y,
+        };
+        contains(left) != contains(right)
+    };
+
+    // pieces shared by both sets are only kept once
+    let mut kept: Vec<(Point, Point)> = first_pieces.into_iter().filter(|&e| keeps(e)).collect();
+    kept.extend(
+        second_pieces
+            .into_iter()
+            .filter(|&e| keeps(e) && !on_boundary(midpoint(e.0, e.1), &first_edges)),
+    );
+
+    // index the pieces by their ends
+    let key = |p: Point| {
+        (
+            (p.x / tolerance).round() as i64,
+            (p.y / tolerance).round() as i64,
+        )
+    };
+    let mut touching: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (k, (p, q)) in kept.iter().enumerate() {
+        touching.entry(key(*p)).or_default().push(k);
+        touching.entry(key(*q)).or_default().push(k);
+    }
+
+    // chain the pieces into closed contours
+    let mut used = vec![false; kept.len()];
+    let mut contours: Vec<Vec<Point>> = Vec::new();
+    for k in 0..kept.len() {
+        if used[k] {
+            continue;
+        }
+        used[k] = true;
+        let (start, mut current) = kept[k];
+        let mut contour = vec![start];
+        while key(current) != key(start) {
+            let next = touching[&key(current)].iter().copied().find(|&n| !used[n]);
+            let Some(n) = next else {
+                break;
+            };
+            used[n] = true;
+            contour.push(current);
+            let (p, q) = kept[n];
+            current = if key(p) == key(current) { q } else { p };
+        }
+        if contour.len() >= 3 {
+            contours.push(contour);
+        }
+    }
+
+    contours
+}
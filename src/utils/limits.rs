@@ -0,0 +1,99 @@
+use crate::error::ElementsError;
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// How much work evaluating a figure may take before it is stopped
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// Most calls nested inside one another, such as lambdas calling lambdas
+    pub max_depth: usize,
+    /// Most steps, counting every call and every sample drawn by a random construction
+    pub max_steps: u64,
+    /// Longest time evaluating may take, or None to take as long as it needs
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: 512,
+            max_steps: 50_000_000,
+            timeout: None,
+        }
+    }
+}
+
+thread_local! {
+    /// The limits of evaluations on this thread
+    static LIMITS: Cell<Limits> = Cell::new(Limits::default());
+    /// The depth of the current call, the steps taken, and when the current evaluation started
+    static USAGE: Cell<(usize, u64, Option<Instant>)> = const { Cell::new((0, 0, None)) };
+}
+
+/// Set the limits of every evaluation after this one on the current thread
+pub fn set_limits(limits: Limits) {
+    LIMITS.with(|l| l.set(limits));
+}
+
+/// Return the limits of evaluations on the current thread
+pub fn limits() -> Limits {
+    LIMITS.with(|l| l.get())
+}
+
+/// Start counting the steps and time of a new evaluation, where the clock is only read when there is a
+/// timeout, as it is not available on every target, such as wasm32-unknown-unknown
+pub fn start() {
+    let started = limits().timeout.map(|_| Instant::now());
+    USAGE.with(|u| u.set((0, 0, started)));
+}
+
+/// Take a step of evaluation, failing once the steps or time of the evaluation run out
+pub fn step() -> Result<(), ElementsError> {
+    let limits = limits();
+    let (depth, steps, started) = USAGE.with(|u| u.get());
+    if steps >= limits.max_steps {
+        return Err(exceeded(format!("more than {} steps", limits.max_steps)));
+    }
+    USAGE.with(|u| u.set((depth, steps + 1, started)));
+
+    // reading the clock is slower than counting, so only check it every so often
+    if let (Some(timeout), Some(started)) = (limits.timeout, started) {
+        if steps % 1024 == 0 && started.elapsed() > timeout {
+            return Err(exceeded(format!(
+                "took longer than {} seconds",
+                timeout.as_secs_f64()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// A call in progress, which leaves its depth when dropped
+pub struct Depth(());
+
+impl Drop for Depth {
+    fn drop(&mut self) {
+        USAGE.with(|u| {
+            let (depth, steps, started) = u.get();
+            u.set((depth.saturating_sub(1), steps, started));
+        });
+    }
+}
+
+/// Enter a call, taking a step, failing if calls are nested deeper than the limit
+pub fn enter() -> Result<Depth, ElementsError> {
+    step()?;
+    let max_depth = limits().max_depth;
+    let (depth, steps, started) = USAGE.with(|u| u.get());
+    if depth >= max_depth {
+        return Err(exceeded(format!("calls nested deeper than {}", max_depth)));
+    }
+    USAGE.with(|u| u.set((depth + 1, steps, started)));
+    Ok(Depth(()))
+}
+
+/// Return the error of an evaluation stopped for going over one of its limits
+fn exceeded(reason: String) -> ElementsError {
+    ElementsError::Limit(format!("evaluation budget exceeded: {}", reason))
+}
@@ -1 +1,10 @@
+pub mod claims;
 pub mod geometry;
+pub mod limits;
+pub mod params;
+#[cfg(feature = "high-precision")]
+pub mod precise;
+pub mod random;
+pub mod solver;
+pub mod tolerance;
+pub mod transform;
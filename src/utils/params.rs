@@ -0,0 +1,65 @@
+use std::cell::RefCell;
+
+/// A parameter of a figure declared with `param`, with the value it takes unless given another, the range
+/// other values must lie in, if any, and whether it was written with whole numbers only
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub default: f64,
+    pub range: Option<(f64, f64)>,
+    pub is_integer: bool,
+}
+
+thread_local! {
+    /// The values given to parameters from outside the figure, such as with --param, by name
+    static OVERRIDES: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+    /// The parameters declared since the last evaluation started, in order
+    static DECLARED: RefCell<Vec<Parameter>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Give parameters the values written after their names, in place of their defaults, in every evaluation
+/// after this one on the current thread
+pub fn set_overrides(overrides: Vec<(String, String)>) {
+    OVERRIDES.with(|o| *o.borrow_mut() = overrides);
+}
+
+/// Return the value given to a parameter from outside the figure, the last one if given several times
+pub fn override_of(name: &str) -> Option<String> {
+    OVERRIDES.with(|o| {
+        o.borrow()
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.clone())
+    })
+}
+
+/// Forget the parameters declared so far, when a new figure starts evaluating
+pub fn start() {
+    DECLARED.with(|d| d.borrow_mut().clear());
+}
+
+/// Remember a parameter declared by the figure
+pub fn declare(parameter: Parameter) {
+    DECLARED.with(|d| d.borrow_mut().push(parameter));
+}
+
+/// Return the parameters declared since the figure started evaluating, in order
+pub fn declared() -> Vec<Parameter> {
+    DECLARED.with(|d| d.borrow().clone())
+}
+
+/// Return the names given values from outside the figure that it never declared as parameters, which are
+/// likely misspelled
+pub fn undeclared() -> Vec<String> {
+    let declared = declared();
+    let mut names: Vec<String> = Vec::new();
+    OVERRIDES.with(|o| {
+        for (name, _) in o.borrow().iter() {
+            if !declared.iter().any(|p| &p.name == name) && !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    });
+    names
+}
@@ -0,0 +1,294 @@
+use crate::lang::types::Point;
+
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the high precision kernel is selected for this run
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Select whether geometric constructions use the high precision kernel
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Return whether the high precision kernel is selected
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A double-double float, holding roughly 106 bits of mantissa as an unevaluated sum of two f64s
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+/// Exact sum of two floats as a (sum, error) pair
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+/// Exact sum of two floats, given that |a| >= |b|
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+/// Exact product of two floats as a (product, error) pair
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+impl DoubleDouble {
+    /// Create a double-double from a normalized pair of floats
+    fn from_pair((hi, lo): (f64, f64)) -> Self {
+        let (hi, lo) = quick_two_sum(hi, lo);
+        Self { hi, lo }
+    }
+
+    /// Return the closest f64 to the value
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    /// Return the square root of the value
+    pub fn sqrt(self) -> Self {
+        if self.hi <= 0.0 {
+            return Self::from(self.hi.max(0.0).sqrt());
+        }
+
+        // refine the f64 estimate with one newton iteration
+        let x = Self::from(self.hi.sqrt());
+        x + (self - x * x) / (x * 2.0)
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(value: f64) -> Self {
+        Self { hi: value, lo: 0.0 }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        let (s, e) = two_sum(self.hi, other.hi);
+        Self::from_pair((s, e + self.lo + other.lo))
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        let (p, e) = two_prod(self.hi, other.hi);
+        Self::from_pair((p, e + self.hi * other.lo + self.lo * other.hi))
+    }
+}
+
+impl Mul<f64> for DoubleDouble {
+    type Output = Self;
+    fn mul(self, other: f64) -> Self {
+        self * Self::from(other)
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+    fn div(self, other: Self) -> Self {
+        // long division, one f64 quotient digit at a time
+        let q1 = self.hi / other.hi;
+        let r = self - other * q1;
+        let q2 = r.hi / other.hi;
+        let r = r - other * q2;
+        let q3 = r.hi / other.hi;
+        Self::from_pair(quick_two_sum(q1, q2)) + Self::from(q3)
+    }
+}
+
+/// A point with double-double coordinates
+#[derive(Debug, Clone, Copy)]
+struct PrecisePoint {
+    x: DoubleDouble,
+    y: DoubleDouble,
+}
+
+impl From<Point> for PrecisePoint {
+    fn from(point: Point) -> Self {
+        Self {
+            x: DoubleDouble::from(point.x),
+            y: DoubleDouble::from(point.y),
+        }
+    }
+}
+
+impl PrecisePoint {
+    /// Round the point back to f64 coordinates
+    fn to_point(self) -> Point {
+        Point {
+            x: self.x.to_f64(),
+            y: self.y.to_f64(),
+        }
+    }
+}
+
+/// Return the intersection of the lines through (p1, p2) and (p3, p4), if they are not parallel
+pub fn intersect_lines(p1: Point, p2: Point, p3: Point, p4: Point) -> Option<Point> {
+    let (p1, p2, p3, p4) = (
+        PrecisePoint::from(p1),
+        PrecisePoint::from(p2),
+        PrecisePoint::from(p3),
+        PrecisePoint::from(p4),
+    );
+
+    // solve p1 + t(p2 - p1) = p3 + s(p4 - p3) using cross products
+    let d1x = p2.x - p1.x;
+    let d1y = p2.y - p1.y;
+    let d2x = p4.x - p3.x;
+    let d2y = p4.y - p3.y;
+    let denom = d1x * d2y - d1y * d2x;
+    if denom.to_f64() == 0.0 {
+        return None;
+    }
+    let t = ((p3.x - p1.x) * d2y - (p3.y - p1.y) * d2x) / denom;
+
+    Some(
+        PrecisePoint {
+            x: p1.x + d1x * t,
+            y: p1.y + d1y * t,
+        }
+        .to_point(),
+    )
+}
+
+/// Return the intersections of the line through (start, end) with a circle, larger x first
+pub fn intersect_line_circle(
+    start: Point,
+    end: Point,
+    center: Point,
+    radius: f64,
+) -> Option<(Point, Point)> {
+    let (start, end, center) = (
+        PrecisePoint::from(start),
+        PrecisePoint::from(end),
+        PrecisePoint::from(center),
+    );
+    let radius = DoubleDouble::from(radius);
+
+    // solve |start + t(end - start) - center|^2 = r^2 for t
+    let dx = end.x - start.x;
+    let dy = end.y - start.y;
+    let fx = start.x - center.x;
+    let fy = start.y - center.y;
+    let a = dx * dx + dy * dy;
+    let b = (fx * dx + fy * dy) * 2.0;
+    let c = fx * fx + fy * fy - radius * radius;
+    let disc = b * b - a * c * 4.0;
+    if disc.to_f64() < 0.0 {
+        return None;
+    }
+    let root = disc.sqrt();
+    let t1 = (-b + root) / (a * 2.0);
+    let t2 = (-b - root) / (a * 2.0);
+
+    let first = PrecisePoint {
+        x: start.x + dx * t1,
+        y: start.y + dy * t1,
+    }
+    .to_point();
+    let second = PrecisePoint {
+        x: start.x + dx * t2,
+        y: start.y + dy * t2,
+    }
+    .to_point();
+
+    if first.x >= second.x {
+        Some((first, second))
+    } else {
+        Some((second, first))
+    }
+}
+
+/// Return the circumcenter of the triangle with vertices a, b, and c
+pub fn circumcenter(a: Point, b: Point, c: Point) -> Point {
+    precise_circumcenter(
+        PrecisePoint::from(a),
+        PrecisePoint::from(b),
+        PrecisePoint::from(c),
+    )
+    .to_point()
+}
+
+/// Return the orthocenter of the triangle with vertices a, b, and c
+pub fn orthocenter(a: Point, b: Point, c: Point) -> Point {
+    let (a, b, c) = (
+        PrecisePoint::from(a),
+        PrecisePoint::from(b),
+        PrecisePoint::from(c),
+    );
+
+    // the orthocenter is the reflection of the circumcenter through the centroid, scaled by two
+    let o = precise_circumcenter(a, b, c);
+    PrecisePoint {
+        x: a.x + b.x + c.x - o.x * 2.0,
+        y: a.y + b.y + c.y - o.y * 2.0,
+    }
+    .to_point()
+}
+
+/// Return the circumcenter of the triangle with vertices a, b, and c, without rounding it
+fn precise_circumcenter(a: PrecisePoint, b: PrecisePoint, c: PrecisePoint) -> PrecisePoint {
+    // translate so that a is the origin, then use the closed form
+    let bx = b.x - a.x;
+    let by = b.y - a.y;
+    let cx = c.x - a.x;
+    let cy = c.y - a.y;
+    let d = (bx * cy - by * cx) * 2.0;
+    let b2 = bx * bx + by * by;
+    let c2 = cx * cx + cy * cy;
+
+    PrecisePoint {
+        x: a.x + (cy * b2 - by * c2) / d,
+        y: a.y + (bx * c2 - cx * b2) / d,
+    }
+}
+
+/// Return the incenter of the triangle with vertices a, b, and c
+pub fn incenter(a: Point, b: Point, c: Point) -> Point {
+    let (a, b, c) = (
+        PrecisePoint::from(a),
+        PrecisePoint::from(b),
+        PrecisePoint::from(c),
+    );
+
+    // calculate the side lengths
+    let la = ((b.x - c.x) * (b.x - c.x) + (b.y - c.y) * (b.y - c.y)).sqrt();
+    let lb = ((a.x - c.x) * (a.x - c.x) + (a.y - c.y) * (a.y - c.y)).sqrt();
+    let lc = ((a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y)).sqrt();
+    let sum = la + lb + lc;
+
+    PrecisePoint {
+        x: (la * a.x + lb * b.x + lc * c.x) / sum,
+        y: (la * a.y + lb * b.y + lc * c.y) / sum,
+    }
+    .to_point()
+}
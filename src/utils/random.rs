@@ -0,0 +1,88 @@
+use crate::error::ElementsError;
+use crate::utils::limits;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::cell::{Cell, RefCell};
+
+thread_local! {
+    /// The seed used for the current run, chosen at random unless set explicitly
+    static SEED: Cell<u64> = Cell::new(rand::random::<u64>());
+    /// The generator used for all random constructions
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::seed_from_u64(SEED.with(|s| s.get())));
+}
+
+/// Reseed the generator so that random constructions are reproducible
+pub fn set_seed(seed: u64) {
+    SEED.with(|s| s.set(seed));
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Return the seed used for the current run
+pub fn seed() -> u64 {
+    SEED.with(|s| s.get())
+}
+
+/// Return a random float in the range [0, 1)
+pub fn random() -> f64 {
+    RNG.with(|rng| rng.borrow_mut().gen::<f64>())
+}
+
+/// A named condition that a random sample must satisfy
+pub struct Constraint<'a, T> {
+    pub name: String,
+    pub check: Box<dyn Fn(&T) -> bool + 'a>,
+}
+
+impl<'a, T> Constraint<'a, T> {
+    /// Create a new constraint given a name and a check
+    pub fn new(name: impl Into<String>, check: impl Fn(&T) -> bool + 'a) -> Self {
+        Self {
+            name: name.into(),
+            check: Box::new(check),
+        }
+    }
+}
+
+/// Draw samples until one satisfies every constraint, giving up after a bounded number of tries
+pub fn sample<T>(
+    max_tries: usize,
+    mut generate: impl FnMut() -> T,
+    constraints: &[Constraint<T>],
+) -> Result<T, ElementsError> {
+    // count how often each constraint rejects a sample
+    let mut failures: Vec<usize> = vec![0; constraints.len()];
+    for _ in 0..max_tries {
+        limits::step()?;
+        let candidate = generate();
+        let mut is_valid = true;
+        for (i, constraint) in constraints.iter().enumerate() {
+            if !(constraint.check)(&candidate) {
+                failures[i] += 1;
+                is_valid = false;
+            }
+        }
+        if is_valid {
+            return Ok(candidate);
+        }
+    }
+
+    // report the constraints that failed, most often first
+    let mut failed: Vec<(usize, &str)> = failures
+        .iter()
+        .zip(constraints)
+        .filter(|(count, _)| **count > 0)
+        .map(|(count, constraint)| (*count, constraint.name.as_str()))
+        .collect();
+    failed.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
+    let reasons: Vec<String> = failed
+        .iter()
+        .map(|(count, name)| format!("{} failed {} times", name, count))
+        .collect();
+    Err(ElementsError::Geometry(format!(
+        "No sample satisfied the constraints after {} tries ({})",
+        max_tries,
+        reasons.join(", ")
+    )))
+}
@@ -0,0 +1,124 @@
+use crate::error::ElementsError;
+
+/// Most steps taken from one starting guess before giving up on it
+const MAX_ITERATIONS: usize = 200;
+
+/// Starting with the given guess, find the unknowns that bring every residual closest to zero with the
+/// Levenberg-Marquardt method, returning them with the largest residual left, or None if the residuals
+/// cannot be computed at the guess
+pub fn least_squares(
+    residuals: &mut impl FnMut(&[f64]) -> Result<Vec<f64>, ElementsError>,
+    guess: Vec<f64>,
+) -> Result<Option<(Vec<f64>, f64)>, ElementsError> {
+    let mut x = guess;
+    let Some(mut r) = attempt(residuals, &x)? else {
+        return Ok(None);
+    };
+    let mut cost = squared(&r);
+    let mut damping = 1e-3;
+    for _ in 0..MAX_ITERATIONS {
+        if cost == 0.0 {
+            break;
+        }
+
+        // the jacobian is found by forward differences, skipping any unknown the residuals fail at
+        let mut jacobian: Vec<Vec<f64>> = vec![vec![0.0; x.len()]; r.len()];
+        for j in 0..x.len() {
+            let h = 1e-7 * x[j].abs().max(1.0);
+            let mut moved = x.clone();
+            moved[j] += h;
+            if let Some(shifted) = attempt(residuals, &moved)? {
+                for (i, row) in jacobian.iter_mut().enumerate() {
+                    row[j] = (shifted[i] - r[i]) / h;
+                }
+            }
+        }
+
+        // take the damped step, growing the damping until the step lowers the cost
+        let mut is_improved = false;
+        while damping < 1e12 {
+            let Some(step) = damped_step(&jacobian, &r, damping) else {
+                damping *= 4.0;
+                continue;
+            };
+            let next: Vec<f64> = x.iter().zip(&step).map(|(x, dx)| x + dx).collect();
+            match attempt(residuals, &next)? {
+                Some(next_r) if squared(&next_r) < cost => {
+                    let size = step.iter().map(|dx| dx.abs()).fold(0.0, f64::max);
+                    let scale = x.iter().map(|x| x.abs()).fold(1.0, f64::max);
+                    x = next;
+                    r = next_r;
+                    cost = squared(&r);
+                    damping = (damping / 3.0).max(1e-12);
+                    is_improved = size > 1e-15 * scale;
+                    break;
+                }
+                _ => damping *= 4.0,
+            }
+        }
+        if !is_improved {
+            break;
+        }
+    }
+    let largest = r.iter().map(|r| r.abs()).fold(0.0, f64::max);
+    Ok(Some((x, largest)))
+}
+
+/// Return the residuals at the unknowns, or None if the geometry they depend on does not exist there, such
+/// as two circles that do not meet, where any other error, such as running out of limits, is returned
+fn attempt(
+    residuals: &mut impl FnMut(&[f64]) -> Result<Vec<f64>, ElementsError>,
+    x: &[f64],
+) -> Result<Option<Vec<f64>>, ElementsError> {
+    match residuals(x) {
+        Ok(r) if r.iter().all(|r| r.is_finite()) => Ok(Some(r)),
+        Ok(_) | Err(ElementsError::Geometry(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Return the sum of the squares of the residuals
+fn squared(r: &[f64]) -> f64 {
+    r.iter().map(|r| r * r).sum()
+}
+
+/// Solve (JᵀJ + λ(diag(JᵀJ) + I)) δ = -Jᵀr for the step δ, or None if the system is singular
+fn damped_step(jacobian: &[Vec<f64>], r: &[f64], damping: f64) -> Option<Vec<f64>> {
+    let n = jacobian.first().map_or(0, |row| row.len());
+    let mut a: Vec<Vec<f64>> = vec![vec![0.0; n + 1]; n];
+    for (row, ri) in jacobian.iter().zip(r) {
+        for i in 0..n {
+            for j in 0..n {
+                a[i][j] += row[i] * row[j];
+            }
+            a[i][n] -= row[i] * ri;
+        }
+    }
+    for (i, equation) in a.iter_mut().enumerate() {
+        equation[i] += damping * (equation[i] + 1.0);
+    }
+
+    // gaussian elimination with partial pivoting, as there are only two unknowns for each point
+    for column in 0..n {
+        let pivot =
+            (column..n).max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))?;
+        if a[pivot][column].abs() < 1e-300 {
+            return None;
+        }
+        a.swap(column, pivot);
+        let (upper, lower) = a.split_at_mut(column + 1);
+        let pivot = &upper[column];
+        for row in lower {
+            let factor = row[column] / pivot[column];
+            for (value, above) in row[column..].iter_mut().zip(&pivot[column..]) {
+                *value -= factor * above;
+            }
+        }
+    }
+    let mut step = vec![0.0; n];
+    for i in (0..n).rev() {
+        let known: f64 = (i + 1..n).map(|j| a[i][j] * step[j]).sum();
+        step[i] = (a[i][n] - known) / a[i][i];
+    }
+    step.iter().all(|dx| dx.is_finite()).then_some(step)
+}
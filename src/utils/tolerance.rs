@@ -0,0 +1,40 @@
+use crate::TOLERANCE;
+
+use std::cell::Cell;
+
+thread_local! {
+    /// How far apart two values may be and still be taken as equal, such as a point and a circle it lies on
+    static EPSILON: Cell<f64> = const { Cell::new(TOLERANCE) };
+    /// The tolerance every evaluation starts with, such as one given with --tolerance
+    static DEFAULT: Cell<f64> = const { Cell::new(TOLERANCE) };
+}
+
+/// Set the tolerance every evaluation after this one on the current thread starts with, which a figure
+/// can still change with set-tolerance
+pub fn set_default_tolerance(tolerance: f64) {
+    DEFAULT.with(|d| d.set(tolerance));
+    set_tolerance(tolerance);
+}
+
+/// Set how far apart values may be and still be equal in every geometric check after this one in the
+/// current evaluation
+pub fn set_tolerance(tolerance: f64) {
+    EPSILON.with(|e| e.set(tolerance));
+}
+
+/// Return to the default tolerance when a new figure starts evaluating, so one set by an earlier figure on
+/// the same thread does not carry over
+pub fn start() {
+    set_tolerance(DEFAULT.with(|d| d.get()));
+}
+
+/// Return how far apart values may be and still be equal in geometric checks on the current thread
+pub fn tolerance() -> f64 {
+    EPSILON.with(|e| e.get())
+}
+
+/// Return whether two values are equal within the tolerance, scaled by the size of the figure they are
+/// measured in, such as a radius, so that large figures are held to the same relative precision as small ones
+pub fn is_near(a: f64, b: f64, scale: f64) -> bool {
+    (a - b).abs() <= tolerance() * scale.abs().max(1.0)
+}
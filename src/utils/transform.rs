@@ -0,0 +1,101 @@
+use crate::{lang::types::Point, utils::tolerance::tolerance};
+
+/// A similarity transformation of the plane, stored as a 2x2 linear part and a translation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub dx: f64,
+    pub dy: f64,
+}
+
+impl Transform {
+    /// Create a rotation by the given degrees about a center point
+    pub fn rotation(center: Point, deg: f64) -> Self {
+        Self::spiral_similarity(center, deg, 1.0)
+    }
+
+    /// Create a rotation by the given degrees combined with a dilation by the given ratio about a center point
+    pub fn spiral_similarity(center: Point, deg: f64, ratio: f64) -> Self {
+        let (sin, cos) = deg.to_radians().sin_cos();
+        let (sin, cos) = (ratio * sin, ratio * cos);
+
+        // rotate and scale about the origin, then correct the offset so the center stays fixed
+        Self {
+            a: cos,
+            b: -sin,
+            c: sin,
+            d: cos,
+            dx: center.x - cos * center.x + sin * center.y,
+            dy: center.y - sin * center.x - cos * center.y,
+        }
+    }
+
+    /// Create the orientation-preserving similarity taking the first pair of points to the second
+    pub fn mapping(from: (Point, Point), to: (Point, Point)) -> Option<Self> {
+        // treat points as complex numbers, so the map is z -> mz + t
+        let (fx, fy) = (from.1.x - from.0.x, from.1.y - from.0.y);
+        let (tx, ty) = (to.1.x - to.0.x, to.1.y - to.0.y);
+        let norm = fx * fx + fy * fy;
+        if norm == 0.0 {
+            return None;
+        }
+        let re = (tx * fx + ty * fy) / norm;
+        let im = (ty * fx - tx * fy) / norm;
+
+        Some(Self {
+            a: re,
+            b: -im,
+            c: im,
+            d: re,
+            dx: to.0.x - (re * from.0.x - im * from.0.y),
+            dy: to.0.y - (im * from.0.x + re * from.0.y),
+        })
+    }
+
+    /// Return the point left in place, or None for a translation or the identity
+    pub fn fixed_point(&self) -> Option<Point> {
+        // solve (I - L)p = (dx, dy) for the linear part L
+        let (a, b, c, d) = (1.0 - self.a, -self.b, -self.c, 1.0 - self.d);
+        let det = a * d - b * c;
+        if det.abs() < tolerance() {
+            return None;
+        }
+        Some(Point {
+            x: (d * self.dx - b * self.dy) / det,
+            y: (a * self.dy - c * self.dx) / det,
+        })
+    }
+
+    /// Create a translation by the given offsets
+    pub fn translation(dx: f64, dy: f64) -> Self {
+        Self {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            dx,
+            dy,
+        }
+    }
+
+    /// Apply the transformation to a point
+    pub fn apply(&self, point: Point) -> Point {
+        Point {
+            x: self.a * point.x + self.b * point.y + self.dx,
+            y: self.c * point.x + self.d * point.y + self.dy,
+        }
+    }
+
+    /// Return the angle in degrees by which directions are rotated
+    pub fn rotation_degrees(&self) -> f64 {
+        self.c.atan2(self.a).to_degrees()
+    }
+
+    /// Return the factor by which lengths are scaled
+    pub fn scale(&self) -> f64 {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+}
@@ -0,0 +1,175 @@
+use crate::cli::{ErrorFormat, VerifyArgs};
+use crate::report;
+use elements_lang::diagnostic::{closest, Diagnostic};
+use elements_lang::interpreter;
+use elements_lang::lexer::{expression_spans, tokenize, Span};
+use elements_lang::utils::params;
+use elements_lang::utils::random;
+use elements_lang::utils::tolerance::set_default_tolerance;
+
+use std::fs;
+
+/// A claim made by the figure, found again in each evaluation by the expression that made it and its order
+/// there, with the seeds it was checked with and those it did not hold for
+struct Verdict {
+    span: Option<Span>,
+    order: usize,
+    name: String,
+    checked: u32,
+    failed: Vec<u64>,
+}
+
+/// Evaluate the input once for each seed, checking every claim and assertion it makes, then print the claims
+/// that held for every seed and report the ones that did not, along with the errors found
+pub fn verify(args: &VerifyArgs, error_format: ErrorFormat) {
+    let file = Some(args.input.clone());
+    let contents = match args.input.as_str() {
+        "-" => std::io::read_to_string(std::io::stdin()),
+        input => fs::read_to_string(input),
+    };
+    let contents = match contents {
+        Ok(contents) => contents,
+        Err(e) => {
+            report(&Diagnostic::error(e.to_string()), error_format);
+            std::process::exit(1);
+        }
+    };
+    let spans = expression_spans(&contents);
+    let tokens = tokenize(contents.clone());
+    params::set_overrides(args.params.clone());
+    if let Some(tolerance) = args.tolerance {
+        set_default_tolerance(tolerance);
+    }
+
+    // the first seed is the one given or a random one, and each seed after it the next
+    let first = args.seed.unwrap_or_else(random::seed);
+    let mut verdicts: Vec<Verdict> = Vec::new();
+    let mut errors: Vec<Diagnostic> = Vec::new();
+    for i in 0..args.seeds {
+        let seed = first.wrapping_add(i as u64);
+        random::set_seed(seed);
+        let (checked, diagnostics) = interpreter::verify(&tokens, &spans);
+
+        // a claim checked again, such as by a lambda called twice, is told apart by its order in the expression
+        let mut previous: Option<Option<Span>> = None;
+        let mut order = 0;
+        for (span, claim) in checked {
+            order = if previous == Some(span) { order + 1 } else { 0 };
+            previous = Some(span);
+            let index = match verdicts
+                .iter()
+                .position(|v| v.span == span && v.order == order)
+            {
+                Some(index) => index,
+                None => {
+                    let name = claim.name.unwrap_or_else(|| {
+                        span.map(|span| source(&contents, span)).unwrap_or_default()
+                    });
+                    verdicts.push(Verdict {
+                        span,
+                        order,
+                        name,
+                        checked: 0,
+                        failed: Vec::new(),
+                    });
+                    verdicts.len() - 1
+                }
+            };
+            verdicts[index].checked += 1;
+            if !claim.holds {
+                verdicts[index].failed.push(seed);
+            }
+        }
+
+        // the same error is usually found with every seed, so each is reported once with the first seed
+        for diagnostic in diagnostics {
+            if !errors
+                .iter()
+                .any(|e| e.span == diagnostic.span && e.message.ends_with(&diagnostic.message))
+            {
+                errors.push(Diagnostic {
+                    message: format!("with --seed {}: {}", seed, diagnostic.message),
+                    ..diagnostic
+                });
+            }
+        }
+    }
+
+    // a value given to a parameter the figure does not declare is most likely misspelled
+    if let Some(name) = params::undeclared().first() {
+        let declared = params::declared();
+        let suggestion = closest(name, declared.iter().map(|p| p.name.as_str()))
+            .map(|closest| format!("did you mean `{}`?", closest));
+        errors.push(Diagnostic {
+            suggestion,
+            ..Diagnostic::error(format!("unknown parameter: {}", name))
+        });
+    }
+
+    // claims that held are printed, and those that did not are reported like errors
+    if verdicts.is_empty() {
+        log::warn!("{} makes no claims or assertions to verify", args.input);
+    }
+    let is_valid = errors.is_empty() && verdicts.iter().all(|v| v.failed.is_empty());
+    verdicts.sort_by_key(|v| v.span.map(|span| (span.start.line, span.start.column)));
+    for verdict in verdicts {
+        match verdict.failed.first() {
+            None => {
+                let location = verdict
+                    .span
+                    .map(|span| format!("{}:{}:", span.start.line, span.start.column))
+                    .unwrap_or_default();
+                println!(
+                    "{}:{} holds for {} of {} seeds: {}",
+                    args.input, location, verdict.checked, verdict.checked, verdict.name
+                );
+            }
+            Some(seed) => {
+                let message = format!(
+                    "fails for {} of {} seeds, such as --seed {}: {}",
+                    verdict.failed.len(),
+                    verdict.checked,
+                    seed,
+                    verdict.name
+                );
+                let diagnostic = Diagnostic {
+                    file: file.clone(),
+                    span: verdict.span,
+                    ..Diagnostic::error(message)
+                };
+                report(&diagnostic, error_format);
+            }
+        }
+    }
+    for error in errors {
+        let file = file.clone();
+        report(&Diagnostic { file, ..error }, error_format);
+    }
+    if !is_valid {
+        std::process::exit(1);
+    }
+}
+
+/// Return the source of a span on one line, with each run of whitespace written as one space
+fn source(contents: &str, span: Span) -> String {
+    let mut text = String::new();
+    for (i, line) in contents.lines().enumerate() {
+        let number = i + 1;
+        if number < span.start.line || number > span.end.line {
+            continue;
+        }
+        let start = if number == span.start.line {
+            span.start.column - 1
+        } else {
+            0
+        };
+        let end = if number == span.end.line {
+            span.end.column - 1
+        } else {
+            usize::MAX
+        };
+        text.extend(line.chars().skip(start).take(end.saturating_sub(start)));
+        text.push(' ');
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
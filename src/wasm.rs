@@ -0,0 +1,45 @@
+use crate::render_source;
+use crate::renderer::{set_precision, RenderOptions};
+use crate::utils::random::set_seed;
+
+use wasm_bindgen::prelude::*;
+
+/// Options for rendering in the browser, matching the command line flags of the same names
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    pub label: bool,
+    pub flip_y: bool,
+    pub grid: bool,
+    pub legend: bool,
+    pub padding: Option<f64>,
+    pub precision: Option<u32>,
+    pub seed: Option<u64>,
+}
+
+#[wasm_bindgen]
+impl Options {
+    /// Create options with every flag turned off
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Given source code and options, render it to an SVG string, or throw the error message
+#[wasm_bindgen]
+pub fn render(source: &str, options: &Options) -> Result<String, String> {
+    set_precision(options.precision.map(|precision| precision as usize));
+    if let Some(seed) = options.seed {
+        set_seed(seed);
+    }
+    let options = RenderOptions {
+        is_label: options.label,
+        flip_y: options.flip_y,
+        grid: options.grid.then_some(1.0),
+        legend: options.legend,
+        padding: options.padding,
+        ..RenderOptions::default()
+    };
+    render_source(source, &options).map_err(|e| e.to_string())
+}
@@ -0,0 +1,83 @@
+//! Render each example with a fixed seed and compare it against its snapshot in `tests/snapshots`, so
+//! changes to geometry or rendering show up as failures. Run `cargo test --test examples -- --bless` to
+//! write the current output as the new snapshots.
+
+use elements_lang::render_source;
+use elements_lang::renderer::{set_precision, RenderOptions};
+use elements_lang::utils::random::set_seed;
+
+use std::fs;
+use std::path::Path;
+
+/// The seed every example is drawn with
+const SEED: u64 = 0;
+
+fn main() {
+    let is_bless = std::env::args().any(|arg| arg == "--bless");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    // find the examples in order of name
+    let mut examples: Vec<_> = fs::read_dir(root.join("examples"))
+        .expect("Failed to read examples")
+        .map(|entry| entry.expect("Failed to read examples").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "et"))
+        .collect();
+    examples.sort();
+
+    // labels are included so their placement is covered, and numbers are rounded so that differences in
+    // the last digits of floating point functions between platforms do not count
+    let options = RenderOptions {
+        is_label: true,
+        ..RenderOptions::default()
+    };
+    set_precision(Some(6));
+
+    let mut failures: Vec<String> = Vec::new();
+    for example in &examples {
+        let name = example.file_stem().unwrap().to_string_lossy();
+        let snapshot = root.join("tests/snapshots").join(format!("{}.svg", name));
+        let source = fs::read_to_string(example).expect("Failed to read example");
+        set_seed(SEED);
+        let svg = match render_source(&source, &options) {
+            Ok(svg) => svg,
+            Err(e) => {
+                failures.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+
+        // write the snapshot when blessing, or else compare against it
+        if is_bless {
+            fs::write(&snapshot, &svg).expect("Failed to write snapshot");
+            println!("blessed {}", name);
+            continue;
+        }
+        match fs::read_to_string(&snapshot) {
+            Ok(expected) if expected == svg => println!("ok {}", name),
+            Ok(expected) => {
+                let line = expected
+                    .lines()
+                    .zip(svg.lines())
+                    .position(|(expected, actual)| expected != actual)
+                    .unwrap_or(expected.lines().count().min(svg.lines().count()));
+                failures.push(format!(
+                    "{}: differs from its snapshot at line {}\n  expected: {}\n  actual:   {}",
+                    name,
+                    line + 1,
+                    expected.lines().nth(line).unwrap_or_default(),
+                    svg.lines().nth(line).unwrap_or_default()
+                ));
+            }
+            Err(_) => failures.push(format!("{}: missing snapshot, run with --bless", name)),
+        }
+    }
+
+    // report every failure before failing
+    for failure in &failures {
+        eprintln!("{}", failure);
+    }
+    if !failures.is_empty() {
+        eprintln!("{} of {} examples failed", failures.len(), examples.len());
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,54 @@
+//! Call the C interface as a C program would, checking the status and error of each call.
+
+#![cfg(feature = "ffi")]
+
+use elements_lang::ffi::{elements_free, elements_last_error, elements_render, ElementsStatus};
+use std::ffi::{CStr, CString};
+use std::ptr;
+
+/// Render a source through the C interface, returning the status with the SVG or the last error
+fn render(source: &str) -> (ElementsStatus, String) {
+    let source = CString::new(source).unwrap();
+    let mut svg = ptr::null_mut();
+    unsafe {
+        let status = elements_render(source.as_ptr(), ptr::null(), &mut svg);
+        if status != ElementsStatus::Ok {
+            assert!(svg.is_null());
+            let error = CStr::from_ptr(elements_last_error());
+            return (status, error.to_string_lossy().into_owned());
+        }
+        let text = CStr::from_ptr(svg).to_string_lossy().into_owned();
+        elements_free(svg);
+        (status, text)
+    }
+}
+
+#[test]
+fn rendering_writes_the_svg() {
+    let (status, svg) = render("(circle (point 0 0) 1)");
+    assert_eq!(status, ElementsStatus::Ok);
+    assert!(svg.contains("<circle"), "{}", svg);
+}
+
+#[test]
+fn errors_are_returned_with_their_status() {
+    let (status, error) = render("(circle (point 0 0)");
+    assert_eq!(status, ElementsStatus::Error);
+    assert!(error.contains("Mismatched parentheses"), "{}", error);
+
+    let mut svg = ptr::null_mut();
+    let status = unsafe { elements_render(ptr::null(), ptr::null(), &mut svg) };
+    assert_eq!(status, ElementsStatus::InvalidArgument);
+    let source = CString::new("(point 0 0)").unwrap();
+    let status = unsafe { elements_render(source.as_ptr(), ptr::null(), ptr::null_mut()) };
+    assert_eq!(status, ElementsStatus::InvalidArgument);
+}
+
+#[test]
+fn each_call_starts_from_the_defaults() {
+    let (status, _) = render("(set-tolerance 0.5) (point 0 0)");
+    assert_eq!(status, ElementsStatus::Ok);
+    let (status, error) = render("(assert (on? (point 0 0.1) (line (point 0 0) (point 1 0))))");
+    assert_eq!(status, ElementsStatus::Error);
+    assert!(error.contains("Assertion failed"), "{}", error);
+}
@@ -0,0 +1,63 @@
+//! Check the centers of triangles against the properties that define them, including triangles with
+//! vertical and horizontal sides, whose slopes are infinite or zero.
+
+use elements_lang::lang::types::{Point, Triangle};
+
+/// Triangles that are acute, obtuse, right with sides along the axes, and far from the origin
+const TRIANGLES: [[(f64, f64); 3]; 5] = [
+    [(0.0, 0.0), (4.0, 0.0), (1.0, 3.0)],
+    [(0.0, 0.0), (10.0, 0.0), (1.0, 1.0)],
+    [(0.0, 0.0), (3.0, 0.0), (0.0, 4.0)],
+    [(2.0, -1.0), (2.0, 5.0), (-3.0, 2.0)],
+    [(1e6, 1e6), (1e6 + 3.0, 1e6 + 1.0), (1e6 + 1.0, 1e6 + 4.0)],
+];
+
+fn triangles() -> Vec<Triangle> {
+    TRIANGLES
+        .iter()
+        .map(|[a, b, c]| {
+            let point = |(x, y): (f64, f64)| Point { x, y };
+            Triangle::new(point(*a), point(*b), point(*c)).unwrap()
+        })
+        .collect()
+}
+
+fn distance(p: Point, q: Point) -> f64 {
+    (p.x - q.x).hypot(p.y - q.y)
+}
+
+/// Return the dot product of the vectors from p to q and from r to s
+fn dot(p: Point, q: Point, r: Point, s: Point) -> f64 {
+    (q.x - p.x) * (s.x - r.x) + (q.y - p.y) * (s.y - r.y)
+}
+
+fn check_centers() {
+    for t in triangles() {
+        let o = t.circumcenter();
+        let r = distance(o, t.a);
+        assert!((distance(o, t.b) - r).abs() < 1e-6 * r, "{:?}", t);
+        assert!((distance(o, t.c) - r).abs() < 1e-6 * r, "{:?}", t);
+
+        // each line from a vertex through the orthocenter is perpendicular to the opposite side
+        let h = t.orthocenter();
+        let scale = distance(t.a, t.b) * distance(t.b, t.c);
+        assert!(dot(t.a, h, t.b, t.c).abs() < 1e-6 * scale, "{:?}", t);
+        assert!(dot(t.b, h, t.c, t.a).abs() < 1e-6 * scale, "{:?}", t);
+        assert!(dot(t.c, h, t.a, t.b).abs() < 1e-6 * scale, "{:?}", t);
+    }
+}
+
+#[test]
+fn centers_satisfy_their_definitions() {
+    check_centers();
+}
+
+#[cfg(feature = "high-precision")]
+#[test]
+fn precise_centers_satisfy_their_definitions() {
+    use elements_lang::utils::precise;
+
+    precise::set_enabled(true);
+    check_centers();
+    precise::set_enabled(false);
+}
@@ -0,0 +1,763 @@
+//! Evaluate short programs and check the values they produce, for behaviour that a rendered snapshot
+//! would not show, such as errors and the values of expressions, running the command line program for
+//! the behaviour of its subcommands.
+
+use elements_lang::analyzer::analyze;
+use elements_lang::diagnostic::{allowed_lints, Severity};
+use elements_lang::error::ElementsError;
+use elements_lang::formatter::format;
+use elements_lang::interpreter::{check, evaluate_located, evaluate_variables, evaluate_with};
+use elements_lang::lang::environment::Environment;
+use elements_lang::lang::functions::FnLineseg;
+use elements_lang::lang::types::{Operation, Point, Value};
+use elements_lang::lexer::{expression_spans, tokenize, FUNCTION_NAMES};
+use elements_lang::render_source;
+use elements_lang::renderer::RenderOptions;
+use elements_lang::utils::limits::{set_limits, Limits};
+
+/// Evaluate a program and return the value of its last expression, without the labels of its points
+fn last(source: &str) -> Result<Value, ElementsError> {
+    let values = evaluate_with(&tokenize(source.to_string()), &mut Environment::new())?;
+    Ok(values.last().cloned().unwrap_or(Value::Undefined))
+}
+
+/// Run the command line program in a directory with the given source on stdin, and return whether it
+/// succeeded along with what it printed to stdout and to stderr
+#[cfg(feature = "cli")]
+fn elements(dir: &std::path::Path, args: &[&str], source: &str) -> (bool, String, String) {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_elements"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run elements");
+    let _ = child.stdin.take().unwrap().write_all(source.as_bytes());
+    let output = child.wait_with_output().expect("Failed to run elements");
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+/// Return an empty directory of the test's own to write files in
+#[cfg(feature = "cli")]
+fn scratch(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("elements-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("Failed to create a scratch directory");
+    dir
+}
+
+#[test]
+fn arithmetic_on_styled_values() {
+    assert_eq!(last("(setq r (hide 2)) (+ r 2)").unwrap(), Value::Int(4));
+    assert_eq!(
+        last("(setq r (hide 2.5)) (* r 2.0)").unwrap(),
+        Value::Float(5.0)
+    );
+}
+
+#[test]
+fn arithmetic_promotes_mixed_numbers_to_floats() {
+    assert_eq!(last("(+ 1 2.5)").unwrap(), Value::Float(3.5));
+    assert_eq!(last("(- 2.5 1)").unwrap(), Value::Float(1.5));
+    assert_eq!(last("(* 2 0.25)").unwrap(), Value::Float(0.5));
+    assert_eq!(last("(/ 1 4.0)").unwrap(), Value::Float(0.25));
+    assert_eq!(last("(/ 7 2)").unwrap(), Value::Int(3));
+    let source = "(+ 1 2.5)";
+    assert!(check(&tokenize(source.to_string()), &expression_spans(source)).is_empty());
+}
+
+#[test]
+fn integer_arithmetic_errors_instead_of_panicking() {
+    assert!(matches!(last("(/ 1 0)"), Err(ElementsError::Type(_))));
+    let big = "(setq n (* 2147483647 2147483647))";
+    for call in ["(* n 4)", "(+ (* n 2) n)", "(- (* n -2) n)"] {
+        let source = format!("{} {}", big, call);
+        assert!(
+            matches!(last(&source), Err(ElementsError::Type(_))),
+            "{}",
+            call
+        );
+    }
+    assert!(matches!(last("(/ 1.0 0)").unwrap(), Value::Float(_)));
+}
+
+#[test]
+fn limits_are_shared_by_the_expressions_of_a_program() {
+    let source = "(setq a (+ 1 2)) (setq b (+ a 3)) (setq c (+ b 4)) (setq d (+ c 5))";
+    set_limits(Limits {
+        max_steps: 6,
+        ..Limits::default()
+    });
+    let diagnostics = check(&tokenize(source.to_string()), &expression_spans(source));
+    set_limits(Limits::default());
+    assert!(diagnostics
+        .iter()
+        .any(|d| d.message.contains("evaluation budget exceeded")));
+}
+
+#[test]
+fn raw_svg_accepts_well_formed_fragments() {
+    let fragment = "(raw-svg \"<g><rect x='0' y='0' width='2' height='1' fill='gold'/></g>\")";
+    assert!(matches!(last(fragment).unwrap(), Value::RawSvg(_)));
+}
+
+#[test]
+fn raw_svg_rejects_unsafe_fragments() {
+    for fragment in [
+        "</svg><g>",
+        "<rect width='1'",
+        "<script>alert(1)</script>",
+        "<g><SCRIPT>alert(1)</SCRIPT></g>",
+        "<rect width='1' height='1' onclick='alert(1)'/>",
+        "<a href='javascript:alert(1)'><text>x</text></a>",
+        "<a xlink:href=' java\tscript:alert(1)'><text>x</text></a>",
+        "<set attributeName='href' to='JavaScript:alert(1)'/>",
+        "<foreignObject><div/></foreignObject>",
+    ] {
+        let source = format!("(raw-svg \"{}\")", fragment);
+        assert!(last(&source).is_err(), "accepted {}", fragment);
+    }
+}
+
+#[test]
+fn tangent_circle_at_passes_through_the_point() {
+    for kind in ["external", "internal"] {
+        let source = format!(
+            "(setq c (circle (point 1 1) 5)) (setq p (point 4 5)) \
+             (setq t (tangent-circle-at c p 2 {})) (on? p t)",
+            kind
+        );
+        assert_eq!(last(&source).unwrap(), Value::Bool(true), "{}", kind);
+    }
+    let quoted = "(setq c (circle (point 0 0) 5)) \
+                  (circle-center (tangent-circle-at c (point 5 0) 2 \"internal\"))";
+    match last(quoted).unwrap() {
+        Value::Point(center) => assert!((center.x - 3.0).abs() < 1e-9 && center.y.abs() < 1e-9),
+        value => panic!("expected a point, got {:?}", value),
+    }
+    assert!(last("(tangent-circle-at (circle (point 0 0) 5) (point 5 0) 2 \"inside\")").is_err());
+    assert!(last("(tangent-circle-at (circle (point 0 0) 5) (point 1 1) 2)").is_err());
+}
+
+#[test]
+fn style_dash_reads_lengths_from_strings() {
+    let source = "(setq X (point 0 0)) (style X :width 0.05 :dash \"0.1 0.1\")";
+    match last(source).unwrap() {
+        Value::Styled(_, style) => assert_eq!(style.dash, Some(vec![0.1, 0.1])),
+        value => panic!("expected a styled value, got {:?}", value),
+    }
+    assert!(last("(style (point 0 0) :dash \"0.1, 0.05\")").is_ok());
+    assert!(last("(style (point 0 0) :dash \"0.1 x\")").is_err());
+    assert!(last("(style (point 0 0) :dash \"\")").is_err());
+}
+
+#[test]
+fn unknown_functions_are_errors() {
+    let source = "(setq A (point 0 0)) (cirlce A 1)";
+    let error = last(source).unwrap_err();
+    assert!(error.to_string().contains("Unknown function: cirlce"));
+
+    let diagnostics = analyze(&tokenize(source.to_string()), &expression_spans(source));
+    let unknown = diagnostics
+        .iter()
+        .find(|d| d.message.contains("Unknown function: cirlce"))
+        .expect("unknown function not reported");
+    assert_eq!(unknown.severity, Severity::Error);
+    assert_eq!(
+        unknown.suggestion.as_deref(),
+        Some("did you mean `circle`?")
+    );
+}
+
+#[test]
+fn every_function_name_is_known() {
+    for name in FUNCTION_NAMES {
+        let source = format!("({})", name);
+        if let Err(error) = last(&source) {
+            assert!(!error.to_string().contains("Unknown function"), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn check_suggests_the_offending_name_in_a_call() {
+    let source = "(setq Alpha (point 0 0))\n(circle Alpah 1)\n(cirlce Alpha 1)";
+    let diagnostics = check(&tokenize(source.to_string()), &expression_spans(source));
+    let suggestions: Vec<Option<&str>> = diagnostics
+        .iter()
+        .map(|d| d.suggestion.as_deref())
+        .collect();
+    assert_eq!(
+        suggestions,
+        vec![
+            Some("did you mean `Alpha`?"),
+            Some("did you mean `circle`?")
+        ]
+    );
+    assert_eq!(diagnostics[1].span.unwrap().start.line, 3);
+}
+
+#[test]
+fn evaluation_errors_have_the_span_of_their_expression() {
+    let source = "(setq A (point 0 0))\n(setq B (point 1 0))\n(circle A B)";
+    let error = evaluate_located(tokenize(source.to_string()), &expression_spans(source))
+        .err()
+        .expect("circle of a point and a point evaluated");
+    assert_eq!(error.span.unwrap().start.line, 3);
+}
+
+#[test]
+fn transforms_that_collapse_lines_are_errors() {
+    let line = "(line (point 0 0) (point 1 0))";
+    let source = format!("(spiral-similarity {} (point 0 0) 0 1e-12)", line);
+    assert!(matches!(last(&source), Err(ElementsError::Geometry(_))));
+    let source = format!("(spiral-similarity {} (point 0 0) 90 2)", line);
+    assert!(matches!(last(&source).unwrap(), Value::Line(_)));
+}
+
+#[test]
+fn figures_of_only_lines_have_a_finite_viewbox() {
+    let svg = render_source("(line (point 0 0) (point 1 1))", &RenderOptions::default()).unwrap();
+    let viewbox = svg.split("viewBox=\"").nth(1).unwrap();
+    let numbers: Vec<f64> = viewbox
+        .split('"')
+        .next()
+        .unwrap()
+        .split(' ')
+        .map(|n| n.parse().unwrap())
+        .collect();
+    assert!(numbers.iter().all(|n| n.is_finite()), "{}", svg);
+    assert!(svg.contains("<line "), "{}", svg);
+}
+
+#[test]
+fn pedal_of_a_point_on_the_circumcircle_is_an_error() {
+    let source = "(setq A (point 0 0)) (setq B (point 1000 0)) (setq C (point 130 770)) \
+                  (setq t (triangle A B C)) (setq P (rotate A (circumcenter t) 37)) (pedal t P)";
+    assert!(matches!(last(source), Err(ElementsError::Geometry(_))));
+    let inside = "(pedal (triangle (point 0 0) (point 4 0) (point 1 3)) (point 1 1))";
+    assert!(matches!(last(inside).unwrap(), Value::Triangle(_)));
+}
+
+#[test]
+fn lambdas_see_the_variables_around_them() {
+    let source = "(setq r 2.0) (locus (lambda (s) (point (* r s) r)) 0.0 1.0 10000)";
+    match last(source).unwrap() {
+        Value::Locus(locus) => {
+            let last = *locus.runs[0].last().unwrap();
+            assert_eq!((last.x, last.y), (2.0, 2.0));
+        }
+        value => panic!("expected a locus, got {:?}", value),
+    }
+}
+
+#[test]
+fn variables_set_around_lambdas_stay_top_level() {
+    let source =
+        "(setq r 2.0) (setq f (lambda (s) (point (* r s) r))) (setq q (locus f 0.0 1.0 100)) \
+                  (setq g (lambda (s) (point s (* r s))))";
+    let (_, variables) = evaluate_variables(tokenize(source.to_string())).unwrap();
+    let mut names: Vec<&String> = variables.locals().map(|(name, _)| name).collect();
+    names.sort();
+    assert_eq!(names, ["f", "g", "q", "r"]);
+    assert_eq!(variables.get("r"), Some(&Value::Float(2.0)));
+}
+
+#[test]
+fn angles_and_segments_report_why_their_arguments_are_wrong() {
+    let error = |source: &str| last(source).unwrap_err().to_string();
+    assert!(error("(angle (point 0 0) (point 1 0))").contains("exactly 3 arguments"));
+    assert!(error("(angle (point 0 0) 1 (point 1 1))").contains("Invalid types for point"));
+
+    // the signature is checked before the call, so the call is made directly to reach its own errors
+    let point = Value::Point(Point { x: 0.0, y: 0.0 });
+    let error = FnLineseg
+        .call(&[point.clone(), point.clone(), point])
+        .unwrap_err();
+    assert!(
+        error.to_string().contains("exactly 2 arguments"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn hatches_multiply_unless_blended_otherwise() {
+    let circle = "(circle (point 0 0) 1)";
+    let render = |source: String| render_source(&source, &RenderOptions::default()).unwrap();
+    let multiply = "mix-blend-mode: multiply";
+    assert!(render(format!("(hatch {})", circle)).contains(multiply));
+    assert!(!render(format!("(blend (hatch {}) normal)", circle)).contains(multiply));
+    assert!(!render(format!("(fill {} red)", circle)).contains(multiply));
+}
+
+#[test]
+fn cevians_end_on_the_opposite_side() {
+    let triangle = "(setq t (triangle (point 0 0) (point 4000 0) (point 1000 3000)))";
+    let cevian = |point: &str| last(&format!("{} (cevian t 2 {})", triangle, point));
+    assert!(matches!(
+        cevian("(point 1000 0)").unwrap(),
+        Value::Lineseg(_)
+    ));
+    assert!(matches!(
+        cevian("(point 4000 0)").unwrap(),
+        Value::Lineseg(_)
+    ));
+    assert!(matches!(
+        cevian("(point 2000 0.0000001)").unwrap(),
+        Value::Lineseg(_)
+    ));
+    for outside in ["(point 5000 0)", "(point -1 0)", "(point 2000 1)"] {
+        assert!(
+            matches!(cevian(outside), Err(ElementsError::Geometry(_))),
+            "{}",
+            outside
+        );
+    }
+}
+
+#[test]
+fn concyclic_skips_points_that_coincide() {
+    let points =
+        "(setq A (point 0 0)) (setq B (point 4 0)) (setq C (point 0 3)) (setq D (point 4 3))";
+    let check = |call: &str| last(&format!("{} {}", points, call)).unwrap();
+    assert_eq!(check("(concyclic? A B C D)"), Value::Bool(true));
+    assert_eq!(check("(concyclic? A A B C D)"), Value::Bool(true));
+    assert_eq!(check("(concyclic? A B B C D)"), Value::Bool(true));
+    assert_eq!(check("(concyclic? A A B B)"), Value::Bool(true));
+    assert_eq!(
+        check("(concyclic? A A B C (point 5 5))"),
+        Value::Bool(false)
+    );
+    assert_eq!(check("(concyclic? A B (point 8 0) C)"), Value::Bool(false));
+}
+
+#[test]
+fn concyclic_uses_the_tolerance() {
+    let points = "(setq A (point 0 0)) (setq B (point 4000 0)) (setq C (point 0 3000)) \
+                  (setq D (point 4000 3000.001))";
+    assert_eq!(
+        last(&format!("{} (concyclic? A B C D)", points)).unwrap(),
+        Value::Bool(false)
+    );
+    assert_eq!(
+        last(&format!(
+            "{} (set-tolerance 1e-6) (concyclic? A B C D)",
+            points
+        ))
+        .unwrap(),
+        Value::Bool(true)
+    );
+}
+
+#[test]
+fn set_tolerance_does_not_carry_over_to_the_next_figure() {
+    let options = RenderOptions::default();
+    let loose = "(set-tolerance 0.5) (assert (on? (point 0 0.1) (line (point 0 0) (point 1 0))))";
+    assert!(render_source(loose, &options).is_ok());
+    let strict = "(assert (on? (point 0 0.1) (line (point 0 0) (point 1 0))))";
+    assert!(render_source(strict, &options).is_err());
+}
+
+#[test]
+fn predicates_scale_the_tolerance_with_the_figure() {
+    // a point off by far more than the tolerance at unit scale is still on each value in a figure a million
+    // times larger, as every predicate measures its error relative to the size of the figure
+    let off = 1e6 + 2e-5;
+    let checks = [
+        format!(
+            "(collinear? (point 0 0) (point 1e6 0) (point 2e6 {}))",
+            off - 1e6
+        ),
+        format!("(on? (point {} 0) (circle (point 0 0) 1e6))", off),
+        format!(
+            "(on? (point 1e6 {}) (line (point 0 0) (point 1e6 0)))",
+            off - 1e6
+        ),
+        format!(
+            "(concurrent? (line (point 0 1e6) (point 1 1e6)) (line (point 1e6 0) (point 1e6 1)) \
+             (line (point {} 0) (point {} 1)))",
+            off, off
+        ),
+        format!("(= 1e6 {})", off),
+        format!(
+            "(concyclic? (point 1e6 0) (point -1e6 0) (point 0 1e6) (point 0 -{}))",
+            off
+        ),
+    ];
+    for check in checks {
+        assert_eq!(last(&check).unwrap(), Value::Bool(true), "{}", check);
+    }
+    assert_eq!(
+        last("(on? (point 0 2e-5) (line (point 0 0) (point 1 0)))").unwrap(),
+        Value::Bool(false)
+    );
+}
+
+#[test]
+fn solve_finds_the_point_its_constraints_describe() {
+    let source = "(setq A (point 0 0)) (setq B (point 4 0)) \
+                  (solve (P) (constraints (= (distance P A) 3) (= (distance P B) 3) (> (point-y P) 0)))";
+    let (_, variables) = evaluate_variables(tokenize(source.to_string())).unwrap();
+    match variables.get("P") {
+        Some(Value::Point(p)) => {
+            assert!((p.x - 2.0).abs() < 1e-6, "{:?}", p);
+            assert!((p.y - 5f64.sqrt()).abs() < 1e-6, "{:?}", p);
+        }
+        value => panic!("expected a point, got {:?}", value),
+    }
+
+    // no point is 1 away from both ends of a segment 4 long
+    let impossible = "(setq A (point 0 0)) (setq B (point 4 0)) \
+                      (solve (P) (constraints (= (distance P A) 1) (= (distance P B) 1)))";
+    assert!(last(impossible).is_err());
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn verify_reports_the_claims_that_fail() {
+    let source = "(setq A (point 0 0)) (setq B (point 4 0)) (setq C (point 1 3))\n\
+                  (claim (collinear? A B C) \"ABC is a line\")\n\
+                  (claim (< (distance A B) 5) \"AB is short\")\n";
+    let (is_ok, stdout, stderr) =
+        elements(&std::env::temp_dir(), &["verify", "-", "-n", "3"], source);
+    assert!(!is_ok);
+    assert!(stderr.contains("2:1: fails for 3 of 3 seeds"), "{}", stderr);
+    assert!(stderr.contains("ABC is a line"), "{}", stderr);
+    assert!(
+        stdout.contains("3:1: holds for 3 of 3 seeds: AB is short"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn generate_draws_each_variant_again_from_its_manifest() {
+    let dir = scratch("generate");
+    let source = "(param a 4 3 6) (param b 3 2 5) \
+                  (triangle (point 0 0) (point a 0) (point 0 b)) (triangle (circle (point 0 0) a))";
+    std::fs::write(dir.join("figure.et"), source).unwrap();
+    let args = [
+        "generate",
+        "figure.et",
+        "-n",
+        "3",
+        "--seed",
+        "5",
+        "--manifest",
+        "m.csv",
+    ];
+    let (is_ok, _, stderr) = elements(&dir, &args, "");
+    assert!(is_ok, "{}", stderr);
+
+    // each variant has the next seed and values of the parameters in their ranges
+    let manifest = std::fs::read_to_string(dir.join("m.csv")).unwrap();
+    let rows: Vec<Vec<&str>> = manifest
+        .lines()
+        .map(|line| line.split(',').collect())
+        .collect();
+    assert_eq!(rows[0], ["file", "seed", "a", "b"]);
+    assert_eq!(rows.len(), 4, "{}", manifest);
+    for (i, row) in rows[1..].iter().enumerate() {
+        assert_eq!(row[0], format!("figure-{}.svg", i + 1));
+        assert_eq!(row[1], (5 + i).to_string());
+        let a: i64 = row[2].parse().unwrap();
+        let b: i64 = row[3].parse().unwrap();
+        assert!((3..=6).contains(&a) && (2..=5).contains(&b), "{:?}", row);
+    }
+
+    // the seed and values of a variant draw the same figure again
+    let row = &rows[2];
+    let (a, b) = (format!("a={}", row[2]), format!("b={}", row[3]));
+    let args = [
+        "figure.et",
+        "--seed",
+        row[1],
+        "--param",
+        &a,
+        "--param",
+        &b,
+        "-o",
+        "again.svg",
+    ];
+    let (is_ok, _, stderr) = elements(&dir, &args, "");
+    assert!(is_ok, "{}", stderr);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("again.svg")).unwrap(),
+        std::fs::read_to_string(dir.join("figure-2.svg")).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn emit_coords_writes_every_top_level_variable() {
+    let dir = scratch("emit-coords");
+    let source =
+        "(setq A (point 0 0)) (setq B (point 3 4)) (setq s (lineseg A B)) (setq c (circle B 2)) \
+                  (setq n 7) (setq f (lambda (t) (point t t)))";
+    let (is_ok, _, stderr) = elements(
+        &dir,
+        &["-", "--stdout", "--emit-coords", "coords.csv"],
+        source,
+    );
+    assert!(is_ok, "{}", stderr);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("coords.csv")).unwrap(),
+        "name,type,x,y,value\nA,point,0,0,\nB,point,3,4,\nc,circle,3,4,2\nn,number,,,7\ns,segment,,,5\n"
+    );
+    let (is_ok, _, stderr) = elements(
+        &dir,
+        &["-", "--stdout", "--emit-coords", "coords.json"],
+        source,
+    );
+    assert!(is_ok, "{}", stderr);
+    let json = std::fs::read_to_string(dir.join("coords.json")).unwrap();
+    assert!(
+        json.contains(r#""B":{"type":"point","x":3,"y":4}"#),
+        "{}",
+        json
+    );
+    assert!(
+        json.contains(r#""s":{"type":"segment","value":5}"#),
+        "{}",
+        json
+    );
+
+    // the coordinates of an earlier run are kept unless forced
+    let (is_ok, _, stderr) = elements(
+        &dir,
+        &["-", "--stdout", "--emit-coords", "coords.csv"],
+        source,
+    );
+    assert!(!is_ok);
+    assert!(
+        stderr.contains("pass --force to overwrite it"),
+        "{}",
+        stderr
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn fmt_keeps_comments_and_the_tokens_and_is_idempotent() {
+    let source = "; the triangle\n(setq   A (point 0 0))   ; origin\n\n\n\n(setq B\n   (point 4 0))\n\
+                  (setq t (triangle A B (point 1 3)))(circumcircle t) ; circle\n\
+                  (setq long (triangle (point 1000000 2000000) (point 3000000 4000000) (point 5000000 6000000000) \
+                  ; inner\n (point 7 8)))\n";
+    let formatted = format(source).unwrap();
+    assert_eq!(
+        formatted,
+        "; the triangle\n\
+         (setq A (point 0 0)) ; origin\n\
+         \n\
+         (setq B (point 4 0))\n\
+         (setq t (triangle A B (point 1 3)))\n\
+         (circumcircle t) ; circle\n\
+         (setq long\n\
+         \x20     (triangle (point 1000000 2000000)\n\
+         \x20               (point 3000000 4000000)\n\
+         \x20               (point 5000000 6000000000) ; inner\n\
+         \x20               (point 7 8)))\n"
+    );
+    assert_eq!(format(&formatted).unwrap(), formatted);
+    assert_eq!(tokenize(formatted), tokenize(source.to_string()));
+
+    // every example reads the same once formatted, and formatting it again changes nothing
+    let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    for entry in std::fs::read_dir(examples).unwrap() {
+        let path = entry.unwrap().path();
+        let source = std::fs::read_to_string(&path).unwrap();
+        let formatted = format(&source).unwrap();
+        assert_eq!(format(&formatted).unwrap(), formatted, "{}", path.display());
+        assert_eq!(tokenize(formatted), tokenize(source), "{}", path.display());
+    }
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn elements_toml_fills_in_the_options_not_given_as_flags() {
+    let dir = scratch("config");
+    std::fs::create_dir(dir.join("figures")).unwrap();
+    let config = "seed = 7\nlabel = true\n\n[theme]\nbackground = \"#eeeeee\"\n";
+    std::fs::write(dir.join("elements.toml"), config).unwrap();
+    std::fs::write(
+        dir.join("figures/figure.et"),
+        "(setq A (point 0 0)) (setq B (point 1 1)) (lineseg A B)",
+    )
+    .unwrap();
+    let render = |args: &[&str]| {
+        let args = [&["figures/figure.et", "--stdout"], args].concat();
+        let (is_ok, svg, stderr) = elements(&dir, &args, "");
+        assert!(is_ok, "{}", stderr);
+        svg
+    };
+
+    // the configuration of the closest directory above the input is used
+    let svg = render(&[]);
+    assert!(svg.contains("rendered with seed 7:"), "{}", svg);
+    assert!(svg.contains(r#"class="label""#), "{}", svg);
+    assert!(svg.contains(r##"fill="#eeeeee""##), "{}", svg);
+
+    // a --no- flag turns off a switch the configuration turns on, leaving the rest of it in place
+    let svg = render(&["--no-label"]);
+    assert!(!svg.contains(r#"class="label""#), "{}", svg);
+    assert!(svg.contains(r##"fill="#eeeeee""##), "{}", svg);
+
+    // flags take precedence over the values of the configuration
+    let svg = render(&["--seed", "8", "--background", "white"]);
+    assert!(svg.contains("rendered with seed 8:"), "{}", svg);
+    assert!(svg.contains(r#"fill="white""#), "{}", svg);
+
+    // a key the configuration does not have is an error naming the file
+    std::fs::write(dir.join("elements.toml"), "labels = true\n").unwrap();
+    let (is_ok, _, stderr) = elements(&dir, &["figures/figure.et", "--stdout"], "");
+    assert!(!is_ok);
+    assert!(stderr.contains("elements.toml"), "{}", stderr);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn lints_warn_about_unused_and_shadowed_variables() {
+    let source = "(setq r 2)\n(setq A (point 0 0))\n(setq s 3) ; allow unused-variable\n\
+                  (setq f (lambda (A) (point A A)))\n(locus f 0 1 10)\n\
+                  (setq g (lambda (k) (point 1 1)))\n(locus g 0 1 10)\n";
+    let allowed = allowed_lints(source);
+    let diagnostics = analyze(&tokenize(source.to_string()), &expression_spans(source));
+    let warnings: Vec<(usize, &str, &str)> = diagnostics
+        .iter()
+        .filter(|d| !d.is_allowed(&allowed))
+        .map(|d| {
+            (
+                d.span.unwrap().start.line,
+                d.lint.unwrap(),
+                d.message.as_str(),
+            )
+        })
+        .collect();
+
+    // top-level points are drawn with their label, so only the number and the lambda parameter go unused
+    assert_eq!(
+        warnings,
+        [
+            (1, "unused-variable", "unused variable: r"),
+            (
+                4,
+                "shadowed-variable",
+                "lambda parameter A shadows an earlier variable"
+            ),
+            (6, "unused-variable", "unused variable: k"),
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn html_export_embeds_the_construction_beside_the_figure() {
+    let source = "(setq A (point 0 0)) (setq B (point 4 0.5)) (label (lineseg A B) \"</script>\")";
+    let (is_ok, html, stderr) = elements(
+        &std::env::temp_dir(),
+        &["-", "--stdout", "--format", "html"],
+        source,
+    );
+    assert!(is_ok, "{}", stderr);
+    assert!(html.starts_with("<!DOCTYPE html>"), "{}", html);
+
+    // each drawn value is marked with the index of its form, so the runtime can move it
+    assert!(html.contains("<svg "), "{}", html);
+    assert!(html.contains(r#"<g data-value="2">"#), "{}", html);
+
+    // the construction holds each form and the position of each labelled point, and cannot end its script early
+    let data = html
+        .split(r#"<script type="application/json" id="elements-data">"#)
+        .nth(1)
+        .and_then(|rest| rest.split("</script>").next())
+        .expect("construction not embedded");
+    assert_eq!(
+        data,
+        r#"{"flip":false,"forms":[["setq",{"v":"A"},["point",0,0]],["setq",{"v":"B"},["point",4,0.5]],"#.to_string()
+            + r#"["label",["lineseg",{"v":"A"},{"v":"B"}],{"v":"\"<\/script>\""}]],"labels":[[3,0,0],[4,4,0.5]]}"#
+    );
+}
+
+#[test]
+fn inversion_maps_lines_by_whether_they_pass_through_the_center() {
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+    // a line missing the center maps to a circle through the center
+    match last("(invert (line (point 0 1) (point 1 1)) (point 0 0) 2)").unwrap() {
+        Value::Circle(c) => {
+            assert!(close(c.center.x, 0.0) && close(c.center.y, 2.0), "{:?}", c);
+            assert!(close(c.radius, 2.0), "{:?}", c);
+        }
+        value => panic!("expected a circle, got {:?}", value),
+    }
+
+    // a line through the center maps to itself
+    match last("(invert (line (point 0 0) (point 1 1)) (point 0 0) 2)").unwrap() {
+        Value::Line(l) => {
+            assert!(close(l.c, 0.0), "{:?}", l);
+            assert!(close(l.a * 3.0 + l.b * 3.0 + l.c, 0.0), "{:?}", l);
+        }
+        value => panic!("expected a line, got {:?}", value),
+    }
+
+    // a segment missing the center maps to an arc of the same circle, and one on the center has no image
+    match last("(invert (lineseg (point -1 1) (point 1 1)) (point 0 0) 2)").unwrap() {
+        Value::Arc(a) => {
+            assert!(close(a.center.x, 0.0) && close(a.center.y, 2.0), "{:?}", a);
+            assert!(
+                close(a.radius, 2.0) && close(a.sweep.abs(), std::f64::consts::PI),
+                "{:?}",
+                a
+            );
+        }
+        value => panic!("expected an arc, got {:?}", value),
+    }
+    let through = "(invert (lineseg (point -1 -1) (point 1 1)) (point 0 0) 2)";
+    assert!(matches!(last(through), Err(ElementsError::Geometry(_))));
+}
+
+#[test]
+fn region_where_traces_the_points_its_predicate_holds_for() {
+    let region = |predicate: &str, resolution: u32| {
+        last(&format!(
+            "(region-where (lambda (p) {}) (circle (point 0 0) 4) {})",
+            predicate, resolution
+        ))
+    };
+
+    // a half-plane is cut from the bounding box along its edge, to within a cell
+    match region("(< (point-x p) 1)", 8).unwrap() {
+        Value::Region(r) => {
+            assert_eq!(r.contours.len(), 1);
+            let xs = r.contours[0].iter().map(|p| p.x);
+            let ys = r.contours[0].iter().map(|p| p.y);
+            assert!(xs.clone().all(|x| (-4.0..=2.0).contains(&x)), "{:?}", r);
+            assert!(xs.fold(f64::MIN, f64::max) >= 0.5, "{:?}", r);
+            assert_eq!(ys.clone().fold(f64::MAX, f64::min), -4.0, "{:?}", r);
+            assert_eq!(ys.fold(f64::MIN, f64::max), 4.0, "{:?}", r);
+        }
+        value => panic!("expected a region, got {:?}", value),
+    }
+
+    // separate parts are traced separately, and no part when the predicate never holds
+    let discs = "(< (* (power p (circle (point -2 0) 1)) (power p (circle (point 2 0) 1))) 0)";
+    assert!(matches!(region(discs, 40).unwrap(), Value::Region(r) if r.contours.len() == 2));
+    assert!(
+        matches!(region("(< (point-x p) -10)", 8).unwrap(), Value::Region(r) if r.contours.is_empty())
+    );
+
+    // the predicate must decide, and the grid must have cells
+    assert!(region("3", 8).unwrap_err().to_string().contains("boolean"));
+    assert!(region("(< (point-x p) 1)", 0).is_err());
+}
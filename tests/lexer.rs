@@ -0,0 +1,80 @@
+//! Tokenize short sources and check the tokens they split into, for numbers, quoted strings, and comments.
+
+use elements_lang::lang::types::Value;
+use elements_lang::lexer::{tokenize, Literal, Token};
+
+/// Return the quoted strings in a source, in order, which are read as names and passed on as strings
+fn strings(source: &str) -> Vec<String> {
+    tokenize(source.to_string())
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Literal(Literal {
+                value: Value::String(s),
+            }) => Some(s),
+            Token::Variable(variable) if variable.name.starts_with('"') => Some(variable.name),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Return the single token a word is read as
+fn token(word: &str) -> Token {
+    tokenize(word.to_string()).remove(0)
+}
+
+/// Return the literal token holding a value
+fn literal(value: Value) -> Token {
+    Token::Literal(Literal { value })
+}
+
+#[test]
+fn numbers_with_a_point_or_exponent_are_floats() {
+    assert_eq!(token("0.5"), literal(Value::Float(0.5)));
+    assert_eq!(token("-2.25"), literal(Value::Float(-2.25)));
+    assert_eq!(token(".5"), literal(Value::Float(0.5)));
+    assert_eq!(token("1e-3"), literal(Value::Float(0.001)));
+    assert_eq!(token("+4."), literal(Value::Float(4.0)));
+    assert_eq!(token("12"), literal(Value::Int(12)));
+    assert_eq!(token("-7"), literal(Value::Int(-7)));
+}
+
+#[test]
+fn names_that_parse_as_floats_stay_names() {
+    for word in ["inf", "-inf", "nan", "NaN", "infinity", "e5", "1e400", "."] {
+        assert!(
+            matches!(token(word), Token::Variable(_)),
+            "{} was read as a number",
+            word
+        );
+    }
+}
+
+#[test]
+fn strings_keep_spaces_and_parentheses() {
+    assert_eq!(
+        strings("(raw-svg \"<g> (a b) </g>\")"),
+        vec!["\"<g> (a b) </g>\"".to_string()]
+    );
+    assert_eq!(
+        tokenize("(raw-svg \"a (b\")".to_string()).len(),
+        tokenize("(raw-svg \"x\")".to_string()).len()
+    );
+}
+
+#[test]
+fn strings_end_at_a_newline() {
+    assert_eq!(
+        strings("(label \"open\n(label \"b\")"),
+        vec!["\"open", "\"b\""]
+    );
+}
+
+#[test]
+fn quotes_in_comments_do_not_start_strings() {
+    let source = "(setq a 1) ; a comment with \"quotes\n(setq b \"c d\")";
+    assert_eq!(strings(source), vec!["\"c d\""]);
+    assert_eq!(
+        tokenize(source.to_string()),
+        tokenize("(setq a 1)\n(setq b \"c d\")".to_string())
+    );
+}